@@ -0,0 +1,97 @@
+//! Achievements
+//!
+//! Carts raise achievements and stats with `achievement()` and
+//! `achievement_stat()`. Each [AchievementHook] receives them, so an embedder
+//! can route them to Steam, itch.io, or a local tracker.
+//!
+//! ```ignore
+//! struct Log;
+//! impl AchievementHook for Log {
+//!     fn unlock(&mut self, id: &str) {
+//!         info!("Unlocked {id}");
+//!     }
+//! }
+//! app.world_mut().resource_mut::<Achievements>().add(Log);
+//! ```
+use bevy::{prelude::*, utils::HashMap};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.init_resource::<Achievements>();
+}
+
+/// Receives the achievements and stats a cart raises
+pub trait AchievementHook: Send + Sync + 'static {
+    /// Unlock the achievement `id`.
+    fn unlock(&mut self, id: &str);
+
+    /// Set the stat `id` to `value`.
+    fn stat(&mut self, _id: &str, _value: f32) {}
+}
+
+/// The achievement hooks and what has been raised this session
+#[derive(Resource, Default)]
+pub struct Achievements {
+    hooks: Vec<Box<dyn AchievementHook>>,
+    unlocked: Vec<String>,
+    stats: HashMap<String, f32>,
+}
+
+impl Achievements {
+    pub fn add(&mut self, hook: impl AchievementHook) -> &mut Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Unlock `id` with every hook. Returns false if it was already unlocked
+    /// this session, in which case the hooks are not called again.
+    pub fn unlock(&mut self, id: &str) -> bool {
+        if self.is_unlocked(id) {
+            return false;
+        }
+        self.unlocked.push(id.to_owned());
+        for hook in &mut self.hooks {
+            hook.unlock(id);
+        }
+        true
+    }
+
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked.iter().any(|x| x == id)
+    }
+
+    /// Set the stat `id` with every hook.
+    pub fn stat(&mut self, id: &str, value: f32) {
+        self.stats.insert(id.to_owned(), value);
+        for hook in &mut self.hooks {
+            hook.stat(id, value);
+        }
+    }
+
+    pub fn get_stat(&self, id: &str) -> Option<f32> {
+        self.stats.get(id).copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    struct Record(Arc<Mutex<Vec<String>>>);
+
+    impl AchievementHook for Record {
+        fn unlock(&mut self, id: &str) {
+            self.0.lock().unwrap().push(id.to_owned());
+        }
+    }
+
+    #[test]
+    fn test_unlock_once() {
+        let record = Arc::new(Mutex::new(Vec::new()));
+        let mut achievements = Achievements::default();
+        achievements.add(Record(record.clone()));
+        assert!(achievements.unlock("win"));
+        assert!(!achievements.unlock("win"));
+        assert_eq!(*record.lock().unwrap(), vec!["win".to_string()]);
+    }
+}
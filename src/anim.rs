@@ -0,0 +1,180 @@
+//! Sprite animation
+//!
+//! An [Anim] steps a retained sprite through sprite sheet indices on the cart
+//! tick, i.e., `FixedUpdate`, so animations stay in step with `_update()`.
+use bevy::prelude::*;
+
+use crate::interpolate::Interpolate;
+
+pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<Anim>()
+        .add_event::<AnimEnded>()
+        .add_systems(FixedUpdate, step_anims);
+}
+
+/// What an animation does after its last frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+pub enum AnimMode {
+    /// Stop on the last frame.
+    Once,
+    /// Start again from the first frame.
+    #[default]
+    Loop,
+    /// Play backwards to the first frame then forwards again.
+    PingPong,
+}
+
+/// Animate a sprite through `frames` at `fps`.
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component)]
+#[require(Interpolate)]
+pub struct Anim {
+    /// Sprite sheet indices
+    pub frames: Vec<usize>,
+    pub fps: f32,
+    pub mode: AnimMode,
+    pub playing: bool,
+    /// Position in `frames`
+    pub frame: usize,
+    /// Seconds since the frame started
+    elapsed: f32,
+    /// Playing backwards in ping-pong mode
+    reverse: bool,
+}
+
+/// Sent when an [AnimMode::Once] animation finishes or when a looping
+/// animation wraps around.
+#[derive(Debug, Clone, Event)]
+pub struct AnimEnded {
+    pub entity: Entity,
+}
+
+impl Anim {
+    pub fn new(frames: impl Into<Vec<usize>>, fps: f32) -> Self {
+        Anim {
+            frames: frames.into(),
+            fps,
+            mode: AnimMode::default(),
+            playing: true,
+            frame: 0,
+            elapsed: 0.0,
+            reverse: false,
+        }
+    }
+
+    pub fn with_mode(mut self, mode: AnimMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Return the current sprite sheet index.
+    pub fn index(&self) -> Option<usize> {
+        self.frames.get(self.frame).copied()
+    }
+
+    /// Advance by `dt` seconds. Returns true if the animation ended or
+    /// wrapped around.
+    pub fn tick(&mut self, dt: f32) -> bool {
+        if !self.playing || self.fps <= 0.0 || self.frames.is_empty() {
+            return false;
+        }
+        self.elapsed += dt;
+        let frame_time = 1.0 / self.fps;
+        let mut ended = false;
+        while self.playing && self.elapsed >= frame_time {
+            self.elapsed -= frame_time;
+            ended |= self.advance();
+        }
+        ended
+    }
+
+    fn advance(&mut self) -> bool {
+        let last = self.frames.len() - 1;
+        match self.mode {
+            AnimMode::Once => {
+                self.frame = (self.frame + 1).min(last);
+                if self.frame == last {
+                    self.playing = false;
+                }
+                !self.playing
+            }
+            AnimMode::Loop => {
+                if self.frame >= last {
+                    self.frame = 0;
+                    true
+                } else {
+                    self.frame += 1;
+                    false
+                }
+            }
+            AnimMode::PingPong => {
+                if last == 0 {
+                    return true;
+                }
+                if self.reverse {
+                    self.frame -= 1;
+                    if self.frame == 0 {
+                        self.reverse = false;
+                        return true;
+                    }
+                } else {
+                    self.frame += 1;
+                    if self.frame == last {
+                        self.reverse = true;
+                    }
+                }
+                false
+            }
+        }
+    }
+}
+
+fn step_anims(
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Anim, &mut Sprite)>,
+    mut ended: EventWriter<AnimEnded>,
+) {
+    let dt = time.delta_secs();
+    for (entity, mut anim, mut sprite) in &mut query {
+        if !anim.playing {
+            continue;
+        }
+        if anim.tick(dt) {
+            ended.send(AnimEnded { entity });
+        }
+        if let (Some(atlas), Some(index)) = (sprite.texture_atlas.as_mut(), anim.index()) {
+            if atlas.index != index {
+                atlas.index = index;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn anim_modes() {
+        let mut anim = Anim::new([1, 2, 3], 10.0);
+        assert!(!anim.tick(0.25));
+        assert_eq!(anim.index(), Some(3));
+        assert!(anim.tick(0.1));
+        assert_eq!(anim.index(), Some(1));
+
+        let mut anim = Anim::new([1, 2], 1.0).with_mode(AnimMode::Once);
+        assert!(anim.tick(1.0));
+        assert!(!anim.tick(5.0));
+        assert_eq!(anim.index(), Some(2));
+        assert!(!anim.playing);
+
+        let mut anim = Anim::new([1, 2, 3], 1.0).with_mode(AnimMode::PingPong);
+        let frames: Vec<_> = (0..5)
+            .map(|_| {
+                anim.tick(1.0);
+                anim.index().unwrap()
+            })
+            .collect();
+        assert_eq!(frames, [2, 3, 2, 1, 2]);
+    }
+}
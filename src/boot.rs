@@ -0,0 +1,286 @@
+//! Boot splash
+//!
+//! While the cart's assets load, show a boot sequence like Pico-8's: a sweep
+//! of the palette followed by a logo and the version. The cart does not start
+//! until the sequence is over or skipped with a key press or click.
+//!
+//! It is shown when the config has a `[boot]` table.
+//!
+//! ```toml
+//! [boot]
+//! duration = 2.0
+//! image = "my-logo.png"
+//! ```
+//!
+//! To replace the sequence entirely, register a system that draws each of its
+//! frames as a boot cart in [BootCarts] and name it in the config:
+//!
+//! ```ignore
+//! let intro = app.register_system(draw_intro);
+//! app.world_mut().resource_mut::<BootCarts>().insert("intro", intro);
+//! ```
+//!
+//! ```toml
+//! [boot]
+//! cart = "intro"
+//! ```
+//!
+//! [BootSequence::custom] does the same without the config.
+use bevy::{ecs::system::SystemId, prelude::*, sprite::Anchor, utils::HashMap};
+
+use crate::{
+    config::{self, Config},
+    error::RunState,
+    pico8::{negate_y, PALETTE, PICO8_FONT},
+    N9Canvas,
+};
+
+const DEFAULT_DURATION: f32 = 1.5;
+/// Fraction of the sequence spent sweeping the palette
+const SWEEP: f32 = 0.4;
+
+pub(crate) fn plugin(app: &mut App) {
+    app.init_resource::<BootCarts>();
+    let Some(boot) = app
+        .world()
+        .get_resource::<Config>()
+        .and_then(|config| config.boot.as_ref())
+        .map(BootSequence::from_config)
+    else {
+        return;
+    };
+    app.register_type::<BootSequence>()
+        .insert_resource(boot)
+        .add_systems(
+            Update,
+            (skip_boot, step_boot)
+                .chain()
+                .run_if(booting.and(in_state(RunState::Uninit).or(in_state(RunState::Loaded)))),
+        )
+        .add_systems(OnExit(RunState::Loaded), end_boot);
+}
+
+/// The boot sequence's progress
+#[derive(Debug, Clone, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct BootSequence {
+    /// Seconds the sequence lasts
+    pub duration: f32,
+    /// Whether a key press or click ends it early
+    pub skippable: bool,
+    /// Shown instead of the logo
+    pub image: Option<String>,
+    /// Name of the boot cart in [BootCarts] to run instead of the built-in
+    /// sequence
+    pub cart: Option<String>,
+    /// Draws a frame of the sequence instead of the built-in one or the
+    /// boot cart
+    #[reflect(ignore)]
+    pub custom: Option<SystemId>,
+    /// Seconds since it started
+    pub elapsed: f32,
+    pub finished: bool,
+    shown_logo: bool,
+}
+
+impl BootSequence {
+    pub fn from_config(boot: &config::Boot) -> Self {
+        BootSequence {
+            duration: boot.duration.unwrap_or(DEFAULT_DURATION),
+            skippable: boot.skippable.unwrap_or(true),
+            image: boot.image.clone(),
+            cart: boot.cart.clone(),
+            custom: None,
+            elapsed: 0.0,
+            finished: false,
+            shown_logo: false,
+        }
+    }
+
+    /// Return the fraction of the sequence that is over.
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0.0 {
+            1.0
+        } else {
+            (self.elapsed / self.duration).min(1.0)
+        }
+    }
+}
+
+/// Boot carts that a config may name in `[boot] cart`
+///
+/// Each draws a frame of the boot sequence in place of the built-in one.
+#[derive(Debug, Default, Resource)]
+pub struct BootCarts {
+    carts: HashMap<String, SystemId>,
+}
+
+impl BootCarts {
+    pub fn insert(&mut self, name: impl Into<String>, system: SystemId) {
+        self.carts.insert(name.into(), system);
+    }
+
+    pub fn get(&self, name: &str) -> Option<SystemId> {
+        self.carts.get(name).copied()
+    }
+}
+
+/// Return true while the boot sequence is shown.
+pub fn booting(boot: Option<Res<BootSequence>>) -> bool {
+    boot.is_some_and(|boot| !boot.finished)
+}
+
+/// Marks entities of the boot sequence, which are despawned when it ends.
+#[derive(Component)]
+pub struct BootSplash;
+
+fn skip_boot(
+    keys: Option<Res<ButtonInput<KeyCode>>>,
+    mouse: Option<Res<ButtonInput<MouseButton>>>,
+    mut boot: ResMut<BootSequence>,
+) {
+    if boot.skippable
+        && (keys.is_some_and(|k| k.get_just_pressed().next().is_some())
+            || mouse.is_some_and(|m| m.get_just_pressed().next().is_some()))
+    {
+        boot.finished = true;
+    }
+}
+
+fn step_boot(
+    time: Res<Time>,
+    canvas: Res<N9Canvas>,
+    asset_server: Res<AssetServer>,
+    carts: Res<BootCarts>,
+    mut boot: ResMut<BootSequence>,
+    mut images: ResMut<Assets<Image>>,
+    mut commands: Commands,
+) {
+    boot.elapsed += time.delta_secs();
+    if boot.elapsed >= boot.duration {
+        boot.finished = true;
+    }
+    if boot.custom.is_none() {
+        if let Some(name) = boot.cart.take() {
+            boot.custom = carts.get(&name);
+            if boot.custom.is_none() {
+                warn!("No boot cart {name:?}; showing the built-in boot sequence.");
+            }
+        }
+    }
+    if let Some(custom) = boot.custom {
+        commands.run_system(custom);
+        return;
+    }
+    let progress = boot.progress();
+    if let Some(image) = images.get_mut(&canvas.handle) {
+        if progress < SWEEP {
+            // Bands of the palette scrolling up the screen
+            let offset = (boot.elapsed * 64.0) as u32;
+            for y in 0..image.height() {
+                let c = PALETTE[((y + offset) / 8 % PALETTE.len() as u32) as usize];
+                let color = Color::srgba_u8(c[0], c[1], c[2], c[3]);
+                for x in 0..image.width() {
+                    let _ = image.set_color_at(x, y, color);
+                }
+            }
+        } else if !boot.shown_logo {
+            image.data.chunks_exact_mut(4).for_each(|pixel| {
+                pixel.copy_from_slice(&PALETTE[0]);
+            });
+        }
+    }
+    if progress >= SWEEP && !boot.shown_logo {
+        boot.shown_logo = true;
+        let size = canvas.size.as_vec2();
+        match &boot.image {
+            Some(path) => {
+                commands.spawn((
+                    Name::new("boot logo"),
+                    Sprite::from_image(asset_server.load(path)),
+                    Transform::from_xyz(size.x / 2.0, negate_y(size.y / 2.0), 1.0),
+                    BootSplash,
+                ));
+            }
+            None => {
+                commands.spawn((
+                    Name::new("boot logo"),
+                    Text2d::new("nano-9"),
+                    TextFont {
+                        font: asset_server.load(PICO8_FONT),
+                        font_size: 10.0,
+                        ..default()
+                    },
+                    TextColor(Color::srgb_u8(PALETTE[8][0], PALETTE[8][1], PALETTE[8][2])),
+                    Anchor::TopLeft,
+                    Transform::from_xyz(2.0, negate_y(2.0), 1.0),
+                    BootSplash,
+                ));
+            }
+        }
+        commands.spawn((
+            Name::new("boot version"),
+            Text2d::new(format!("version {}", env!("CARGO_PKG_VERSION"))),
+            TextFont {
+                font: asset_server.load(PICO8_FONT),
+                font_size: 5.0,
+                ..default()
+            },
+            TextColor(Color::srgb_u8(PALETTE[6][0], PALETTE[6][1], PALETTE[6][2])),
+            Anchor::TopLeft,
+            Transform::from_xyz(2.0, negate_y(14.0), 1.0),
+            BootSplash,
+        ));
+    }
+}
+
+fn end_boot(
+    boot: Option<Res<BootSequence>>,
+    query: Query<Entity, With<BootSplash>>,
+    canvas: Res<N9Canvas>,
+    mut images: ResMut<Assets<Image>>,
+    mut commands: Commands,
+) {
+    if boot.is_none() {
+        return;
+    }
+    for id in &query {
+        commands.entity(id).despawn_recursive();
+    }
+    if let Some(image) = images.get_mut(&canvas.handle) {
+        image.data.fill(0);
+    }
+    // Boot only once.
+    commands.remove_resource::<BootSequence>();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn progress() {
+        let mut boot = BootSequence::from_config(&config::Boot::default());
+        assert_eq!(boot.progress(), 0.0);
+        boot.elapsed = DEFAULT_DURATION / 2.0;
+        assert_eq!(boot.progress(), 0.5);
+        boot.elapsed = DEFAULT_DURATION * 2.0;
+        assert_eq!(boot.progress(), 1.0);
+        boot.duration = 0.0;
+        assert_eq!(boot.progress(), 1.0);
+    }
+
+    #[test]
+    fn boot_cart() {
+        let config: Config = toml::from_str(
+            r#"
+[boot]
+cart = "intro"
+"#,
+        )
+        .unwrap();
+        let boot = BootSequence::from_config(config.boot.as_ref().unwrap());
+        assert_eq!(boot.cart.as_deref(), Some("intro"));
+        assert!(boot.custom.is_none());
+    }
+}
@@ -5,6 +5,10 @@
 -- [1]: https://github.com/PikuseruConsole/pikuseru/
 -- [2]: @shanecelis@mastodon.gamedev.place
 -- [3]: https://opensource.org/licenses/MIT
+--
+-- Nothing loads this prelude until the scripting layer returns. Carts call
+-- the same builtins as `Pico8` methods, e.g., `Pico8::add()` and
+-- `Pico8::foreach()` in src/pico8/api/table.rs.
 
 local NUMBER_BITS <const> = 32
 printh = print
@@ -81,62 +85,91 @@ bnot = function(x)
   return ~x
 end
 
-function add(a,v)
+-- add(t, v, [i]) inserts v at index i, or at the end, and returns v.
+function add(a,v,i)
     if a == nil then
         warn("add to nil")
         return
     end
-    table.insert(a,v)
+    if i == nil then
+        table.insert(a,v)
+    else
+        table.insert(a,i,v)
+    end
+    return v
 end
+-- del(t, v) removes the first v, shifting later elements down, and returns
+-- it.
 function del(a,dv)
     if a == nil then
         warn("del from nil")
         return
     end
-    for i,v in ipairs(a) do
-        if v==dv  then
+    for i=1,#a do
+        if a[i]==dv then
             return table.remove(a,i)
         end
     end
 end
-
+-- deli(t, [i]) removes the element at index i, or the last one, and returns
+-- it. An index outside the table removes nothing.
 function deli(a,i)
     if a == nil then
         warn("del from nil")
         return
     end
-    return table.remove(a,i)
-end
-function foreach(a,f)
-    if not a then
-        warn("foreach got a nil value")
+    local n = #a
+    i = i or n
+    if i < 1 or i > n then
         return
     end
-    for i,v in ipairs(a) do
-        f(v)
-    end
+    return table.remove(a,i)
 end
+-- count(t, [v]) returns the length of t or how many times v occurs in it.
 function count(a,v)
+    if a == nil then
+        return 0
+    end
     if v == nil then
         return #a
-    else
-        local count = 0
-        for i,v in ipairs(a) do
-            if v==dv then
-                count = count + 1
-            end
+    end
+    local n = 0
+    for i=1,#a do
+        if a[i]==v then
+            n = n + 1
         end
-        return count
     end
+    return n
 end
+-- all(t) iterates over the values of t in order. Like Pico-8, deleting the
+-- current value with del() while iterating does not skip the next one, and
+-- values added to the end are visited.
 function all(a)
-    local i = 0
-    local n = #a
+    if a == nil then
+        return function() end
+    end
+    local i = 1
+    local last = nil
     return function()
-        i = i + 1
-        if i <= n  then
-            return a[i]
+        -- If the last value is still in place, move past it. Otherwise it was
+        -- deleted and the next value has shifted into its index.
+        if i <= #a and a[i] == last then
+            i = i + 1
         end
+        while a[i] == nil and i <= #a do
+            i = i + 1
+        end
+        last = a[i]
+        return last
+    end
+end
+function foreach(a,f)
+    if not a then
+        warn("foreach got a nil value")
+        return
+    end
+    for v in all(a) do
+        f(v)
     end
 end
 -- string.sub does not respect UTF-8 boundaries.
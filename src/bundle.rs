@@ -0,0 +1,436 @@
+//! Single-file bundles
+//!
+//! A ".n9" bundle is a zip archive of a game: its "Nano9.toml" config at the
+//! root and every asset the config references, e.g., carts, scripts, images,
+//! and audio. Mounted as an asset source, a game ships as one file.
+//!
+//! ```ignore
+//! let bundle = Bundle::open("game.n9")?;
+//! let config = bundle.config()?;
+//! app.add_plugins(bundle).add_plugins(Nano9Plugins::new(config));
+//! ```
+//!
+//! Entries may be stored or deflated. [Bundle::to_bytes] deflates them.
+//!
+//! With a [Bundle::key], scripts are also obfuscated: deflated then XORed with
+//! a stream derived from the key. An exporter embeds the key in its
+//! executable, which passes it to [Bundle::open_with_key]. Like a PNG cart,
+//! this deters casual reading but does not stop a determined one.
+use crate::config::{expand_glob, Config};
+use bevy::{
+    asset::io::{
+        memory::{Dir, MemoryAssetReader},
+        AssetSource, AssetSourceId,
+    },
+    prelude::*,
+};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// File extension of bundles
+pub const BUNDLE_EXTENSION: &str = "n9";
+/// Asset source a bundle is mounted as
+pub const BUNDLE_SOURCE: &str = "n9bundle";
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIR: u32 = 0x06054b50;
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+/// Deflated then obfuscated, an unassigned method ("N9")
+const OBFUSCATED: u16 = 0x394e;
+/// Extensions of the files obfuscated with a key
+pub const SCRIPT_EXTENSIONS: [&str; 3] = ["lua", "p8lua", "p8"];
+const TRUNCATED: BundleError = BundleError::Format("truncated");
+
+#[derive(thiserror::Error, Debug)]
+pub enum BundleError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("not a bundle: {0}")]
+    Format(&'static str),
+    #[error("unsupported compression method {0} for {1:?}")]
+    Compression(u16, PathBuf),
+    #[error("could not inflate {0:?}")]
+    Inflate(PathBuf),
+    #[error("{0:?} is obfuscated and needs a key")]
+    NoKey(PathBuf),
+    #[error("checksum mismatch for {0:?}")]
+    Checksum(PathBuf),
+    #[error("size mismatch for {0:?}")]
+    Size(PathBuf),
+    #[error("bundle has no Nano9.toml")]
+    NoConfig,
+    #[error("could not read config: {0}")]
+    Config(#[from] toml::de::Error),
+    #[error("too large for a zip archive: {0}")]
+    TooLarge(String),
+}
+
+/// The files of a game
+#[derive(Debug, Clone, Default)]
+pub struct Bundle {
+    pub files: BTreeMap<PathBuf, Vec<u8>>,
+    /// Key to obfuscate scripts with
+    pub key: Option<u64>,
+}
+
+impl Bundle {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, BundleError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Open a bundle whose scripts were obfuscated with `key`.
+    pub fn open_with_key(path: impl AsRef<Path>, key: u64) -> Result<Self, BundleError> {
+        Self::from_bytes_with_key(&fs::read(path)?, Some(key))
+    }
+
+    /// Bundle `config` and the assets it references from `assets_dir`. Audio
+    /// bank globs are bundled as the files they match.
+    pub fn from_config(config: &Config, assets_dir: &Path) -> Result<Self, BundleError> {
+        let mut files = BTreeMap::new();
+        let contents = toml::to_string(config).map_err(io::Error::other)?;
+        files.insert(PathBuf::from("Nano9.toml"), contents.into_bytes());
+        for pattern in config.asset_paths() {
+            for asset in expand_glob(assets_dir, &pattern)? {
+                let bytes = fs::read(assets_dir.join(&asset))?;
+                files.insert(asset, bytes);
+            }
+        }
+        Ok(Bundle { files, key: None })
+    }
+
+    pub fn with_key(mut self, key: u64) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Return the bundle's config with its asset paths in the bundle.
+    pub fn config(&self) -> Result<Config, BundleError> {
+        let bytes = self
+            .files
+            .get(Path::new("Nano9.toml"))
+            .ok_or(BundleError::NoConfig)?;
+        let mut config: Config =
+            toml::from_str(std::str::from_utf8(bytes).map_err(|_| BundleError::NoConfig)?)?;
+        config.set_asset_source(BUNDLE_SOURCE);
+        Ok(config)
+    }
+
+    /// Read a zip archive.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BundleError> {
+        Self::from_bytes_with_key(bytes, None)
+    }
+
+    /// Read a zip archive whose scripts may be obfuscated with `key`.
+    pub fn from_bytes_with_key(bytes: &[u8], key: Option<u64>) -> Result<Self, BundleError> {
+        // The end of central directory record is at least 22 bytes from the
+        // end, more if it has a comment.
+        let end = (0..=bytes.len().saturating_sub(22))
+            .rev()
+            .find(|i| read_u32(bytes, *i) == Some(END_OF_CENTRAL_DIR))
+            .ok_or(BundleError::Format("no end of central directory"))?;
+        let count = read_u16(bytes, end + 10).ok_or(TRUNCATED)?;
+        let mut offset = read_u32(bytes, end + 16).ok_or(TRUNCATED)? as usize;
+        let mut files = BTreeMap::new();
+        for _ in 0..count {
+            let entry = CentralEntry::read(bytes, offset)?;
+            offset = entry.next;
+            if entry.name.ends_with('/') {
+                // A directory
+                continue;
+            }
+            let path = PathBuf::from(&entry.name);
+            let local = entry.local_offset;
+            if read_u32(bytes, local) != Some(LOCAL_HEADER) {
+                return Err(BundleError::Format("bad local header"));
+            }
+            let name_len = read_u16(bytes, local + 26).ok_or(TRUNCATED)?;
+            let extra_len = read_u16(bytes, local + 28).ok_or(TRUNCATED)?;
+            let start = local + 30 + name_len as usize + extra_len as usize;
+            let data = bytes
+                .get(start..start + entry.compressed_size)
+                .ok_or(TRUNCATED)?;
+            // Inflate no more than the declared size, so a small entry
+            // cannot expand without bound.
+            let inflate = |data: &[u8]| {
+                miniz_oxide::inflate::decompress_to_vec_with_limit(data, entry.size)
+                    .map_err(|_| BundleError::Inflate(path.clone()))
+            };
+            let data = match entry.method {
+                STORED => data.to_vec(),
+                DEFLATED => inflate(data)?,
+                OBFUSCATED => {
+                    let key = key.ok_or_else(|| BundleError::NoKey(path.clone()))?;
+                    inflate(&obfuscate(data, key, &entry.name))?
+                }
+                method => return Err(BundleError::Compression(method, path)),
+            };
+            if data.len() != entry.size {
+                return Err(BundleError::Size(path));
+            }
+            if crc32(&data) != entry.crc {
+                return Err(BundleError::Checksum(path));
+            }
+            files.insert(path, data);
+        }
+        Ok(Bundle { files, key })
+    }
+
+    /// Write a zip archive. Zip64 is not supported, so an archive may have
+    /// at most 65535 files of 4 GiB.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BundleError> {
+        let count: u16 = fit(self.files.len(), || format!("{} files", self.files.len()))?;
+        let mut out = Vec::new();
+        let mut central = Vec::new();
+        for (path, data) in &self.files {
+            let name = path.to_string_lossy().replace('\\', "/");
+            let name_len: u16 = fit(name.len(), || format!("name of {path:?}"))?;
+            let size: u32 = fit(data.len(), || format!("{path:?}"))?;
+            let mut compressed = miniz_oxide::deflate::compress_to_vec(data, 6);
+            let script = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| SCRIPT_EXTENSIONS.contains(&ext));
+            let (method, stored) = match self.key {
+                Some(key) if script => {
+                    compressed = obfuscate(&compressed, key, &name);
+                    (OBFUSCATED, compressed.as_slice())
+                }
+                _ if compressed.len() < data.len() => (DEFLATED, compressed.as_slice()),
+                _ => (STORED, data.as_slice()),
+            };
+            let stored_size: u32 = fit(stored.len(), || format!("{path:?}"))?;
+            let crc = crc32(data);
+            let offset: u32 = fit(out.len(), || "archive".into())?;
+            // Version 2.0, no flags, no time, no extra field
+            let header = |out: &mut Vec<u8>| {
+                out.extend(20u16.to_le_bytes());
+                out.extend(0u16.to_le_bytes());
+                out.extend(method.to_le_bytes());
+                out.extend(0u32.to_le_bytes());
+                out.extend(crc.to_le_bytes());
+                out.extend(stored_size.to_le_bytes());
+                out.extend(size.to_le_bytes());
+                out.extend(name_len.to_le_bytes());
+                out.extend(0u16.to_le_bytes());
+            };
+            out.extend(LOCAL_HEADER.to_le_bytes());
+            header(&mut out);
+            out.extend(name.as_bytes());
+            out.extend(stored);
+
+            central.extend(CENTRAL_HEADER.to_le_bytes());
+            central.extend(20u16.to_le_bytes());
+            header(&mut central);
+            // Comment length, disk, internal and external attributes
+            central.extend([0; 10]);
+            central.extend(offset.to_le_bytes());
+            central.extend(name.as_bytes());
+        }
+        let central_offset: u32 = fit(out.len(), || "archive".into())?;
+        let central_size: u32 = fit(central.len(), || "central directory".into())?;
+        out.extend(&central);
+        out.extend(END_OF_CENTRAL_DIR.to_le_bytes());
+        out.extend([0; 4]);
+        out.extend(count.to_le_bytes());
+        out.extend(count.to_le_bytes());
+        out.extend(central_size.to_le_bytes());
+        out.extend(central_offset.to_le_bytes());
+        out.extend(0u16.to_le_bytes());
+        Ok(out)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), BundleError> {
+        fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+}
+
+/// Mounts the bundle as the "n9bundle://" asset source. Add it before the
+/// [DefaultPlugins].
+impl Plugin for Bundle {
+    fn build(&self, app: &mut App) {
+        let dir = Dir::default();
+        for (path, data) in &self.files {
+            dir.insert_asset(path, data.clone());
+        }
+        let reader = MemoryAssetReader { root: dir };
+        app.register_asset_source(
+            AssetSourceId::from_static(BUNDLE_SOURCE),
+            AssetSource::build().with_reader(move || Box::new(reader.clone())),
+        );
+    }
+}
+
+/// Convert a length to the width of its zip field or fail with what it is the
+/// length of.
+fn fit<T: TryFrom<usize>>(n: usize, what: impl FnOnce() -> String) -> Result<T, BundleError> {
+    T::try_from(n).map_err(|_| BundleError::TooLarge(what()))
+}
+
+struct CentralEntry {
+    name: String,
+    method: u16,
+    crc: u32,
+    compressed_size: usize,
+    /// Uncompressed size
+    size: usize,
+    local_offset: usize,
+    /// Offset of the next entry
+    next: usize,
+}
+
+impl CentralEntry {
+    fn read(bytes: &[u8], offset: usize) -> Result<Self, BundleError> {
+        if read_u32(bytes, offset) != Some(CENTRAL_HEADER) {
+            return Err(BundleError::Format("bad central directory"));
+        }
+        let method = read_u16(bytes, offset + 10).ok_or(TRUNCATED)?;
+        let crc = read_u32(bytes, offset + 16).ok_or(TRUNCATED)?;
+        let compressed_size = read_u32(bytes, offset + 20).ok_or(TRUNCATED)? as usize;
+        let size = read_u32(bytes, offset + 24).ok_or(TRUNCATED)? as usize;
+        let name_len = read_u16(bytes, offset + 28).ok_or(TRUNCATED)?;
+        let extra_len = read_u16(bytes, offset + 30).ok_or(TRUNCATED)?;
+        let comment_len = read_u16(bytes, offset + 32).ok_or(TRUNCATED)?;
+        let local_offset = read_u32(bytes, offset + 42).ok_or(TRUNCATED)? as usize;
+        let name = bytes
+            .get(offset + 46..offset + 46 + name_len as usize)
+            .ok_or(TRUNCATED)?;
+        Ok(CentralEntry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            method,
+            crc,
+            compressed_size,
+            size,
+            local_offset,
+            next: offset + 46 + name_len as usize + extra_len as usize + comment_len as usize,
+        })
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// XOR `data` with a stream seeded by `key` and the entry's `name`, so each
+/// entry has its own stream. Applying it twice restores the data.
+fn obfuscate(data: &[u8], key: u64, name: &str) -> Vec<u8> {
+    // FNV-1a
+    let mut state = name.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x100000001b3)
+    }) ^ key;
+    // SplitMix64
+    let mut next = move || {
+        state = state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    };
+    data.chunks(8)
+        .flat_map(|chunk| {
+            let stream = next().to_le_bytes();
+            chunk
+                .iter()
+                .zip(stream)
+                .map(|(a, b)| a ^ b)
+                .collect::<Vec<u8>>()
+        })
+        .collect()
+}
+
+/// The CRC-32 of zip entries
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut bundle = Bundle::default();
+        bundle
+            .files
+            .insert("Nano9.toml".into(), b"name = \"bundle\"\n".to_vec());
+        bundle.files.insert("gfx/sprites.png".into(), vec![7; 1000]);
+        let read = Bundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+        assert_eq!(read.files, bundle.files);
+        assert_eq!(read.config().unwrap().name.as_deref(), Some("bundle"));
+    }
+
+    #[test]
+    fn test_obfuscated() {
+        let mut bundle = Bundle::default().with_key(0x5eed);
+        let script = b"function _draw() cls() end".to_vec();
+        bundle.files.insert("main.p8lua".into(), script.clone());
+        let bytes = bundle.to_bytes().unwrap();
+        assert!(!bytes.windows(8).any(|w| w == b"function"));
+        assert!(matches!(
+            Bundle::from_bytes(&bytes),
+            Err(BundleError::NoKey(_))
+        ));
+        let read = Bundle::from_bytes_with_key(&bytes, Some(0x5eed)).unwrap();
+        assert_eq!(read.files[Path::new("main.p8lua")], script);
+    }
+
+    #[test]
+    fn test_declared_size() {
+        let mut bundle = Bundle::default();
+        bundle.files.insert("zeros".into(), vec![0; 1000]);
+        let mut bytes = bundle.to_bytes().unwrap();
+        let central = bytes
+            .windows(4)
+            .position(|w| w == CENTRAL_HEADER.to_le_bytes())
+            .unwrap();
+        for size in [10u32, 2000] {
+            bytes[central + 24..central + 28].copy_from_slice(&size.to_le_bytes());
+            assert!(matches!(
+                Bundle::from_bytes(&bytes),
+                Err(BundleError::Inflate(_) | BundleError::Size(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_too_large() {
+        let mut bundle = Bundle::default();
+        bundle.files.insert("a".repeat(0x10000).into(), vec![]);
+        assert!(matches!(bundle.to_bytes(), Err(BundleError::TooLarge(_))));
+
+        let mut bundle = Bundle::default();
+        for i in 0..0x10000 {
+            bundle.files.insert(format!("{i}").into(), vec![]);
+        }
+        assert!(matches!(bundle.to_bytes(), Err(BundleError::TooLarge(_))));
+    }
+}
@@ -1,5 +1,6 @@
-use super::PColor;
+use super::{PColor, ParseColorError};
 use bevy::prelude::*;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Copy, Reflect, Default)]
 pub enum N9Color {
@@ -17,6 +18,18 @@ impl N9Color {
     }
 }
 
+/// Parse "pen" or a color as [PColor] does.
+impl FromStr for N9Color {
+    type Err = ParseColorError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().eq_ignore_ascii_case("pen") {
+            Ok(N9Color::Pen)
+        } else {
+            s.parse().map(N9Color::PColor)
+        }
+    }
+}
+
 impl From<PColor> for N9Color {
     fn from(c: PColor) -> Self {
         N9Color::PColor(c)
@@ -1,6 +1,32 @@
 use bevy::prelude::*;
+use std::str::FromStr;
 
-use crate::pico8::{Error, PalMap};
+use crate::pico8::{Error, PalMap, Palette, PALETTE};
+
+/// Names of Pico-8's colors in palette order
+pub const COLOR_NAMES: [&str; 16] = [
+    "black",
+    "dark-blue",
+    "dark-purple",
+    "dark-green",
+    "brown",
+    "dark-grey",
+    "light-grey",
+    "white",
+    "red",
+    "orange",
+    "yellow",
+    "green",
+    "blue",
+    "lavender",
+    "pink",
+    "light-peach",
+];
+
+/// A color string that is neither hex nor a color name
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid color {0:?}")]
+pub struct ParseColorError(pub String);
 
 #[derive(Debug, Clone, Copy, Reflect)]
 pub enum PColor {
@@ -17,6 +43,17 @@ impl PColor {
         }
     }
 
+    /// Return the nearest palette color of an RGB color. Transparent colors
+    /// and palette colors are returned as is.
+    pub fn snap(&self, palette: &Palette) -> PColor {
+        match self {
+            PColor::Color(c) if c.alpha > 0.0 => {
+                palette.nearest(*c).map(PColor::Palette).unwrap_or(*self)
+            }
+            x => *x,
+        }
+    }
+
     pub fn write_color(
         &self,
         palette: &[[u8; 4]],
@@ -34,6 +71,32 @@ impl PColor {
     }
 }
 
+/// Parse a hex color like "#ff77a8" or "#f7a" with an optional alpha, or the
+/// name of one of Pico-8's colors like "red" or "dark_blue".
+///
+/// A name is the color itself rather than a palette index, so it does not
+/// change with the palette.
+impl FromStr for PColor {
+    type Err = ParseColorError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseColorError(s.to_string());
+        if s.starts_with('#') {
+            return Srgba::hex(s)
+                .map(|c| PColor::Color(c.into()))
+                .map_err(|_| error());
+        }
+        let name = s.trim().to_ascii_lowercase().replace(['_', ' '], "-");
+        COLOR_NAMES
+            .iter()
+            .position(|n| *n == name)
+            .map(|i| {
+                let [r, g, b, a] = PALETTE[i];
+                PColor::Color(Srgba::rgba_u8(r, g, b, a).into())
+            })
+            .ok_or_else(error)
+    }
+}
+
 impl From<Color> for PColor {
     fn from(c: Color) -> Self {
         PColor::Color(c.into())
@@ -57,3 +120,34 @@ impl From<i32> for PColor {
         PColor::Palette(n as usize)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rgba(c: PColor) -> [u8; 4] {
+        match c {
+            PColor::Color(c) => Srgba::from(c).to_u8_array(),
+            PColor::Palette(i) => panic!("expected a color not palette {i}"),
+        }
+    }
+
+    #[test]
+    fn parse_color() {
+        assert_eq!(rgba("#ff77a8".parse().unwrap()), [0xff, 0x77, 0xa8, 0xff]);
+        assert_eq!(rgba("#f008".parse().unwrap()), [0xff, 0, 0, 0x88]);
+        assert_eq!(rgba("red".parse().unwrap()), [0xff, 0x00, 0x4d, 0xff]);
+        assert_eq!(rgba("Dark_Blue".parse().unwrap()), [0x1d, 0x2b, 0x53, 0xff]);
+        assert!("#ff77a".parse::<PColor>().is_err());
+        assert!("chartreuse".parse::<PColor>().is_err());
+    }
+
+    #[test]
+    fn snap_color() {
+        let palette = Palette::from_slice(&PALETTE);
+        let pink = PColor::Color(Srgba::rgb_u8(0xff, 0x70, 0xa0).into());
+        assert!(matches!(pink.snap(&palette), PColor::Palette(14)));
+        let clear = PColor::Color(LinearRgba::NONE);
+        assert!(matches!(clear.snap(&palette), PColor::Color(_)));
+    }
+}
@@ -0,0 +1,402 @@
+//! Color-blind filters
+//!
+//! A [ColorFilter] transforms the colors of the screen after the cart is
+//! drawn, including sprites, shapes, and text that never pass through the
+//! canvas. Bevy UI, e.g., the minibuffer, is drawn after it and is not
+//! filtered. "Simulate" shows what a player with the deficiency sees, so a
+//! developer can check that a cart stays readable. "Compensate" shifts the
+//! colors they cannot tell apart toward ones they can.
+//!
+//! The filter comes from the player's [Settings] if set, otherwise from the
+//! [Config]:
+//!
+//! ```toml
+//! color_filter = { deficiency = "deuteranopia", mode = "simulate" }
+//! ```
+use bevy::{
+    asset::embedded_asset,
+    core_pipeline::{
+        core_2d::graph::{Core2d, Node2d},
+        fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+    },
+    ecs::query::QueryItem,
+    prelude::*,
+    render::{
+        extract_component::{
+            ComponentUniforms, DynamicUniformIndex, ExtractComponent, ExtractComponentPlugin,
+            UniformComponentPlugin,
+        },
+        render_graph::{
+            NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+        },
+        render_resource::{
+            binding_types::{sampler, texture_2d, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+        view::ViewTarget,
+        RenderApp,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config, pico8::Pico8, settings::Settings, split_screen::SplitCamera, Nano9Camera,
+};
+
+const SHADER: &str = "embedded://nano9/color_filter.wgsl";
+
+pub(crate) struct ColorFilterPlugin;
+
+impl Plugin for ColorFilterPlugin {
+    fn build(&self, app: &mut App) {
+        embedded_asset!(app, "color_filter.wgsl");
+        app.register_type::<ColorFilter>()
+            .add_plugins((
+                ExtractComponentPlugin::<ColorMatrix>::default(),
+                UniformComponentPlugin::<ColorMatrix>::default(),
+            ))
+            .add_systems(PostUpdate, apply_color_filter);
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_graph_node::<ViewNodeRunner<ColorFilterNode>>(Core2d, ColorFilterLabel)
+            .add_render_graph_edges(
+                Core2d,
+                (
+                    Node2d::Tonemapping,
+                    ColorFilterLabel,
+                    Node2d::EndMainPassPostProcessing,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        // The pipeline needs the render device, which exists only now.
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<ColorFilterPipeline>();
+        }
+    }
+}
+
+/// A kind of color blindness
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorDeficiency {
+    /// No green cones: reds and greens look alike.
+    Deuteranopia,
+    /// No red cones: reds and greens look alike and reds look dark.
+    Protanopia,
+    /// No blue cones: blues and greens look alike.
+    Tritanopia,
+}
+
+/// What a [ColorFilter] does about a [ColorDeficiency]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterMode {
+    /// Show the screen as the player sees it.
+    #[default]
+    Simulate,
+    /// Shift colors to make them easier to tell apart.
+    Compensate,
+}
+
+/// A filter over the whole screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub struct ColorFilter {
+    pub deficiency: ColorDeficiency,
+    #[serde(default)]
+    pub mode: FilterMode,
+}
+
+impl ColorFilter {
+    pub fn simulate(deficiency: ColorDeficiency) -> Self {
+        ColorFilter {
+            deficiency,
+            mode: FilterMode::Simulate,
+        }
+    }
+
+    pub fn compensate(deficiency: ColorDeficiency) -> Self {
+        ColorFilter {
+            deficiency,
+            mode: FilterMode::Compensate,
+        }
+    }
+
+    /// Return the matrix applied to linear RGB.
+    pub fn matrix(&self) -> Mat3 {
+        // Machado, Oliveira, and Fernandes (2009) at full severity
+        let rows = match self.deficiency {
+            ColorDeficiency::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            ColorDeficiency::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            ColorDeficiency::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        };
+        let simulate = Mat3::from_cols_array_2d(&rows).transpose();
+        match self.mode {
+            FilterMode::Simulate => simulate,
+            // Daltonize: add the color lost to the channels that remain.
+            FilterMode::Compensate => {
+                let rows = match self.deficiency {
+                    // The red-green error goes to green and blue.
+                    ColorDeficiency::Deuteranopia | ColorDeficiency::Protanopia => {
+                        [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]]
+                    }
+                    // The blue-yellow error goes to red and green, i.e., to
+                    // luminance.
+                    ColorDeficiency::Tritanopia => {
+                        [[1.0, 0.0, 0.7], [0.0, 1.0, 0.7], [0.0, 0.0, 0.0]]
+                    }
+                };
+                let shift = Mat3::from_cols_array_2d(&rows).transpose();
+                Mat3::IDENTITY + shift * (Mat3::IDENTITY - simulate)
+            }
+        }
+    }
+}
+
+/// The matrix the [ColorFilterNode] applies to a camera's view
+#[derive(Debug, Clone, Copy, PartialEq, Component, ExtractComponent, ShaderType)]
+struct ColorMatrix {
+    matrix: Mat3,
+}
+
+fn apply_color_filter(
+    settings: Option<Res<Settings>>,
+    config: Option<Res<Config>>,
+    cameras: Query<
+        (Entity, &Camera, Option<&ColorMatrix>),
+        Or<(With<Nano9Camera>, With<SplitCamera>)>,
+    >,
+    mut commands: Commands,
+) {
+    let filter = settings
+        .and_then(|settings| settings.color_filter)
+        .or_else(|| config.and_then(|config| config.color_filter));
+    let matrix = filter.map(|filter| ColorMatrix {
+        matrix: filter.matrix(),
+    });
+    // The filter covers the camera's whole target, not just its viewport, so
+    // only the last camera drawn, the top split camera if the canvas is split,
+    // filters the screen. Filtering every camera would filter the views under
+    // it again.
+    let top = cameras
+        .iter()
+        .max_by_key(|(_, camera, _)| camera.order)
+        .map(|(id, ..)| id);
+    for (id, _, last) in &cameras {
+        let matrix = matrix.filter(|_| Some(id) == top);
+        if last == matrix.as_ref() {
+            continue;
+        }
+        match matrix {
+            Some(matrix) => commands.entity(id).insert(matrix),
+            None => commands.entity(id).remove::<ColorMatrix>(),
+        };
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct ColorFilterLabel;
+
+#[derive(Default)]
+struct ColorFilterNode;
+
+impl ViewNode for ColorFilterNode {
+    type ViewQuery = (
+        &'static ViewTarget,
+        &'static ColorMatrix,
+        &'static DynamicUniformIndex<ColorMatrix>,
+    );
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (view_target, _matrix, index): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let pipeline = world.resource::<ColorFilterPipeline>();
+        let Some(render_pipeline) = world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(pipeline.pipeline_id)
+        else {
+            return Ok(());
+        };
+        let Some(uniforms) = world
+            .resource::<ComponentUniforms<ColorMatrix>>()
+            .uniforms()
+            .binding()
+        else {
+            return Ok(());
+        };
+        // The source and destination swap with every write, so the bind group
+        // is made here rather than ahead of time.
+        let post_process = view_target.post_process_write();
+        let bind_group = render_context.render_device().create_bind_group(
+            "color_filter_bind_group",
+            &pipeline.layout,
+            &BindGroupEntries::sequential((post_process.source, &pipeline.sampler, uniforms)),
+        );
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("color_filter_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_render_pipeline(render_pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[index.index()]);
+        render_pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
+
+#[derive(Resource)]
+struct ColorFilterPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    pipeline_id: CachedRenderPipelineId,
+}
+
+impl FromWorld for ColorFilterPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let layout = render_device.create_bind_group_layout(
+            "color_filter_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                    uniform_buffer::<ColorMatrix>(true),
+                ),
+            ),
+        );
+        // Nearest sampling keeps the pixels sharp.
+        let sampler = render_device.create_sampler(&SamplerDescriptor::default());
+        let shader = world.load_asset(SHADER);
+        let pipeline_id =
+            world
+                .resource_mut::<PipelineCache>()
+                .queue_render_pipeline(RenderPipelineDescriptor {
+                    label: Some("color_filter_pipeline".into()),
+                    layout: vec![layout.clone()],
+                    vertex: fullscreen_shader_vertex_state(),
+                    fragment: Some(FragmentState {
+                        shader,
+                        shader_defs: vec![],
+                        entry_point: "fragment".into(),
+                        targets: vec![Some(ColorTargetState {
+                            format: TextureFormat::bevy_default(),
+                            blend: None,
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                    primitive: PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: MultisampleState::default(),
+                    push_constant_ranges: vec![],
+                    zero_initialize_workgroup_memory: false,
+                });
+        ColorFilterPipeline {
+            layout,
+            sampler,
+            pipeline_id,
+        }
+    }
+}
+
+impl Pico8<'_, '_> {
+    /// color_filter([filter])
+    ///
+    /// Set the player's color-blind filter or clear it with `None`. A
+    /// cleared filter falls back to the config's.
+    pub fn color_filter(&mut self, filter: Option<ColorFilter>) {
+        self.persist.settings.color_filter = filter;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn filters_keep_gray() {
+        for deficiency in [
+            ColorDeficiency::Deuteranopia,
+            ColorDeficiency::Protanopia,
+            ColorDeficiency::Tritanopia,
+        ] {
+            for filter in [
+                ColorFilter::simulate(deficiency),
+                ColorFilter::compensate(deficiency),
+            ] {
+                let gray = filter.matrix() * Vec3::splat(0.5);
+                assert!(
+                    gray.abs_diff_eq(Vec3::splat(0.5), 1e-3),
+                    "{filter:?} {gray}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn simulate_red_green() {
+        let filter = ColorFilter::simulate(ColorDeficiency::Deuteranopia);
+        let red = filter.matrix() * Vec3::X;
+        let green = filter.matrix() * Vec3::Y;
+        // Red and green both come out yellowish with little blue.
+        assert!(red.x > red.z && green.x > green.z);
+    }
+
+    #[test]
+    fn compensate_tritan_into_red_green() {
+        let filter = ColorFilter::compensate(ColorDeficiency::Tritanopia);
+        let blue = filter.matrix() * Vec3::Z;
+        // Blue keeps its channel and gains red and green to stand apart.
+        assert!((blue.z - 1.0).abs() < 1e-4, "{blue}");
+        assert!(blue.x > 0.5 && blue.y > 0.2, "{blue}");
+    }
+
+    #[test]
+    fn filter_top_split_camera() {
+        use bevy::ecs::system::RunSystemOnce;
+        let mut world = World::new();
+        world.insert_resource(Settings {
+            color_filter: Some(ColorFilter::simulate(ColorDeficiency::Tritanopia)),
+            ..default()
+        });
+        let main = world.spawn((Camera::default(), Nano9Camera)).id();
+        let split = |order| (Camera { order, ..default() }, SplitCamera(order as usize));
+        let bottom = world.spawn(split(1)).id();
+        let top = world.spawn(split(2)).id();
+        world.run_system_once(apply_color_filter).unwrap();
+        assert!(world.get::<ColorMatrix>(main).is_none());
+        assert!(world.get::<ColorMatrix>(bottom).is_none());
+        assert!(world.get::<ColorMatrix>(top).is_some());
+
+        world.despawn(bottom);
+        world.despawn(top);
+        world.run_system_once(apply_color_filter).unwrap();
+        assert!(world.get::<ColorMatrix>(main).is_some());
+    }
+}
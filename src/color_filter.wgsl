@@ -0,0 +1,18 @@
+// Transform the colors of the screen by a matrix.
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var screen_sampler: sampler;
+
+struct ColorMatrix {
+    // Applied to linear RGB
+    matrix: mat3x3<f32>,
+}
+@group(0) @binding(2) var<uniform> color_matrix: ColorMatrix;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(screen_texture, screen_sampler, in.uv);
+    let rgb = clamp(color_matrix.matrix * color.rgb, vec3(0.0), vec3(1.0));
+    return vec4(rgb, color.a);
+}
@@ -0,0 +1,127 @@
+//! Components attached by name
+//!
+//! A cart cannot name Rust types, so a game registers the components a cart
+//! may attach with [ComponentConstructors], each made from a table of
+//! [Fields].
+//!
+//! ```ignore
+//! app.world_mut()
+//!     .resource_mut::<ComponentConstructors>()
+//!     .register("Health", |fields| {
+//!         match fields.get("hp") {
+//!             Some(Var::Number(hp)) => Ok(Health(*hp as u8)),
+//!             _ => Err(Error::InvalidArgument("hp".into())),
+//!         }
+//!     });
+//! ```
+//!
+//! The cart then calls `pico8.attach(id, "Health", fields)` on an entity it
+//! kept.
+use bevy::{prelude::*, utils::HashMap};
+use std::borrow::Cow;
+
+use crate::{
+    pico8::{Error, Pico8},
+    vars::Var,
+};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.init_resource::<ComponentConstructors>();
+}
+
+/// The fields a component is made from
+pub type Fields = HashMap<String, Var>;
+
+type ComponentConstructor =
+    Box<dyn Fn(&Fields, &mut EntityWorldMut) -> Result<(), Error> + Send + Sync>;
+
+/// Components that carts may attach to entities by name
+#[derive(Resource, Default)]
+pub struct ComponentConstructors(HashMap<Cow<'static, str>, ComponentConstructor>);
+
+impl ComponentConstructors {
+    /// Register `name` to insert the component `f` makes from its fields.
+    pub fn register<C: Component>(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        f: impl Fn(&Fields) -> Result<C, Error> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.0.insert(
+            name.into(),
+            Box::new(move |fields, entity| {
+                entity.insert(f(fields)?);
+                Ok(())
+            }),
+        );
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// Insert the component registered as `name` into `entity`.
+    pub fn attach(
+        &self,
+        name: &str,
+        fields: &Fields,
+        entity: &mut EntityWorldMut,
+    ) -> Result<(), Error> {
+        let constructor = self
+            .0
+            .get(name)
+            .ok_or_else(|| Error::NoSuch(format!("component {name}").into()))?;
+        constructor(fields, entity)
+    }
+}
+
+impl Pico8<'_, '_> {
+    /// attach(id, name, fields)
+    ///
+    /// Attach the component registered as `name` to `id`, e.g., a kept
+    /// sprite. Failures are logged since the entity may not exist yet.
+    pub fn attach(&mut self, id: Entity, name: impl Into<String>, fields: Fields) {
+        let name = name.into();
+        self.commands.queue(move |world: &mut World| {
+            world.resource_scope(|world, constructors: Mut<ComponentConstructors>| {
+                let Ok(mut entity) = world.get_entity_mut(id) else {
+                    warn!("Cannot attach {name:?} to {id}; it does not exist.");
+                    return;
+                };
+                if let Err(e) = constructors.attach(&name, &fields, &mut entity) {
+                    warn!("Cannot attach {name:?} to {id}: {e}");
+                }
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Component, Debug, PartialEq)]
+    struct Health(u8);
+
+    #[test]
+    fn attach_by_name() {
+        let mut constructors = ComponentConstructors::default();
+        constructors.register("Health", |fields| match fields.get("hp") {
+            Some(Var::Number(hp)) => Ok(Health(*hp as u8)),
+            _ => Err(Error::InvalidArgument("hp".into())),
+        });
+        assert!(constructors.contains("Health"));
+        assert!(!constructors.contains("Mana"));
+
+        let mut world = World::new();
+        let id = world.spawn_empty().id();
+        let mut entity = world.entity_mut(id);
+        let fields: Fields = [("hp".to_owned(), Var::Number(3.0))].into_iter().collect();
+        constructors.attach("Health", &fields, &mut entity).unwrap();
+        assert!(constructors
+            .attach("Health", &Fields::default(), &mut entity)
+            .is_err());
+        assert!(constructors.attach("Mana", &fields, &mut entity).is_err());
+        assert_eq!(world.get::<Health>(id), Some(&Health(3)));
+    }
+}
@@ -0,0 +1,148 @@
+//! Directory globs, e.g., "sfx/*.ogg"
+use bevy::{
+    asset::io::{AssetReaderError, ErasedAssetReader},
+    tasks::futures_lite::StreamExt,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Return true if `name` matches `pattern` where "*" matches any run of
+/// characters and "?" matches any one character.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where to resume after the last "*" if a match fails
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Split `pattern` into its directory and its file name if the file name has
+/// wildcards. Only the file name may have them.
+fn split_glob(pattern: &Path) -> Option<(&Path, &str)> {
+    let name = pattern
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|name| name.contains(['*', '?']))?;
+    Some((pattern.parent().unwrap_or(Path::new("")), name))
+}
+
+/// Return the files in `assets_dir` that match `pattern` sorted by path.
+///
+/// A path without wildcards is returned as is. This reads the file system; to
+/// expand a glob while loading, use [read_glob].
+pub fn expand_glob(assets_dir: &Path, pattern: &Path) -> io::Result<Vec<PathBuf>> {
+    let Some((dir, name)) = split_glob(pattern) else {
+        return Ok(vec![pattern.to_path_buf()]);
+    };
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(assets_dir.join(dir))? {
+        let entry = entry?;
+        if entry.file_type()?.is_file()
+            && entry
+                .file_name()
+                .to_str()
+                .is_some_and(|file_name| glob_match(name, file_name))
+        {
+            paths.push(dir.join(entry.file_name()));
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Return the files `reader` has that match `pattern` sorted by path.
+///
+/// A path without wildcards is returned as is.
+pub async fn read_glob(
+    reader: &dyn ErasedAssetReader,
+    pattern: &Path,
+) -> Result<Vec<PathBuf>, AssetReaderError> {
+    let Some((dir, name)) = split_glob(pattern) else {
+        return Ok(vec![pattern.to_path_buf()]);
+    };
+    let mut entries = reader.read_directory(dir).await?;
+    let mut paths = Vec::new();
+    while let Some(path) = entries.next().await {
+        if path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .is_some_and(|file_name| glob_match(name, file_name))
+            && !reader.is_directory(&path).await?
+        {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.ogg", "jump.ogg"));
+        assert!(glob_match("*.ogg", ".ogg"));
+        assert!(!glob_match("*.ogg", "jump.wav"));
+        assert!(glob_match("sfx?.*", "sfx1.wav"));
+        assert!(!glob_match("sfx?.*", "sfx10.wav"));
+        assert!(glob_match("*a*b", "xaxxab"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn test_expand_glob() {
+        let root = std::env::temp_dir().join(format!("nano9-glob-{}", std::process::id()));
+        fs::create_dir_all(root.join("sfx")).unwrap();
+        for name in ["b.ogg", "a.ogg", "c.wav"] {
+            fs::write(root.join("sfx").join(name), b"").unwrap();
+        }
+        assert_eq!(
+            expand_glob(&root, Path::new("sfx/*.ogg")).unwrap(),
+            vec![PathBuf::from("sfx/a.ogg"), PathBuf::from("sfx/b.ogg")]
+        );
+        assert_eq!(
+            expand_glob(&root, Path::new("sfx/c.wav")).unwrap(),
+            vec![PathBuf::from("sfx/c.wav")]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_read_glob() {
+        use bevy::asset::io::memory::{Dir, MemoryAssetReader};
+        let root = Dir::default();
+        for name in ["sfx/b.ogg", "sfx/a.ogg", "sfx/c.wav", "sfx/d.ogg/e.ogg"] {
+            root.insert_asset(Path::new(name), Vec::new());
+        }
+        let reader = MemoryAssetReader { root };
+        assert_eq!(
+            bevy::tasks::block_on(read_glob(&reader, Path::new("sfx/*.ogg"))).unwrap(),
+            vec![PathBuf::from("sfx/a.ogg"), PathBuf::from("sfx/b.ogg")]
+        );
+    }
+}
@@ -2,11 +2,21 @@
 use crate::level::{self};
 use crate::{
     config::{self, *},
-    pico8::{self, image::pixel_art_settings, Gfx, Pico8Asset},
+    pico8::{
+        self,
+        aseprite::{Aseprite, AsepriteError},
+        image::pixel_art_settings,
+        p8::{P8Error, P8},
+        Gfx, Pico8Asset,
+    },
 };
 use bevy::{
-    asset::{io::Reader, AssetLoader, AssetPath, LoadContext},
+    asset::{
+        io::{AssetReaderError, Reader},
+        AssetLoader, AssetPath, AssetServerMode, LoadContext,
+    },
     prelude::*,
+    utils::HashMap,
 };
 use std::io;
 
@@ -30,6 +40,8 @@ pub enum ConfigLoaderError {
     Load(#[from] bevy::asset::LoadDirectError),
     #[error("Could not read asset: {0}")]
     AssetBytes(#[from] bevy::asset::ReadAssetBytesError),
+    #[error("Could not read directory: {0}")]
+    AssetReader(#[from] AssetReaderError),
     #[error("Decoding error: {0}")]
     Decoding(#[from] png::DecodingError),
     #[error("image {image_index} ({image_size:?}) does not fit sprite size {sprite_size:?}")]
@@ -44,12 +56,26 @@ pub enum ConfigLoaderError {
         image_size: UVec2,
         sprite_counts: UVec2,
     },
+    #[error("Could not read aseprite file: {0}")]
+    Aseprite(#[from] AsepriteError),
+    #[error("Could not read p8 cart: {0}")]
+    P8(#[from] P8Error),
     #[error("invalid template {0:?}")]
     InvalidTemplate(String),
 }
 
-#[derive(Default)]
-pub struct ConfigLoader;
+pub struct ConfigLoader {
+    /// Reads the directories of audio bank globs
+    asset_server: AssetServer,
+}
+
+impl FromWorld for ConfigLoader {
+    fn from_world(world: &mut World) -> Self {
+        ConfigLoader {
+            asset_server: world.resource::<AssetServer>().clone(),
+        }
+    }
+}
 
 impl AssetLoader for ConfigLoader {
     type Asset = pico8::Pico8Asset;
@@ -68,7 +94,7 @@ impl AssetLoader for ConfigLoader {
         let mut config: Config =
             toml::from_str::<Config>(content).map_err(|e| io::Error::other(format!("{e}")))?;
         config.inject_template(None)?;
-        into_asset(config, load_context).await
+        into_asset(config, &self.asset_server, load_context).await
     }
 
     fn extensions(&self) -> &[&str] {
@@ -152,56 +178,47 @@ pub struct LuaLoaderSettings {
 
 async fn into_asset(
     config: Config,
+    asset_server: &AssetServer,
     load_context: &mut LoadContext<'_>,
 ) -> Result<Pico8Asset, ConfigLoaderError> {
+    let cart_config = config.clone();
     let mut sprite_sheets = vec![];
     for (i, mut sheet) in config.sprite_sheets.into_iter().enumerate() {
-        // let flags: Vec<u8>;
-        // if sheet.path.extension() == Some(OsStr::new("tsx")) {
-        //     #[cfg(feature = "level")]
-        //     {
-        //         let tiledset = load_context
-        //             .loader()
-        //             .immediate()
-        //             .load::<TiledSet>(&*sheet.path)
-        //             .await?;
-        //         let tileset = &tiledset.get().0;
-        //         let handle = load_context
-        //             .add_labeled_asset(format!("atlas{i}"), layout_from_tileset(tileset));
-        //         let tile_size = UVec2::new(tileset.tile_width, tileset.tile_height);
-        //         if let Some(sprite_size) = sheet.sprite_size {
-        //             assert_eq!(sprite_size, tile_size);
-        //         }
-        //         let flags = flags_from_tileset(tileset);
-        //         sprite_sheets.push(pico8::SpriteSheet {
-        //             handle: pico8::SprHandle::Image(
-        //                 load_context
-        //                     .loader()
-        //                     .with_settings(pixel_art_settings)
-        //                     .load(
-        //                         &*tileset
-        //                             .image
-        //                             .as_ref()
-        //                             .ok_or(ConfigLoaderError::Message(format!(
-        //                                 "could not load .tsx image {i}"
-        //                             )))?
-        //                             .source,
-        //                     ),
-        //             ),
-        //             sprite_size: tile_size,
-        //             flags,
-        //             layout: handle,
-        //         })
-        //     }
-        //     #[cfg(not(feature = "level"))]
-        //     panic!(
-        //         "Can not load {:?} file without 'level' feature.",
-        //         &sheet.path
-        //     );
-        // } else if sheet.path.extension() == Some(OsStr::new("p8")) {
-        //     todo!()
-        // } else {
-        let (handle, layout_maybe) = if sheet.indexed {
+        if sheet.path.ends_with(".tsx") {
+            sprite_sheets.push(tsx_sprite_sheet(i, &sheet, load_context).await?);
+            continue;
+        }
+        if sheet.path.ends_with(".p8") {
+            sprite_sheets.push(p8_sprite_sheet(i, &mut sheet, load_context).await?);
+            continue;
+        }
+        let (handle, layout_maybe, anims) = if is_aseprite(&sheet.path) {
+            let bytes = load_context.read_asset_bytes(&*sheet.path).await?;
+            let aseprite = Aseprite::from_bytes(&bytes)?;
+            // Each frame is a sprite unless the config says otherwise.
+            if sheet.sprite_counts.is_none() {
+                sheet.sprite_size.get_or_insert(aseprite.size);
+            }
+            let layout = get_layout(
+                i,
+                aseprite.sheet_size(),
+                &mut sheet.sprite_size,
+                sheet.sprite_counts,
+                sheet.padding,
+                sheet.offset,
+            )?
+            .map(|layout| load_context.add_labeled_asset(format!("atlas{i}"), layout));
+            let handle = match aseprite.to_gfx() {
+                Ok(gfx) => pico8::SprHandle::Gfx(
+                    load_context.add_labeled_asset(format!("spritesheet{i}"), gfx),
+                ),
+                Err(e) if sheet.indexed => return Err(e.into()),
+                Err(_) => pico8::SprHandle::Image(
+                    load_context.add_labeled_asset(format!("spritesheet{i}"), aseprite.to_image()),
+                ),
+            };
+            (handle, layout, aseprite.anims())
+        } else if sheet.indexed {
             let bytes = load_context.read_asset_bytes(&*sheet.path).await?;
             let gfx = Gfx::from_png(&bytes)?;
             let image_size = UVec2::new(gfx.width as u32, gfx.height as u32);
@@ -219,6 +236,7 @@ async fn into_asset(
                     load_context.add_labeled_asset(format!("spritesheet{i}"), gfx),
                 ),
                 layout,
+                HashMap::default(),
             )
         } else {
             let loaded = load_context
@@ -243,6 +261,7 @@ async fn into_asset(
                     load_context.add_loaded_labeled_asset(format!("spritesheet{i}"), loaded),
                 ),
                 layout,
+                HashMap::default(),
             )
         };
         sprite_sheets.push(pico8::SpriteSheet {
@@ -250,8 +269,9 @@ async fn into_asset(
             sprite_size: sheet.sprite_size.unwrap_or(UVec2::splat(8)),
             flags: vec![],
             layout: layout_maybe.unwrap_or(Handle::default()),
+            anims,
+            transparent: sheet.transparent,
         })
-        // }
     }
     let mut palettes = Vec::new();
     if config.palettes.is_empty() {
@@ -270,68 +290,299 @@ async fn into_asset(
             palettes.push(pico8::Palette::from_image(image.get(), palette.row));
         }
     }
+    let mut maps = Vec::with_capacity(config.maps.len());
+    for map in config.maps.into_iter() {
+        maps.push(match map.path.extension().and_then(|s| s.to_str()) {
+            Some("p8") => p8_map(&map, load_context).await?,
+            Some(ext) => tiled_map(ext, &map, load_context)?,
+            None => {
+                return Err(ConfigLoaderError::Message(format!(
+                    "The map path {:?} did not have an extension.",
+                    &map.path
+                )))
+            }
+        });
+    }
+    let mut audio_banks = Vec::with_capacity(config.audio_banks.len());
+    for (i, bank) in config.audio_banks.into_iter().enumerate() {
+        audio_banks.push(match bank {
+            AudioBank::P8 { p8, count } => p8_audio_bank(i, &p8, count, load_context).await?,
+            AudioBank::Paths { paths } => pico8::audio::AudioBank::new(
+                paths
+                    .into_iter()
+                    .map(|p| pico8::audio::Audio::AudioSource(load_context.load(p)))
+                    .collect(),
+            ),
+            AudioBank::Dir { dir } => pico8::audio::AudioBank::new(
+                audio_glob(&dir, asset_server)
+                    .await?
+                    .into_iter()
+                    .map(|p| pico8::audio::Audio::AudioSource(load_context.load(p)))
+                    .collect(),
+            ),
+        });
+    }
+    let mut string_tables = Vec::with_capacity(config.string_tables.len());
+    for table in config.string_tables.into_iter() {
+        let bytes = load_context.read_asset_bytes(&*table.path).await?;
+        let mut string_table =
+            pico8::StringTable::from_toml(table.locale, std::str::from_utf8(&bytes)?)
+                .map_err(|e| ConfigLoaderError::Message(format!("{:?}: {e}", &table.path)))?;
+        string_table.font = table.font;
+        string_tables.push(string_table);
+    }
     let state = pico8::Pico8Asset {
-                palettes,
-                border: load_context.loader()
-                                    .with_settings(pixel_art_settings)
-                                    .load(pico8::PICO8_BORDER),
-                maps: config.maps.into_iter().map(|map| {
-                    let extension = map.path.extension().and_then(|s| s. to_str());
-                    if let Some(ext) = extension {
-                        match ext {
-                            "p8" => todo!(),
-                            "tmx" => {
-                                    #[cfg(feature = "level")]
-                                    return Ok(level::Tiled::Map {
-                                        handle: load_context.load(&*map.path),
-                                    }.into());
-                                    #[cfg(not(feature = "level"))]
-                                    Err(ConfigLoaderError::Message(format!("The map {:?} is a Tiled map; consider using the '--features=level' flag.", &map.path)))
-                            }
-                            "world" => {
-                                    #[cfg(feature = "level")]
-                                    return Ok(level::Tiled::World {
-                                        handle: load_context.load(&*map.path),
-                                    }.into());
-                                    #[cfg(not(feature = "level"))]
-                                    Err(ConfigLoaderError::Message(format!("The map {:?} is a Tiled world; consider using the '--features=level' flag.", &map.path)))
-                            }
-                            _ => Err(ConfigLoaderError::Message(format!("Unknown map format {:?}", &map.path)))
-                        }
-                    } else {
-                        Err(ConfigLoaderError::Message(format!("The map path {:?} did not have an extension.", &map.path)))
-                    }
-                }).collect::<Result<Vec<_>, _>>()?,
-                audio_banks: config.audio_banks.into_iter().map(|bank| pico8::audio::AudioBank(match bank {
-                    AudioBank::P8 { p8, count } => {
-                            (0..count).map(|i|
-                                           pico8::audio::Audio::Sfx(load_context.load(AssetPath::from_path(&p8).into_owned().with_label(format!("sfx{i}"))))
-                            ).collect::<Vec<_>>()
-                    }
-                    AudioBank::Paths { paths } => {
-                        paths.into_iter().map(|p| pico8::audio::Audio::AudioSource(load_context.load(p))).collect::<Vec<_>>()
-                    }
-                })).collect::<Vec<_>>(),
-                sprite_sheets,
-                font: config.fonts.into_iter().map(|font|
-                                                     match font {
-                                                         config::Font::Default { default: yes } if yes => {
-                                                             pico8::N9Font {
-                                                                 handle: TextFont::default().font,
-                                                             }
-                                                         },
-                                                         config::Font::Path { path, height: _ } => {
-                                                             pico8::N9Font {
-                                                                 handle: load_context.load(path),
-                                                             }
-                                                         }
-                                                         config::Font::Default { .. } => { panic!("Must use a path if not default font.") }
-                                                     }).collect::<Vec<_>>(),
-
-            };
+        palettes,
+        border: load_context
+            .loader()
+            .with_settings(pixel_art_settings)
+            .load(pico8::PICO8_BORDER),
+        maps,
+        audio_banks,
+        channel_fx: config
+            .channels
+            .into_iter()
+            .map(|channel| channel.fx)
+            .collect(),
+        sprite_sheets,
+        string_tables,
+        config: Some(cart_config),
+        font: config
+            .fonts
+            .into_iter()
+            .map(|font| match font {
+                config::Font::Default { default: yes } if yes => pico8::N9Font {
+                    handle: TextFont::default().font,
+                    height: None,
+                },
+                config::Font::Path { path, height } => pico8::N9Font {
+                    handle: load_context.load(path),
+                    height,
+                },
+                config::Font::Default { .. } => {
+                    panic!("Must use a path if not default font.")
+                }
+            })
+            .collect::<Vec<_>>(),
+    };
     Ok(state)
 }
 
+/// Make a map of a Tiled map or world.
+#[cfg_attr(not(feature = "level"), allow(unused_variables))]
+fn tiled_map(
+    ext: &str,
+    map: &config::Map,
+    load_context: &mut LoadContext<'_>,
+) -> Result<pico8::Map, ConfigLoaderError> {
+    match ext {
+        "tmx" => {
+            #[cfg(feature = "level")]
+            return Ok(level::Tiled::Map {
+                handle: load_context.load(&*map.path),
+            }
+            .into());
+            #[cfg(not(feature = "level"))]
+            Err(ConfigLoaderError::Message(format!(
+                "The map {:?} is a Tiled map; consider using the '--features=level' flag.",
+                &map.path
+            )))
+        }
+        "world" => {
+            #[cfg(feature = "level")]
+            return Ok(level::Tiled::World {
+                handle: load_context.load(&*map.path),
+            }
+            .into());
+            #[cfg(not(feature = "level"))]
+            Err(ConfigLoaderError::Message(format!(
+                "The map {:?} is a Tiled world; consider using the '--features=level' flag.",
+                &map.path
+            )))
+        }
+        _ => Err(ConfigLoaderError::Message(format!(
+            "Unknown map format {:?}",
+            &map.path
+        ))),
+    }
+}
+
+/// Make a map of a Pico-8 cart's `__map__` section and the rows it shares
+/// with `__gfx__`. Its sprites are from the first sprite sheet.
+async fn p8_map(
+    map: &config::Map,
+    load_context: &mut LoadContext<'_>,
+) -> Result<pico8::Map, ConfigLoaderError> {
+    let bytes = load_context.read_asset_bytes(&*map.path).await?;
+    let p8 = P8::parse(std::str::from_utf8(&bytes)?);
+    Ok(pico8::P8Map {
+        entries: p8.map()?,
+        sheet_index: 0,
+        columns: map.columns.unwrap_or(pico8::MAP_COLUMNS),
+    }
+    .into())
+}
+
+/// Expand `pattern`, which may name its asset source, e.g.,
+/// "n9bundle://sfx/*.ogg", with that source's reader.
+async fn audio_glob(
+    pattern: &std::path::Path,
+    asset_server: &AssetServer,
+) -> Result<Vec<AssetPath<'static>>, ConfigLoaderError> {
+    let pattern = pattern
+        .to_str()
+        .ok_or_else(|| ConfigLoaderError::Message(format!("Invalid path {pattern:?}")))?;
+    let pattern = AssetPath::try_parse(pattern)
+        .map_err(|e| ConfigLoaderError::Message(format!("{pattern:?}: {e}")))?;
+    let source = asset_server
+        .get_source(pattern.source().clone())
+        .map_err(|e| ConfigLoaderError::Message(e.to_string()))?;
+    let reader = match asset_server.mode() {
+        AssetServerMode::Unprocessed => source.reader(),
+        AssetServerMode::Processed => source
+            .processed_reader()
+            .map_err(|e| ConfigLoaderError::Message(e.to_string()))?,
+    };
+    Ok(config::read_glob(reader, pattern.path())
+        .await?
+        .into_iter()
+        .map(|path| AssetPath::from(path).with_source(pattern.source().clone_owned()))
+        .collect())
+}
+
+/// Make an audio bank of the first `count` sfx of a Pico-8 cart's `__sfx__`
+/// section and the patterns of its `__music__` section.
+async fn p8_audio_bank(
+    i: usize,
+    path: &std::path::Path,
+    count: usize,
+    load_context: &mut LoadContext<'_>,
+) -> Result<pico8::audio::AudioBank, ConfigLoaderError> {
+    use pico8::audio::{Audio, AudioBank, MusicPattern};
+    let bytes = load_context.read_asset_bytes(path).await?;
+    let p8 = P8::parse(std::str::from_utf8(&bytes)?);
+    let sfx: Vec<_> = p8
+        .sfx()?
+        .into_iter()
+        .take(count)
+        .enumerate()
+        .map(|(j, sfx)| load_context.add_labeled_asset(format!("bank{i}/sfx{j}"), sfx))
+        .collect();
+    let music = p8
+        .music()?
+        .into_iter()
+        .map(|part| {
+            Audio::Music(MusicPattern {
+                flags: part.flags,
+                channels: part
+                    .sfx
+                    .map(|n| n.and_then(|n| sfx.get(n as usize)).cloned()),
+            })
+        })
+        .collect();
+    Ok(AudioBank {
+        sfx: sfx.into_iter().map(Audio::Sfx).collect(),
+        music,
+    })
+}
+
+/// Make an indexed sprite sheet of a Pico-8 cart's `__gfx__` and `__gff__`
+/// sections.
+async fn p8_sprite_sheet(
+    i: usize,
+    sheet: &mut config::SpriteSheet,
+    load_context: &mut LoadContext<'_>,
+) -> Result<pico8::SpriteSheet, ConfigLoaderError> {
+    let bytes = load_context.read_asset_bytes(&*sheet.path).await?;
+    let p8 = P8::parse(std::str::from_utf8(&bytes)?);
+    let gfx = p8.gfx()?;
+    let flags = p8.flags()?;
+    sheet.sprite_size.get_or_insert(pico8::PICO8_SPRITE_SIZE);
+    let layout = get_layout(
+        i,
+        UVec2::new(gfx.width as u32, gfx.height as u32),
+        &mut sheet.sprite_size,
+        sheet.sprite_counts,
+        sheet.padding,
+        sheet.offset,
+    )?
+    .map(|layout| load_context.add_labeled_asset(format!("atlas{i}"), layout));
+    Ok(pico8::SpriteSheet {
+        handle: pico8::SprHandle::Gfx(
+            load_context.add_labeled_asset(format!("spritesheet{i}"), gfx),
+        ),
+        layout: layout.unwrap_or_default(),
+        sprite_size: sheet.sprite_size.unwrap_or(pico8::PICO8_SPRITE_SIZE),
+        flags,
+        anims: HashMap::default(),
+        transparent: sheet.transparent.clone(),
+    })
+}
+
+/// Make a sprite sheet of a Tiled tileset with flags from its tiles'
+/// "p8flags" properties.
+#[cfg(feature = "level")]
+async fn tsx_sprite_sheet(
+    i: usize,
+    sheet: &config::SpriteSheet,
+    load_context: &mut LoadContext<'_>,
+) -> Result<pico8::SpriteSheet, ConfigLoaderError> {
+    let tiledset = load_context
+        .loader()
+        .immediate()
+        .load::<level::asset::TiledSet>(&*sheet.path)
+        .await?;
+    let tileset = &tiledset.get().0;
+    let tile_size = UVec2::new(tileset.tile_width, tileset.tile_height);
+    if let Some(sprite_size) = sheet.sprite_size {
+        if sprite_size != tile_size {
+            return Err(ConfigLoaderError::Message(format!(
+                "image {i} has sprite size {sprite_size} but its tiles are {tile_size}"
+            )));
+        }
+    }
+    let image = tileset
+        .image
+        .as_ref()
+        .ok_or(ConfigLoaderError::Message(format!(
+            "could not load .tsx image {i}"
+        )))?;
+    let handle = pico8::SprHandle::Image(
+        load_context
+            .loader()
+            .with_settings(pixel_art_settings)
+            .load(image.source.clone()),
+    );
+    let layout = load_context.add_labeled_asset(
+        format!("atlas{i}"),
+        level::tiled::layout_from_tileset(tileset),
+    );
+    Ok(pico8::SpriteSheet {
+        handle,
+        layout,
+        sprite_size: tile_size,
+        flags: level::tiled::flags_from_tileset(tileset),
+        anims: HashMap::default(),
+        transparent: sheet.transparent.clone(),
+    })
+}
+
+#[cfg(not(feature = "level"))]
+async fn tsx_sprite_sheet(
+    _i: usize,
+    sheet: &config::SpriteSheet,
+    _load_context: &mut LoadContext<'_>,
+) -> Result<pico8::SpriteSheet, ConfigLoaderError> {
+    Err(ConfigLoaderError::Message(format!(
+        "The image {:?} is a Tiled tileset; consider using the '--features=level' flag.",
+        &sheet.path
+    )))
+}
+
+fn is_aseprite(path: &str) -> bool {
+    path.ends_with(".aseprite") || path.ends_with(".ase")
+}
+
 fn get_layout(
     image_index: usize,
     image_size: UVec2,
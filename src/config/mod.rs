@@ -3,10 +3,16 @@ mod memory_dir;
 pub use memory_dir::*;
 mod loader;
 pub use loader::*;
+mod glob;
+pub use glob::*;
 pub mod front_matter;
 use crate::{
-    error::RunState,
+    boot::BootSequence,
+    color_filter::ColorFilter,
+    error::{ErrorMode, RunState},
     pico8::{self, Pico8Handle},
+    ui::CanvasAnchor,
+    N9Canvas,
 };
 use bevy::{asset::embedded_asset, prelude::*};
 use serde::{Deserialize, Serialize};
@@ -31,7 +37,7 @@ pub(crate) fn plugin(app: &mut App) {
 //     Content(String),
 // }
 
-#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+#[derive(Default, Debug, Clone, Deserialize, Serialize, Resource)]
 pub struct Config {
     pub name: Option<String>,
     pub frames_per_second: Option<u8>,
@@ -39,6 +45,27 @@ pub struct Config {
     pub template: Option<String>,
     pub author: Option<String>,
     pub license: Option<String>,
+    /// The 128x128 label image, e.g., for the web player
+    pub label: Option<String>,
+    pub on_error: Option<ErrorMode>,
+    /// Keep music playing while the cart is paused.
+    pub music_on_pause: Option<bool>,
+    /// Run one tick per update and avoid the wall clock so replays are
+    /// identical.
+    pub deterministic: Option<bool>,
+    /// Seed of `rnd()` when the cart starts
+    pub seed: Option<f32>,
+    /// Rasterize `print()` into the canvas on the CPU rather than drawing text
+    /// entities.
+    pub framebuffer: Option<bool>,
+    /// Draw RGB colors as the nearest color of the palette.
+    pub snap_colors: Option<bool>,
+    /// Each color's next darker color for `lighting()`, by default Pico-8's
+    pub light_ramp: Option<Vec<usize>>,
+    /// A color-blind filter over the screen unless the player sets one
+    pub color_filter: Option<ColorFilter>,
+    /// Locale of `tr()`, e.g., "en", by default the first string table's
+    pub locale: Option<String>,
     pub screen: Option<Screen>,
     pub defaults: Option<Defaults>,
     #[serde(default, rename = "palette")]
@@ -52,6 +79,67 @@ pub struct Config {
     pub audio_banks: Vec<AudioBank>,
     #[serde(default, rename = "map")]
     pub maps: Vec<Map>,
+    #[serde(default, rename = "channel")]
+    pub channels: Vec<Channel>,
+    #[serde(default, rename = "strings")]
+    pub string_tables: Vec<StringTable>,
+    /// Keys of each player, in place of Pico-8's for players 0 and 1
+    #[serde(default, rename = "keyboard")]
+    pub keyboards: Vec<Keyboard>,
+    pub boot: Option<Boot>,
+}
+
+/// The keys of one player's buttons, e.g., one zone of a keyboard shared by
+/// up to four players
+///
+/// Keys are named like Bevy's [KeyCode], e.g., "KeyA" or "ArrowLeft".
+///
+/// ```toml
+/// [[keyboard]]
+/// left = ["KeyA"]
+/// right = ["KeyD"]
+/// up = ["KeyW"]
+/// down = ["KeyS"]
+/// o = ["KeyQ"]
+/// x = ["KeyE"]
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Reflect)]
+#[serde(default)]
+pub struct Keyboard {
+    pub left: Vec<String>,
+    pub right: Vec<String>,
+    pub up: Vec<String>,
+    pub down: Vec<String>,
+    pub o: Vec<String>,
+    pub x: Vec<String>,
+}
+
+impl Keyboard {
+    /// Return the key names of each button in button order.
+    pub fn buttons(&self) -> [&[String]; 6] {
+        [
+            &self.left,
+            &self.right,
+            &self.up,
+            &self.down,
+            &self.o,
+            &self.x,
+        ]
+    }
+}
+
+/// Boot sequence shown while the cart loads
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Boot {
+    /// Seconds it lasts
+    pub duration: Option<f32>,
+    /// Whether a key press or click ends it early, true by default
+    pub skippable: Option<bool>,
+    /// Image shown instead of the logo
+    pub image: Option<String>,
+    /// Name of a boot cart registered with [BootCarts][crate::boot::BootCarts]
+    /// that runs instead of the built-in sequence
+    pub cart: Option<String>,
 }
 
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
@@ -64,15 +152,36 @@ pub struct Defaults {
 #[serde(untagged)]
 pub enum AudioBank {
     // #[serde(rename = "p8")]
-    P8 { p8: PathBuf, count: usize },
+    P8 {
+        p8: PathBuf,
+        count: usize,
+    },
     // #[serde(rename = "paths")]
-    Paths { paths: Vec<PathBuf> },
+    Paths {
+        paths: Vec<PathBuf>,
+    },
+    /// The files matching a glob, e.g., "sfx/*.ogg", in sorted order
+    Dir {
+        dir: PathBuf,
+    },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// Settings of an sfx channel in order
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Channel {
+    #[serde(default)]
+    pub fx: Vec<pico8::audio::ChannelFx>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Screen {
     pub canvas_size: UVec2,
     pub screen_size: Option<UVec2>,
+    pub anchor: Option<CanvasAnchor>,
+    /// Color outside the canvas as sRGB, e.g., `[32, 32, 32]`
+    pub letterbox_color: Option<[u8; 3]>,
+    /// Image stretched over the window behind the canvas
+    pub letterbox_image: Option<String>,
 }
 
 // #[derive(Debug, Clone, Deserialize)]
@@ -91,12 +200,17 @@ pub struct SpriteSheet {
     pub offset: Option<UVec2>,
     #[serde(default)]
     pub indexed: bool,
+    /// Colors drawn transparent until the cart calls `palt()`, e.g.,
+    /// `[0, 14]`. Color 0 by default.
+    pub transparent: Option<Vec<usize>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 // #[serde(untagged)]
 pub struct Map {
     path: PathBuf,
+    /// Tiles per row, 128 by default
+    pub columns: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -108,6 +222,15 @@ pub enum Font {
     // pub height: Option<f32>,
 }
 
+/// Strings of a locale in a TOML file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StringTable {
+    pub locale: String,
+    pub path: String,
+    /// Index of the font to print with in this locale
+    pub font: Option<usize>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Palette {
     pub path: String,
@@ -120,16 +243,20 @@ pub fn update_asset(
 
     mut next_state: ResMut<NextState<RunState>>,
     mut pico8_handle: Option<ResMut<Pico8Handle>>,
+    mut commands: Commands,
 ) {
     for e in reader.read() {
         info!("update asset event {e:?}");
         if let AssetEvent::LoadedWithDependencies { id } = e {
             if let Some(ref mut pico8_handle) = pico8_handle {
-                if let Some(_pico8_asset) = assets.get(*id) {
+                if let Some(pico8_asset) = assets.get(*id) {
                     if pico8_handle.handle.id() != *id {
                         warn!("Script loaded but does not match Pico8Handle.");
                         continue;
                     }
+                    if let Some(config) = pico8_asset.config.clone() {
+                        commands.queue(move |world: &mut World| swap_config(world, config));
+                    }
                     info!("Goto Loaded state");
                     next_state.set(RunState::Loaded);
                 } else {
@@ -142,11 +269,23 @@ pub fn update_asset(
     }
 }
 
+/// Make the config of a loaded cart the [Config] resource, e.g., after
+/// another cart is swapped in, and size the canvas to it.
+fn swap_config(world: &mut World, config: Config) {
+    if let Some(mut canvas) = world.get_resource_mut::<N9Canvas>() {
+        canvas.size = config.canvas_size();
+    }
+    world.insert_resource(config);
+}
+
 pub fn run_pico8_when_loaded(
     state: Res<State<RunState>>,
+    boot: Option<Res<BootSequence>>,
     mut next_state: ResMut<NextState<RunState>>,
 ) {
     match **state {
+        // Wait for the boot sequence.
+        RunState::Loaded if boot.is_some_and(|boot| !boot.finished) => {}
         RunState::Loaded => {
             next_state.set(RunState::Init);
         }
@@ -195,6 +334,7 @@ impl Config {
             self.screen = Some(Screen {
                 canvas_size: UVec2::splat(128),
                 screen_size: Some(UVec2::splat(512)),
+                ..default()
             });
         }
         if self.palettes.is_empty() {
@@ -226,6 +366,7 @@ impl Config {
             self.screen = Some(Screen {
                 canvas_size: UVec2::new(240, 160),
                 screen_size: Some(UVec2::new(480, 320)),
+                ..default()
             });
         }
         if self.palettes.is_empty() {
@@ -248,11 +389,112 @@ impl Config {
         config.inject_gameboy();
         config
     }
+
+    /// Return the configured canvas size.
+    pub fn canvas_size(&self) -> UVec2 {
+        self.screen
+            .as_ref()
+            .map(|s| s.canvas_size)
+            .unwrap_or(DEFAULT_CANVAS_SIZE)
+    }
+
+    /// Return the paths of the assets this config references.
+    ///
+    /// Paths with an explicit asset source like "embedded://" are omitted
+    /// since they do not live in the assets directory.
+    pub fn asset_paths(&self) -> Vec<PathBuf> {
+        let palettes = self.palettes.iter().map(|p| PathBuf::from(&p.path));
+        let fonts = self.fonts.iter().filter_map(|f| match f {
+            Font::Path { path, .. } => Some(PathBuf::from(path)),
+            Font::Default { .. } => None,
+        });
+        let sprite_sheets = self.sprite_sheets.iter().map(|s| PathBuf::from(&s.path));
+        let audio_banks = self.audio_banks.iter().flat_map(|a| match a {
+            AudioBank::P8 { p8, .. } => vec![p8.clone()],
+            AudioBank::Paths { paths } => paths.clone(),
+            AudioBank::Dir { dir } => vec![dir.clone()],
+        });
+        let maps = self.maps.iter().map(|m| m.path.clone());
+        let string_tables = self.string_tables.iter().map(|s| PathBuf::from(&s.path));
+        let images = self
+            .boot
+            .iter()
+            .filter_map(|b| b.image.as_ref())
+            .chain(
+                self.screen
+                    .iter()
+                    .filter_map(|s| s.letterbox_image.as_ref()),
+            )
+            .chain(self.label.iter())
+            .map(PathBuf::from);
+        let mut paths: Vec<PathBuf> = palettes
+            .chain(fonts)
+            .chain(sprite_sheets)
+            .chain(audio_banks)
+            .chain(maps)
+            .chain(string_tables)
+            .chain(images)
+            .filter(|p| !p.to_string_lossy().contains("://"))
+            .collect();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    /// Read the assets from the asset source `source`, e.g., "n9bundle",
+    /// by prefixing the paths that have no source with "source://".
+    pub fn set_asset_source(&mut self, source: &str) {
+        let prefix = |path: &mut String| {
+            if !path.contains("://") {
+                *path = format!("{source}://{path}");
+            }
+        };
+        let prefix_buf = |path: &mut PathBuf| {
+            let mut s = path.to_string_lossy().into_owned();
+            prefix(&mut s);
+            *path = PathBuf::from(s);
+        };
+        for palette in &mut self.palettes {
+            prefix(&mut palette.path);
+        }
+        for font in &mut self.fonts {
+            if let Font::Path { path, .. } = font {
+                prefix(path);
+            }
+        }
+        for sheet in &mut self.sprite_sheets {
+            prefix(&mut sheet.path);
+        }
+        for bank in &mut self.audio_banks {
+            match bank {
+                AudioBank::P8 { p8, .. } => prefix_buf(p8),
+                AudioBank::Paths { paths } => paths.iter_mut().for_each(prefix_buf),
+                AudioBank::Dir { dir } => prefix_buf(dir),
+            }
+        }
+        for map in &mut self.maps {
+            prefix_buf(&mut map.path);
+        }
+        for table in &mut self.string_tables {
+            prefix(&mut table.path);
+        }
+        if let Some(image) = self.boot.as_mut().and_then(|b| b.image.as_mut()) {
+            prefix(image);
+        }
+        if let Some(image) = self
+            .screen
+            .as_mut()
+            .and_then(|s| s.letterbox_image.as_mut())
+        {
+            prefix(image);
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::color_filter::ColorDeficiency;
 
     #[test]
     fn test_config_0() {
@@ -320,6 +562,25 @@ sprite_size = [8, 8]
         assert_eq!(config.sprite_sheets[0].sprite_size, Some(UVec2::splat(8)));
     }
 
+    #[test]
+    fn test_config_channel_fx() {
+        let config: Config = toml::from_str(
+            r#"
+[[channel]]
+fx = [{ type = "echo", delay = 0.25, feedback = 0.5 }]
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.channels[0].fx,
+            [pico8::audio::ChannelFx::Echo {
+                delay: 0.25,
+                feedback: 0.5,
+                mix: 0.5
+            }]
+        );
+    }
+
     #[test]
     fn test_config_3() {
         let config: Config = toml::from_str(
@@ -360,6 +621,38 @@ paths = [
         );
     }
 
+    #[test]
+    fn test_config_keyboard() {
+        let config: Config = toml::from_str(
+            r#"
+[[keyboard]]
+left = ["KeyA"]
+o = ["KeyQ", "Tab"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.keyboards.len(), 1);
+        assert_eq!(config.keyboards[0].o, ["KeyQ", "Tab"]);
+        assert!(config.keyboards[0].x.is_empty());
+    }
+
+    #[test]
+    fn test_config_audio_dir() {
+        let config: Config = toml::from_str(
+            r#"
+[[audio_bank]]
+dir = "sfx/*.ogg"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.audio_banks[0],
+            AudioBank::Dir {
+                dir: "sfx/*.ogg".into()
+            }
+        );
+    }
+
     #[test]
     fn test_config_5() {
         let config: Config = toml::from_str(
@@ -393,4 +686,150 @@ path = "blah.p8"
         assert_eq!(config.maps.len(), 2);
         assert_eq!(config.maps[0].path, PathBuf::from("blah.ldtk"));
     }
+
+    #[test]
+    fn test_config_map_columns() {
+        let config: Config = toml::from_str(
+            r#"
+[[map]]
+path = "wide.p8"
+columns = 256
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.maps[0].columns, Some(256));
+    }
+
+    #[test]
+    fn test_config_transparent() {
+        let config: Config = toml::from_str(
+            r#"
+[[image]]
+path = "sprites.png"
+indexed = true
+transparent = [0, 14]
+[[image]]
+path = "more.png"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.sprite_sheets[0].transparent, Some(vec![0, 14]));
+        assert_eq!(config.sprite_sheets[1].transparent, None);
+    }
+
+    #[test]
+    fn test_set_asset_source() {
+        let mut config: Config = toml::from_str(
+            r#"
+[[image]]
+path = "sprites.png"
+[[audio_bank]]
+p8 = "https://example.com/sfx.p8"
+count = 1
+"#,
+        )
+        .unwrap();
+        config.set_asset_source("n9bundle");
+        assert_eq!(config.sprite_sheets[0].path, "n9bundle://sprites.png");
+        assert_eq!(
+            config.audio_banks[0],
+            AudioBank::P8 {
+                p8: "https://example.com/sfx.p8".into(),
+                count: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_config_strings() {
+        let config: Config = toml::from_str(
+            r#"
+locale = "ja"
+[[strings]]
+locale = "en"
+path = "strings/en.toml"
+[[strings]]
+locale = "ja"
+path = "strings/ja.toml"
+font = 1
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.locale.as_deref(), Some("ja"));
+        assert_eq!(config.string_tables.len(), 2);
+        assert_eq!(config.string_tables[1].font, Some(1));
+    }
+
+    #[test]
+    fn test_config_on_error() {
+        let config: Config = toml::from_str(
+            r#"
+on_error = "overlay"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.on_error, Some(ErrorMode::Overlay));
+    }
+
+    #[test]
+    fn test_config_color_filter() {
+        let config: Config = toml::from_str(
+            r#"
+color_filter = { deficiency = "tritanopia" }
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.color_filter,
+            Some(ColorFilter::simulate(ColorDeficiency::Tritanopia))
+        );
+    }
+
+    #[test]
+    fn test_config_letterbox() {
+        let config: Config = toml::from_str(
+            r#"
+[screen]
+canvas_size = [128, 128]
+anchor = "top-left"
+letterbox_color = [32, 32, 32]
+"#,
+        )
+        .unwrap();
+        let screen = config.screen.unwrap();
+        assert_eq!(screen.anchor, Some(CanvasAnchor::TopLeft));
+        assert_eq!(screen.letterbox_color, Some([32, 32, 32]));
+        assert!(screen.letterbox_image.is_none());
+    }
+
+    #[test]
+    fn test_config_deterministic() {
+        let config: Config = toml::from_str(
+            r#"
+deterministic = true
+seed = 42
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.deterministic, Some(true));
+        assert_eq!(config.seed, Some(42.0));
+    }
+
+    #[test]
+    fn test_asset_paths() {
+        let mut config: Config = toml::from_str(
+            r#"
+[[image]]
+path = "sprites.png"
+[[audio_bank]]
+paths = ["blip.ogg", "sprites.png"]
+"#,
+        )
+        .unwrap();
+        config.inject_pico8();
+        assert_eq!(
+            config.asset_paths(),
+            vec![PathBuf::from("blip.ogg"), PathBuf::from("sprites.png")]
+        );
+    }
 }
@@ -1,10 +1,28 @@
-use bevy::{color::palettes::css, prelude::*, window::RequestRedraw};
+use bevy::{asset::AssetPath, color::palettes::css, prelude::*, window::RequestRedraw};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    pico8::{self, Pico8, Pico8Handle},
+    reset::ResetCart,
+    PColor,
+};
 
 pub(crate) fn plugin(app: &mut App) {
     app.init_state::<RunState>()
+        .register_type::<Halt>()
+        .register_type::<ErrorMode>()
+        .add_event::<ScriptErrored>()
+        .init_resource::<Halt>()
+        .init_resource::<ErrorMode>()
         .add_systems(Startup, spawn_error_message_layout);
 
+    #[cfg(not(target_arch = "wasm32"))]
+    install_panic_hook();
+
     if app.is_plugin_added::<WindowPlugin>() {
+        app.add_systems(OnEnter(RunState::Halted), draw_error_screen)
+            .add_systems(Update, halted_keys.run_if(in_state(RunState::Halted)))
+            .add_systems(Update, dismiss_overlay);
         app.add_systems(OnEnter(RunState::Messages), show::<ErrorMessages>)
             .add_systems(
                 OnExit(RunState::Messages),
@@ -28,6 +46,285 @@ pub enum RunState {
     ///
     /// XXX: Change name to suit.
     Messages,
+    /// A cart callback failed. The error screen stays up until the cart is
+    /// reset or reloaded.
+    Halted,
+    /// The cart is being reset. It goes back to [RunState::Loaded] once
+    /// reloaded.
+    Reset,
+}
+
+/// What to do when a cart callback fails.
+#[derive(Debug, Clone, Copy, Default, Resource, Reflect, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorMode {
+    /// Stop the cart and show the error screen.
+    #[default]
+    Halt,
+    /// Keep the cart running and show the error in an overlay that can be
+    /// dismissed with the escape key.
+    Overlay,
+}
+
+/// The error that halted the cart.
+///
+/// Applying it as a command records the message and logs it. Depending on the
+/// [ErrorMode], it then moves to [RunState::Halted] where it is drawn onto the
+/// canvas or it is shown in the error overlay.
+#[derive(Debug, Clone, Default, Resource, Reflect)]
+pub struct Halt {
+    /// The callback that failed, e.g., "_update".
+    pub callback: Option<String>,
+    pub message: String,
+    pub traceback: Option<String>,
+}
+
+/// Sent whenever a cart callback fails regardless of the [ErrorMode].
+///
+/// Host applications may read these to log, report telemetry, or show their
+/// own error UI.
+#[derive(Debug, Clone, Event)]
+pub struct ScriptErrored {
+    pub callback: Option<String>,
+    pub message: String,
+    pub traceback: Option<String>,
+    /// The asset path of the cart, if it was loaded from one.
+    pub cart: Option<AssetPath<'static>>,
+}
+
+impl Halt {
+    pub fn new(message: impl Into<String>) -> Self {
+        Halt {
+            callback: None,
+            message: message.into(),
+            traceback: None,
+        }
+    }
+
+    pub fn with_callback(mut self, callback: impl Into<String>) -> Self {
+        self.callback = Some(callback.into());
+        self
+    }
+
+    pub fn with_traceback(mut self, traceback: impl Into<String>) -> Self {
+        self.traceback = Some(traceback.into());
+        self
+    }
+}
+
+impl Command for Halt {
+    fn apply(self, world: &mut World) {
+        match &self.callback {
+            Some(callback) => error!("{callback}: {}", self.message),
+            None => error!("{}", self.message),
+        }
+        if let Some(traceback) = &self.traceback {
+            error!("{traceback}");
+        }
+        let cart = world
+            .get_resource::<Pico8Handle>()
+            .and_then(|pico8_handle| pico8_handle.handle.path())
+            .map(|path| path.clone_owned());
+        world.send_event(ScriptErrored {
+            callback: self.callback.clone(),
+            message: self.message.clone(),
+            traceback: self.traceback.clone(),
+            cart,
+        });
+        match world
+            .get_resource::<ErrorMode>()
+            .copied()
+            .unwrap_or_default()
+        {
+            ErrorMode::Halt => {
+                world.insert_resource(self);
+                world
+                    .resource_mut::<NextState<RunState>>()
+                    .set(RunState::Halted);
+            }
+            ErrorMode::Overlay => {
+                if let Err(e) = world.run_system_cached_with(show_overlay, self) {
+                    warn!("Could not show error overlay: {e}");
+                }
+            }
+        }
+    }
+}
+
+fn show_overlay(
+    In(halt): In<Halt>,
+    mut query: Query<(Entity, &mut Visibility), With<ErrorMessages>>,
+    mut last: ResMut<Halt>,
+    mut commands: Commands,
+) {
+    let Ok((id, mut visibility)) = query.get_single_mut() else {
+        return;
+    };
+    // An erroring `_update` will fail every tick. Only show it once.
+    if *visibility == Visibility::Visible && last.message == halt.message {
+        return;
+    }
+    let text = match &halt.traceback {
+        Some(traceback) => format!("{}\n{traceback}", halt.message),
+        None => halt.message.clone(),
+    };
+    commands.entity(id).with_child(Text::new(text));
+    *visibility = Visibility::Visible;
+    *last = halt;
+}
+
+fn dismiss_overlay(
+    input: Res<ButtonInput<KeyCode>>,
+    mode: Res<ErrorMode>,
+    query: Query<(Entity, &Visibility), With<ErrorMessages>>,
+    mut commands: Commands,
+) {
+    if *mode != ErrorMode::Overlay || !input.just_pressed(KeyCode::Escape) {
+        return;
+    }
+    if let Ok((id, Visibility::Visible)) = query.get_single() {
+        commands
+            .entity(id)
+            .despawn_descendants()
+            .insert(Visibility::Hidden);
+    }
+}
+
+/// Halt the cart if the piped system returns an error.
+///
+/// ```ignore
+/// app.add_systems(Update, update.pipe(halt_on_error));
+/// ```
+pub fn halt_on_error<E: std::fmt::Display>(In(result): In<Result<(), E>>, mut commands: Commands) {
+    if let Err(e) = result {
+        commands.queue(Halt::new(e.to_string()));
+    }
+}
+
+/// Like [halt_on_error] but records which callback failed.
+///
+/// ```ignore
+/// app.add_systems(Update, update.pipe(halt_on_error_in("_update")));
+/// ```
+pub fn halt_on_error_in<E: std::fmt::Display>(
+    callback: &'static str,
+) -> impl FnMut(In<Result<(), E>>, Commands) {
+    move |In(result): In<Result<(), E>>, mut commands: Commands| {
+        if let Err(e) = result {
+            commands.queue(Halt::new(e.to_string()).with_callback(callback));
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+thread_local! {
+    /// Where the last panic on this thread happened, recorded by the panic
+    /// hook. A cart system panics on the thread that catches it, so panics on
+    /// other threads cannot be mistaken for its own.
+    static PANIC_LOCATION: std::cell::Cell<Option<String>> = const { std::cell::Cell::new(None) };
+}
+
+/// Record where panics happen before handing them to the previous hook.
+#[cfg(not(target_arch = "wasm32"))]
+fn install_panic_hook() {
+    static INSTALL: std::sync::Once = std::sync::Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if let Some(location) = info.location() {
+                let _ = PANIC_LOCATION.try_with(|last| last.set(Some(format!("at {location}"))));
+            }
+            previous(info);
+        }));
+    });
+}
+
+/// Halt the cart instead of exiting if the system panics.
+///
+/// The panic is shown on the crash screen, from which the cart may be reset
+/// or the app quit. Panics cannot be caught on the web, so there the system
+/// runs as is.
+///
+/// ```ignore
+/// app.add_systems(Update, catch_panic_in("_update", update));
+/// ```
+pub fn catch_panic_in<M>(
+    callback: &'static str,
+    system: impl IntoSystem<(), (), M>,
+) -> impl FnMut(&mut World) {
+    let mut system = IntoSystem::into_system(system);
+    let mut initialized = false;
+    move |world: &mut World| {
+        if !initialized {
+            system.initialize(world);
+            initialized = true;
+        }
+        #[cfg(target_arch = "wasm32")]
+        system.run((), world);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            // Forget any panic this thread caught before.
+            PANIC_LOCATION.with(|last| last.take());
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                system.run((), world);
+            }));
+            if let Err(payload) = result {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown panic".into());
+                let mut halt = Halt::new(format!("panicked: {message}")).with_callback(callback);
+                if let Some(location) = PANIC_LOCATION.with(|last| last.take()) {
+                    halt = halt.with_traceback(location);
+                }
+                halt.apply(world);
+            }
+        }
+    }
+}
+
+/// Reset the cart with R or quit with Q while halted.
+fn halted_keys(
+    input: Res<ButtonInput<KeyCode>>,
+    mut reset: EventWriter<ResetCart>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if input.just_pressed(KeyCode::KeyR) {
+        reset.send(ResetCart);
+    } else if input.just_pressed(KeyCode::KeyQ) {
+        exit.send(AppExit::Success);
+    }
+}
+
+/// Draw the message and traceback on the canvas like Pico-8 does.
+fn draw_error_screen(halt: Res<Halt>, mut pico8: Pico8) {
+    if let Err(e) = draw_halt(&halt, &mut pico8) {
+        warn!("Could not draw error screen: {e}");
+    }
+}
+
+fn draw_halt(halt: &Halt, pico8: &mut Pico8) -> Result<(), pico8::Error> {
+    // Not every palette has sixteen colors.
+    let last = pico8.paln(None)?.saturating_sub(1);
+    let color = |n: usize| Some(PColor::Palette(n.min(last)).into());
+    pico8.camera(Some(Vec2::ZERO));
+    pico8.cls(Some(PColor::Palette(0)))?;
+    let header = match &halt.callback {
+        Some(callback) => format!("runtime error in {callback}"),
+        None => "runtime error".into(),
+    };
+    pico8.print(header, None, color(8), None, None)?;
+    for line in halt.message.lines() {
+        pico8.print(line, None, color(7), None, None)?;
+    }
+    if let Some(traceback) = &halt.traceback {
+        for line in traceback.lines() {
+            pico8.print(line, None, color(6), None, None)?;
+        }
+    }
+    pico8.print("press r to reset or q to quit", None, color(5), None, None)?;
+    Ok(())
 }
 
 #[derive(Component)]
@@ -105,3 +402,51 @@ pub fn clear_messages(query: Query<Entity, With<ErrorMessages>>, mut commands: C
     let id = query.single();
     commands.entity(id).despawn_descendants();
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn halt_sends_event() {
+        let mut world = World::new();
+        world.init_resource::<Events<ScriptErrored>>();
+        world.init_resource::<NextState<RunState>>();
+        Halt::new("attempt to call a nil value")
+            .with_callback("_update")
+            .apply(&mut world);
+        let events = world.resource::<Events<ScriptErrored>>();
+        let e = events.iter_current_update_events().next().unwrap();
+        assert_eq!(e.callback.as_deref(), Some("_update"));
+        assert_eq!(e.message, "attempt to call a nil value");
+        assert!(e.cart.is_none());
+        assert_eq!(world.resource::<Halt>().message, e.message);
+    }
+
+    #[test]
+    fn panic_halts() {
+        let mut world = World::new();
+        world.init_resource::<Events<ScriptErrored>>();
+        world.init_resource::<NextState<RunState>>();
+        let mut update = catch_panic_in("_update", || panic!("out of bananas"));
+        update(&mut world);
+        let halt = world.resource::<Halt>();
+        assert_eq!(halt.callback.as_deref(), Some("_update"));
+        assert_eq!(halt.message, "panicked: out of bananas");
+    }
+
+    #[test]
+    fn panic_location_is_the_carts() {
+        install_panic_hook();
+        let mut world = World::new();
+        world.init_resource::<Events<ScriptErrored>>();
+        world.init_resource::<NextState<RunState>>();
+        // A panic elsewhere is not blamed on the cart.
+        let _ = std::thread::spawn(|| panic!("elsewhere")).join();
+        let line = line!() + 1;
+        let mut update = catch_panic_in("_update", || panic!("out of bananas"));
+        update(&mut world);
+        let traceback = world.resource::<Halt>().traceback.clone().unwrap();
+        assert!(traceback.contains(&format!(":{line}:")), "{traceback}");
+    }
+}
@@ -0,0 +1,202 @@
+//! Cart label
+//!
+//! Like Pico-8's F7, [CaptureLabel] takes a screenshot, scales the canvas to
+//! fit 128x128, snaps it to the palette, and saves it as the cart's label.
+use crate::{
+    config::Config,
+    pico8::{Palette, Pico8Asset, Pico8Handle, Pico8State},
+    ui::CanvasViewport,
+    N9Canvas,
+};
+use bevy::{
+    asset::io::file::FileAssetReader,
+    prelude::*,
+    render::view::screenshot::{Screenshot, ScreenshotCaptured},
+    window::PrimaryWindow,
+};
+use std::{fs, io, path::Path};
+
+/// The width and height of a label
+pub const LABEL_SIZE: u32 = 128;
+
+/// A 128x128 image of palette indices
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub pixels: Vec<u8>,
+}
+
+impl Label {
+    /// Scale the `region` of `image` that shows a canvas of `canvas_size` to
+    /// fit the label and letterbox it with color 0.
+    pub fn from_region(image: &Image, region: Rect, canvas_size: UVec2, palette: &Palette) -> Self {
+        let canvas_size = canvas_size.as_vec2();
+        let scale = (Vec2::splat(LABEL_SIZE as f32) / canvas_size).min_element();
+        let offset = (Vec2::splat(LABEL_SIZE as f32) - canvas_size * scale) / 2.0;
+        let image_per_canvas = region.size() / canvas_size;
+        let mut pixels = Vec::with_capacity((LABEL_SIZE * LABEL_SIZE) as usize);
+        for y in 0..LABEL_SIZE {
+            for x in 0..LABEL_SIZE {
+                let canvas = (Vec2::new(x as f32, y as f32) + 0.5 - offset) / scale;
+                let index = if canvas.cmplt(Vec2::ZERO).any() || canvas.cmpge(canvas_size).any() {
+                    None
+                } else {
+                    let pos = (region.min + canvas * image_per_canvas).as_uvec2();
+                    image.get_color_at(pos.x, pos.y).ok().and_then(|color| {
+                        let color = color.to_srgba();
+                        palette
+                            .index_of(color.to_u8_array())
+                            .or_else(|| palette.nearest(color))
+                    })
+                };
+                pixels.push(index.unwrap_or(0) as u8);
+            }
+        }
+        Label { pixels }
+    }
+
+    /// Return the `__label__` section of a `.p8` cart: a line of digits per
+    /// row where "g" to "v" are the colors 16 to 31.
+    pub fn to_p8_section(&self) -> String {
+        let mut section = String::with_capacity(((LABEL_SIZE + 1) * LABEL_SIZE) as usize);
+        for row in self.pixels.chunks(LABEL_SIZE as usize) {
+            section.extend(
+                row.iter()
+                    .map(|&i| char::from_digit(i.min(31) as u32, 32).unwrap_or('0')),
+            );
+            section.push('\n');
+        }
+        section
+    }
+
+    /// Encode the label as an RGBA PNG.
+    pub fn to_png(&self, palette: &Palette) -> Result<Vec<u8>, png::EncodingError> {
+        let mut data = Vec::with_capacity(self.pixels.len() * 4);
+        for &i in &self.pixels {
+            data.extend(palette.data.get(i as usize).unwrap_or(&[0, 0, 0, 0xff]));
+        }
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, LABEL_SIZE, LABEL_SIZE);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&data)?;
+        Ok(bytes)
+    }
+}
+
+/// Capture the screen as the cart's label.
+///
+/// The label is written to `path` in the assets directory and set as the
+/// [Config]'s label, so it is exported with the cart.
+///
+/// ```ignore
+/// commands.queue(CaptureLabel::default());
+/// ```
+#[derive(Debug, Clone)]
+pub struct CaptureLabel {
+    pub path: String,
+}
+
+impl Default for CaptureLabel {
+    fn default() -> Self {
+        CaptureLabel {
+            path: "label.png".into(),
+        }
+    }
+}
+
+/// Sent once a [CaptureLabel] is saved.
+#[derive(Debug, Clone, Event)]
+pub struct LabelCaptured {
+    pub path: String,
+    pub label: Label,
+}
+
+impl Command for CaptureLabel {
+    fn apply(self, world: &mut World) {
+        let path = self.path;
+        world.spawn(Screenshot::primary_window()).observe(
+            move |trigger: Trigger<ScreenshotCaptured>, mut commands: Commands| {
+                let screenshot = trigger.event().0.clone();
+                let path = path.clone();
+                commands.queue(move |world: &mut World| {
+                    match save_label(world, &screenshot, &path) {
+                        Ok(label) => {
+                            info!("Saved label to {path:?}.");
+                            world.send_event(LabelCaptured { path, label });
+                        }
+                        Err(e) => warn!("Could not save label: {e}"),
+                    }
+                });
+            },
+        );
+    }
+}
+
+fn save_label(world: &mut World, screenshot: &Image, path: &str) -> Result<Label, io::Error> {
+    let scale_factor = world
+        .query_filtered::<&Window, With<PrimaryWindow>>()
+        .get_single(world)
+        .map(|window| window.scale_factor())
+        .unwrap_or(1.0);
+    let canvas_size = world.resource::<N9Canvas>().size;
+    let viewport = world.resource::<CanvasViewport>();
+    let region = Rect::from_corners(
+        viewport.canvas_to_window(Vec2::ZERO) * scale_factor,
+        viewport.canvas_to_window(canvas_size.as_vec2()) * scale_factor,
+    );
+    let palette = world
+        .get_resource::<Pico8Handle>()
+        .and_then(|pico8_handle| {
+            world
+                .resource::<Assets<Pico8Asset>>()
+                .get(&pico8_handle.handle)
+        })
+        .and_then(|asset| asset.palettes.get(world.resource::<Pico8State>().palette))
+        .ok_or_else(|| io::Error::other("no palette"))?;
+    let label = Label::from_region(screenshot, region, canvas_size, palette);
+    let bytes = label.to_png(palette).map_err(io::Error::other)?;
+    let dest = FileAssetReader::get_base_path().join("assets").join(path);
+    if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&dest, bytes)?;
+    if let Some(mut config) = world.get_resource_mut::<Config>() {
+        config.label = Some(Path::new(path).to_string_lossy().into_owned());
+    }
+    Ok(label)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bevy::render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    };
+
+    #[test]
+    fn label_letterbox() {
+        let palette = Palette::from_slice(&crate::pico8::PALETTE);
+        // A 256x128 white canvas fits the label with 32 rows of letterbox
+        // above and below.
+        let image = Image::new_fill(
+            Extent3d {
+                width: 256,
+                height: 128,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0xff, 0xf1, 0xe8, 0xff],
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::MAIN_WORLD,
+        );
+        let region = Rect::new(0.0, 0.0, 256.0, 128.0);
+        let label = Label::from_region(&image, region, UVec2::new(256, 128), &palette);
+        assert_eq!(label.pixels.len(), 128 * 128);
+        assert_eq!(label.pixels[0], 0);
+        assert_eq!(label.pixels[64 * 128], 7);
+        let section = label.to_p8_section();
+        assert_eq!(section.lines().count(), 128);
+        assert_eq!(section.lines().nth(64), Some("7".repeat(128).as_str()));
+    }
+}
@@ -0,0 +1,204 @@
+//! Export a cart and the assets it references into a folder ready to
+//! distribute.
+use crate::config::{expand_glob, Config};
+use bevy::{asset::io::file::FileAssetReader, prelude::*};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+mod label;
+pub use label::*;
+
+pub(crate) fn plugin(app: &mut App) {
+    app.add_event::<LabelCaptured>();
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExportError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("could not serialize config: {0}")]
+    Toml(#[from] toml::ser::Error),
+}
+
+/// Export the running cart for desktop targets.
+///
+/// The published layout looks like this:
+///
+/// ```text
+/// dest/
+///   my-game            <- the executable, if any
+///   assets/
+///     Nano9.toml       <- the config
+///     sprites.png      <- every asset the config references
+/// ```
+#[derive(Debug, Clone)]
+pub struct Export {
+    /// Where assets are read from.
+    pub assets_dir: PathBuf,
+    pub dest: PathBuf,
+    /// The executable to copy into `dest`.
+    pub executable: Option<PathBuf>,
+}
+
+impl Export {
+    /// Export the current executable and the default assets directory into
+    /// `dest`.
+    pub fn new(dest: impl Into<PathBuf>) -> Self {
+        Export {
+            assets_dir: FileAssetReader::get_base_path().join("assets"),
+            dest: dest.into(),
+            executable: std::env::current_exe().ok(),
+        }
+    }
+
+    /// Write the export. Returns the files written.
+    pub fn run(&self, config: &Config) -> Result<Vec<PathBuf>, ExportError> {
+        let mut written = Vec::new();
+        let assets_dest = self.dest.join("assets");
+        fs::create_dir_all(&assets_dest)?;
+        if let Some(executable) = &self.executable {
+            let file_name = executable
+                .file_name()
+                .ok_or_else(|| io::Error::other("executable has no file name"))?;
+            let path = self.dest.join(file_name);
+            fs::copy(executable, &path)?;
+            written.push(path);
+        }
+        let path = assets_dest.join("Nano9.toml");
+        fs::write(&path, toml::to_string(config)?)?;
+        written.push(path);
+        for pattern in config.asset_paths() {
+            for asset in expand_glob(&self.assets_dir, &pattern)? {
+                written.push(copy_asset(&self.assets_dir, &assets_dest, &asset)?);
+            }
+        }
+        info!("Exported {} files to {:?}.", written.len(), &self.dest);
+        Ok(written)
+    }
+}
+
+/// A web player page for a wasm build in the style of Pico-8's.
+///
+/// It shows the label until clicked, has a fullscreen button, and shows a
+/// virtual gamepad on touch devices. The canvas has the id "nano9" that the
+/// "web" feature expects.
+#[derive(Debug, Clone)]
+pub struct WebShell {
+    pub name: String,
+    /// The label image shown before the cart starts.
+    pub label: Option<PathBuf>,
+    /// The JavaScript emitted by wasm-bindgen, e.g., "my_game.js".
+    pub js: String,
+}
+
+impl WebShell {
+    pub fn new(name: impl Into<String>, js: impl Into<String>) -> Self {
+        WebShell {
+            name: name.into(),
+            label: None,
+            js: js.into(),
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<PathBuf>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Return the contents of index.html.
+    pub fn html(&self) -> String {
+        let label = self
+            .label
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map(|f| f.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        include_str!("shell.html")
+            .replace("{{loader}}", include_str!("shell.js").trim_end())
+            .replace("{{js}}", &self.js)
+            .replace("{{label}}", &label)
+            .replace("{{name}}", &escape_html(&self.name))
+    }
+
+    /// Write index.html and copy the label into `dest`. Returns the files
+    /// written.
+    pub fn write(&self, dest: &Path) -> Result<Vec<PathBuf>, ExportError> {
+        fs::create_dir_all(dest)?;
+        let mut written = Vec::new();
+        if let Some(label) = &self.label {
+            let file_name = label
+                .file_name()
+                .ok_or_else(|| io::Error::other("label has no file name"))?;
+            let path = dest.join(file_name);
+            fs::copy(label, &path)?;
+            written.push(path);
+        }
+        let path = dest.join("index.html");
+        fs::write(&path, self.html())?;
+        written.push(path);
+        Ok(written)
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn copy_asset(from_dir: &Path, to_dir: &Path, asset: &Path) -> Result<PathBuf, io::Error> {
+    let dest = to_dir.join(asset);
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(from_dir.join(asset), &dest)?;
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn export_folder() {
+        let root = std::env::temp_dir().join(format!("nano9-export-{}", std::process::id()));
+        let assets_dir = root.join("assets");
+        fs::create_dir_all(assets_dir.join("gfx")).unwrap();
+        fs::write(assets_dir.join("gfx/sprites.png"), b"png").unwrap();
+        let config: Config = toml::from_str(
+            r#"
+name = "export"
+[[image]]
+path = "gfx/sprites.png"
+"#,
+        )
+        .unwrap();
+        let export = Export {
+            assets_dir,
+            dest: root.join("dist"),
+            executable: None,
+        };
+        let written = export.run(&config).unwrap();
+        assert_eq!(written.len(), 2);
+        assert_eq!(
+            fs::read(root.join("dist/assets/gfx/sprites.png")).unwrap(),
+            b"png"
+        );
+        assert!(root.join("dist/assets/Nano9.toml").exists());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn web_shell() {
+        let html = WebShell::new("Jelpi & Co", "jelpi.js")
+            .with_label("label/jelpi.png")
+            .html();
+        assert!(html.contains("<title>Jelpi &amp; Co</title>"));
+        assert!(html.contains("import init from \"./jelpi.js\";"));
+        assert!(html.contains("url('jelpi.png')"));
+        assert!(!html.contains("{{"));
+    }
+}
@@ -0,0 +1,28 @@
+import init from "./{{js}}";
+
+const canvas = document.getElementById("nano9");
+const start = document.getElementById("start");
+
+// Browsers suspend audio until a user gesture, so start on click.
+start.addEventListener("click", () => {
+  start.remove();
+  canvas.focus();
+  init();
+}, { once: true });
+
+document.getElementById("fullscreen").addEventListener("click", () => {
+  document.getElementById("player").requestFullscreen();
+});
+
+// Show a virtual gamepad on touch devices that sends key events to the canvas.
+if (window.matchMedia("(pointer: coarse)").matches) {
+  const gamepad = document.getElementById("gamepad");
+  gamepad.classList.add("touch");
+  for (const button of gamepad.querySelectorAll("button")) {
+    const code = button.dataset.key;
+    const key = code.startsWith("Key") ? code.slice(3).toLowerCase() : code;
+    const send = (type) => canvas.dispatchEvent(new KeyboardEvent(type, { key, code, bubbles: true }));
+    button.addEventListener("touchstart", (e) => { e.preventDefault(); send("keydown"); });
+    button.addEventListener("touchend", (e) => { e.preventDefault(); send("keyup"); });
+  }
+}
@@ -0,0 +1,179 @@
+//! Game states
+//!
+//! A cart's own states, e.g., "title", "play", and "game over", each with
+//! optional enter, update, draw, and exit systems. They live inside
+//! [RunState::Run]: pausing or halting the cart suspends them, and a
+//! transition requested meanwhile happens once the cart runs again. A state
+//! system that panics halts the cart like an error would.
+//!
+//! ```ignore
+//! fn init(mut states: ResMut<GameStates>, mut commands: Commands) {
+//!     states.insert("title", GameState::default()
+//!         .on_update(commands.register_system(title_update))
+//!         .on_draw(commands.register_system(title_draw)));
+//!     states.set("title").unwrap();
+//! }
+//! ```
+use bevy::{ecs::system::SystemId, prelude::*, utils::HashMap};
+use std::borrow::Cow;
+
+use crate::{
+    error::{catch_panic_in, RunState},
+    pico8::Error,
+};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.init_resource::<GameStates>()
+        .add_systems(OnEnter(RunState::Init), reset_game_state)
+        .add_systems(
+            FixedUpdate,
+            catch_panic_in("_update", update_game_state).run_if(in_state(RunState::Run)),
+        )
+        .add_systems(
+            Update,
+            catch_panic_in("_draw", draw_game_state).run_if(in_state(RunState::Run)),
+        );
+}
+
+/// The systems of a game state
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GameState {
+    pub enter: Option<SystemId>,
+    /// Runs on the cart tick
+    pub update: Option<SystemId>,
+    /// Runs every frame
+    pub draw: Option<SystemId>,
+    pub exit: Option<SystemId>,
+}
+
+impl GameState {
+    pub fn on_enter(mut self, system: SystemId) -> Self {
+        self.enter = Some(system);
+        self
+    }
+
+    pub fn on_update(mut self, system: SystemId) -> Self {
+        self.update = Some(system);
+        self
+    }
+
+    pub fn on_draw(mut self, system: SystemId) -> Self {
+        self.draw = Some(system);
+        self
+    }
+
+    pub fn on_exit(mut self, system: SystemId) -> Self {
+        self.exit = Some(system);
+        self
+    }
+}
+
+/// The game states and which one is current
+#[derive(Debug, Default, Resource)]
+pub struct GameStates {
+    states: HashMap<Cow<'static, str>, GameState>,
+    current: Option<Cow<'static, str>>,
+    next: Option<Cow<'static, str>>,
+}
+
+impl GameStates {
+    pub fn insert(&mut self, name: impl Into<Cow<'static, str>>, state: GameState) {
+        self.states.insert(name.into(), state);
+    }
+
+    /// Go to state `name` on the next cart tick.
+    pub fn set(&mut self, name: impl Into<Cow<'static, str>>) -> Result<(), Error> {
+        let name = name.into();
+        if !self.states.contains_key(&name) {
+            return Err(Error::NoSuch(format!("game state {name:?}").into()));
+        }
+        self.next = Some(name);
+        Ok(())
+    }
+
+    /// Return the current state's name.
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    fn get_current(&self) -> Option<&GameState> {
+        self.current.as_ref().and_then(|name| self.states.get(name))
+    }
+
+    /// Make the pending transition and return the systems to run in order.
+    fn transition(&mut self) -> Vec<SystemId> {
+        let Some(next) = self.next.take() else {
+            return vec![];
+        };
+        let mut systems = vec![];
+        systems.extend(self.get_current().and_then(|state| state.exit));
+        self.current = Some(next);
+        systems.extend(self.get_current().and_then(|state| state.enter));
+        systems
+    }
+}
+
+fn update_game_state(mut states: ResMut<GameStates>, mut commands: Commands) {
+    for system in states.transition() {
+        commands.run_system(system);
+    }
+    if let Some(update) = states.get_current().and_then(|state| state.update) {
+        commands.run_system(update);
+    }
+}
+
+fn draw_game_state(states: Res<GameStates>, mut commands: Commands) {
+    // Don't draw a state before it has been entered.
+    if let Some(draw) = states.get_current().and_then(|state| state.draw) {
+        commands.run_system(draw);
+    }
+}
+
+/// Exit the current state when the cart is reset, so its init starts fresh.
+fn reset_game_state(mut states: ResMut<GameStates>, mut commands: Commands) {
+    if let Some(exit) = states.get_current().and_then(|state| state.exit) {
+        commands.run_system(exit);
+    }
+    states.current = None;
+    states.next = None;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn transition() {
+        let mut world = World::new();
+        let enter = world.register_system(|| {});
+        let exit = world.register_system(|| {});
+        let mut states = GameStates::default();
+        states.insert("title", GameState::default().on_exit(exit));
+        states.insert("play", GameState::default().on_enter(enter));
+        assert!(states.set("credits").is_err());
+        states.set("title").unwrap();
+        assert!(states.transition().is_empty());
+        assert_eq!(states.current(), Some("title"));
+        states.set("play").unwrap();
+        assert_eq!(states.current(), Some("title"));
+        assert_eq!(states.transition(), [exit, enter]);
+        assert_eq!(states.current(), Some("play"));
+    }
+
+    #[test]
+    fn panic_halts() {
+        let mut world = World::new();
+        world.init_resource::<Events<crate::error::ScriptErrored>>();
+        world.init_resource::<NextState<RunState>>();
+        let update = world.register_system(|| panic!("out of bananas"));
+        let mut states = GameStates::default();
+        states.insert("play", GameState::default().on_update(update));
+        states.set("play").unwrap();
+        world.insert_resource(states);
+        let mut update = catch_panic_in("_update", update_game_state);
+        update(&mut world);
+        let halt = world.resource::<crate::error::Halt>();
+        assert_eq!(halt.callback.as_deref(), Some("_update"));
+        assert_eq!(halt.message, "panicked: out of bananas");
+    }
+}
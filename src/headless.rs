@@ -0,0 +1,209 @@
+//! Run carts without a window
+//!
+//! [Headless] runs a cart tick by tick without a window or GPU and returns the
+//! canvas as a PNG, so carts and games can have rendering regression tests.
+//!
+//! ```ignore
+//! let mut headless = Headless::new(Config::pico8());
+//! headless.app.add_systems(OnEnter(RunState::Init), init);
+//! headless.run_until_running(100)?;
+//! headless.tick(30);
+//! assert!(headless.matches_golden("tests/golden/init.png")?);
+//! ```
+//!
+//! Only what is drawn into the canvas image is captured, e.g., `cls()`,
+//! `pset()`, and writes to screen memory. Sprites and text are rendered by the
+//! GPU and do not appear in the frame.
+use bevy::{
+    ecs::system::RunSystemOnce,
+    prelude::*,
+    render::{
+        settings::{RenderCreation, WgpuSettings},
+        RenderPlugin,
+    },
+    utils::Duration,
+    window::ExitCondition,
+    winit::WinitPlugin,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::{run_pico8_when_loaded, Config, MemoryDir},
+    error::{halt_on_error_in, RunState, ScriptErrored},
+    pico8, N9Canvas, Nano9Plugin,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum HeadlessError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("png encoding error: {0}")]
+    Encoding(#[from] png::EncodingError),
+    #[error("png decoding error: {0}")]
+    Decoding(#[from] png::DecodingError),
+    #[error("cart did not run after {0} updates")]
+    Timeout(usize),
+    #[error("no canvas")]
+    NoCanvas,
+    #[error("no golden frame at {0:?}; set NANO9_BLESS to save one")]
+    NoGolden(PathBuf),
+    #[error("could not run test: {0}")]
+    Test(String),
+}
+
+/// A windowless app running a cart
+pub struct Headless {
+    pub app: App,
+    finished: bool,
+}
+
+impl Headless {
+    /// Create a headless app. The cart runs in deterministic mode unless the
+    /// config says otherwise.
+    pub fn new(mut config: Config) -> Self {
+        config.deterministic.get_or_insert(true);
+        let mut app = App::new();
+        app.add_plugins((
+            MemoryDir::new("n9mem"),
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: ExitCondition::DontExit,
+                    close_when_requested: false,
+                })
+                .set(RenderPlugin {
+                    render_creation: RenderCreation::Automatic(WgpuSettings {
+                        backends: None,
+                        ..default()
+                    }),
+                    ..default()
+                })
+                .disable::<WinitPlugin>(),
+            Nano9Plugin { config },
+        ))
+        .add_systems(PreUpdate, run_pico8_when_loaded);
+        Headless {
+            app,
+            finished: false,
+        }
+    }
+
+    fn update(&mut self) {
+        if !self.finished {
+            self.app.finish();
+            self.app.cleanup();
+            self.finished = true;
+        }
+        self.app.update();
+    }
+
+    /// Update until the cart is loaded and running.
+    pub fn run_until_running(&mut self, max_updates: usize) -> Result<(), HeadlessError> {
+        for _ in 0..max_updates {
+            self.update();
+            if self
+                .app
+                .world()
+                .get_resource::<State<RunState>>()
+                .is_some_and(|state| **state == RunState::Run)
+            {
+                return Ok(());
+            }
+            // Give the asset loaders a chance.
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        Err(HeadlessError::Timeout(max_updates))
+    }
+
+    /// Run `n` ticks.
+    pub fn tick(&mut self, n: usize) {
+        for _ in 0..n {
+            self.update();
+        }
+    }
+
+    /// Run a cart's self-test once the cart is running.
+    ///
+    /// Returns every error the cart reported, including the test's own
+    /// failure, reported as though by a `_test` callback.
+    ///
+    /// ```ignore
+    /// fn test(mut pico8: Pico8) -> Result<(), pico8::Error> {
+    ///     pico8.cls(Some(PColor::Palette(1)))?;
+    ///     pico8.assert_pix(UVec2::ZERO, 1)
+    /// }
+    ///
+    /// assert!(headless.run_test(test)?.is_empty());
+    /// ```
+    pub fn run_test<M>(
+        &mut self,
+        test: impl IntoSystem<(), Result<(), pico8::Error>, M>,
+    ) -> Result<Vec<ScriptErrored>, HeadlessError> {
+        self.run_until_running(1000)?;
+        self.app
+            .world_mut()
+            .run_system_once(test.pipe(halt_on_error_in("_test")))
+            .map_err(|e| HeadlessError::Test(e.to_string()))?;
+        Ok(self
+            .app
+            .world_mut()
+            .resource_mut::<Events<ScriptErrored>>()
+            .drain()
+            .collect())
+    }
+
+    /// Return a copy of the canvas.
+    pub fn frame(&self) -> Option<Image> {
+        let world = self.app.world();
+        let canvas = world.get_resource::<N9Canvas>()?;
+        world
+            .resource::<Assets<Image>>()
+            .get(&canvas.handle)
+            .cloned()
+    }
+
+    /// Encode the canvas as a PNG.
+    pub fn frame_png(&self) -> Result<Vec<u8>, HeadlessError> {
+        let image = self.frame().ok_or(HeadlessError::NoCanvas)?;
+        let size = image.size();
+        let mut bytes = vec![];
+        let mut encoder = png::Encoder::new(&mut bytes, size.x, size.y);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&image.data)?;
+        Ok(bytes)
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), HeadlessError> {
+        fs::write(path, self.frame_png()?)?;
+        Ok(())
+    }
+
+    /// Compare the canvas to the PNG at `path`.
+    ///
+    /// If the environment variable `NANO9_BLESS` is set, the canvas is saved
+    /// there instead and this returns true. A missing golden is an error
+    /// otherwise.
+    pub fn matches_golden(&self, path: impl AsRef<Path>) -> Result<bool, HeadlessError> {
+        let path = path.as_ref();
+        if std::env::var_os("NANO9_BLESS").is_some() {
+            self.save_png(path)?;
+            return Ok(true);
+        }
+        if !path.exists() {
+            return Err(HeadlessError::NoGolden(path.to_path_buf()));
+        }
+        let image = self.frame().ok_or(HeadlessError::NoCanvas)?;
+        let decoder = png::Decoder::new(io::BufReader::new(fs::File::open(path)?));
+        let mut reader = decoder.read_info()?;
+        let mut golden = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut golden)?;
+        Ok(info.color_type == png::ColorType::Rgba
+            && info.bit_depth == png::BitDepth::Eight
+            && UVec2::new(info.width, info.height) == image.size()
+            && golden[..info.buffer_size()] == image.data[..])
+    }
+}
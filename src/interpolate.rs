@@ -0,0 +1,219 @@
+//! Frame interpolation
+//!
+//! A cart that ticks at 30 Hz moves its entities 30 times a second no matter
+//! how fast the display is. An entity with [Interpolate] is drawn between its
+//! last two tick transforms instead, so retained sprites move smoothly at
+//! 60 Hz and above.
+//!
+//! Retained sprites and animated sprites have one already; set
+//! [Interpolate::enabled] to false to draw one where the last tick left it.
+//! Other entities may add it. The [Nano9Camera][crate::Nano9Camera] does not
+//! have one, since a camera between pixels shifts the whole screen by part of
+//! a pixel; a cart that prefers a smooth camera to crisp pixels may add it.
+//!
+//! ```ignore
+//! commands.spawn((Sprite::default(), Transform::default(), Interpolate::default()));
+//! ```
+//!
+//! Only movement on the cart tick is smoothed. Moving an entity in any other
+//! schedule, e.g., `_draw()`, places it there at once.
+use bevy::{prelude::*, transform::TransformSystem};
+
+use crate::pico8::Determinism;
+
+pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<Interpolate>()
+        .add_systems(FixedFirst, restore_tick_transforms)
+        .add_systems(FixedLast, record_tick_transforms)
+        .add_systems(
+            PostUpdate,
+            interpolate_transforms.before(TransformSystem::TransformPropagate),
+        );
+}
+
+/// Draw an entity between its last two tick transforms.
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component)]
+pub struct Interpolate {
+    /// If false, the entity is drawn where the last tick left it.
+    pub enabled: bool,
+    #[reflect(ignore)]
+    ticks: Option<Ticks>,
+}
+
+impl Default for Interpolate {
+    fn default() -> Self {
+        Interpolate {
+            enabled: true,
+            ticks: None,
+        }
+    }
+}
+
+impl Interpolate {
+    /// Jump to the current transform without smoothing, e.g., after a
+    /// teleport or a respawn.
+    pub fn snap(&mut self) {
+        self.ticks = None;
+    }
+}
+
+/// The transforms of the last two ticks and the one last drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Ticks {
+    previous: Transform,
+    current: Transform,
+    shown: Transform,
+}
+
+impl Ticks {
+    fn new(transform: Transform) -> Self {
+        Ticks {
+            previous: transform,
+            current: transform,
+            shown: transform,
+        }
+    }
+
+    fn record(&mut self, transform: Transform) {
+        self.previous = self.current;
+        self.current = transform;
+        self.shown = transform;
+    }
+
+    /// Return the transform `s` of the way from the previous tick to the
+    /// current one.
+    fn lerp(&self, s: f32) -> Transform {
+        Transform {
+            translation: self.previous.translation.lerp(self.current.translation, s),
+            rotation: self.previous.rotation.slerp(self.current.rotation, s),
+            scale: self.previous.scale.lerp(self.current.scale, s),
+        }
+    }
+}
+
+/// Give the cart back the transforms it left on the last tick.
+fn restore_tick_transforms(mut query: Query<(&mut Transform, &Interpolate)>) {
+    for (mut transform, interpolate) in &mut query {
+        if let Some(ticks) = interpolate.ticks {
+            if *transform == ticks.shown && ticks.shown != ticks.current {
+                *transform = ticks.current;
+            }
+        }
+    }
+}
+
+fn record_tick_transforms(mut query: Query<(&Transform, &mut Interpolate)>) {
+    for (transform, mut interpolate) in &mut query {
+        match interpolate.ticks.as_mut() {
+            Some(ticks) => ticks.record(*transform),
+            None => interpolate.ticks = Some(Ticks::new(*transform)),
+        }
+    }
+}
+
+fn interpolate_transforms(
+    time: Res<Time<Fixed>>,
+    determinism: Option<Res<Determinism>>,
+    mut query: Query<(&mut Transform, &mut Interpolate)>,
+) {
+    // A deterministic cart ticks once per frame, so there is nothing between
+    // ticks to draw.
+    let s = if determinism.is_some_and(|d| d.enabled) {
+        1.0
+    } else {
+        time.overstep_fraction()
+    };
+    for (mut transform, mut interpolate) in &mut query {
+        let enabled = interpolate.enabled;
+        let Some(ticks) = interpolate.ticks.as_mut() else {
+            continue;
+        };
+        if *transform != ticks.shown {
+            // Moved outside the cart tick.
+            *ticks = Ticks::new(*transform);
+            continue;
+        }
+        let shown = if enabled {
+            ticks.lerp(s)
+        } else {
+            ticks.current
+        };
+        if shown != *transform {
+            ticks.shown = shown;
+            *transform = shown;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lerp_ticks() {
+        let mut ticks = Ticks::new(Transform::from_xyz(0.0, 0.0, 1.0));
+        ticks.record(Transform::from_xyz(4.0, -2.0, 1.0));
+        assert_eq!(ticks.lerp(0.0).translation, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(ticks.lerp(0.5).translation, Vec3::new(2.0, -1.0, 1.0));
+        assert_eq!(ticks.lerp(1.0), ticks.current);
+        ticks.record(Transform::from_xyz(8.0, -4.0, 1.0));
+        assert_eq!(ticks.previous.translation, Vec3::new(4.0, -2.0, 1.0));
+        assert_eq!(ticks.shown, ticks.current);
+    }
+
+    #[test]
+    fn interpolate_between_ticks() {
+        use bevy::time::TimeUpdateStrategy;
+        use std::time::Duration;
+
+        let mut app = App::new();
+        app.add_plugins((bevy::time::TimePlugin, plugin))
+            .insert_resource(Time::<Fixed>::from_duration(Duration::from_millis(40)))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                20,
+            )))
+            .add_systems(
+                FixedUpdate,
+                |mut query: Query<&mut Transform, With<Interpolate>>| {
+                    for mut transform in &mut query {
+                        transform.translation.x += 2.0;
+                    }
+                },
+            );
+        let smooth = app
+            .world_mut()
+            .spawn((Transform::default(), Interpolate::default()))
+            .id();
+        let stepped = app
+            .world_mut()
+            .spawn((
+                Transform::default(),
+                Interpolate {
+                    enabled: false,
+                    ..default()
+                },
+            ))
+            .id();
+        let mut xs = vec![];
+        for _ in 0..7 {
+            // The first update has no time pass; the cart ticks every other
+            // one after.
+            app.update();
+            let x = |id| app.world().get::<Transform>(id).unwrap().translation.x;
+            xs.push((x(smooth), x(stepped)));
+        }
+        assert_eq!(
+            xs,
+            [
+                (0.0, 0.0),
+                (0.0, 0.0),
+                (2.0, 2.0),
+                (2.0, 2.0),
+                (2.0, 4.0),
+                (3.0, 4.0),
+                (4.0, 6.0),
+            ]
+        );
+    }
+}
@@ -3,14 +3,24 @@
 #![allow(clippy::type_complexity)]
 pub use bevy;
 use bevy::prelude::*;
+pub mod achievement;
 mod color;
+pub mod color_filter;
+pub mod components;
 pub mod error;
+pub mod export;
 mod ext;
+pub mod game_state;
+pub mod headless;
+pub mod interpolate;
 #[cfg(feature = "level")]
 pub mod level;
 #[cfg(feature = "minibuffer")]
 pub mod minibuffer;
+#[cfg(feature = "net")]
+pub mod net;
 pub mod pico8;
+pub mod player;
 mod plugin;
 mod plugins;
 pub mod prelude;
@@ -18,16 +28,62 @@ pub mod prelude;
 pub use color::*;
 pub use ext::*;
 pub use plugin::*;
+pub mod anim;
+pub mod boot;
+pub mod bundle;
 pub mod config;
 pub mod cursor;
 pub mod raycast;
+pub mod reset;
+pub mod settings;
+pub mod split_screen;
+pub mod sprite;
+pub mod storage;
+pub mod timer;
+#[cfg(feature = "tools")]
+pub mod tools;
+pub mod ui;
+pub mod vars;
 pub use plugins::*;
 
 pub(crate) fn plugin(app: &mut App) {
     // Add other plugins.
-    app.add_plugins((config::plugin, error::plugin, pico8::plugin));
-    if app.is_plugin_added::<WindowPlugin>() {
+    app.add_plugins((
+        achievement::plugin,
+        anim::plugin,
+        boot::plugin,
+        config::plugin,
+        error::plugin,
+        export::plugin,
+        game_state::plugin,
+        split_screen::plugin,
+        storage::plugin,
+        settings::plugin,
+        timer::plugin,
+        ui::plugin,
+        pico8::plugin,
+        player::plugin,
+        reset::plugin,
+    ));
+    app.add_plugins((
+        color_filter::ColorFilterPlugin,
+        components::plugin,
+        interpolate::plugin,
+        vars::plugin,
+    ));
+    let subsystems = app
+        .world()
+        .get_resource::<Subsystems>()
+        .copied()
+        .unwrap_or_default();
+    if app.is_plugin_added::<WindowPlugin>() && subsystems.level {
         #[cfg(feature = "level")]
         app.add_plugins(level::plugin);
     }
+    #[cfg(feature = "net")]
+    app.add_plugins(net::plugin);
+    #[cfg(feature = "tools")]
+    if app.is_plugin_added::<WindowPlugin>() && subsystems.window {
+        app.add_plugins(tools::plugin);
+    }
 }
@@ -1,8 +1,22 @@
-use crate::error::RunState;
 #[cfg(feature = "scripting")]
 use crate::{call, pico8::lua::with_system_param};
-use bevy::{core::FrameCount, prelude::*};
+use crate::{
+    config::Config,
+    error::RunState,
+    export::{CaptureLabel, Export},
+    pico8::{DebugLayer, Pico8, Pico8Asset, Pico8Handle},
+    reset::ResetCart,
+    ui::CanvasViewport,
+};
+use bevy::{
+    asset::io::file::FileAssetReader,
+    core::FrameCount,
+    prelude::*,
+    render::view::screenshot::{Screenshot, ScreenshotCaptured},
+    window::PrimaryWindow,
+};
 use bevy_minibuffer::prelude::*;
+use std::path::Path;
 
 #[cfg(feature = "scripting")]
 use bevy_mod_scripting::core::{
@@ -27,6 +41,12 @@ impl Default for Nano9Acts {
         Self {
             acts: Acts::new([
                 Act::new(toggle_pause).bind(keyseq! { Space N P }),
+                Act::new(export_cart).bind(keyseq! { Space N X }),
+                Act::new(load_cart).bind(keyseq! { Space N L }),
+                Act::new(toggle_pixel_inspector).bind(keyseq! { Space N I }),
+                Act::new(toggle_debug_layer).bind(keyseq! { Space N D }),
+                Act::new(edit_sprite_flags).bind(keyseq! { Space N F }),
+                Act::new(capture_label).bind(keyseq! { F7 }),
                 #[cfg(feature = "scripting")]
                 Act::new(lua_eval).bind(keyseq! { Space N E }),
             ]),
@@ -46,6 +66,21 @@ impl ActsPlugin for Nano9Acts {
 impl Plugin for Nano9Acts {
     fn build(&self, app: &mut App) {
         self.warn_on_unused_acts();
+        app.init_resource::<PixelInspector>()
+            .init_resource::<SpriteFlagEditor>()
+            .add_systems(
+                Update,
+                (
+                    inspect_pixel.run_if(
+                        resource_exists::<Pico8Handle>
+                            .and(|inspector: Res<PixelInspector>| inspector.enabled),
+                    ),
+                    toggle_sprite_flags.run_if(
+                        resource_exists::<Pico8Handle>
+                            .and(|editor: Res<SpriteFlagEditor>| editor.sprite.is_some()),
+                    ),
+                ),
+            );
         let world = app.world_mut();
         #[cfg(feature = "scripting")]
         NamespaceBuilder::<World>::new_unregistered(world).register(
@@ -80,6 +115,248 @@ pub fn toggle_pause(
     });
 }
 
+/// Export the running cart, its config, and its assets into a folder.
+pub fn export_cart(mut minibuffer: Minibuffer) {
+    minibuffer.prompt::<TextField>("Export to: ").observe(
+        |mut trigger: Trigger<Submit<String>>,
+         config: Option<Res<Config>>,
+         mut minibuffer: Minibuffer,
+         mut commands: Commands| {
+            if let Ok(dest) = trigger.event_mut().take_result() {
+                let config = config.map(|c| c.clone()).unwrap_or_default();
+                match Export::new(dest).run(&config) {
+                    Ok(written) => minibuffer.message(format!("Exported {} files.", written.len())),
+                    Err(e) => minibuffer.message(format!("Export failed: {e}")),
+                }
+            } else {
+                commands.entity(trigger.entity()).despawn_recursive();
+            }
+        },
+    );
+}
+
+/// Shows the canvas pixel under the mouse cursor in the echo area when
+/// enabled.
+#[derive(Debug, Default, Resource)]
+pub struct PixelInspector {
+    pub enabled: bool,
+    last: Option<UVec2>,
+}
+
+/// Toggle the pixel inspector.
+pub fn toggle_pixel_inspector(mut inspector: ResMut<PixelInspector>, mut minibuffer: Minibuffer) {
+    inspector.enabled = !inspector.enabled;
+    inspector.last = None;
+    minibuffer.message(if inspector.enabled {
+        "Pixel inspector on"
+    } else {
+        "Pixel inspector off"
+    });
+}
+
+/// Show or hide what is drawn with `debug_print()`, `debug_line()`, and
+/// `debug_rect()`.
+pub fn toggle_debug_layer(mut layer: ResMut<DebugLayer>, mut minibuffer: Minibuffer) {
+    layer.visible = !layer.visible;
+    minibuffer.message(if layer.visible {
+        "Debug layer shown"
+    } else {
+        "Debug layer hidden"
+    });
+}
+
+/// Show the position, palette index, and color of the canvas pixel under the
+/// cursor whenever it changes.
+///
+/// The color is read back from the rendered frame a frame or so later, so
+/// sprites, shapes, and text are seen as well as the canvas image.
+fn inspect_pixel(
+    mut inspector: ResMut<PixelInspector>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    viewport: Res<CanvasViewport>,
+    pico8: Pico8,
+    mut commands: Commands,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let Some(pos) = window
+        .cursor_position()
+        .map(|pos| viewport.window_to_canvas(pos).floor())
+        .filter(|pos| pos.cmpge(Vec2::ZERO).all() && pos.cmplt(pico8.canvas_size().as_vec2()).all())
+        .map(|pos| pos.as_uvec2())
+    else {
+        return;
+    };
+    let Some(physical) = window.physical_cursor_position() else {
+        return;
+    };
+    if inspector.last == Some(pos) {
+        return;
+    }
+    inspector.last = Some(pos);
+    commands.spawn(Screenshot::primary_window()).observe(
+        move |trigger: Trigger<ScreenshotCaptured>, pico8: Pico8, mut minibuffer: Minibuffer| {
+            let physical = physical.as_uvec2();
+            let Ok(color) = trigger.event().0.get_color_at(physical.x, physical.y) else {
+                return;
+            };
+            let color = color.to_srgba();
+            let index = pico8
+                .palette(None)
+                .ok()
+                .and_then(|palette| palette.index_of(color.to_u8_array()));
+            let [r, g, b, a] = color.to_u8_array();
+            let rgba = format!("rgba({r}, {g}, {b}, {a})");
+            minibuffer.message(match index {
+                Some(index) => format!("({}, {}) color {index} {rgba}", pos.x, pos.y),
+                None => format!("({}, {}) {rgba} not in palette", pos.x, pos.y),
+            });
+        },
+    );
+}
+
+/// The sprite whose flags the number keys toggle
+#[derive(Debug, Default, Resource)]
+pub struct SpriteFlagEditor {
+    pub sprite: Option<usize>,
+}
+
+/// Return the flag byte as Pico-8's dots, flag 0 first.
+fn flag_dots(flags: u8) -> String {
+    (0..8)
+        .map(|bit| {
+            if flags & (1 << bit) != 0 {
+                '●'
+            } else {
+                '○'
+            }
+        })
+        .collect()
+}
+
+/// Edit the flags of a sprite of the current sheet.
+///
+/// Once a sprite is chosen, the keys 0 through 7 toggle its flags with `fset()`
+/// and Enter or Escape finishes. Changes are not saved with the cart.
+pub fn edit_sprite_flags(mut minibuffer: Minibuffer) {
+    minibuffer.prompt::<TextField>("Sprite flags of: ").observe(
+        |mut trigger: Trigger<Submit<String>>,
+         mut editor: ResMut<SpriteFlagEditor>,
+         pico8: Pico8,
+         mut minibuffer: Minibuffer,
+         mut commands: Commands| {
+            if let Ok(input) = trigger.event_mut().take_result() {
+                match input.trim().parse::<usize>() {
+                    Ok(sprite) => {
+                        editor.sprite = Some(sprite);
+                        let flags = pico8.fget(Some(sprite), None).unwrap_or(0);
+                        minibuffer.message(format!(
+                            "sprite {sprite} flags {} (0-7 toggle, enter to finish)",
+                            flag_dots(flags)
+                        ));
+                    }
+                    Err(_) => minibuffer.message(format!("No such sprite {input:?}")),
+                }
+            } else {
+                commands.entity(trigger.entity()).despawn_recursive();
+            }
+        },
+    );
+}
+
+fn toggle_sprite_flags(
+    input: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<SpriteFlagEditor>,
+    mut pico8: Pico8,
+    mut minibuffer: Minibuffer,
+) {
+    // Skip the frame the sprite was chosen, whose Enter submitted it.
+    if editor.is_changed() {
+        return;
+    }
+    let Some(sprite) = editor.sprite else {
+        return;
+    };
+    if input.any_just_pressed([KeyCode::Enter, KeyCode::Escape]) {
+        editor.sprite = None;
+        minibuffer.message(format!("Done editing sprite {sprite} flags"));
+        return;
+    }
+    const KEYS: [KeyCode; 8] = [
+        KeyCode::Digit0,
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+    ];
+    let Some(bit) = KEYS.iter().position(|key| input.just_pressed(*key)) else {
+        return;
+    };
+    let bit = bit as u8;
+    let result = pico8.fget(Some(sprite), Some(bit)).and_then(|on| {
+        pico8.fset(sprite, Some(bit), 1 - on)?;
+        pico8.fget(Some(sprite), None)
+    });
+    match result {
+        Ok(flags) => minibuffer.message(format!("sprite {sprite} flags {}", flag_dots(flags))),
+        Err(e) => minibuffer.message(format!("Could not set flag: {e}")),
+    }
+}
+
+/// Capture the screen as the cart's label like Pico-8's F7.
+pub fn capture_label(mut minibuffer: Minibuffer, mut commands: Commands) {
+    commands.queue(CaptureLabel::default());
+    minibuffer.message("Captured label.");
+}
+
+/// Return the configs under `dir` that may be loaded as carts.
+fn cart_paths(dir: &Path, prefix: &Path, paths: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let relative = prefix.join(entry.file_name());
+        if path.is_dir() {
+            cart_paths(&path, &relative, paths);
+        } else if path.extension().is_some_and(|ext| ext == "toml") {
+            paths.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+}
+
+/// Load another cart in place of the running one.
+///
+/// Once it loads, its config replaces the [Config] resource, so the canvas
+/// takes the new cart's screen size.
+pub fn load_cart(mut minibuffer: Minibuffer) {
+    let mut carts = Vec::new();
+    cart_paths(
+        &FileAssetReader::get_base_path().join("assets"),
+        Path::new(""),
+        &mut carts,
+    );
+    carts.sort();
+    minibuffer.read("Load cart: ", carts).observe(
+        |mut trigger: Trigger<Completed<String>>,
+         asset_server: Res<AssetServer>,
+         mut events: EventWriter<ResetCart>,
+         mut commands: Commands| {
+            if let Ok(path) = trigger.event_mut().take_result() {
+                let handle: Handle<Pico8Asset> = asset_server.load(path);
+                commands.insert_resource(Pico8Handle::from(handle));
+                events.send(ResetCart);
+            } else {
+                commands.entity(trigger.entity()).despawn_recursive();
+            }
+        },
+    );
+}
+
 #[cfg(feature = "scripting")]
 pub fn lua_eval(mut minibuffer: Minibuffer) {
     minibuffer.prompt::<TextField>("Lua Eval: ").observe(
@@ -0,0 +1,200 @@
+//! Networking
+//!
+//! With the "net" feature, carts may connect to a server and send and receive
+//! byte strings, e.g., for online leaderboards or simple multiplayer. Natively
+//! a connection is a UDP socket. On the web it is a WebSocket.
+//!
+//! ```ignore
+//! fn update(mut net: Net) {
+//!     let id = net.connect("127.0.0.1:7777")?;
+//!     net.send(id, b"hello")?;
+//!     while let Some(bytes) = net.recv(id)? {
+//!         info!("received {bytes:?}");
+//!     }
+//! }
+//! ```
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.init_non_send_resource::<Connections>();
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum NetError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("no connection {0}")]
+    NoConnection(usize),
+    #[error("connection error: {0}")]
+    Connection(String),
+}
+
+/// The open connections
+///
+/// It is a non-send resource because WebSockets must stay on the main thread.
+#[derive(Default)]
+pub struct Connections(Vec<Option<Connection>>);
+
+/// Connect, send, and receive
+#[derive(SystemParam)]
+pub struct Net<'w> {
+    connections: NonSendMut<'w, Connections>,
+}
+
+impl Net<'_> {
+    /// connect(address)
+    ///
+    /// Open a connection and return its id. The address is "host:port"
+    /// natively and a "ws[s]://" URL on the web.
+    pub fn connect(&mut self, address: &str) -> Result<usize, NetError> {
+        let connection = Connection::open(address)?;
+        let connections = &mut self.connections.0;
+        match connections.iter().position(Option::is_none) {
+            Some(id) => {
+                connections[id] = Some(connection);
+                Ok(id)
+            }
+            None => {
+                connections.push(Some(connection));
+                Ok(connections.len() - 1)
+            }
+        }
+    }
+
+    fn get(&mut self, id: usize) -> Result<&mut Connection, NetError> {
+        self.connections
+            .0
+            .get_mut(id)
+            .and_then(Option::as_mut)
+            .ok_or(NetError::NoConnection(id))
+    }
+
+    /// send(id, bytes)
+    pub fn send(&mut self, id: usize, bytes: &[u8]) -> Result<(), NetError> {
+        self.get(id)?.send(bytes)
+    }
+
+    /// recv(id)
+    ///
+    /// Return the next message received or None if there is none yet.
+    pub fn recv(&mut self, id: usize) -> Result<Option<Vec<u8>>, NetError> {
+        self.get(id)?.recv()
+    }
+
+    /// close(id)
+    pub fn close(&mut self, id: usize) -> Result<(), NetError> {
+        let connection = self
+            .connections
+            .0
+            .get_mut(id)
+            .and_then(Option::take)
+            .ok_or(NetError::NoConnection(id))?;
+        connection.close();
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod connection {
+    use super::NetError;
+    use std::{
+        io,
+        net::{SocketAddr, UdpSocket},
+    };
+
+    /// The largest UDP payload
+    const MAX_DATAGRAM: usize = 65_507;
+
+    pub struct Connection(UdpSocket);
+
+    impl Connection {
+        pub fn open(address: &str) -> Result<Self, NetError> {
+            let any: SocketAddr = ([0, 0, 0, 0], 0).into();
+            let socket = UdpSocket::bind(any)?;
+            socket.connect(address)?;
+            socket.set_nonblocking(true)?;
+            Ok(Connection(socket))
+        }
+
+        pub fn send(&mut self, bytes: &[u8]) -> Result<(), NetError> {
+            self.0.send(bytes)?;
+            Ok(())
+        }
+
+        pub fn recv(&mut self) -> Result<Option<Vec<u8>>, NetError> {
+            let mut buffer = vec![0; MAX_DATAGRAM];
+            match self.0.recv(&mut buffer) {
+                Ok(n) => {
+                    buffer.truncate(n);
+                    Ok(Some(buffer))
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        }
+
+        pub fn close(self) {}
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod connection {
+    use super::NetError;
+    use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+    use wasm_bindgen::{closure::Closure, JsCast};
+    use web_sys::{BinaryType, MessageEvent, WebSocket};
+
+    pub struct Connection {
+        socket: WebSocket,
+        received: Rc<RefCell<VecDeque<Vec<u8>>>>,
+        _on_message: Closure<dyn FnMut(MessageEvent)>,
+    }
+
+    fn js_error(e: wasm_bindgen::JsValue) -> NetError {
+        NetError::Connection(format!("{e:?}"))
+    }
+
+    impl Connection {
+        pub fn open(address: &str) -> Result<Self, NetError> {
+            let socket = WebSocket::new(address).map_err(js_error)?;
+            socket.set_binary_type(BinaryType::Arraybuffer);
+            let received = Rc::new(RefCell::new(VecDeque::new()));
+            let queue = received.clone();
+            let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |e: MessageEvent| {
+                let data = e.data();
+                let bytes = if let Some(buffer) = data.dyn_ref::<js_sys::ArrayBuffer>() {
+                    js_sys::Uint8Array::new(buffer).to_vec()
+                } else if let Some(text) = data.as_string() {
+                    text.into_bytes()
+                } else {
+                    return;
+                };
+                queue.borrow_mut().push_back(bytes);
+            });
+            socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+            Ok(Connection {
+                socket,
+                received,
+                _on_message: on_message,
+            })
+        }
+
+        pub fn send(&mut self, bytes: &[u8]) -> Result<(), NetError> {
+            if self.socket.ready_state() != WebSocket::OPEN {
+                return Err(NetError::Connection("websocket is not open".into()));
+            }
+            self.socket.send_with_u8_array(bytes).map_err(js_error)
+        }
+
+        pub fn recv(&mut self) -> Result<Option<Vec<u8>>, NetError> {
+            Ok(self.received.borrow_mut().pop_front())
+        }
+
+        pub fn close(self) {
+            self.socket.set_onmessage(None);
+            let _ = self.socket.close();
+        }
+    }
+}
+
+use connection::Connection;
@@ -0,0 +1,25 @@
+use super::*;
+
+use crate::achievement::Achievements;
+
+impl super::Pico8<'_, '_> {
+    /// achievement(id)
+    ///
+    /// Unlock the achievement `id`.
+    pub fn achievement(&mut self, id: impl Into<String>) {
+        let id = id.into();
+        self.commands.queue(move |world: &mut World| {
+            world.resource_mut::<Achievements>().unlock(&id);
+        });
+    }
+
+    /// achievement_stat(id, value)
+    ///
+    /// Set the stat `id`, e.g., a counter of enemies defeated.
+    pub fn achievement_stat(&mut self, id: impl Into<String>, value: f32) {
+        let id = id.into();
+        self.commands.queue(move |world: &mut World| {
+            world.resource_mut::<Achievements>().stat(&id, value);
+        });
+    }
+}
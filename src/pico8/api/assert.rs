@@ -0,0 +1,71 @@
+//! Assertions for cart self-tests
+//!
+//! A failed assertion returns [Error::Assertion]. When a self-test is run by
+//! [crate::headless::Headless::run_test], the failure is reported like any
+//! other cart error, as a [crate::error::ScriptErrored] event.
+use super::*;
+
+/// Hash image data with 64-bit FNV-1a, which is stable across platforms and
+/// releases.
+pub(crate) fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl super::Pico8<'_, '_> {
+    fn canvas_image(&self) -> Result<&Image, Error> {
+        self.images
+            .get(&self.canvas.handle)
+            .ok_or(Error::NoAsset("canvas".into()))
+    }
+
+    /// Return a hash of the canvas's pixels.
+    pub fn screen_hash(&self) -> Result<u64, Error> {
+        Ok(fnv1a(&self.canvas_image()?.data))
+    }
+
+    /// Fail unless the canvas pixel at `pos` is `color`.
+    pub fn assert_pix(&self, pos: UVec2, color: impl Into<N9Color>) -> Result<(), Error> {
+        let expected = self.get_color(color)?.to_srgba().to_u8_array();
+        let actual = self
+            .canvas_image()?
+            .get_color_at(pos.x, pos.y)?
+            .to_srgba()
+            .to_u8_array();
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(Error::Assertion(
+                format!(
+                    "pixel ({}, {}) is {actual:02x?} not {expected:02x?}",
+                    pos.x, pos.y
+                )
+                .into(),
+            ))
+        }
+    }
+
+    /// Fail unless the canvas's [screen_hash](Self::screen_hash) is `hash`.
+    pub fn assert_screen(&self, hash: u64) -> Result<(), Error> {
+        let actual = self.screen_hash()?;
+        if actual == hash {
+            Ok(())
+        } else {
+            Err(Error::Assertion(
+                format!("screen hash is {actual:#018x} not {hash:#018x}").into(),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fnv1a_hash() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    }
+}
@@ -1,4 +1,7 @@
 use super::*;
+use bevy::utils::HashMap;
+
+use crate::{anim::Anim, config::Config};
 
 #[derive(Clone, Asset, Debug, Reflect)]
 pub struct Pico8Asset {
@@ -8,11 +11,19 @@ pub struct Pico8Asset {
     pub(crate) maps: Vec<Map>,
     pub(crate) font: Vec<N9Font>,
     pub(crate) audio_banks: Vec<AudioBank>,
+    /// Effects of each sfx channel
+    pub(crate) channel_fx: Vec<Vec<ChannelFx>>,
+    pub(crate) string_tables: Vec<StringTable>,
+    /// The config the cart was loaded from
+    #[reflect(ignore)]
+    pub(crate) config: Option<Config>,
 }
 
 #[derive(Clone, Debug, Reflect)]
 pub struct N9Font {
     pub handle: Handle<Font>,
+    /// Font size to use when this font is a fallback for another
+    pub height: Option<f32>,
 }
 
 #[derive(Debug, Clone, Reflect)]
@@ -21,6 +32,10 @@ pub struct SpriteSheet {
     pub layout: Handle<TextureAtlasLayout>,
     pub sprite_size: UVec2,
     pub flags: Vec<u8>,
+    /// Animations by name, e.g., the tags of an Aseprite file
+    pub anims: HashMap<String, Anim>,
+    /// Colors drawn transparent until the cart calls `palt()`
+    pub transparent: Option<Vec<usize>>,
 }
 
 impl FromWorld for Pico8Asset {
@@ -32,10 +47,14 @@ impl FromWorld for Pico8Asset {
             border: asset_server.load_with_settings(PICO8_BORDER, pixel_art_settings),
             font: vec![N9Font {
                 handle: asset_server.load(PICO8_FONT),
+                height: None,
             }],
             audio_banks: Vec::new(),
+            channel_fx: Vec::new(),
             sprite_sheets: Vec::new(),
             maps: Vec::new(),
+            string_tables: Vec::new(),
+            config: None,
         }
     }
 }
@@ -3,6 +3,52 @@ use super::*;
 #[derive(Event, Debug)]
 pub(crate) struct UpdateCameraPos(pub(crate) Vec2);
 
+/// What the camera follows
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum FollowTarget {
+    Entity(Entity),
+    Pos(Vec2),
+}
+
+/// Move the camera toward a target every frame.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct CameraFollow {
+    pub target: FollowTarget,
+    /// Fraction of the distance to move each frame where 1 snaps to the
+    /// target.
+    pub lerp: f32,
+    /// Half the size of the box around the canvas center in which the target
+    /// may move without moving the camera
+    pub deadzone: Vec2,
+}
+
+impl CameraFollow {
+    pub fn new(target: FollowTarget) -> Self {
+        CameraFollow {
+            target,
+            lerp: 1.0,
+            deadzone: Vec2::ZERO,
+        }
+    }
+
+    pub fn with_lerp(mut self, lerp: f32) -> Self {
+        self.lerp = lerp;
+        self
+    }
+
+    pub fn with_deadzone(mut self, deadzone: Vec2) -> Self {
+        self.deadzone = deadzone;
+        self
+    }
+
+    /// Return the camera position that moves `camera` toward `target`.
+    fn step(&self, camera: Vec2, target: Vec2, canvas_size: Vec2) -> Vec2 {
+        let offset = target - (camera + canvas_size / 2.0);
+        let shift = offset - offset.clamp(-self.deadzone, self.deadzone);
+        camera + shift * self.lerp.clamp(0.0, 1.0)
+    }
+}
+
 impl super::Pico8<'_, '_> {
     pub fn camera(&mut self, pos: Option<Vec2>) -> Vec2 {
         if let Some(pos) = pos.map(pixel_snap) {
@@ -21,4 +67,58 @@ impl super::Pico8<'_, '_> {
             self.state.draw_state.camera_position
         }
     }
+
+    /// Follow a target with the camera or stop following with `None`.
+    ///
+    /// The camera moves before the cart draws each frame, so what is drawn
+    /// is never a frame behind.
+    pub fn camera_follow(&mut self, follow: Option<CameraFollow>) {
+        self.state.camera_follow = follow;
+    }
+}
+
+pub(crate) fn follow_camera(mut pico8: Pico8, transforms: Query<&GlobalTransform>) {
+    let Some(follow) = pico8.state.camera_follow else {
+        return;
+    };
+    let target = match follow.target {
+        FollowTarget::Pos(pos) => pos,
+        FollowTarget::Entity(id) => match transforms.get(id) {
+            Ok(transform) => {
+                let t = transform.translation();
+                Vec2::new(t.x, negate_y(t.y))
+            }
+            Err(_) => {
+                // The entity is gone.
+                pico8.state.camera_follow = None;
+                return;
+            }
+        },
+    };
+    let camera = pico8.state.draw_state.camera_position;
+    let next = follow.step(camera, target, pico8.canvas.size.as_vec2());
+    if pixel_snap(next) != camera {
+        pico8.camera(Some(next));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn follow_deadzone() {
+        let size = Vec2::splat(128.0);
+        let follow = CameraFollow::new(FollowTarget::Pos(Vec2::ZERO))
+            .with_deadzone(Vec2::splat(8.0))
+            .with_lerp(0.5);
+        assert_eq!(
+            follow.step(Vec2::ZERO, Vec2::new(70.0, 64.0), size),
+            Vec2::ZERO
+        );
+        assert_eq!(
+            follow.step(Vec2::ZERO, Vec2::new(84.0, 64.0), size),
+            Vec2::new(6.0, 0.0)
+        );
+    }
 }
@@ -1,9 +1,14 @@
 use super::*;
+use crate::{
+    config::{Config, DEFAULT_CANVAS_SIZE},
+    N9Canvas,
+};
 
 impl super::Pico8<'_, '_> {
     // cls([n])
     pub fn cls(&mut self, color: Option<PColor>) -> Result<(), Error> {
         trace!("cls");
+        self.charge(cost::CLS);
         let c = self.get_color(color.unwrap_or(PColor::Palette(0)))?;
         self.state.draw_state.clear_screen();
         let image = self
@@ -20,6 +25,7 @@ impl super::Pico8<'_, '_> {
     }
 
     pub fn pset(&mut self, pos: UVec2, color: impl Into<N9Color>) -> Result<(), Error> {
+        self.charge(cost::CALL);
         let c = self.get_color(color.into())?;
         let image = self
             .images
@@ -29,8 +35,30 @@ impl super::Pico8<'_, '_> {
         Ok(())
     }
 
-    // XXX: pget needed
-    // pub fn pget()
+    /// Return the color of the canvas pixel at `pos`.
+    pub(crate) fn canvas_color(&self, pos: UVec2) -> Result<Srgba, Error> {
+        let image = self
+            .images
+            .get(&self.canvas.handle)
+            .ok_or(Error::NoAsset("canvas".into()))?;
+        Ok(image.get_color_at(pos.x, pos.y)?.to_srgba())
+    }
+
+    /// pget(x, y)
+    ///
+    /// Return the palette index of the canvas pixel at `pos` or of the nearest
+    /// color if it is not in the palette. Only what is drawn into the canvas
+    /// image, e.g., by `pset()` and `cls()`, is seen, and in framebuffer mode
+    /// `print()` too. Sprites, maps, and shapes are entities rendered on the
+    /// GPU, so they are never seen, unlike with Pico-8.
+    pub fn pget(&self, pos: UVec2) -> Result<usize, Error> {
+        let color = self.canvas_color(pos)?;
+        let palette = self.palette(None)?;
+        palette
+            .index_of(color.to_u8_array())
+            .or_else(|| palette.nearest(color))
+            .ok_or(Error::NoSuch("palette color".into()))
+    }
 
     /// Return the size of the canvas
     ///
@@ -40,4 +68,51 @@ impl super::Pico8<'_, '_> {
     pub fn canvas_size(&self) -> UVec2 {
         self.canvas.size
     }
+
+    /// Resize the canvas, e.g., to a widescreen 192x108.
+    ///
+    /// The canvas image, camera, and viewport are rebuilt on the next update
+    /// and everything drawn is cleared.
+    pub fn set_canvas_size(&mut self, size: UVec2) -> Result<(), Error> {
+        if size.cmpeq(UVec2::ZERO).any() {
+            return Err(Error::InvalidArgument(
+                "canvas size must not be zero".into(),
+            ));
+        }
+        self.commands.queue(move |world: &mut World| {
+            world.resource_mut::<N9Canvas>().size = size;
+        });
+        self.commands.send_event(ClearEvent::default());
+        Ok(())
+    }
+
+    /// vid([mode])
+    ///
+    /// Return the screen mode and switch to `mode` if given like `poke(0x5f2c,
+    /// mode)`: 0 is the configured canvas size, 1 halves its width, e.g.,
+    /// 64x128, 2 halves its height, and 3 halves both. Pico-8's mirrored and
+    /// rotated modes are not supported.
+    pub fn vid(&mut self, mode: Option<u8>) -> Result<u8, Error> {
+        let last = self.state.screen_mode;
+        let Some(mode) = mode else {
+            return Ok(last);
+        };
+        let divisor = match mode {
+            0 => UVec2::ONE,
+            1 => UVec2::new(2, 1),
+            2 => UVec2::new(1, 2),
+            3 => UVec2::splat(2),
+            _ => return Err(Error::Unsupported(format!("screen mode {mode}").into())),
+        };
+        self.state.screen_mode = mode;
+        self.commands.queue(move |world: &mut World| {
+            let size = world
+                .get_resource::<Config>()
+                .map(Config::canvas_size)
+                .unwrap_or(DEFAULT_CANVAS_SIZE);
+            world.resource_mut::<N9Canvas>().size = (size / divisor).max(UVec2::ONE);
+        });
+        self.commands.send_event(ClearEvent::default());
+        Ok(last)
+    }
 }
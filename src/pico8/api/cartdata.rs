@@ -0,0 +1,97 @@
+use super::*;
+
+/// Number of values a cart may persist.
+pub const CARTDATA_LEN: usize = 64;
+
+#[derive(Clone, Debug, Reflect)]
+pub struct CartData {
+    pub id: String,
+    pub values: Vec<f32>,
+}
+
+impl CartData {
+    /// Return an error unless `id` is 1 to 64 characters of a-z, 0-9, and
+    /// underscore.
+    fn validate_id(id: &str) -> Result<(), Error> {
+        if !crate::storage::is_valid_name(id) {
+            return Err(Error::InvalidArgument(
+                format!("cartdata id {id:?}; use 1-64 of a-z, 0-9, and _").into(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn key(&self) -> String {
+        format!("cartdata/{}", self.id)
+    }
+}
+
+impl super::Pico8<'_, '_> {
+    /// cartdata(id)
+    ///
+    /// Open the persistent data for `id`. Returns true if data was previously
+    /// stored.
+    pub fn cartdata(&mut self, id: &str) -> Result<bool, Error> {
+        if self.state.cartdata.is_some() {
+            return Err(Error::Unsupported("calling cartdata() twice".into()));
+        }
+        CartData::validate_id(id)?;
+        let mut cartdata = CartData {
+            id: id.into(),
+            values: vec![0.0; CARTDATA_LEN],
+        };
+        let stored = self.persist.storage.get(&cartdata.key());
+        if let Some(stored) = &stored {
+            for (value, line) in cartdata.values.iter_mut().zip(stored.lines()) {
+                *value = line.parse().unwrap_or(0.0);
+            }
+        }
+        self.state.cartdata = Some(cartdata);
+        Ok(stored.is_some())
+    }
+
+    /// dget(index)
+    pub fn dget(&self, index: usize) -> Result<f32, Error> {
+        let cartdata = self
+            .state
+            .cartdata
+            .as_ref()
+            .ok_or(Error::NoSuch("cartdata".into()))?;
+        cartdata
+            .values
+            .get(index)
+            .copied()
+            .ok_or(Error::InvalidArgument(format!("dget index {index}").into()))
+    }
+
+    /// dset(index, value)
+    pub fn dset(&mut self, index: usize, value: f32) -> Result<(), Error> {
+        let cartdata = self
+            .state
+            .cartdata
+            .as_mut()
+            .ok_or(Error::NoSuch("cartdata".into()))?;
+        *cartdata
+            .values
+            .get_mut(index)
+            .ok_or(Error::InvalidArgument(format!("dset index {index}").into()))? = value;
+        let contents: Vec<String> = cartdata.values.iter().map(|v| v.to_string()).collect();
+        self.persist
+            .storage
+            .set(&cartdata.key(), &contents.join("\n"))
+            .map_err(|e| Error::Unsupported(format!("storage {e}").into()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cartdata_ids() {
+        assert!(CartData::validate_id("my_game").is_ok());
+        assert!(CartData::validate_id("../../x").is_err());
+        assert!(CartData::validate_id("").is_err());
+        assert!(CartData::validate_id("My Game").is_err());
+    }
+}
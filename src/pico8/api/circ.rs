@@ -11,56 +11,8 @@ impl super::Pico8<'_, '_> {
         let color = self.get_color(color.unwrap_or(N9Color::Pen))?;
         let r: UVec2 = r.into();
         let size: UVec2 = r * UVec2::splat(2) + UVec2::ONE;
-        let mut pixmap = Pixmap::new(size.x, size.y).expect("pixmap");
-        let oval =
-            tiny_skia::Rect::from_ltrb(0.0, 0.0, size.x as f32, size.y as f32).expect("circ rect");
-        let path = PathBuilder::from_oval(oval).expect("circ path");
-        let mut paint = Paint::default();
-        paint.anti_alias = false;
-        paint.set_color_rgba8(255, 255, 255, 255);
-        pixmap.fill_path(
-            &path,
-            &paint,
-            FillRule::Winding,
-            tiny_skia::Transform::identity(),
-            None,
-        );
-
-        let mut image = Image::new(
-            Extent3d {
-                width: size.x,
-                height: size.y,
-                depth_or_array_layers: 1,
-            },
-            TextureDimension::D2,
-            pixmap.take(),
-            TextureFormat::Rgba8UnormSrgb,
-            RenderAssetUsages::RENDER_WORLD,
-        );
-        image.sampler = ImageSampler::nearest();
-        let handle = self.images.add(image);
-        let clearable = Clearable::default();
-        let offset = 0.5;
-        let id = self
-            .commands
-            .spawn((
-                Name::new("circfill"),
-                Sprite {
-                    image: handle,
-                    color,
-                    anchor: Anchor::Custom(Vec2::new(
-                        -offset / size.x as f32,
-                        offset / size.y as f32,
-                    )),
-                    custom_size: Some(Vec2::new(size.x as f32, size.y as f32)),
-                    ..default()
-                },
-                Transform::from_xyz(pos.x as f32, negate_y(pos.y as f32), clearable.suggest_z()),
-                clearable,
-            ))
-            .id();
-        self.state.draw_state.mark_drawn();
-        Ok(id)
+        self.charge(cost::pixels((size.x * size.y) as f32));
+        self.draw_shape("circfill", Shape::OvalFill, pos - r.as_vec2(), size, color)
     }
 
     pub fn circ(
@@ -73,58 +25,7 @@ impl super::Pico8<'_, '_> {
         let color = self.get_color(color.unwrap_or(N9Color::Pen))?;
         let r: UVec2 = r.into();
         let size: UVec2 = r * UVec2::splat(2) + UVec2::ONE;
-        let mut pixmap = Pixmap::new(size.x, size.y).expect("pixmap");
-        let oval =
-            tiny_skia::Rect::from_ltrb(0.0, 0.0, size.x as f32, size.y as f32).expect("circ rect");
-        let path = PathBuilder::from_oval(oval).expect("circ path");
-        let mut paint = Paint::default();
-        paint.anti_alias = false;
-        paint.set_color_rgba8(255, 255, 255, 255);
-        let mut stroke = Stroke::default();
-        stroke.width = 0.0;
-        pixmap.stroke_path(
-            &path,
-            &paint,
-            &stroke,
-            tiny_skia::Transform::identity(),
-            None,
-        );
-
-        let mut image = Image::new(
-            Extent3d {
-                width: size.x,
-                height: size.y,
-                depth_or_array_layers: 1,
-            },
-            TextureDimension::D2,
-            pixmap.take(),
-            TextureFormat::Rgba8UnormSrgb,
-            RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
-        );
-        image.sampler = ImageSampler::nearest();
-
-        let offset = 0.5;
-        let handle = self.images.add(image);
-        let clearable = Clearable::default();
-        let id = self
-            .commands
-            .spawn((
-                Name::new("circ"),
-                Sprite {
-                    image: handle,
-                    color,
-                    anchor: Anchor::Custom(Vec2::new(
-                        -offset / size.x as f32,
-                        offset / size.y as f32,
-                    )),
-                    custom_size: Some(Vec2::new(size.x as f32, size.y as f32)),
-                    ..default()
-                },
-                Transform::from_xyz(pos.x as f32, negate_y(pos.y as f32), clearable.suggest_z()),
-                clearable,
-            ))
-            .id();
-        self.state.draw_state.mark_drawn();
-        Ok(id)
+        self.charge(cost::pixels((2 * (size.x + size.y)) as f32));
+        self.draw_shape("circ", Shape::Oval, pos - r.as_vec2(), size, color)
     }
 }
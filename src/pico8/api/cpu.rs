@@ -0,0 +1,134 @@
+use super::*;
+
+/// Pico-8's virtual CPU runs at 8 MHz.
+pub const CYCLES_PER_SECOND: f32 = 8_000_000.0;
+
+/// Approximate cost in cycles of Pico-8's API calls
+///
+/// These are rough figures so that `stat(1)` rises and falls with the work a
+/// cart does. They are not cycle-exact.
+pub mod cost {
+    /// Cost of calling any API function
+    pub const CALL: u32 = 4;
+    /// Cost of `cls()`, which fills the whole screen
+    pub const CLS: u32 = 1024;
+
+    /// Cost of drawing `n` pixels
+    pub fn pixels(n: f32) -> u32 {
+        CALL + (n.abs() / 2.0) as u32
+    }
+
+    /// Cost of printing `n` characters
+    pub fn chars(n: usize) -> u32 {
+        CALL + 8 * n as u32
+    }
+}
+
+/// Emulated CPU cycles spent by the cart
+#[derive(Debug, Clone, Default, Reflect)]
+pub struct CpuCycles {
+    /// Cycles spent this frame
+    pub(crate) frame: u64,
+    /// Cycles spent last frame
+    pub(crate) last_frame: u64,
+    /// Cycles available per frame
+    pub(crate) budget: f32,
+}
+
+impl CpuCycles {
+    /// Fraction of a frame used so far. It may exceed 1.0.
+    pub fn usage(&self) -> f32 {
+        self.frame as f32 / self.budget.max(1.0)
+    }
+
+    /// Fraction of the last frame used
+    pub fn last_usage(&self) -> f32 {
+        self.last_frame as f32 / self.budget.max(1.0)
+    }
+}
+
+pub(crate) fn plugin(app: &mut App) {
+    app.add_systems(First, start_frame);
+}
+
+fn start_frame(mut state: ResMut<Pico8State>, time: Res<Time<Fixed>>) {
+    let cpu = &mut state.cpu;
+    cpu.last_frame = std::mem::take(&mut cpu.frame);
+    cpu.budget = CYCLES_PER_SECOND * time.timestep().as_secs_f32();
+}
+
+impl super::Pico8<'_, '_> {
+    /// Spend `cycles` of the emulated CPU.
+    ///
+    /// API calls charge themselves. A scripting layer may also charge for the
+    /// operations of its VM.
+    pub fn charge(&mut self, cycles: u32) {
+        self.state.cpu.frame += cycles as u64;
+    }
+
+    /// stat(n)
+    ///
+    /// Only some of Pico-8's stats are supported:
+    /// - 1: CPU usage of this frame so far where 1.0 is the whole frame
+    /// - 2: CPU usage of the last frame
+    /// - 8: target cart ticks per second or 0 if uncapped
+    /// - 32-33: mouse x and y in canvas pixels, clamped to the canvas
+    /// - 34: mouse buttons held: 1 left, 2 right, 4 middle
+    /// - 35: 1 if the mouse is over the canvas or 0 if not
+    /// - 36: mouse wheel movement this frame: 1 up or -1 down
+    /// - 46-49: sfx playing on channels 0-3 or -1
+    /// - 50-53: note playing on channels 0-3 or -1
+    /// - 54: music pattern playing or -1
+    /// - 55: music patterns played since `music()` started
+    /// - 56: ticks played of the current music pattern
+    pub fn stat(&self, n: u8) -> Result<f32, Error> {
+        match n {
+            1 => Ok(self.state.cpu.usage()),
+            2 => Ok(self.state.cpu.last_usage()),
+            8 => Ok(self.state.fps as f32),
+            32 => Ok(self.state.mouse.pos.x as f32),
+            33 => Ok(self.state.mouse.pos.y as f32),
+            34 => Ok(self.state.mouse.buttons as f32),
+            35 => Ok(if self.state.mouse.inside { 1.0 } else { 0.0 }),
+            36 => Ok(self.state.mouse.wheel as f32),
+            46..=53 => {
+                let stats = &self.state.audio_stats;
+                let playing = if n < 50 {
+                    &stats.sfx[..]
+                } else {
+                    &stats.note[..]
+                };
+                let channel = ((n - 46) % 4) as usize;
+                Ok(playing
+                    .get(channel)
+                    .copied()
+                    .flatten()
+                    .map_or(-1.0, |i| i as f32))
+            }
+            54 => Ok(self
+                .state
+                .audio_stats
+                .pattern
+                .map_or(-1.0, |pattern| pattern as f32)),
+            55 => Ok(self.state.audio_stats.patterns as f32),
+            56 => Ok(self.state.audio_stats.ticks as f32),
+            _ => Err(Error::UnsupportedStat(n)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cpu_usage() {
+        let mut cpu = CpuCycles {
+            budget: CYCLES_PER_SECOND / 30.0,
+            ..default()
+        };
+        cpu.frame = cost::pixels(128.0 * 128.0) as u64 * 10;
+        assert!((cpu.usage() - 0.307).abs() < 0.001);
+        assert_eq!(cpu.last_usage(), 0.0);
+    }
+}
@@ -0,0 +1,130 @@
+//! Debug draw layer
+//!
+//! `debug_print()`, `debug_line()`, and `debug_rect()` draw above the cart on
+//! a layer that `cls()` and [ClearEvent] do not clear. It is cleared at the
+//! start of every frame instead, so draw to it every frame like the cart's own
+//! draw system. Hide it with [DebugLayer].
+use super::*;
+use crate::pico8::{Clearable, DrawTag, OVERLAY_Z};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<DebugLayer>()
+        .init_resource::<DebugLayer>()
+        .add_systems(First, clear_debug_layer)
+        .add_systems(
+            PostUpdate,
+            show_debug_layer.before(bevy::render::view::VisibilitySystems::VisibilityPropagate),
+        );
+}
+
+/// Draw above everything but the cursor sprite.
+const DEBUG_Z: f32 = OVERLAY_Z + 1.0;
+
+/// Whether the debug layer is shown
+#[derive(Debug, Clone, Copy, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct DebugLayer {
+    pub visible: bool,
+}
+
+impl Default for DebugLayer {
+    fn default() -> Self {
+        DebugLayer { visible: true }
+    }
+}
+
+/// Marks an entity drawn on the debug layer.
+#[derive(Debug, Component)]
+pub struct DebugDrawn;
+
+fn clear_debug_layer(query: Query<Entity, With<DebugDrawn>>, mut commands: Commands) {
+    for id in &query {
+        commands.entity(id).despawn_recursive();
+    }
+}
+
+fn show_debug_layer(
+    layer: Res<DebugLayer>,
+    mut visibilities: Query<&mut Visibility, With<DebugDrawn>>,
+) {
+    let visibility = if layer.visible {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    for mut v in &mut visibilities {
+        v.set_if_neq(visibility);
+    }
+}
+
+impl super::Pico8<'_, '_> {
+    /// Run `draw` without charging the cart or tagging what is drawn, then
+    /// move the drawn entity onto the debug layer.
+    fn debug_draw(
+        &mut self,
+        draw: impl FnOnce(&mut Self) -> Result<Entity, Error>,
+    ) -> Result<Entity, Error> {
+        let cycles = self.state.cpu.frame;
+        let tag = self.state.draw_state.tag.take();
+        let result = draw(self);
+        self.state.cpu.frame = cycles;
+        self.state.draw_state.tag = tag;
+        let id = result?;
+        self.commands
+            .entity(id)
+            .queue(|mut entity: EntityWorldMut| {
+                if let Some(mut transform) = entity.get_mut::<Transform>() {
+                    transform.translation.z = DEBUG_Z;
+                }
+            })
+            .remove::<(Clearable, DrawTag)>()
+            .insert(DebugDrawn);
+        Ok(id)
+    }
+
+    /// debug_print(text, x, y, [color])
+    ///
+    /// Print on the debug layer. Unlike `print()`, the print cursor does not
+    /// move and the text is never drawn into the canvas.
+    pub fn debug_print(
+        &mut self,
+        text: impl Into<String>,
+        pos: Vec2,
+        color: Option<N9Color>,
+    ) -> Result<Entity, Error> {
+        let text = text.into();
+        self.debug_draw(|pico8| {
+            let id = pico8.commands.spawn_empty().id();
+            pico8.commands.queue(move |world: &mut World| {
+                let cursor = world.resource::<Pico8State>().draw_state.print_cursor;
+                if let Err(e) =
+                    Self::print_world(world, Some(id), text, Some(pos), color, &default())
+                {
+                    warn!("debug_print error {e}");
+                }
+                world.resource_mut::<Pico8State>().draw_state.print_cursor = cursor;
+            });
+            Ok(id)
+        })
+    }
+
+    /// debug_line(x0, y0, x1, y1, [color])
+    pub fn debug_line(
+        &mut self,
+        a: IVec2,
+        b: IVec2,
+        color: Option<N9Color>,
+    ) -> Result<Entity, Error> {
+        self.debug_draw(|pico8| pico8.line(a, b, color))
+    }
+
+    /// debug_rect(x0, y0, x1, y1, [color])
+    pub fn debug_rect(
+        &mut self,
+        upper_left: Vec2,
+        lower_right: Vec2,
+        color: Option<N9Color>,
+    ) -> Result<Entity, Error> {
+        self.debug_draw(|pico8| pico8.rect(upper_left, lower_right, color))
+    }
+}
@@ -27,4 +27,6 @@ pub enum Error {
     UnsupportedPeek(usize),
     #[error("unsupported stat at address {0}")]
     UnsupportedStat(u8),
+    #[error("assertion failed: {0}")]
+    Assertion(Cow<'static, str>),
 }
@@ -0,0 +1,156 @@
+use super::*;
+use bevy::utils::HashMap;
+
+/// Strings of one locale
+#[derive(Debug, Clone, Default, Reflect)]
+pub struct StringTable {
+    pub locale: String,
+    pub strings: HashMap<String, String>,
+    /// Font to print with in this locale unless another is given
+    pub font: Option<usize>,
+}
+
+impl StringTable {
+    /// Read a TOML string table. Keys of nested tables are joined with dots,
+    /// so `[menu] start = "Start"` is "menu.start".
+    pub fn from_toml(locale: impl Into<String>, s: &str) -> Result<Self, toml::de::Error> {
+        fn flatten(prefix: &str, table: toml::Table, strings: &mut HashMap<String, String>) {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                match value {
+                    toml::Value::String(s) => {
+                        strings.insert(key, s);
+                    }
+                    toml::Value::Table(table) => flatten(&key, table, strings),
+                    value => {
+                        strings.insert(key, value.to_string());
+                    }
+                }
+            }
+        }
+        let mut strings = HashMap::default();
+        flatten("", s.parse()?, &mut strings);
+        Ok(StringTable {
+            locale: locale.into(),
+            strings,
+            font: None,
+        })
+    }
+}
+
+/// Replace "{1}", "{2}", ... in `template` with `args`.
+fn substitute(template: &str, args: &[String]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let arg = rest.find('}').and_then(|end| {
+            let n: usize = rest[1..end].parse().ok()?;
+            Some((args.get(n.checked_sub(1)?)?, end))
+        });
+        match arg {
+            Some((arg, end)) => {
+                result.push_str(arg);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+impl Pico8Asset {
+    /// Return the string table for `locale`, its language, e.g., "pt" for
+    /// "pt-BR", or the first one.
+    pub(crate) fn string_table(&self, locale: Option<&str>) -> Option<&StringTable> {
+        locale
+            .and_then(|locale| {
+                let language = locale.split(['-', '_']).next().unwrap_or(locale);
+                self.string_tables
+                    .iter()
+                    .find(|table| table.locale == locale)
+                    .or_else(|| {
+                        self.string_tables
+                            .iter()
+                            .find(|table| table.locale == language)
+                    })
+            })
+            .or_else(|| self.string_tables.first())
+    }
+
+    /// The font of the locale's string table
+    pub(crate) fn locale_font(&self, locale: Option<&str>) -> Option<usize> {
+        self.string_table(locale).and_then(|table| table.font)
+    }
+}
+
+impl super::Pico8<'_, '_> {
+    /// tr(key, ...)
+    ///
+    /// Return the string for `key` in the current locale with "{1}", "{2}",
+    /// ... replaced by `args`. A key missing from the locale falls back to the
+    /// first string table, then to the key itself.
+    pub fn tr(&self, key: &str, args: &[impl std::fmt::Display]) -> Result<String, Error> {
+        let pico8_asset = self.pico8_asset()?;
+        let template = pico8_asset
+            .string_table(self.state.locale.as_deref())
+            .and_then(|table| table.strings.get(key))
+            .or_else(|| {
+                pico8_asset
+                    .string_tables
+                    .first()
+                    .and_then(|table| table.strings.get(key))
+            })
+            .map(String::as_str)
+            .unwrap_or(key);
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        Ok(substitute(template, &args))
+    }
+
+    /// locale([locale])
+    ///
+    /// Return the current locale and set it if given.
+    pub fn locale(&mut self, locale: Option<&str>) -> Option<String> {
+        let last = self.state.locale.clone();
+        if let Some(locale) = locale {
+            self.state.locale = Some(locale.to_owned());
+        }
+        last
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_substitute() {
+        let args = ["3".to_string(), "lives".to_string()];
+        assert_eq!(substitute("{1} {2} left", &args), "3 lives left");
+        assert_eq!(substitute("{3} {x} {", &args), "{3} {x} {");
+    }
+
+    #[test]
+    fn test_string_table() {
+        let table = StringTable::from_toml(
+            "en",
+            r#"
+title = "Nano-9"
+[menu]
+start = "Start"
+"#,
+        )
+        .unwrap();
+        assert_eq!(table.strings["title"], "Nano-9");
+        assert_eq!(table.strings["menu.start"], "Start");
+    }
+}
@@ -0,0 +1,476 @@
+use super::*;
+use bevy::input::InputSystem;
+
+use crate::{
+    config::{Config, Keyboard},
+    player::{PlayerSlots, PLAYER_COUNT},
+    settings::Settings,
+};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.init_resource::<KeyLayout>()
+        .add_systems(
+            PreUpdate,
+            (
+                apply_keys.run_if(
+                    resource_exists_and_changed::<Config>
+                        .or(resource_exists_and_changed::<Settings>),
+                ),
+                read_buttons.run_if(resource_exists::<Pico8Handle>),
+            )
+                .chain()
+                .after(InputSystem),
+        )
+        .add_systems(
+            FixedPreUpdate,
+            tick_buttons.run_if(resource_exists::<Pico8Handle>),
+        );
+}
+
+/// Pico-8's buttons: left, right, up, down, O, and X
+pub const BUTTON_COUNT: usize = 6;
+
+/// Ticks a button is held before `btnp()` repeats
+const REPEAT_DELAY: u16 = 15;
+/// Ticks between repeats of `btnp()`
+const REPEAT_PERIOD: u16 = 4;
+
+/// Pico-8's keys for players 0 and 1 in button order
+const KEYS: [[&[KeyCode]; BUTTON_COUNT]; 2] = {
+    use KeyCode::*;
+    [
+        [
+            &[ArrowLeft],
+            &[ArrowRight],
+            &[ArrowUp],
+            &[ArrowDown],
+            &[KeyZ, KeyC, KeyN],
+            &[KeyX, KeyV, KeyM],
+        ],
+        [
+            &[KeyS],
+            &[KeyF],
+            &[KeyE],
+            &[KeyD],
+            &[ShiftLeft, Tab],
+            &[KeyA, KeyQ],
+        ],
+    ]
+};
+
+/// The keys of each player's buttons
+///
+/// By default players 0 and 1 have Pico-8's keys. A config's `[[keyboard]]`
+/// entries replace them, so up to [PLAYER_COUNT] players may share one
+/// keyboard.
+#[derive(Debug, Clone, PartialEq, Eq, Resource)]
+pub struct KeyLayout {
+    /// The keys of each button of player `p` at index `p`
+    pub players: Vec<[Vec<KeyCode>; BUTTON_COUNT]>,
+}
+
+impl Default for KeyLayout {
+    fn default() -> Self {
+        KeyLayout {
+            players: KEYS
+                .into_iter()
+                .map(|player| player.map(<[KeyCode]>::to_vec))
+                .collect(),
+        }
+    }
+}
+
+impl KeyLayout {
+    /// Make the layout of `keyboards`, or the default one if there are none.
+    pub fn from_keyboards(keyboards: &[Keyboard]) -> Result<Self, Error> {
+        if keyboards.is_empty() {
+            return Ok(KeyLayout::default());
+        }
+        if keyboards.len() > PLAYER_COUNT {
+            return Err(Error::InvalidArgument(
+                format!("{} keyboards for {PLAYER_COUNT} players", keyboards.len()).into(),
+            ));
+        }
+        let players = keyboards
+            .iter()
+            .map(|keyboard| -> Result<_, Error> {
+                let mut keys: [Vec<KeyCode>; BUTTON_COUNT] = Default::default();
+                for (codes, names) in keys.iter_mut().zip(keyboard.buttons()) {
+                    *codes = names
+                        .iter()
+                        .map(|name| {
+                            key_code(name).ok_or_else(|| {
+                                Error::InvalidArgument(format!("key {name:?}").into())
+                            })
+                        })
+                        .collect::<Result<_, Error>>()?;
+                }
+                Ok(keys)
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(KeyLayout { players })
+    }
+}
+
+/// Return the key named like its [KeyCode] variant, e.g., "KeyA".
+pub fn key_code(name: &str) -> Option<KeyCode> {
+    use KeyCode::*;
+    const LETTERS: [KeyCode; 26] = [
+        KeyA, KeyB, KeyC, KeyD, KeyE, KeyF, KeyG, KeyH, KeyI, KeyJ, KeyK, KeyL, KeyM, KeyN, KeyO,
+        KeyP, KeyQ, KeyR, KeyS, KeyT, KeyU, KeyV, KeyW, KeyX, KeyY, KeyZ,
+    ];
+    const DIGITS: [KeyCode; 10] = [
+        Digit0, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9,
+    ];
+    const NUMPAD: [KeyCode; 10] = [
+        Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    ];
+    let single = |rest: &str, first: u8| {
+        let [c] = rest.as_bytes() else {
+            return None;
+        };
+        c.checked_sub(first).map(usize::from)
+    };
+    if let Some(i) = name.strip_prefix("Key").and_then(|rest| single(rest, b'A')) {
+        return LETTERS.get(i).copied();
+    }
+    if let Some(i) = name
+        .strip_prefix("Digit")
+        .and_then(|rest| single(rest, b'0'))
+    {
+        return DIGITS.get(i).copied();
+    }
+    if let Some(i) = name
+        .strip_prefix("Numpad")
+        .and_then(|rest| single(rest, b'0'))
+    {
+        return NUMPAD.get(i).copied();
+    }
+    Some(match name {
+        "ArrowLeft" => ArrowLeft,
+        "ArrowRight" => ArrowRight,
+        "ArrowUp" => ArrowUp,
+        "ArrowDown" => ArrowDown,
+        "Space" => Space,
+        "Enter" => Enter,
+        "Tab" => Tab,
+        "Backspace" => Backspace,
+        "ShiftLeft" => ShiftLeft,
+        "ShiftRight" => ShiftRight,
+        "ControlLeft" => ControlLeft,
+        "ControlRight" => ControlRight,
+        "AltLeft" => AltLeft,
+        "AltRight" => AltRight,
+        "Comma" => Comma,
+        "Period" => Period,
+        "Slash" => Slash,
+        "Semicolon" => Semicolon,
+        "Quote" => Quote,
+        "BracketLeft" => BracketLeft,
+        "BracketRight" => BracketRight,
+        "Backslash" => Backslash,
+        "Backquote" => Backquote,
+        "Minus" => Minus,
+        "Equal" => Equal,
+        "NumpadEnter" => NumpadEnter,
+        "NumpadAdd" => NumpadAdd,
+        "NumpadSubtract" => NumpadSubtract,
+        "NumpadMultiply" => NumpadMultiply,
+        "NumpadDivide" => NumpadDivide,
+        "NumpadDecimal" => NumpadDecimal,
+        _ => return None,
+    })
+}
+
+/// Gamepad buttons in button order
+const GAMEPAD_BUTTONS: [GamepadButton; BUTTON_COUNT] = {
+    use GamepadButton::*;
+    [DPadLeft, DPadRight, DPadUp, DPadDown, South, East]
+};
+
+/// How far the left stick tilts before it presses a direction
+const STICK_THRESHOLD: f32 = 0.5;
+
+/// Return a gamepad's buttons as bits like [KEYS]. The left stick presses
+/// the directions as well as the d-pad.
+fn gamepad_bits(gamepad: &Gamepad) -> u8 {
+    let stick = gamepad.left_stick();
+    let tilted = [
+        stick.x < -STICK_THRESHOLD,
+        stick.x > STICK_THRESHOLD,
+        stick.y > STICK_THRESHOLD,
+        stick.y < -STICK_THRESHOLD,
+    ];
+    GAMEPAD_BUTTONS
+        .into_iter()
+        .enumerate()
+        .filter(|(b, button)| gamepad.pressed(*button) || tilted.get(*b) == Some(&true))
+        .fold(0, |bits, (b, _)| bits | 1 << b)
+}
+
+/// The buttons of every player as the cart sees them
+///
+/// Input is read every frame but the cart ticks at its own rate, so a button
+/// pressed and released between two ticks still counts as down for one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Buttons {
+    /// Bit `b` of `raw[p]` is set while button `b` of player `p` is down.
+    raw: [u8; PLAYER_COUNT],
+    /// Buttons pressed since the last tick
+    tapped: [u8; PLAYER_COUNT],
+    /// Ticks each button has been down, counting from 1
+    ticks: [[u16; BUTTON_COUNT]; PLAYER_COUNT],
+}
+
+impl Buttons {
+    fn read(&mut self, read: [u8; PLAYER_COUNT]) {
+        for (tapped, read) in self.tapped.iter_mut().zip(read) {
+            *tapped |= read;
+        }
+        self.raw = read;
+    }
+
+    fn tick(&mut self) {
+        for p in 0..PLAYER_COUNT {
+            let down = self.raw[p] | self.tapped[p];
+            for (b, ticks) in self.ticks[p].iter_mut().enumerate() {
+                *ticks = if down & (1 << b) != 0 {
+                    ticks.saturating_add(1)
+                } else {
+                    0
+                };
+            }
+        }
+        self.tapped = [0; PLAYER_COUNT];
+    }
+
+    fn down(&self, b: usize, p: usize) -> bool {
+        self.ticks[p][b] > 0
+    }
+
+    /// Return true on the tick `b` is pressed and then every few ticks while
+    /// it is held.
+    fn pressed(&self, b: usize, p: usize) -> bool {
+        let ticks = self.ticks[p][b];
+        ticks == 1 || (ticks > REPEAT_DELAY && (ticks - REPEAT_DELAY - 1) % REPEAT_PERIOD == 0)
+    }
+
+    /// Return players 0 and 1's buttons in bits 0-5 and 8-13.
+    fn bits(&self, f: impl Fn(&Self, usize, usize) -> bool) -> u16 {
+        (0..2)
+            .flat_map(|p| (0..BUTTON_COUNT).map(move |b| (b, p)))
+            .filter(|(b, p)| f(self, *b, *p))
+            .fold(0, |bits, (b, p)| bits | 1 << (b + 8 * p))
+    }
+}
+
+impl super::Pico8<'_, '_> {
+    /// btn(b, [p])
+    ///
+    /// Return true while button `b` of player `p`, 0 by default, is down.
+    pub fn btn(&self, b: u8, p: Option<u8>) -> Result<bool, Error> {
+        let (b, p) = button(b, p)?;
+        Ok(self.state.buttons.down(b, p))
+    }
+
+    /// btnp(b, [p])
+    ///
+    /// Return true on the tick button `b` of player `p` is pressed. Held, it
+    /// repeats after 15 ticks and then every 4 like Pico-8.
+    pub fn btnp(&self, b: u8, p: Option<u8>) -> Result<bool, Error> {
+        let (b, p) = button(b, p)?;
+        Ok(self.state.buttons.pressed(b, p))
+    }
+
+    /// btn()
+    ///
+    /// Return the buttons down as a bitfield: bits 0-5 are player 0's buttons
+    /// and bits 8-13 are player 1's.
+    pub fn btn_bits(&self) -> u16 {
+        self.state.buttons.bits(Buttons::down)
+    }
+
+    /// btnp()
+    ///
+    /// Return the buttons pressed as a bitfield like [Self::btn_bits].
+    pub fn btnp_bits(&self) -> u16 {
+        self.state.buttons.bits(Buttons::pressed)
+    }
+}
+
+fn button(b: u8, p: Option<u8>) -> Result<(usize, usize), Error> {
+    let p = p.unwrap_or(0);
+    if b as usize >= BUTTON_COUNT {
+        return Err(Error::NoSuchButton(b));
+    }
+    if p as usize >= PLAYER_COUNT {
+        return Err(Error::NoSuch(format!("player {p}").into()));
+    }
+    Ok((b as usize, p as usize))
+}
+
+/// Lay out the keys of the player's settings if any or else the config's.
+fn apply_keys(
+    config: Option<Res<Config>>,
+    settings: Option<Res<Settings>>,
+    mut layout: ResMut<KeyLayout>,
+) {
+    let keyboards = settings
+        .as_deref()
+        .map(|settings| settings.keyboards.as_slice())
+        .filter(|keyboards| !keyboards.is_empty())
+        .or(config.as_deref().map(|config| config.keyboards.as_slice()))
+        .unwrap_or_default();
+    match KeyLayout::from_keyboards(keyboards) {
+        Ok(keys) => *layout = keys,
+        Err(e) => warn!("Could not use the configured keyboards: {e}"),
+    }
+}
+
+/// Read the keys of every player and the gamepad of every claimed player
+/// slot.
+fn read_buttons(
+    keys: Option<Res<ButtonInput<KeyCode>>>,
+    layout: Res<KeyLayout>,
+    slots: Option<Res<PlayerSlots>>,
+    gamepads: Query<&Gamepad>,
+    mut state: ResMut<Pico8State>,
+) {
+    let mut read = [0; PLAYER_COUNT];
+    if let Some(keys) = keys {
+        for (bits, player) in read.iter_mut().zip(&layout.players) {
+            for (b, codes) in player.iter().enumerate() {
+                if keys.any_pressed(codes.iter().copied()) {
+                    *bits |= 1 << b;
+                }
+            }
+        }
+    }
+    if let Some(slots) = slots {
+        for (slot, id) in slots.iter() {
+            if let (Some(bits), Ok(gamepad)) = (read.get_mut(slot), gamepads.get(id)) {
+                *bits |= gamepad_bits(gamepad);
+            }
+        }
+    }
+    // Nothing watches the buttons for changes, so do not mark the state
+    // changed every frame.
+    let state = state.bypass_change_detection();
+    if state.buttons.raw != read {
+        state.buttons.read(read);
+    }
+}
+
+fn tick_buttons(mut state: ResMut<Pico8State>) {
+    state.bypass_change_detection().buttons.tick();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn read(buttons: &mut Buttons, p0: u8) {
+        let mut read = [0; PLAYER_COUNT];
+        read[0] = p0;
+        buttons.read(read);
+    }
+
+    #[test]
+    fn tap_between_ticks() {
+        let mut buttons = Buttons::default();
+        read(&mut buttons, 0b10);
+        read(&mut buttons, 0);
+        buttons.tick();
+        assert!(buttons.down(1, 0));
+        assert!(buttons.pressed(1, 0));
+        buttons.tick();
+        assert!(!buttons.down(1, 0));
+    }
+
+    #[test]
+    fn btnp_repeats() {
+        let mut buttons = Buttons::default();
+        read(&mut buttons, 0b10000);
+        let pressed: Vec<u16> = (1..=24)
+            .filter(|_| {
+                buttons.tick();
+                buttons.pressed(4, 0)
+            })
+            .collect();
+        assert_eq!(pressed, [1, 16, 20, 24]);
+    }
+
+    #[test]
+    fn button_bits() {
+        let mut buttons = Buttons::default();
+        let mut read = [0; PLAYER_COUNT];
+        read[0] = 0b11;
+        read[1] = 0b100000;
+        read[2] = 0b1;
+        buttons.read(read);
+        buttons.tick();
+        assert_eq!(buttons.bits(Buttons::down), 0b10_0000_0000_0011);
+        assert!(button(6, None).is_err());
+        assert!(button(0, Some(8)).is_err());
+    }
+
+    #[test]
+    fn keyboard_zones() {
+        assert_eq!(key_code("KeyQ"), Some(KeyCode::KeyQ));
+        assert_eq!(key_code("Digit7"), Some(KeyCode::Digit7));
+        assert_eq!(key_code("Numpad2"), Some(KeyCode::Numpad2));
+        assert_eq!(key_code("ArrowUp"), Some(KeyCode::ArrowUp));
+        assert_eq!(key_code("Keya"), None);
+        assert_eq!(key_code("Key"), None);
+
+        assert_eq!(
+            KeyLayout::from_keyboards(&[]).unwrap(),
+            KeyLayout::default()
+        );
+        let zone = |left: &str| Keyboard {
+            left: vec![left.into()],
+            ..default()
+        };
+        let layout = KeyLayout::from_keyboards(&[
+            zone("KeyA"),
+            zone("KeyJ"),
+            zone("ArrowLeft"),
+            zone("Numpad4"),
+        ])
+        .unwrap();
+        assert_eq!(layout.players.len(), 4);
+        assert_eq!(layout.players[3][0], [KeyCode::Numpad4]);
+        assert!(layout.players[3][1].is_empty());
+        assert!(KeyLayout::from_keyboards(&[zone("Nope")]).is_err());
+    }
+
+    #[test]
+    fn settings_keys_override_config() {
+        use bevy::ecs::system::RunSystemOnce;
+        let zone = |left: &str| Keyboard {
+            left: vec![left.into()],
+            ..default()
+        };
+        let mut world = World::new();
+        world.init_resource::<KeyLayout>();
+        world.insert_resource(Config {
+            keyboards: vec![zone("KeyA")],
+            ..default()
+        });
+        world.insert_resource(Settings::default());
+        world.run_system_once(apply_keys).unwrap();
+        assert_eq!(world.resource::<KeyLayout>().players[0][0], [KeyCode::KeyA]);
+        world.resource_mut::<Settings>().keyboards = vec![zone("KeyJ")];
+        world.run_system_once(apply_keys).unwrap();
+        assert_eq!(world.resource::<KeyLayout>().players[0][0], [KeyCode::KeyJ]);
+    }
+
+    #[test]
+    fn gamepad_buttons() {
+        let mut gamepad = Gamepad::default();
+        assert_eq!(gamepad_bits(&gamepad), 0);
+        gamepad.digital_mut().press(GamepadButton::East);
+        gamepad.analog_mut().set(GamepadAxis::LeftStickY, -1.0);
+        assert_eq!(gamepad_bits(&gamepad), 0b101000);
+    }
+}
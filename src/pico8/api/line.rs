@@ -8,42 +8,13 @@ impl super::Pico8<'_, '_> {
         let min = a.min(b);
         let delta = b - a;
         let size = UVec2::new(delta.x.unsigned_abs(), delta.y.unsigned_abs()) + UVec2::ONE;
-        let mut image = Image::new_fill(
-            Extent3d {
-                width: size.x,
-                height: size.y,
-                depth_or_array_layers: 1,
-            },
-            TextureDimension::D2,
-            &[0u8, 0u8, 0u8, 0u8],
-            TextureFormat::Rgba8UnormSrgb,
-            RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
-        );
-        image.sampler = ImageSampler::nearest();
-        let c = a - min;
-        let d = b - min;
-        for (x, y) in
-            bresenham::Bresenham::new((c.x as isize, c.y as isize), (d.x as isize, d.y as isize))
-        {
-            image.set_color_at(x as u32, y as u32, Color::WHITE)?;
-        }
-        let handle = self.images.add(image);
-        let clearable = Clearable::default();
-        let id = self
-            .commands
-            .spawn((
-                Name::new("line"),
-                Sprite {
-                    image: handle,
-                    color,
-                    anchor: Anchor::TopLeft,
-                    custom_size: Some(Vec2::new(size.x as f32, size.y as f32)),
-                    ..default()
-                },
-                Transform::from_xyz(min.x as f32, negate_y(min.y as f32), clearable.suggest_z()),
-                clearable,
-            ))
-            .id();
-        Ok(id)
+        self.charge(cost::pixels(size.max_element() as f32));
+        self.draw_shape(
+            "line",
+            Shape::Line(a - min, b - min),
+            min.as_vec2(),
+            size,
+            color,
+        )
     }
 }
@@ -1,9 +1,207 @@
 use super::*;
-use bevy::utils::hashbrown::hash_map::DefaultHashBuilder;
+use bevy::utils::{hashbrown::hash_map::DefaultHashBuilder, HashMap};
 use std::hash::{BuildHasher, Hash, Hasher};
 
+/// Where a ray cast by `mray()` hit the map
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MapHit {
+    /// The map cell that was hit
+    pub cell: IVec2,
+    /// Where the ray entered the cell in pixels
+    pub pos: Vec2,
+}
+
+/// The most cells `cast_ray()` walks before giving up
+const MAX_RAY_CELLS: i64 = 1 << 16;
+
+/// Walk the grid of `cell_size` cells from `from` to `to` and return the
+/// first `solid` cell.
+///
+/// The walk crosses one cell boundary per step, so it takes at most as many
+/// steps as there are columns and rows between the end cells, capped at
+/// [MAX_RAY_CELLS].
+fn cast_ray(
+    from: Vec2,
+    to: Vec2,
+    cell_size: Vec2,
+    mut solid: impl FnMut(IVec2) -> bool,
+) -> Option<MapHit> {
+    let start = from / cell_size;
+    let mut cell = start.floor().as_ivec2();
+    if solid(cell) {
+        return Some(MapHit { cell, pos: from });
+    }
+    let dir = (to - from) / cell_size;
+    if !dir.is_finite() || dir == Vec2::ZERO {
+        return None;
+    }
+    let end = (to / cell_size).floor();
+    let steps = ((end.x as i64 - cell.x as i64).abs() + (end.y as i64 - cell.y as i64).abs())
+        .min(MAX_RAY_CELLS);
+    let step = IVec2::new(dir.x.signum() as i32, dir.y.signum() as i32);
+    // The fraction of the ray at which it crosses into the next column or row
+    let next = |p: f32, c: i32, d: f32| {
+        if d > 0.0 {
+            (c as f32 + 1.0 - p) / d
+        } else if d < 0.0 {
+            (c as f32 - p) / d
+        } else {
+            f32::INFINITY
+        }
+    };
+    let mut t_max = Vec2::new(next(start.x, cell.x, dir.x), next(start.y, cell.y, dir.y));
+    let t_delta = (1.0 / dir).abs();
+    for _ in 0..steps {
+        let t;
+        if t_max.x < t_max.y {
+            t = t_max.x;
+            t_max.x += t_delta.x;
+            cell.x += step.x;
+        } else {
+            t = t_max.y;
+            t_max.y += t_delta.y;
+            cell.y += step.y;
+        }
+        if t > 1.0 {
+            return None;
+        }
+        if solid(cell) {
+            return Some(MapHit {
+                cell,
+                pos: from + (to - from) * t,
+            });
+        }
+    }
+    None
+}
+
+/// The tiles `autotile()` picks from by a cell's neighbors
+#[derive(Debug, Clone, PartialEq)]
+pub enum Autotile {
+    /// Tiles indexed by a 4-bit mask of the edge neighbors: 1 up, 2 left,
+    /// 4 right, and 8 down.
+    Edges([usize; 16]),
+    /// Tiles by an 8-bit mask that also has the corners: 1 up-left, 2 up,
+    /// 4 up-right, 8 left, 16 right, 32 down-left, 64 down, and 128
+    /// down-right. A corner only counts if both edges beside it do, which
+    /// leaves 47 masks. A cell whose mask is missing keeps its tile.
+    Corners(HashMap<u8, usize>),
+}
+
+impl Autotile {
+    /// Return the tile for the cell at `cell` given which cells are `filled`.
+    fn tile(&self, cell: IVec2, filled: impl Fn(IVec2) -> bool) -> Option<usize> {
+        let at = |x, y| filled(cell + IVec2::new(x, y));
+        let (up, left, right, down) = (at(0, -1), at(-1, 0), at(1, 0), at(0, 1));
+        match self {
+            Autotile::Edges(tiles) => {
+                let mask = up as usize
+                    | ((left as usize) << 1)
+                    | ((right as usize) << 2)
+                    | ((down as usize) << 3);
+                Some(tiles[mask])
+            }
+            Autotile::Corners(tiles) => {
+                let bits = [
+                    up && left && at(-1, -1),
+                    up,
+                    up && right && at(1, -1),
+                    left,
+                    right,
+                    down && left && at(-1, 1),
+                    down,
+                    down && right && at(1, 1),
+                ];
+                let mask = bits
+                    .iter()
+                    .enumerate()
+                    .fold(0u8, |mask, (i, bit)| mask | ((*bit as u8) << i));
+                tiles.get(&mask).copied()
+            }
+        }
+    }
+}
+
 impl super::Pico8<'_, '_> {
-    fn sprite_map(&self, map_index: Option<usize>) -> Result<&Map, Error> {
+    /// autotile(rect, flag, tiles, [map_index])
+    ///
+    /// Replace each cell in `rect` whose sprite has `flag` set with the tile
+    /// matching its neighbors that also have it. Neighbors outside the rect
+    /// count, but those outside the map do not. Every tile in `tiles` should
+    /// have `flag` set so that later calls see them as filled. Return the
+    /// number of cells changed.
+    pub fn autotile(
+        &mut self,
+        rect: URect,
+        flag: u8,
+        tiles: &Autotile,
+        map_index: Option<usize>,
+    ) -> Result<usize, Error> {
+        let filled = |cell: IVec2| {
+            cell.cmpge(IVec2::ZERO).all()
+                && self
+                    .mget(cell.as_vec2(), map_index, None)
+                    .is_some_and(|sprite| self.fget(Some(sprite), Some(flag)).unwrap_or(0) != 0)
+        };
+        // Pick every tile before setting any so the result does not depend on
+        // the order cells are visited.
+        let mut changes = Vec::new();
+        for y in rect.min.y..rect.max.y {
+            for x in rect.min.x..rect.max.x {
+                let cell = UVec2::new(x, y).as_ivec2();
+                if !filled(cell) {
+                    continue;
+                }
+                if let Some(tile) = tiles.tile(cell, filled) {
+                    if self.mget(cell.as_vec2(), map_index, None) != Some(tile) {
+                        changes.push((cell.as_vec2(), tile));
+                    }
+                }
+            }
+        }
+        for (pos, tile) in &changes {
+            self.mset(*pos, *tile, map_index, None)?;
+        }
+        Ok(changes.len())
+    }
+
+    /// Cast a ray in pixels from `from` to `to` over the map and return the
+    /// first cell whose sprite has `flag` set, or any flag if `None`.
+    ///
+    /// Both ends must be finite.
+    pub fn mray(
+        &self,
+        from: Vec2,
+        to: Vec2,
+        flag: Option<u8>,
+        map_index: Option<usize>,
+    ) -> Result<Option<MapHit>, Error> {
+        if !from.is_finite() || !to.is_finite() {
+            return Err(Error::InvalidArgument(
+                format!("mray from {from} to {to} is not finite").into(),
+            ));
+        }
+        let cell_size = self.sprite_sheet(None)?.sprite_size.as_vec2();
+        let map = self.sprite_map(map_index)?;
+        let has_flag = |sprite: usize| self.fget(Some(sprite), flag).unwrap_or(0) != 0;
+        Ok(cast_ray(from, to, cell_size, |cell| {
+            if cell.x < 0 || cell.y < 0 {
+                return false;
+            }
+            match map {
+                Map::P8(ref map) => map
+                    .index(cell.as_uvec2())
+                    .and_then(|i| map.get(i))
+                    .is_some_and(|sprite| has_flag(*sprite as usize)),
+                #[cfg(feature = "level")]
+                Map::Level(_) => self
+                    .mget(cell.as_vec2(), map_index, None)
+                    .is_some_and(has_flag),
+            }
+        }))
+    }
+
+    pub(crate) fn sprite_map(&self, map_index: Option<usize>) -> Result<&Map, Error> {
         let index = map_index.unwrap_or(0);
         self.pico8_asset()?
             .maps
@@ -11,7 +209,7 @@ impl super::Pico8<'_, '_> {
             .ok_or(Error::NoSuch(format!("map index {index}").into()))
     }
 
-    fn sprite_map_mut(&mut self, map_index: Option<usize>) -> Result<&mut Map, Error> {
+    pub(crate) fn sprite_map_mut(&mut self, map_index: Option<usize>) -> Result<&mut Map, Error> {
         let index = map_index.unwrap_or(0);
         self.pico8_asset_mut()?
             .maps
@@ -27,6 +225,7 @@ impl super::Pico8<'_, '_> {
         mask: Option<u8>,
         map_index: Option<usize>,
     ) -> Result<Entity, Error> {
+        self.charge(cost::pixels((size.x * size.y * 64) as f32));
         screen_start = self.state.draw_state.apply_camera_delta(screen_start);
         if cfg!(feature = "negate-y") {
             screen_start.y = -screen_start.y;
@@ -37,27 +236,41 @@ impl super::Pico8<'_, '_> {
             size.hash(&mut hasher);
             mask.inspect(|m| m.hash(&mut hasher));
             map_index.inspect(|i| i.hash(&mut hasher));
+            // Redraw the map when its tiles or sprites change.
+            self.state.revision.hash(&mut hasher);
             hasher.finish()
         };
+        let priority = self.state.draw_state.priority;
         // See if there's already an entity here.
         if let Some(id) = self.clear_cache.get(&hash) {
             let id = *id;
             self.commands.queue(move |world: &mut World| {
+                let mut z = None;
                 if let Some(mut clearable) = world.get_mut::<Clearable>(id) {
                     clearable.time_to_live = 2;
+                    // Keep its place unless the priority changed, even back
+                    // to 0.
+                    if clearable.priority != priority {
+                        clearable.priority = priority;
+                        z = Some(clearable.suggest_z());
+                    }
                 }
                 if let Some(mut visibility) = world.get_mut::<Visibility>(id) {
                     *visibility = Visibility::Inherited;
                 }
-                if let Some(_transform) = world.get_mut::<Transform>(id) {
-                    // TODO: Need to update the transform.
-                    // transform.
+                // The map may have scrolled.
+                if let Some(mut transform) = world.get_mut::<Transform>(id) {
+                    transform.translation.x = screen_start.x;
+                    transform.translation.y = screen_start.y;
+                    if let Some(z) = z {
+                        transform.translation.z = z;
+                    }
                 }
             });
-            return Ok(id);
+            return Ok(self.tag_drawn(id));
         }
 
-        match self.sprite_map(map_index)?.clone() {
+        let id = match self.sprite_map(map_index)?.clone() {
             Map::P8(map) => {
                 let palette = self.palette(None)?.clone();
 
@@ -70,10 +283,13 @@ impl super::Pico8<'_, '_> {
                     sprite_sheets,
                     Some(hash),
                     &mut self.commands,
-                    |handle| {
+                    |sheet, handle| {
                         self.gfx_handles.get_or_create(
                             &palette,
-                            &self.state.pal_map,
+                            &self
+                                .state
+                                .pal_map
+                                .with_sheet_transparency(sheet.transparent.as_deref()),
                             None,
                             handle,
                             &self.gfxs,
@@ -84,7 +300,11 @@ impl super::Pico8<'_, '_> {
             }
             #[cfg(feature = "level")]
             Map::Level(map) => Ok(map.map(screen_start, 0, &mut self.commands)),
+        }?;
+        if priority != 0 {
+            self.setz(id, priority);
         }
+        Ok(self.tag_drawn(id))
     }
 
     pub fn mget(
@@ -95,9 +315,10 @@ impl super::Pico8<'_, '_> {
     ) -> Option<usize> {
         let map: &Map = self.sprite_map(map_index).ok()?;
         match *map {
-            Map::P8(ref map) => {
-                Some(map[(pos.x as u32 + pos.y as u32 * MAP_COLUMNS) as usize] as usize)
-            }
+            Map::P8(ref map) => map
+                .index(pos.as_uvec2())
+                .and_then(|i| map.get(i))
+                .map(|sprite| *sprite as usize),
 
             #[cfg(feature = "level")]
             Map::Level(ref map) => self.tiled.mget(map, pos, map_index, layer_index),
@@ -111,10 +332,12 @@ impl super::Pico8<'_, '_> {
         map_index: Option<usize>,
         _layer_index: Option<usize>,
     ) -> Result<(), Error> {
+        self.state.revision += 1;
         let map = self.sprite_map_mut(map_index)?;
         match map {
             Map::P8(ref mut map) => map
-                .get_mut((pos.x as u32 + pos.y as u32 * MAP_COLUMNS) as usize)
+                .index(pos.as_uvec2())
+                .and_then(|i| map.get_mut(i))
                 .map(|value| *value = sprite_index as u8)
                 .ok_or(Error::NoSuch("map entry".into())),
             #[cfg(feature = "level")]
@@ -126,3 +349,75 @@ impl super::Pico8<'_, '_> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ray_hits_wall() {
+        let wall = |cell: IVec2| cell.x == 3;
+        let hit = cast_ray(
+            Vec2::new(4.0, 4.0),
+            Vec2::new(60.0, 18.0),
+            Vec2::splat(8.0),
+            wall,
+        );
+        assert_eq!(hit.map(|h| h.cell), Some(IVec2::new(3, 1)));
+        assert_eq!(hit.unwrap().pos.x, 24.0);
+        let hit = cast_ray(
+            Vec2::new(4.0, 4.0),
+            Vec2::new(20.0, 4.0),
+            Vec2::splat(8.0),
+            wall,
+        );
+        assert!(hit.is_none());
+        let hit = cast_ray(
+            Vec2::new(4.0, 4.0),
+            Vec2::new(4.0, -20.0),
+            Vec2::splat(8.0),
+            |c| c.y == -2,
+        );
+        assert_eq!(hit.map(|h| h.pos), Some(Vec2::new(4.0, -8.0)));
+    }
+
+    #[test]
+    fn ray_stops() {
+        let mut visited = 0;
+        let hit = cast_ray(Vec2::ZERO, Vec2::new(1e30, 0.0), Vec2::splat(8.0), |_| {
+            visited += 1;
+            false
+        });
+        assert!(hit.is_none());
+        assert_eq!(visited, MAX_RAY_CELLS + 1);
+        let never = |_| false;
+        let size = Vec2::splat(8.0);
+        assert!(cast_ray(Vec2::ONE, Vec2::ONE, size, never).is_none());
+        assert!(cast_ray(Vec2::ONE, Vec2::INFINITY, size, never).is_none());
+        assert!(cast_ray(Vec2::ONE, Vec2::NAN, size, never).is_none());
+        // A ray ending on a boundary still reaches the cell past it.
+        let hit = cast_ray(Vec2::new(4.0, 4.0), Vec2::new(16.0, 4.0), size, |c| {
+            c.x == 2
+        });
+        assert_eq!(hit.map(|h| h.cell), Some(IVec2::new(2, 0)));
+    }
+
+    #[test]
+    fn autotile_masks() {
+        // A plus sign of filled cells around (1, 1) and the corner (0, 0)
+        let filled = |c: IVec2| {
+            (c.x == 1 && (0..3).contains(&c.y))
+                || (c.y == 1 && (0..3).contains(&c.x))
+                || c == IVec2::ZERO
+        };
+        let edges = Autotile::Edges(std::array::from_fn(|i| 100 + i));
+        assert_eq!(edges.tile(IVec2::new(1, 1), filled), Some(115));
+        assert_eq!(edges.tile(IVec2::new(1, 0), filled), Some(110));
+        let corners = Autotile::Corners(HashMap::from_iter([(0b0101_1011, 1), (0b0101_1010, 2)]));
+        // Up-left counts since up and left are filled.
+        assert_eq!(corners.tile(IVec2::new(1, 1), filled), Some(1));
+        let filled = |c: IVec2| filled(c) && c != IVec2::ZERO;
+        assert_eq!(corners.tile(IVec2::new(1, 1), filled), Some(2));
+        assert_eq!(corners.tile(IVec2::new(1, 0), filled), None);
+    }
+}
@@ -0,0 +1,92 @@
+use std::f32::consts::TAU;
+
+impl super::Pico8<'_, '_> {
+    /// sgn(x)
+    ///
+    /// Returns 1 for zero unlike `f32::signum()` which considers the sign of
+    /// zero.
+    pub fn sgn(x: f32) -> f32 {
+        if x < 0.0 {
+            -1.0
+        } else {
+            1.0
+        }
+    }
+
+    /// mid(x, y, z)
+    ///
+    /// Returns the middle value regardless of argument order.
+    pub fn mid(x: f32, y: f32, z: f32) -> f32 {
+        x.min(y).max(x.max(y).min(z))
+    }
+
+    /// flr(x)
+    pub fn flr(x: f32) -> f32 {
+        x.floor()
+    }
+
+    /// ceil(x)
+    pub fn ceil(x: f32) -> f32 {
+        x.ceil()
+    }
+
+    /// sqrt(x)
+    ///
+    /// Returns 0 for negative numbers.
+    pub fn sqrt(x: f32) -> f32 {
+        if x < 0.0 {
+            0.0
+        } else {
+            x.sqrt()
+        }
+    }
+
+    /// sin(x)
+    ///
+    /// Angles are in turns and the y-axis points down, so `sin(0.25)` is -1.
+    pub fn sin(x: f32) -> f32 {
+        -(x * TAU).sin()
+    }
+
+    /// cos(x)
+    ///
+    /// Angles are in turns.
+    pub fn cos(x: f32) -> f32 {
+        (x * TAU).cos()
+    }
+
+    /// atan2(dx, dy)
+    ///
+    /// Returns the angle in turns in [0, 1) with the y-axis pointing down, so
+    /// `atan2(0, 1)` is 0.75. Note that `dx` comes first unlike `f32::atan2()`.
+    pub fn atan2(dx: f32, dy: f32) -> f32 {
+        if dx == 0.0 && dy == 0.0 {
+            return 0.25;
+        }
+        (1.0 - dy.atan2(dx) / TAU).rem_euclid(1.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    type P = crate::pico8::Pico8<'static, 'static>;
+
+    #[test]
+    fn pico8_conventions() {
+        assert_eq!(P::sgn(0.0), 1.0);
+        assert_eq!(P::sgn(-0.0), 1.0);
+        assert_eq!(P::mid(3.0, 1.0, 2.0), 2.0);
+        assert_eq!(P::mid(1.0, 3.0, 2.0), 2.0);
+        assert_eq!(P::mid(2.0, 3.0, 1.0), 2.0);
+        assert_eq!(P::flr(-1.5), -2.0);
+        assert_eq!(P::ceil(-1.5), -1.0);
+        assert_eq!(P::sqrt(-4.0), 0.0);
+        assert_eq!(P::atan2(1.0, 0.0), 0.0);
+        assert_eq!(P::atan2(0.0, 1.0), 0.75);
+        assert_eq!(P::atan2(0.0, -1.0), 0.25);
+        assert_eq!(P::atan2(-1.0, 0.0), 0.5);
+        assert_eq!(P::atan2(0.0, 0.0), 0.25);
+        assert!((P::sin(0.25) + 1.0).abs() < 1e-6);
+        assert!((P::cos(0.5) + 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,209 @@
+use super::*;
+
+use crate::pico8::{SprHandle, CUSTOM_FONT_ADDR, CUSTOM_FONT_LEN};
+
+pub const GFX_ADDR: usize = 0x0000;
+pub const GFX_LEN: usize = 0x2000;
+const GFX: std::ops::Range<usize> = GFX_ADDR..GFX_ADDR + GFX_LEN;
+pub const MAP_ADDR: usize = 0x2000;
+pub const MAP_LEN: usize = 0x1000;
+const MAP: std::ops::Range<usize> = MAP_ADDR..MAP_ADDR + MAP_LEN;
+const CUSTOM_FONT: std::ops::Range<usize> = CUSTOM_FONT_ADDR..CUSTOM_FONT_ADDR + CUSTOM_FONT_LEN;
+pub const SCREEN_ADDR: usize = 0x6000;
+pub const SCREEN_LEN: usize = 0x2000;
+const SCREEN: std::ops::Range<usize> = SCREEN_ADDR..SCREEN_ADDR + SCREEN_LEN;
+pub const SCREEN_MODE_ADDR: usize = 0x5f2c;
+
+impl super::Pico8<'_, '_> {
+    /// peek(addr)
+    ///
+    /// Only some regions of Pico-8's memory are supported.
+    pub fn peek(&self, addr: usize) -> Result<u8, Error> {
+        match addr {
+            a if GFX.contains(&a) => self.peek_gfx(a - GFX_ADDR),
+            a if MAP.contains(&a) => self.peek_map(a - MAP_ADDR),
+            a if CUSTOM_FONT.contains(&a) => Ok(self.state.custom_font.data[a - CUSTOM_FONT_ADDR]),
+            a if SCREEN.contains(&a) => self.peek_screen(a - SCREEN_ADDR),
+            SCREEN_MODE_ADDR => Ok(self.state.screen_mode),
+            _ => Err(Error::UnsupportedPeek(addr)),
+        }
+    }
+
+    /// poke(addr, value)
+    ///
+    /// Only some regions of Pico-8's memory are supported.
+    pub fn poke(&mut self, addr: usize, value: u8) -> Result<(), Error> {
+        self.poke_bytes(addr, [value])
+    }
+
+    /// Write `bytes` from `dest` and then, if any were in sprite memory,
+    /// rebuild the sprite sheet images once rather than once per byte.
+    fn poke_bytes(
+        &mut self,
+        dest: usize,
+        bytes: impl IntoIterator<Item = u8>,
+    ) -> Result<(), Error> {
+        let mut gfx = false;
+        let mut result = Ok(());
+        for (addr, value) in (dest..).zip(bytes) {
+            gfx |= GFX.contains(&addr);
+            result = self.poke_byte(addr, value);
+            if result.is_err() {
+                break;
+            }
+        }
+        if gfx {
+            self.gfx_changed();
+        }
+        result
+    }
+
+    fn poke_byte(&mut self, addr: usize, value: u8) -> Result<(), Error> {
+        match addr {
+            a if GFX.contains(&a) => self.poke_gfx(a - GFX_ADDR, value),
+            a if MAP.contains(&a) => self.poke_map(a - MAP_ADDR, value),
+            a if CUSTOM_FONT.contains(&a) => {
+                self.state.custom_font.data[a - CUSTOM_FONT_ADDR] = value;
+                Ok(())
+            }
+            a if SCREEN.contains(&a) => self.poke_screen(a - SCREEN_ADDR, value),
+            SCREEN_MODE_ADDR => self.vid(Some(value)).map(|_| ()),
+            _ => Err(Error::UnsupportedPoke(addr)),
+        }
+    }
+
+    /// memcpy(dest, src, len)
+    pub fn memcpy(&mut self, dest: usize, src: usize, len: usize) -> Result<(), Error> {
+        let bytes = (src..src + len)
+            .map(|addr| self.peek(addr))
+            .collect::<Result<Vec<u8>, Error>>()?;
+        self.poke_bytes(dest, bytes)
+    }
+
+    /// memset(dest, value, len)
+    pub fn memset(&mut self, dest: usize, value: u8, len: usize) -> Result<(), Error> {
+        self.poke_bytes(dest, std::iter::repeat_n(value, len))
+    }
+
+    /// Forget the images made from sprite sheets and redraw maps.
+    pub(crate) fn gfx_changed(&mut self) {
+        self.gfx_handles.invalidate();
+        self.state.revision += 1;
+    }
+
+    /// Return the custom font used after the "\x0e" control code.
+    pub fn custom_font(&mut self) -> &mut BitmapFont {
+        &mut self.state.custom_font
+    }
+
+    /// Return the canvas pixels of the screen byte at `offset`. The low
+    /// nibble is the left pixel.
+    fn screen_pixels(&self, offset: usize) -> Result<[UVec2; 2], Error> {
+        let width = self.canvas.size.x as usize;
+        let x = (offset * 2) % width;
+        let y = (offset * 2) / width;
+        if y >= self.canvas.size.y as usize {
+            return Err(Error::UnsupportedPeek(SCREEN_ADDR + offset));
+        }
+        Ok([
+            UVec2::new(x as u32, y as u32),
+            UVec2::new(x as u32 + 1, y as u32),
+        ])
+    }
+
+    /// Read two pixels of the canvas as palette indices.
+    ///
+    /// Only the canvas is read. Sprites, shapes, and text drawn as entities
+    /// are not part of it.
+    fn peek_screen(&self, offset: usize) -> Result<u8, Error> {
+        let palette = self.palette(None)?;
+        let image = self
+            .images
+            .get(&self.canvas.handle)
+            .ok_or(Error::NoAsset("canvas".into()))?;
+        let mut value = 0;
+        for (i, pos) in self.screen_pixels(offset)?.into_iter().enumerate() {
+            let color = Srgba::from(image.get_color_at(pos.x, pos.y)?).to_u8_array();
+            let index = palette.index_of(color).unwrap_or(0) as u8;
+            value |= (index & 0xf) << (4 * i);
+        }
+        Ok(value)
+    }
+
+    /// Write two pixels of the canvas from palette indices.
+    fn poke_screen(&mut self, offset: usize, value: u8) -> Result<(), Error> {
+        let pixels = self
+            .screen_pixels(offset)
+            .map_err(|_| Error::UnsupportedPoke(SCREEN_ADDR + offset))?;
+        let colors = [
+            self.get_color(PColor::Palette((value & 0xf) as usize))?,
+            self.get_color(PColor::Palette((value >> 4) as usize))?,
+        ];
+        let image = self
+            .images
+            .get_mut(&self.canvas.handle)
+            .ok_or(Error::NoAsset("canvas".into()))?;
+        for (pos, color) in pixels.into_iter().zip(colors) {
+            image.set_color_at(pos.x, pos.y, color)?;
+        }
+        Ok(())
+    }
+
+    /// Return the handle of the first sprite sheet if it is a `Gfx`.
+    fn memory_gfx(&self, addr: usize) -> Result<Handle<Gfx>, Error> {
+        match &self.sprite_sheet(None)?.handle {
+            SprHandle::Gfx(handle) => Ok(handle.clone()),
+            SprHandle::Image(_) => Err(Error::Unsupported(
+                format!("memory at {addr:#x} for an image sprite sheet").into(),
+            )),
+        }
+    }
+
+    /// Read two pixels of the first sprite sheet. The low nibble is the left
+    /// pixel.
+    fn peek_gfx(&self, offset: usize) -> Result<u8, Error> {
+        let handle = self.memory_gfx(GFX_ADDR + offset)?;
+        let gfx = self.gfxs.get(&handle).ok_or(Error::NoSuch("Gfx".into()))?;
+        let x = (offset * 2) % gfx.width;
+        let y = (offset * 2) / gfx.width;
+        let left = gfx.get(x, y).unwrap_or(0);
+        let right = gfx.get(x + 1, y).unwrap_or(0);
+        Ok((left & 0xf) | (right << 4))
+    }
+
+    /// Write two pixels of the first sprite sheet.
+    fn poke_gfx(&mut self, offset: usize, value: u8) -> Result<(), Error> {
+        let handle = self.memory_gfx(GFX_ADDR + offset)?;
+        let gfx = self
+            .gfxs
+            .get_mut(&handle)
+            .ok_or(Error::NoSuch("Gfx".into()))?;
+        let x = (offset * 2) % gfx.width;
+        let y = (offset * 2) / gfx.width;
+        gfx.set(x, y, value & 0xf);
+        gfx.set(x + 1, y, value >> 4);
+        Ok(())
+    }
+
+    /// Read a tile of the first map.
+    fn peek_map(&self, offset: usize) -> Result<u8, Error> {
+        match self.sprite_map(None)? {
+            Map::P8(map) => Ok(map.get(offset).copied().unwrap_or(0)),
+            #[cfg(feature = "level")]
+            Map::Level(_) => Err(Error::UnsupportedPeek(MAP_ADDR + offset)),
+        }
+    }
+
+    /// Write a tile of the first map.
+    fn poke_map(&mut self, offset: usize, value: u8) -> Result<(), Error> {
+        self.state.revision += 1;
+        match self.sprite_map_mut(None)? {
+            Map::P8(map) => map
+                .get_mut(offset)
+                .map(|tile| *tile = value)
+                .ok_or(Error::UnsupportedPoke(MAP_ADDR + offset)),
+            #[cfg(feature = "level")]
+            Map::Level(_) => Err(Error::UnsupportedPoke(MAP_ADDR + offset)),
+        }
+    }
+}
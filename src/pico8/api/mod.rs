@@ -1,5 +1,7 @@
 mod error;
 pub use error::*;
+mod achievement;
+mod assert;
 mod asset;
 use super::*;
 pub use asset::*;
@@ -10,24 +12,46 @@ pub use state::*;
 mod handle;
 pub use handle::*;
 mod camera;
-use camera::*;
+pub use camera::*;
 mod param;
 pub use param::*;
 mod sfx;
 pub use sfx::*;
 mod circ;
+mod cpu;
+mod debug;
+pub use cpu::*;
+pub use debug::*;
 mod map;
+pub use map::*;
+mod math;
 mod oval;
 mod pal;
 mod print;
+pub use print::*;
 mod rect;
+mod rnd;
+mod table;
+mod time;
 pub use pal::*;
+pub use rnd::*;
+pub use table::*;
+pub use time::*;
 mod canvas;
+mod cartdata;
+pub use cartdata::*;
+mod i18n;
+pub use i18n::*;
+mod input;
+pub use input::*;
 #[cfg(feature = "level")]
 mod level;
 mod line;
+mod mem;
+mod mouse;
 #[cfg(feature = "level")]
 pub use level::*;
+pub use mouse::*;
 
 use bevy::{
     image::ImageSampler,
@@ -36,18 +60,21 @@ use bevy::{
         render_resource::{Extent3d, TextureDimension, TextureFormat},
     },
     sprite::Anchor,
-    text::TextLayoutInfo,
+    text::{ComputedTextBlock, TextBounds, TextLayoutInfo},
 };
-use tiny_skia::{self, FillRule, Paint, PathBuilder, Pixmap, Stroke};
 
 use crate::{
     pico8::{
-        self, audio::AudioBank, image::pixel_art_settings, ClearEvent, Clearable, Map, PalMap,
-        Palette,
+        self,
+        audio::{AudioBank, ChannelFx},
+        image::pixel_art_settings,
+        shape::Shape,
+        BitmapFont, ClearEvent, Clearable, Map, PalMap, Palette,
     },
     DrawState, FillColor, N9Color, Nano9Camera, PColor,
 };
 
+/// Tiles per row of a Pico-8 map unless configured otherwise
 pub const MAP_COLUMNS: u32 = 128;
 pub const PICO8_SPRITE_SIZE: UVec2 = UVec2::new(8, 8);
 pub const PICO8_TILE_COUNT: UVec2 = UVec2::new(16, 16);
@@ -79,6 +106,7 @@ pub(crate) fn plugin(app: &mut App) {
         .register_type::<SpriteSheet>()
         .init_asset::<Pico8Asset>()
         .init_resource::<Pico8State>()
+        .add_event::<Printed>()
         .add_observer(
             |trigger: Trigger<UpdateCameraPos>,
              camera: Single<&mut Transform, With<Nano9Camera>>| {
@@ -87,6 +115,24 @@ pub(crate) fn plugin(app: &mut App) {
                 camera.translation.x = pos.0.x;
                 camera.translation.y = negate_y(pos.0.y);
             },
+        )
+        .add_plugins((
+            cpu::plugin,
+            debug::plugin,
+            input::plugin,
+            mouse::plugin,
+            time::plugin,
+        ))
+        .add_systems(
+            PreUpdate,
+            camera::follow_camera.run_if(resource_exists::<Pico8Handle>),
+        )
+        .add_systems(
+            PostUpdate,
+            (
+                print::style_text.after(bevy::text::update_text2d_layout),
+                print::narrate,
+            ),
         );
 }
 
@@ -0,0 +1,229 @@
+use super::*;
+use bevy::{
+    input::mouse::MouseWheel,
+    window::{CursorGrabMode, PrimaryWindow},
+};
+
+use crate::{error::RunState, pico8::OVERLAY_Z, ui::CanvasViewport, N9Canvas};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.add_systems(
+        PreUpdate,
+        update_mouse
+            .after(bevy::input::InputSystem)
+            .run_if(resource_exists::<Pico8Handle>),
+    )
+    .add_systems(
+        PostUpdate,
+        (
+            sync_cursor_options,
+            draw_cursor_sprite
+                .run_if(resource_exists::<Pico8Handle>)
+                .before(TransformSystem::TransformPropagate),
+        ),
+    )
+    .add_systems(OnEnter(RunState::Loaded), restore_cursor);
+}
+
+/// A sprite drawn in place of the mouse cursor
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct CursorSprite {
+    pub index: usize,
+    /// The pixel of the sprite at the mouse position
+    pub hotspot: Vec2,
+}
+
+/// The mouse in canvas pixels for `stat(32)` through `stat(36)`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+pub struct MouseState {
+    /// The canvas pixel under the cursor, clamped to the canvas
+    pub pos: UVec2,
+    /// False if the cursor is outside the canvas or the window
+    pub inside: bool,
+    /// Buttons held: 1 is left, 2 is right, and 4 is middle.
+    pub buttons: u8,
+    /// Wheel movement this frame: 1 is up and -1 is down.
+    pub wheel: i8,
+}
+
+/// Marks the entity drawing the [CursorSprite].
+#[derive(Debug, Component)]
+pub(crate) struct CursorSpriteMarker;
+
+/// Draw the cursor sprite above everything else.
+const CURSOR_Z: f32 = OVERLAY_Z + 2.0;
+
+impl super::Pico8<'_, '_> {
+    /// cursor_spr(n, [hotspot]) draws sprite `n` in place of the mouse cursor.
+    /// `None` restores the system cursor.
+    pub fn cursor_spr(&mut self, index: Option<usize>, hotspot: Option<Vec2>) {
+        self.state.cursor_sprite = index.map(|index| CursorSprite {
+            index,
+            hotspot: hotspot.unwrap_or_default(),
+        });
+    }
+
+    /// show_cursor(show) shows or hides the mouse cursor, including any
+    /// [CursorSprite].
+    pub fn show_cursor(&mut self, show: bool) {
+        self.state.cursor_visible = show;
+    }
+
+    /// lock_cursor(lock) keeps the mouse cursor from leaving the window so
+    /// the cart can use relative motion. Some platforms confine the cursor to
+    /// the window instead.
+    pub fn lock_cursor(&mut self, lock: bool) {
+        self.state.cursor_locked = lock;
+    }
+
+    /// mouse() returns the mouse's canvas pixel, buttons, and wheel.
+    pub fn mouse(&self) -> MouseState {
+        self.state.mouse
+    }
+}
+
+fn update_mouse(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    viewport: Res<CanvasViewport>,
+    canvas: Res<N9Canvas>,
+    buttons: Option<Res<ButtonInput<MouseButton>>>,
+    mut wheel: EventReader<MouseWheel>,
+    mut state: ResMut<Pico8State>,
+) {
+    let mut mouse = state.mouse;
+    match windows.get_single().ok().and_then(Window::cursor_position) {
+        Some(pos) => (mouse.pos, mouse.inside) = viewport.window_to_pixel(pos, canvas.size),
+        // Keep the last position like Pico-8 does.
+        None => mouse.inside = false,
+    }
+    mouse.buttons = buttons.map_or(0, |buttons| {
+        [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+            .into_iter()
+            .enumerate()
+            .filter(|(_, button)| buttons.pressed(*button))
+            .fold(0, |bits, (i, _)| bits | 1 << i)
+    });
+    let scroll: f32 = wheel.read().map(|event| event.y).sum();
+    mouse.wheel = if scroll > 0.0 {
+        1
+    } else if scroll < 0.0 {
+        -1
+    } else {
+        0
+    };
+    // Only mark the state changed when the mouse is, so the cursor options
+    // are not synced every frame.
+    if state.mouse != mouse {
+        state.mouse = mouse;
+    }
+}
+
+fn sync_cursor_options(
+    state: Res<Pico8State>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    let visible = state.cursor_visible && state.cursor_sprite.is_none();
+    let grab_mode = if state.cursor_locked {
+        if cfg!(target_os = "windows") {
+            CursorGrabMode::Confined
+        } else {
+            CursorGrabMode::Locked
+        }
+    } else {
+        CursorGrabMode::None
+    };
+    if window.cursor_options.visible != visible || window.cursor_options.grab_mode != grab_mode {
+        window.cursor_options.visible = visible;
+        window.cursor_options.grab_mode = grab_mode;
+    }
+}
+
+fn draw_cursor_sprite(
+    mut pico8: Pico8,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    viewport: Res<CanvasViewport>,
+    camera: Query<&Transform, (With<Nano9Camera>, Without<CursorSpriteMarker>)>,
+    mut cursor: Query<
+        (Entity, &mut Sprite, &mut Transform, &mut Visibility),
+        With<CursorSpriteMarker>,
+    >,
+    mut last: Local<Option<CursorSprite>>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let Some(cursor_sprite) = pico8.state.cursor_sprite else {
+        if last.take().is_some() {
+            for (id, ..) in &cursor {
+                pico8.commands.entity(id).despawn_recursive();
+            }
+        }
+        return;
+    };
+    let changed = *last != Some(cursor_sprite);
+    *last = Some(cursor_sprite);
+    let Some(pos) = window
+        .cursor_position()
+        .filter(|_| pico8.state.cursor_visible)
+    else {
+        for (_, _, _, mut visibility) in &mut cursor {
+            *visibility = Visibility::Hidden;
+        }
+        return;
+    };
+    let camera = camera
+        .get_single()
+        .map(|transform| transform.translation.xy())
+        .unwrap_or_default();
+    let pos = pixel_snap(viewport.window_to_canvas(pos) - cursor_sprite.hotspot);
+    let translation = (Vec2::new(pos.x, negate_y(pos.y)) + camera).extend(CURSOR_Z);
+    match cursor.get_single_mut() {
+        Ok((_, mut sprite, mut transform, mut visibility)) => {
+            if changed {
+                match pico8.make_sprite(
+                    Spr::Cur {
+                        sprite: cursor_sprite.index,
+                    },
+                    None,
+                    BVec2::FALSE,
+                ) {
+                    Ok((new_sprite, _)) => *sprite = new_sprite,
+                    Err(e) => warn!("Could not draw cursor sprite: {e}"),
+                }
+            }
+            transform.translation = translation;
+            *visibility = Visibility::Inherited;
+        }
+        Err(_) => match pico8.make_sprite(
+            Spr::Cur {
+                sprite: cursor_sprite.index,
+            },
+            None,
+            BVec2::FALSE,
+        ) {
+            Ok((sprite, _)) => {
+                pico8.commands.spawn((
+                    Name::new("cursor_spr"),
+                    sprite,
+                    Transform::from_translation(translation),
+                    CursorSpriteMarker,
+                ));
+            }
+            Err(e) => warn!("Could not draw cursor sprite: {e}"),
+        },
+    }
+}
+
+/// Give the next cart the system cursor back.
+fn restore_cursor(mut state: ResMut<Pico8State>) {
+    state.cursor_sprite = None;
+    state.cursor_visible = true;
+    state.cursor_locked = false;
+    state.mouse = MouseState::default();
+}
@@ -1,6 +1,35 @@
 use super::*;
 
 impl super::Pico8<'_, '_> {
+    /// Spawn `shape` with its upper left corner at `upper_left`.
+    pub(crate) fn draw_shape(
+        &mut self,
+        name: &'static str,
+        shape: Shape,
+        upper_left: Vec2,
+        size: UVec2,
+        color: Color,
+    ) -> Result<Entity, Error> {
+        let (mesh, material, instance) = self.shapes.get(shape, size, color)?;
+        let size = size.as_vec2();
+        let center = upper_left + size / 2.0;
+        let clearable = self.clearable();
+        let id = self
+            .commands
+            .spawn((
+                Name::new(name),
+                mesh,
+                material,
+                instance,
+                Transform::from_xyz(center.x, negate_y(center.y), clearable.suggest_z())
+                    .with_scale(size.extend(1.0)),
+                clearable,
+            ))
+            .id();
+        self.state.draw_state.mark_drawn();
+        Ok(self.tag_drawn(id))
+    }
+
     pub fn ovalfill(
         &mut self,
         upper_left: Vec2,
@@ -10,60 +39,11 @@ impl super::Pico8<'_, '_> {
         let upper_left = pixel_snap(self.state.draw_state.apply_camera_delta(upper_left));
         let lower_right = pixel_snap(self.state.draw_state.apply_camera_delta(lower_right));
         let color = self.get_color(color.unwrap_or(N9Color::Pen))?;
-        // let min = a.min(b);
         let size: UVec2 = ((lower_right.as_ivec2() - upper_left.as_ivec2()) + IVec2::ONE)
             .try_into()
             .unwrap();
-        // // let size = UVec2::new((a.x - b.x).abs() + 1,
-        // //                       (a.y - b.y).abs() + 1);
-        // let size = UVec2::new(delta.x.abs() as u32, delta.y.abs() as u32) + UVec2::ONE;
-        // dbg!(a, b, size);
-        let mut pixmap = Pixmap::new(size.x, size.y).expect("pixmap");
-        let oval =
-            tiny_skia::Rect::from_ltrb(0.0, 0.0, size.x as f32, size.y as f32).expect("oval rect");
-        let path = PathBuilder::from_oval(oval).expect("oval path");
-        let mut paint = Paint::default();
-        paint.anti_alias = false;
-        paint.set_color_rgba8(255, 255, 255, 255);
-        pixmap.fill_path(
-            &path,
-            &paint,
-            FillRule::Winding,
-            tiny_skia::Transform::identity(),
-            None,
-        );
-
-        let mut image = Image::new(
-            Extent3d {
-                width: size.x,
-                height: size.y,
-                depth_or_array_layers: 1,
-            },
-            TextureDimension::D2,
-            pixmap.take(),
-            TextureFormat::Rgba8UnormSrgb,
-            RenderAssetUsages::RENDER_WORLD,
-        );
-        image.sampler = ImageSampler::nearest();
-        let handle = self.images.add(image);
-        let clearable = Clearable::default();
-        let id = self
-            .commands
-            .spawn((
-                Name::new("ovalfill"),
-                Sprite {
-                    image: handle,
-                    color,
-                    anchor: Anchor::TopLeft,
-                    custom_size: Some(Vec2::new(size.x as f32, size.y as f32)),
-                    ..default()
-                },
-                Transform::from_xyz(upper_left.x, negate_y(upper_left.y), clearable.suggest_z()),
-                clearable,
-            ))
-            .id();
-        self.state.draw_state.mark_drawn();
-        Ok(id)
+        self.charge(cost::pixels((size.x * size.y) as f32));
+        self.draw_shape("ovalfill", Shape::OvalFill, upper_left, size, color)
     }
 
     pub fn oval(
@@ -78,54 +58,7 @@ impl super::Pico8<'_, '_> {
         let size: UVec2 = ((lower_right.as_ivec2() - upper_left.as_ivec2()) + IVec2::ONE)
             .try_into()
             .unwrap();
-        let mut pixmap = Pixmap::new(size.x, size.y).expect("pixmap");
-        let oval =
-            tiny_skia::Rect::from_ltrb(0.0, 0.0, size.x as f32, size.y as f32).expect("oval rect");
-        let path = PathBuilder::from_oval(oval).expect("oval path");
-        let mut paint = Paint::default();
-        paint.anti_alias = false;
-        paint.set_color_rgba8(255, 255, 255, 255);
-        let mut stroke = Stroke::default();
-        stroke.width = 0.0;
-        pixmap.stroke_path(
-            &path,
-            &paint,
-            &stroke,
-            tiny_skia::Transform::identity(),
-            None,
-        );
-
-        let mut image = Image::new(
-            Extent3d {
-                width: size.x,
-                height: size.y,
-                depth_or_array_layers: 1,
-            },
-            TextureDimension::D2,
-            pixmap.take(),
-            TextureFormat::Rgba8UnormSrgb,
-            RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
-        );
-
-        image.sampler = ImageSampler::nearest();
-        let handle = self.images.add(image);
-        let clearable = Clearable::default();
-        let id = self
-            .commands
-            .spawn((
-                Name::new("oval"),
-                Sprite {
-                    image: handle,
-                    color,
-                    anchor: Anchor::TopLeft,
-                    custom_size: Some(Vec2::new(size.x as f32, size.y as f32)),
-                    ..default()
-                },
-                Transform::from_xyz(upper_left.x, negate_y(upper_left.y), clearable.suggest_z()),
-                clearable,
-            ))
-            .id();
-        self.state.draw_state.mark_drawn();
-        Ok(id)
+        self.charge(cost::pixels((2 * (size.x + size.y)) as f32));
+        self.draw_shape("oval", Shape::Oval, upper_left, size, color)
     }
 }
@@ -17,7 +17,7 @@ impl super::Pico8<'_, '_> {
     }
 
     pub(crate) fn get_color(&self, c: impl Into<N9Color>) -> Result<Color, Error> {
-        match c.into().into_pcolor(&self.state.draw_state.pen) {
+        match self.state.pcolor(c, self.pico8_asset()?) {
             PColor::Palette(n) => self.palette(None)?.get_color(n).map(|c| c.into()),
             PColor::Color(c) => Ok(c.into()),
         }
@@ -37,6 +37,16 @@ impl super::Pico8<'_, '_> {
         Ok(last_color)
     }
 
+    /// snap_colors([snap]) sets whether RGB colors are drawn as the nearest
+    /// color of the palette and returns the last setting.
+    pub fn snap_colors(&mut self, snap: Option<bool>) -> bool {
+        let last = self.state.snap_colors;
+        if let Some(snap) = snap {
+            self.state.snap_colors = snap;
+        }
+        last
+    }
+
     pub fn pal_map(&mut self, original_to_new: Option<(usize, usize)>, mode: Option<PalModify>) {
         let mode = mode.unwrap_or_default();
         assert!(matches!(mode, PalModify::Following));
@@ -2,7 +2,8 @@ use super::*;
 use bevy::ecs::system::SystemParam;
 
 use crate::{
-    pico8::{self, audio::SfxChannels, Gfx, GfxHandles},
+    pico8::{self, shape::Shapes, Gfx, GfxHandles},
+    settings::Persist,
     N9Canvas,
 };
 
@@ -16,13 +17,15 @@ pub struct Pico8<'w, 's> {
     pub(crate) state: ResMut<'w, Pico8State>,
     pub(crate) commands: Commands<'w, 's>,
     pub(crate) canvas: Res<'w, N9Canvas>,
-    pub(crate) sfx_channels: Res<'w, SfxChannels>,
+    pub(crate) audio: SfxParam<'w>,
     #[cfg(feature = "level")]
     pub(crate) tiled: crate::level::tiled::Level<'w, 's>,
     pub(crate) gfxs: ResMut<'w, Assets<Gfx>>,
     pub(crate) gfx_handles: ResMut<'w, GfxHandles>,
+    pub(crate) shapes: Shapes<'w>,
     pub(crate) pico8_assets: ResMut<'w, Assets<Pico8Asset>>,
     pub(crate) pico8_handle: Res<'w, Pico8Handle>,
     pub(crate) defaults: Res<'w, pico8::Defaults>,
     pub(crate) clear_cache: Res<'w, ClearCache>,
+    pub(crate) persist: Persist<'w>,
 }
@@ -1,5 +1,182 @@
 use super::*;
 
+use std::sync::OnceLock;
+
+use bevy::window::PrimaryWindow;
+
+use crate::pico8::p8scii;
+
+/// Switch to the custom font.
+const CUSTOM_FONT_ON: char = '\x0e';
+/// Switch to the default font.
+const CUSTOM_FONT_OFF: char = '\x0f';
+
+/// Bevy lays out lines at this multiple of the font size.
+const LINE_HEIGHT: f32 = 1.2;
+
+/// Characters between tab stops, 16 pixels in Pico-8's font
+const TAB_STOP: usize = 4;
+
+/// Replace each tab with spaces up to the next tab stop of its line.
+fn expand_tabs(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut column = 0;
+    for c in text.chars() {
+        match c {
+            '\t' => {
+                let spaces = TAB_STOP - column % TAB_STOP;
+                result.extend(std::iter::repeat_n(' ', spaces));
+                column += spaces;
+            }
+            '\n' => {
+                result.push(c);
+                column = 0;
+            }
+            _ => {
+                result.push(c);
+                column += 1;
+            }
+        }
+    }
+    result
+}
+
+/// Return how far to scroll so that `lines` lines of `line_height` printed at
+/// the cursor `y` fit on a canvas `height` tall.
+fn scroll_amount(y: f32, lines: usize, line_height: f32, height: f32) -> f32 {
+    (y + lines as f32 * line_height - height).max(0.0)
+}
+
+/// Move the canvas and the drawn entities up `dy` pixels, filling the bottom
+/// with `fill`.
+fn scroll_up(image: &mut Image, dy: u32, fill: Color) {
+    let (width, height) = (image.width(), image.height());
+    let row_bytes = image.data.len() / height.max(1) as usize;
+    let dy = dy.min(height);
+    image.data.copy_within(dy as usize * row_bytes.., 0);
+    for y in height - dy..height {
+        for x in 0..width {
+            let _ = image.set_color_at(x, y, fill);
+        }
+    }
+}
+
+fn scroll_entities(world: &mut World, dy: f32) {
+    let mut query = world.query_filtered::<&mut Transform, With<Clearable>>();
+    for mut transform in query.iter_mut(world) {
+        transform.translation.y -= negate_y(dy);
+    }
+}
+
+/// Horizontal alignment of printed text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl From<Align> for JustifyText {
+    fn from(align: Align) -> Self {
+        match align {
+            Align::Left => JustifyText::Left,
+            Align::Center => JustifyText::Center,
+            Align::Right => JustifyText::Right,
+        }
+    }
+}
+
+/// Options for [Pico8::print_with] beyond those of `print()`
+#[derive(Debug, Clone, Default, Reflect)]
+pub struct PrintStyle {
+    pub font_size: Option<f32>,
+    pub font_index: Option<usize>,
+    /// Wrap lines wider than this many pixels.
+    pub max_width: Option<f32>,
+    /// With a `max_width`, lines are aligned within `[x, x + max_width]`.
+    /// Without one, `x` is the left, center, or right of the text.
+    pub align: Align,
+    /// Extra pixels between lines
+    pub line_spacing: f32,
+    /// Outline each glyph with this color.
+    pub outline: Option<N9Color>,
+    /// Draw a shadow at this offset with this color.
+    pub shadow: Option<(Vec2, N9Color)>,
+}
+
+/// Extra pixels between the lines of printed text
+#[derive(Component, Debug, Clone, Copy)]
+pub(crate) struct LineSpacing(f32);
+
+/// How far behind the text its outline and shadow are drawn
+const BEHIND: f32 = 0.0001;
+
+/// A copy of the parent text's glyphs drawn at each offset in its own color.
+///
+/// The copies reuse the parent's layout, so an outline costs one entity rather
+/// than printing the text eight more times.
+#[derive(Component, Debug, Clone)]
+pub(crate) struct TextEffect(Vec<Vec2>);
+
+impl TextEffect {
+    fn outline() -> Self {
+        TextEffect(
+            (-1..=1)
+                .flat_map(|y| (-1..=1).map(move |x| Vec2::new(x as f32, y as f32)))
+                .filter(|v| *v != Vec2::ZERO)
+                .collect(),
+        )
+    }
+}
+
+/// Sent with the text of each `print()`, so accessibility layers and
+/// overlays can surface on-screen text
+///
+/// Control codes are removed. Carts usually print the same text every frame,
+/// which a [Narrator] skips.
+#[derive(Debug, Clone, Event)]
+pub struct Printed {
+    pub text: String,
+    pub pos: Option<Vec2>,
+}
+
+impl Printed {
+    fn new(text: &str, pos: Option<Vec2>) -> Self {
+        Printed {
+            text: text
+                .chars()
+                .filter(|c| *c == '\n' || !c.is_control())
+                .collect(),
+            pos,
+        }
+    }
+}
+
+/// Speaks printed text, e.g., with a text-to-speech engine
+///
+/// It is called with text that was not printed in the previous frame.
+#[derive(Resource)]
+pub struct Narrator(pub Box<dyn Fn(&str) + Send + Sync>);
+
+pub(crate) fn narrate(
+    mut reader: EventReader<Printed>,
+    narrator: Option<Res<Narrator>>,
+    mut last_frame: Local<Vec<String>>,
+) {
+    let Some(narrator) = narrator else {
+        reader.clear();
+        return;
+    };
+    let this_frame: Vec<String> = reader.read().map(|printed| printed.text.clone()).collect();
+    for text in &this_frame {
+        if !text.trim().is_empty() && !last_frame.contains(text) {
+            (narrator.0)(text);
+        }
+    }
+    *last_frame = this_frame;
+}
+
 impl super::Pico8<'_, '_> {
     pub fn cursor(&mut self, pos: Option<Vec2>, color: Option<PColor>) -> (Vec2, PColor) {
         let last_pos = self.state.draw_state.print_cursor;
@@ -25,54 +202,336 @@ impl super::Pico8<'_, '_> {
         font_size: Option<f32>,
         font_index: Option<usize>,
     ) -> Result<Entity, Error> {
-        let text = text.into();
+        self.print_with(
+            text,
+            pos,
+            color,
+            PrintStyle {
+                font_size,
+                font_index,
+                ..default()
+            },
+        )
+    }
+
+    /// Print the given text with word wrapping, alignment, or line spacing.
+    ///
+    /// In framebuffer mode, the text is drawn into the canvas, the style is
+    /// ignored, and [Entity::PLACEHOLDER] is returned.
+    pub fn print_with(
+        &mut self,
+        text: impl Into<String>,
+        pos: Option<Vec2>,
+        color: Option<N9Color>,
+        style: PrintStyle,
+    ) -> Result<Entity, Error> {
+        let text = expand_tabs(&text.into());
+        self.charge(cost::chars(text.chars().count()));
+        self.commands.send_event(Printed::new(&text, pos));
+        if self.canvas.framebuffer {
+            self.print_canvas(&text, pos, color)?;
+            return Ok(Entity::PLACEHOLDER);
+        }
         let id = self.commands.spawn_empty().id();
+        self.tag_drawn(id);
         self.commands.queue(move |world: &mut World| {
-            if let Err(e) =
-                Self::print_world(world, Some(id), text, pos, color, font_size, font_index)
-            {
+            let result = if text.contains(CUSTOM_FONT_ON) {
+                Self::print_runs_world(world, id, text, pos, color, &style)
+            } else {
+                Self::print_world(world, Some(id), text, pos, color, &style)
+            };
+            if let Err(e) = result {
                 warn!("print error {e}");
             }
         });
         Ok(id)
     }
 
+    /// Draw text into the canvas with Pico-8's font or, after "\x0e", the
+    /// custom font. Returns the x after the last character.
+    fn print_canvas(
+        &mut self,
+        text: &str,
+        pos: Option<Vec2>,
+        color: Option<N9Color>,
+    ) -> Result<f32, Error> {
+        let (text, add_newline) = match text.strip_suffix('\0') {
+            Some(text) => (text, false),
+            None => (text, true),
+        };
+        let c = self.get_color(color.unwrap_or(N9Color::Pen))?;
+        if pos.is_none() {
+            let dy = scroll_amount(
+                self.state.draw_state.print_cursor.y,
+                text.split('\n').count(),
+                pico8_font().height() as f32,
+                self.canvas.size.y as f32,
+            );
+            if dy > 0.0 {
+                let fill = self.get_color(PColor::Palette(0))?;
+                let image = self
+                    .images
+                    .get_mut(&self.canvas.handle)
+                    .ok_or(Error::NoAsset("canvas".into()))?;
+                scroll_up(image, dy as u32, fill);
+                self.state.draw_state.print_cursor.y -= dy;
+                self.commands
+                    .queue(move |world: &mut World| scroll_entities(world, dy));
+            }
+        }
+        let start = pos
+            .map(|p| pixel_snap(self.state.draw_state.apply_camera_delta(p)))
+            .unwrap_or_else(|| pixel_snap(self.state.draw_state.print_cursor))
+            .as_ivec2();
+        let image = self
+            .images
+            .get_mut(&self.canvas.handle)
+            .ok_or(Error::NoAsset("canvas".into()))?;
+        let size = UVec2::new(image.width(), image.height());
+        let mut custom = false;
+        let mut x = start.x;
+        let mut y = start.y;
+        let mut line_height = 0;
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                x = start.x;
+                y += line_height as i32;
+                line_height = 0;
+            }
+            for run in line.split_inclusive([CUSTOM_FONT_ON, CUSTOM_FONT_OFF]) {
+                let (run, next_custom) = match run.strip_suffix(CUSTOM_FONT_ON) {
+                    Some(run) => (run, true),
+                    None => match run.strip_suffix(CUSTOM_FONT_OFF) {
+                        Some(run) => (run, false),
+                        None => (run, custom),
+                    },
+                };
+                let font = if custom {
+                    &self.state.custom_font
+                } else {
+                    pico8_font()
+                };
+                let chars = p8scii::utf8_to_vec(run);
+                font.rasterize(&chars, |dx, dy| {
+                    let p = IVec2::new(x + dx, y + dy);
+                    if p.x >= 0 && p.y >= 0 && (p.x as u32) < size.x && (p.y as u32) < size.y {
+                        let _ = image.set_color_at(p.x as u32, p.y as u32, c);
+                    }
+                });
+                x += font.text_size(&chars).x as i32;
+                line_height = line_height.max(font.height());
+                custom = next_custom;
+            }
+        }
+        let cursor = &mut self.state.draw_state.print_cursor;
+        if add_newline {
+            *cursor = Vec2::new(start.x as f32, (y + line_height as i32) as f32);
+        } else {
+            cursor.x = x as f32;
+        }
+        self.state.draw_state.mark_drawn();
+        Ok(x as f32)
+    }
+
+    /// Print text that switches between the regular font and the custom font
+    /// with the "\x0e" and "\x0f" control codes.
+    fn print_runs_world(
+        world: &mut World,
+        dest: Entity,
+        mut text: String,
+        mut pos: Option<Vec2>,
+        color: Option<N9Color>,
+        style: &PrintStyle,
+    ) -> Result<f32, Error> {
+        let add_newline = if text.ends_with('\0') {
+            text.pop();
+            false
+        } else {
+            true
+        };
+        let mut custom = false;
+        let mut dest = Some(dest);
+        let mut start: Option<Vec2> = None;
+        let mut height: f32 = 0.0;
+        let mut x = 0.0;
+        for run in text.split_inclusive([CUSTOM_FONT_ON, CUSTOM_FONT_OFF]) {
+            let (run, next_custom) = match run.strip_suffix(CUSTOM_FONT_ON) {
+                Some(run) => (run, true),
+                None => match run.strip_suffix(CUSTOM_FONT_OFF) {
+                    Some(run) => (run, false),
+                    None => (run, custom),
+                },
+            };
+            if !run.is_empty() {
+                let id = dest.take().unwrap_or_else(|| world.spawn_empty().id());
+                let size = if custom {
+                    Self::print_custom_world(world, id, run, pos.take(), color)?
+                } else {
+                    Self::print_world(
+                        world,
+                        Some(id),
+                        format!("{run}\0"),
+                        pos.take(),
+                        color,
+                        style,
+                    )?;
+                    world
+                        .get::<TextLayoutInfo>(id)
+                        .map(|layout| layout.size)
+                        .unwrap_or_default()
+                };
+                let cursor = world.resource::<Pico8State>().draw_state.print_cursor;
+                start.get_or_insert(Vec2::new(cursor.x - size.x, cursor.y));
+                height = height.max(size.y);
+                x = cursor.x;
+            }
+            custom = next_custom;
+        }
+        if add_newline {
+            if let Some(start) = start {
+                let mut state = world.resource_mut::<Pico8State>();
+                state.draw_state.print_cursor = Vec2::new(start.x, start.y + height);
+            }
+        }
+        Ok(x)
+    }
+
+    /// Print with the custom font. Returns the size of the printed text.
+    fn print_custom_world(
+        world: &mut World,
+        id: Entity,
+        text: &str,
+        pos: Option<Vec2>,
+        color: Option<N9Color>,
+    ) -> Result<Vec2, Error> {
+        let assets = world.resource::<Assets<Pico8Asset>>();
+        let state = world.resource::<Pico8State>();
+        let pico8_handle = world.resource::<Pico8Handle>();
+        let pico8_asset = assets
+            .get(&pico8_handle.handle)
+            .ok_or(Error::NoSuch("Pico8Asset".into()))?;
+        let c = pico8_asset.get_color(
+            state.pcolor(color.unwrap_or(N9Color::Pen), pico8_asset),
+            state.palette,
+        )?;
+        let pos = pos
+            .map(|p| pixel_snap(state.draw_state.apply_camera_delta(p)))
+            .unwrap_or_else(|| pixel_snap(state.draw_state.print_cursor));
+        let priority = state.draw_state.priority;
+        let font = state.custom_font.clone();
+        let text = p8scii::utf8_to_vec(text);
+        let size = font.text_size(&text);
+        let mut data = vec![0u8; (size.x * size.y * 4) as usize];
+        font.rasterize(&text, |x, y| {
+            if x >= 0 && y >= 0 && (x as u32) < size.x && (y as u32) < size.y {
+                let i = ((x as u32 + y as u32 * size.x) * 4) as usize;
+                data[i..i + 4].copy_from_slice(&[0xff; 4]);
+            }
+        });
+        if size.x > 0 && size.y > 0 {
+            let mut image = Image::new(
+                Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                data,
+                TextureFormat::Rgba8UnormSrgb,
+                RenderAssetUsages::RENDER_WORLD,
+            );
+            image.sampler = ImageSampler::nearest();
+            let handle = world.resource_mut::<Assets<Image>>().add(image);
+            let clearable = Clearable::default().with_priority(priority);
+            world.entity_mut(id).insert((
+                Name::new("print"),
+                Sprite {
+                    image: handle,
+                    color: c,
+                    anchor: Anchor::TopLeft,
+                    ..default()
+                },
+                Transform::from_xyz(pos.x, negate_y(pos.y), clearable.suggest_z()),
+                clearable,
+            ));
+        }
+        let size = size.as_vec2();
+        let mut state = world.resource_mut::<Pico8State>();
+        state.draw_state.print_cursor.x = pos.x + size.x;
+        state.draw_state.mark_drawn();
+        Ok(size)
+    }
+
     pub(crate) fn print_world(
         world: &mut World,
         dest: Option<Entity>,
         text: String,
         pos: Option<Vec2>,
         color: Option<N9Color>,
-        font_size: Option<f32>,
-        font_index: Option<usize>,
+        style: &PrintStyle,
     ) -> Result<f32, Error> {
-        let (id, add_newline) =
-            Self::pre_print_world(world, dest, text, pos, color, font_size, font_index)?;
+        let (id, add_newline) = Self::pre_print_world(world, dest, text, pos, color, style)?;
         world
             .run_system_cached(bevy::text::update_text2d_layout)
             .expect("update_text2d_layout");
         world
-            .run_system_cached_with(Self::post_print_world, (id, add_newline))
+            .run_system_cached_with(
+                Self::post_print_world,
+                (id, add_newline, style.line_spacing),
+            )
             .expect("post_print_world")
     }
 
     fn post_print_world(
-        In((id, add_newline)): In<(Entity, bool)>,
-        query: Query<(&Transform, &TextLayoutInfo)>,
+        In((id, add_newline, line_spacing)): In<(Entity, bool, f32)>,
+        query: Query<(&Transform, &Anchor, &TextFont, &TextLayoutInfo)>,
         mut state: ResMut<Pico8State>,
     ) -> Result<f32, Error> {
-        let (transform, text_layout) = query
+        let (transform, anchor, text_font, text_layout) = query
             .get(id)
             .map_err(|_| Error::NoSuch("text layout".into()))?;
         let pos = &transform.translation;
+        let mut size = text_layout.size;
+        // The spacing is applied later by `style_text`.
+        size.y += (line_count(size.y, text_font.font_size) - 1.0) * line_spacing;
+        let left = pos.x - (anchor.as_vec().x + 0.5) * size.x;
         if add_newline {
             state.draw_state.print_cursor.x = pos.x;
-            state.draw_state.print_cursor.y = negate_y(pos.y) + text_layout.size.y;
+            state.draw_state.print_cursor.y = negate_y(pos.y) + size.y + line_spacing;
         } else {
-            state.draw_state.print_cursor.x = pos.x + text_layout.size.x;
+            state.draw_state.print_cursor.x = left + size.x;
         }
         state.draw_state.mark_drawn();
-        Ok(pos.x + text_layout.size.x)
+        Ok(left + size.x)
+    }
+
+    /// Scroll the canvas up if text printed at the cursor would pass the
+    /// bottom.
+    fn scroll_world(world: &mut World, text: &str, style: &PrintStyle) -> Result<(), Error> {
+        let canvas = world.resource::<crate::N9Canvas>();
+        let (handle, height) = (canvas.handle.clone(), canvas.size.y as f32);
+        let state = world.resource::<Pico8State>();
+        let dy = scroll_amount(
+            state.draw_state.print_cursor.y,
+            text.trim_end_matches('\0').split('\n').count(),
+            style.font_size.unwrap_or(5.0) * LINE_HEIGHT + style.line_spacing,
+            height,
+        );
+        if dy <= 0.0 {
+            return Ok(());
+        }
+        let pico8_handle = world.resource::<Pico8Handle>();
+        let fill = world
+            .resource::<Assets<Pico8Asset>>()
+            .get(&pico8_handle.handle)
+            .ok_or(Error::NoSuch("Pico8Asset".into()))?
+            .get_color(PColor::Palette(0), state.palette)?;
+        if let Some(image) = world.resource_mut::<Assets<Image>>().get_mut(&handle) {
+            scroll_up(image, dy as u32, fill);
+        }
+        scroll_entities(world, dy);
+        world.resource_mut::<Pico8State>().draw_state.print_cursor.y -= dy;
+        Ok(())
     }
 
     fn pre_print_world(
@@ -81,9 +540,11 @@ impl super::Pico8<'_, '_> {
         mut text: String,
         pos: Option<Vec2>,
         color: Option<N9Color>,
-        font_size: Option<f32>,
-        font_index: Option<usize>,
+        style: &PrintStyle,
     ) -> Result<(Entity, bool), Error> {
+        if pos.is_none() {
+            Self::scroll_world(world, &text, style)?;
+        }
         let assets = world
             .get_resource::<Assets<Pico8Asset>>()
             .expect("Pico8Assets");
@@ -92,17 +553,16 @@ impl super::Pico8<'_, '_> {
         let pico8_asset = assets
             .get(&pico8_handle.handle)
             .ok_or(Error::NoSuch("Pico8Asset".into()))?;
-        let font = pico8_asset
-            .font
-            .get(font_index.unwrap_or(0))
-            .ok_or(Error::NoSuch("font".into()))?
-            .handle
-            .clone();
+        let font_index = style
+            .font_index
+            .or_else(|| pico8_asset.locale_font(state.locale.as_deref()))
+            .unwrap_or(0);
+        if font_index >= pico8_asset.font.len() {
+            return Err(Error::NoSuch("font".into()));
+        }
 
         let c = pico8_asset.get_color(
-            color
-                .unwrap_or(N9Color::Pen)
-                .into_pcolor(&state.draw_state.pen),
+            state.pcolor(color.unwrap_or(N9Color::Pen), pico8_asset),
             state.palette,
         )?;
         // XXX: Should the camera delta apply to the print cursor position?
@@ -114,30 +574,94 @@ impl super::Pico8<'_, '_> {
                     state.draw_state.print_cursor.y,
                 ))
             });
-        let clearable = Clearable::default();
+        let clearable = Clearable::default().with_priority(state.draw_state.priority);
         let add_newline = if text.ends_with('\0') {
             text.pop();
             false
         } else {
             true
         };
-        let font_size = font_size.unwrap_or(5.0);
+        let font_size = style.font_size.unwrap_or(5.0);
+        let runs = font_runs(
+            &text,
+            &pico8_asset.font,
+            font_index,
+            world.resource::<Assets<Font>>(),
+        );
+        let text_font = |index: usize| {
+            let n9font = &pico8_asset.font[index];
+            TextFont {
+                font: n9font.handle.clone(),
+                font_smoothing: bevy::text::FontSmoothing::None,
+                font_size: if index == font_index {
+                    font_size
+                } else {
+                    n9font.height.unwrap_or(font_size)
+                },
+            }
+        };
+        let mut runs = runs.into_iter().map(|(index, run)| (text_font(index), run));
+        let (root_font, root_text) = runs
+            .next()
+            .unwrap_or_else(|| (text_font(font_index), String::new()));
+        let spans: Vec<_> = runs.collect();
+        let effect_color =
+            |color: N9Color| pico8_asset.get_color(state.pcolor(color, pico8_asset), state.palette);
+        let mut effects = Vec::new();
+        if let Some(color) = style.outline {
+            effects.push((TextEffect::outline(), effect_color(color)?, -BEHIND));
+        }
+        if let Some((offset, color)) = style.shadow {
+            effects.push((
+                TextEffect(vec![offset]),
+                effect_color(color)?,
+                -2.0 * BEHIND,
+            ));
+        }
+        let anchor = match (style.max_width, style.align) {
+            (None, Align::Center) => Anchor::TopCenter,
+            (None, Align::Right) => Anchor::TopRight,
+            _ => Anchor::TopLeft,
+        };
         let z = clearable.suggest_z();
         let id = entity.unwrap_or_else(|| world.spawn_empty().id());
         world.entity_mut(id).insert((
             Name::new("print"),
             Transform::from_xyz(pos.x, negate_y(pos.y), z),
-            Text2d::new(text),
+            Text2d::new(root_text),
             Visibility::default(),
             TextColor(c),
-            TextFont {
-                font,
-                font_smoothing: bevy::text::FontSmoothing::None,
-                font_size,
+            root_font,
+            TextLayout::new_with_justify(style.align.into()),
+            TextBounds {
+                width: style.max_width,
+                height: None,
             },
-            Anchor::TopLeft,
+            anchor,
             clearable,
         ));
+        if style.line_spacing != 0.0 {
+            world.entity_mut(id).insert(LineSpacing(style.line_spacing));
+        }
+        for (text_font, run) in spans {
+            let span = world
+                .spawn((TextSpan::new(run), TextColor(c), text_font))
+                .id();
+            world.entity_mut(id).add_child(span);
+        }
+        for (effect, color, z) in effects {
+            let child = world
+                .spawn((
+                    Name::new("print effect"),
+                    effect,
+                    Text2d::default(),
+                    TextColor(color),
+                    anchor,
+                    Transform::from_xyz(0.0, 0.0, z),
+                ))
+                .id();
+            world.entity_mut(id).add_child(child);
+        }
         Ok((id, add_newline))
     }
 
@@ -167,3 +691,181 @@ impl super::Pico8<'_, '_> {
         }
     }
 }
+
+/// Pico-8's font as a bitmap for drawing into the canvas
+pub(crate) fn pico8_font() -> &'static BitmapFont {
+    static FONT: OnceLock<BitmapFont> = OnceLock::new();
+    FONT.get_or_init(|| {
+        let font_size = 5.0;
+        let mut font = BitmapFont::from_ttf(include_bytes!("../pico-8-wide.ttf"), font_size)
+            .expect("pico-8 font");
+        // Match the line height of printed text entities.
+        font.data[2] = (font_size * LINE_HEIGHT).round() as u8;
+        font
+    })
+}
+
+/// Apply line spacing and effects whenever Bevy lays out printed text.
+pub(crate) fn style_text(
+    mut query: Query<
+        (Entity, &TextFont, Option<&LineSpacing>, &mut TextLayoutInfo),
+        (Changed<ComputedTextBlock>, Without<TextEffect>),
+    >,
+    mut effects: Query<(&TextEffect, &mut TextLayoutInfo)>,
+    children: Query<&Children>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+) {
+    let scale_factor = windows
+        .get_single()
+        .map(|window| window.resolution.scale_factor())
+        .unwrap_or(1.0);
+    for (id, text_font, line_spacing, mut text_layout) in &mut query {
+        if let Some(LineSpacing(spacing)) = line_spacing {
+            space_lines(
+                &mut text_layout,
+                text_font.font_size,
+                *spacing,
+                scale_factor,
+            );
+        }
+        for child in children.iter_descendants(id) {
+            let Ok((effect, mut effect_layout)) = effects.get_mut(child) else {
+                continue;
+            };
+            effect_layout.size = text_layout.size;
+            effect_layout.glyphs = effect
+                .0
+                .iter()
+                .flat_map(|offset| {
+                    // Glyph positions are in physical pixels from the bottom.
+                    let offset = Vec2::new(offset.x, -offset.y) * scale_factor;
+                    text_layout.glyphs.iter().map(move |glyph| {
+                        let mut glyph = glyph.clone();
+                        glyph.position += offset;
+                        // The effect's only span is itself.
+                        glyph.span_index = 0;
+                        glyph
+                    })
+                })
+                .collect();
+        }
+    }
+}
+
+fn line_count(height: f32, font_size: f32) -> f32 {
+    (height / (font_size * LINE_HEIGHT)).round().max(1.0)
+}
+
+/// Add `spacing` pixels between the lines of a text layout.
+///
+/// Glyph positions are in physical pixels measured from the bottom of the
+/// text block while its size is in logical pixels.
+fn space_lines(layout: &mut TextLayoutInfo, font_size: f32, spacing: f32, scale_factor: f32) {
+    let line_height = font_size * LINE_HEIGHT;
+    let lines = line_count(layout.size.y, font_size);
+    let height = layout.size.y * scale_factor;
+    for glyph in &mut layout.glyphs {
+        let line = ((height - glyph.position.y) / (line_height * scale_factor))
+            .floor()
+            .clamp(0.0, lines - 1.0);
+        glyph.position.y += (lines - 1.0 - line) * spacing * scale_factor;
+    }
+    layout.size.y += (lines - 1.0) * spacing;
+}
+
+/// Split `text` into runs of the first font that covers each character.
+///
+/// The font at `font_index` is tried first then the other fonts in order.
+/// Fonts that have not loaded yet are assumed to cover everything.
+fn font_runs(
+    text: &str,
+    n9fonts: &[N9Font],
+    font_index: usize,
+    fonts: &Assets<Font>,
+) -> Vec<(usize, String)> {
+    let chain: Vec<usize> = std::iter::once(font_index)
+        .chain((0..n9fonts.len()).filter(|i| *i != font_index))
+        .collect();
+    let fonts: Vec<Option<&Font>> = chain
+        .iter()
+        .map(|i| fonts.get(&n9fonts[*i].handle))
+        .collect();
+    let faces: Vec<Option<ttf_parser::Face>> = fonts
+        .iter()
+        .map(|font| font.and_then(|font| ttf_parser::Face::parse(&font.data, 0).ok()))
+        .collect();
+    let covers = |face: &Option<ttf_parser::Face>, c: char| {
+        c.is_control()
+            || face
+                .as_ref()
+                .map(|face| face.glyph_index(c).is_some())
+                .unwrap_or(true)
+    };
+    let mut runs: Vec<(usize, String)> = Vec::new();
+    for c in text.chars() {
+        let index = faces
+            .iter()
+            .position(|face| covers(face, c))
+            .map(|i| chain[i])
+            .unwrap_or(font_index);
+        match runs.last_mut() {
+            Some((last, run)) if *last == index || c.is_whitespace() => run.push(c),
+            _ => runs.push((index, c.to_string())),
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_expand_tabs() {
+        assert_eq!(expand_tabs("a\tb"), "a   b");
+        assert_eq!(expand_tabs("abcd\tb\n\tc"), "abcd    b\n    c");
+    }
+
+    #[test]
+    fn test_scroll_amount() {
+        assert_eq!(scroll_amount(120.0, 1, 6.0, 128.0), 0.0);
+        assert_eq!(scroll_amount(126.0, 2, 6.0, 128.0), 10.0);
+    }
+
+    #[test]
+    fn test_printed_text() {
+        let printed = Printed::new("\x0ehi\x0f\nthere", None);
+        assert_eq!(printed.text, "hi\nthere");
+    }
+
+    #[test]
+    fn test_font_runs() {
+        let mut fonts = Assets::<Font>::default();
+        let pico8 =
+            fonts.add(Font::try_from_bytes(include_bytes!("../pico-8-wide.ttf").to_vec()).unwrap());
+        let n9fonts = [
+            N9Font {
+                handle: pico8,
+                height: None,
+            },
+            N9Font {
+                handle: Handle::default(),
+                height: Some(12.0),
+            },
+        ];
+        assert_eq!(
+            font_runs("hi 日本", &n9fonts, 0, &fonts),
+            vec![(0, "hi ".into()), (1, "日本".into())]
+        );
+        assert_eq!(font_runs("hi", &n9fonts, 1, &fonts), vec![(1, "hi".into())]);
+    }
+
+    #[test]
+    fn test_pico8_font() {
+        let font = pico8_font();
+        assert_eq!(font.char_width(b'a'), 4);
+        assert_eq!(font.height(), 6);
+        assert_eq!(font.glyph(b' '), &[0; 8]);
+        assert_eq!(font.glyph(b'a')[..5], [0b000, 0b110, 0b101, 0b111, 0b101]);
+    }
+}
@@ -10,7 +10,8 @@ impl super::Pico8<'_, '_> {
         let upper_left = pixel_snap(self.state.draw_state.apply_camera_delta(upper_left));
         let lower_right = pixel_snap(self.state.draw_state.apply_camera_delta(lower_right));
         let size = (lower_right - upper_left) + Vec2::ONE;
-        let clearable = Clearable::default();
+        self.charge(cost::pixels(size.x * size.y));
+        let clearable = self.clearable();
         let color = color.map(|x| x.into());
         let id = self
             .commands
@@ -86,7 +87,7 @@ impl super::Pico8<'_, '_> {
             ))
             .id();
         self.state.draw_state.mark_drawn();
-        Ok(id)
+        Ok(self.tag_drawn(id))
     }
 
     pub fn rect(
@@ -99,7 +100,8 @@ impl super::Pico8<'_, '_> {
         let lower_right = pixel_snap(self.state.draw_state.apply_camera_delta(lower_right));
         let c = self.get_color(color.unwrap_or(N9Color::Pen))?;
         let size = (lower_right - upper_left) + Vec2::ONE;
-        let clearable = Clearable::default();
+        self.charge(cost::pixels(2.0 * (size.x + size.y)));
+        let clearable = self.clearable();
         let id = self
             .commands
             .spawn((
@@ -122,7 +124,7 @@ impl super::Pico8<'_, '_> {
             ))
             .id();
         self.state.draw_state.mark_drawn();
-        Ok(id)
+        Ok(self.tag_drawn(id))
     }
 
     pub fn fillp(&mut self, pattern: Option<u16>) -> u16 {
@@ -0,0 +1,137 @@
+use super::*;
+
+/// Pico-8's pseudo-random number generator
+///
+/// This follows the algorithm used by Pico-8 (as documented by zepto8) so a
+/// seeded cart produces the same sequence here as it does there.
+#[derive(Debug, Clone, Copy, Reflect)]
+pub struct Rng {
+    lo: u32,
+    hi: u32,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng::new(0.0)
+    }
+}
+
+impl Rng {
+    /// Create a generator as `srand(seed)` would.
+    pub fn new(seed: f32) -> Self {
+        let bits = (seed * 65536.0) as i64 as u32;
+        let lo = if bits == 0 { 0xdeadbeef } else { bits };
+        let mut rng = Rng {
+            lo,
+            hi: lo ^ 0xbead29ba,
+        };
+        for _ in 0..32 {
+            rng.next_bits(0);
+        }
+        rng
+    }
+
+    /// Create a generator seeded from the system clock.
+    pub fn from_clock() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        Rng::new(nanos as f32 / 65536.0)
+    }
+
+    /// Advance and return a 16.16 fixed-point value in [0, range).
+    fn next_bits(&mut self, range: u32) -> u32 {
+        self.hi = self.hi.rotate_left(16).wrapping_add(self.lo);
+        self.lo = self.lo.wrapping_add(self.hi);
+        if range == 0 {
+            0
+        } else {
+            self.hi % range
+        }
+    }
+
+    /// Return a number in [0, x).
+    pub fn rnd(&mut self, x: f32) -> f32 {
+        let range = (x * 65536.0) as i64 as u32;
+        self.next_bits(range) as f32 / 65536.0
+    }
+
+    /// Return an index in [0, len).
+    ///
+    /// Short lists match `flr(rnd(len))` but take the integer part of the
+    /// 16.16 value directly, since converting it to `f32` may round up to
+    /// `len`. Past 0xffff items, `rnd()` would wrap its 16.16 range, so the
+    /// index is scaled from the raw bits instead.
+    pub fn index(&mut self, len: usize) -> usize {
+        if len <= 0xffff {
+            return (self.next_bits((len as u32) << 16) >> 16) as usize;
+        }
+        self.next_bits(0);
+        ((self.hi as u128 * len as u128) >> 32) as usize
+    }
+}
+
+impl super::Pico8<'_, '_> {
+    /// rnd([x])
+    ///
+    /// Return a random number in [0, x) where x defaults to 1.
+    pub fn rnd(&mut self, x: Option<f32>) -> f32 {
+        self.state.rng.rnd(x.unwrap_or(1.0))
+    }
+
+    /// rnd(tbl)
+    ///
+    /// Return a random element of `items` or `None` if it is empty.
+    pub fn rnd_in<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        let index = self.state.rng.index(items.len());
+        items.get(index)
+    }
+
+    /// srand(seed)
+    pub fn srand(&mut self, seed: f32) {
+        self.state.rng = Rng::new(seed);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn seeded_rng_repeats() {
+        let mut a = Rng::new(42.0);
+        let mut b = Rng::new(42.0);
+        for _ in 0..100 {
+            let x = a.rnd(10.0);
+            assert_eq!(x, b.rnd(10.0));
+            assert!((0.0..10.0).contains(&x));
+        }
+        assert_ne!(Rng::new(1.0).rnd(1.0), Rng::new(2.0).rnd(1.0));
+        assert_eq!(Rng::new(0.0).rnd(0.0), 0.0);
+    }
+
+    #[test]
+    fn index_long_lists() {
+        let mut rng = Rng::new(7.0);
+        for len in [0x10000, 70_000, 1 << 20] {
+            let indices: Vec<usize> = (0..64).map(|_| rng.index(len)).collect();
+            assert!(indices.iter().all(|&i| i < len));
+            assert!(indices.iter().any(|&i| i >= 0xffff / 2));
+        }
+        let mut a = Rng::new(3.0);
+        let mut b = Rng::new(3.0);
+        assert_eq!(a.index(10), b.rnd(10.0) as usize);
+    }
+
+    #[test]
+    fn index_short_lists() {
+        let mut rng = Rng::new(5.0);
+        for len in [1, 2, 300, 0xfffe, 0xffff] {
+            assert!((0..1000).all(|_| rng.index(len) < len));
+        }
+    }
+}
@@ -1,7 +1,20 @@
 use super::*;
-use bevy::audio::PlaybackMode;
+use bevy::{audio::PlaybackMode, ecs::system::SystemParam};
 
-use crate::pico8::audio::{AudioCommand, SfxDest};
+use crate::pico8::audio::{
+    Audio, AudioCommand, ChannelEffects, Loop, Pico8Note, ReservedChannels, Sfx, SfxChannels,
+    SfxDest,
+};
+
+/// The number of notes of a Pico-8 sfx
+const SFX_NOTES: usize = 32;
+
+/// Sfx channels and assets
+#[derive(SystemParam)]
+pub struct SfxParam<'w> {
+    pub(crate) channels: Res<'w, SfxChannels>,
+    pub(crate) sfxs: ResMut<'w, Assets<Sfx>>,
+}
 
 #[derive(Debug, Clone, Copy)]
 pub enum SfxCommand {
@@ -17,11 +30,16 @@ impl From<u8> for SfxCommand {
 }
 
 impl super::Pico8<'_, '_> {
-    // sfx( n, [channel,] [offset,] [length] )
+    /// sfx( n, [channel,] [offset,] [length] )
+    ///
+    /// Without a `channel` or with -1, sfx `n` plays on an idle channel or
+    /// else steals the channel that started playing first; channels reserved
+    /// by `music()` are skipped. A `channel` of -2 stops sfx `n` wherever it
+    /// plays.
     pub fn sfx(
         &mut self,
         n: impl Into<SfxCommand>,
-        channel: Option<u8>,
+        channel: Option<i8>,
         offset: Option<u8>,
         length: Option<u8>,
         bank: Option<u8>,
@@ -30,58 +48,164 @@ impl super::Pico8<'_, '_> {
         assert!(length.is_none(), "length not implemented");
         let n = n.into();
         let bank = bank.unwrap_or(0);
+        let dest = match channel {
+            None | Some(-1) => SfxDest::Any,
+            Some(-2) => {
+                if let SfxCommand::Play(n) = n {
+                    let sfx = self.get_sfx(bank, n)?;
+                    if let Audio::Sfx(sfx) = sfx {
+                        self.commands.queue(AudioCommand::StopSfx(sfx));
+                    }
+                }
+                return Ok(());
+            }
+            Some(chan) => {
+                SfxDest::Channel(u8::try_from(chan).map_err(|_| Error::NoChannel(chan as u8))?)
+            }
+        };
         match n {
             SfxCommand::Release => {
-                if let Some(chan) = channel {
-                    // let chan = self.sfx_channels[chan as usize];
-                    self.commands
-                        .queue(AudioCommand::Release(SfxDest::Channel(chan)));
-                } else {
-                    self.commands.queue(AudioCommand::Release(SfxDest::Any));
-                }
+                self.commands.queue(AudioCommand::Release(dest));
             }
             SfxCommand::Stop => {
-                if let Some(chan) = channel {
-                    // let chan = self.sfx_channels[chan as usize];
-                    self.commands.queue(AudioCommand::Stop(
-                        SfxDest::Channel(chan),
-                        Some(PlaybackMode::Remove),
-                    ));
-                } else {
-                    self.commands
-                        .queue(AudioCommand::Stop(SfxDest::All, Some(PlaybackMode::Remove)));
-                }
+                let dest = match dest {
+                    SfxDest::Any => SfxDest::All,
+                    dest => dest,
+                };
+                self.commands
+                    .queue(AudioCommand::Stop(dest, Some(PlaybackMode::Remove)));
             }
             SfxCommand::Play(n) => {
-                let sfx = self
-                    .pico8_asset()?
-                    .audio_banks
-                    .get(bank as usize)
-                    .ok_or(Error::NoAsset(format!("bank {bank}").into()))?
-                    .get(n as usize)
-                    .ok_or(Error::NoAsset(format!("sfx {n}").into()))?
-                    .clone();
+                let sfx = self.get_sfx(bank, n)?;
+                self.commands
+                    .queue(AudioCommand::Play(sfx, dest, PlaybackSettings::REMOVE));
+            }
+        }
+        Ok(())
+    }
 
-                if let Some(chan) = channel {
-                    // let chan = self.sfx_channels[chan as usize];
-                    self.commands.queue(AudioCommand::Play(
-                        sfx,
-                        SfxDest::Channel(chan),
-                        PlaybackSettings::REMOVE,
-                    ));
-                } else {
-                    self.commands.queue(AudioCommand::Play(
-                        sfx,
-                        SfxDest::Any,
-                        PlaybackSettings::REMOVE,
-                    ));
-                }
+    fn get_sfx(&self, bank: u8, n: u8) -> Result<Audio, Error> {
+        Ok(self
+            .pico8_asset()?
+            .audio_banks
+            .get(bank as usize)
+            .ok_or(Error::NoAsset(format!("bank {bank}").into()))?
+            .get(n as usize)
+            .ok_or(Error::NoAsset(format!("sfx {n}").into()))?
+            .clone())
+    }
+
+    /// Return sfx `n` of `bank` to compose.
+    fn sfx_asset_mut(&mut self, n: u8, bank: Option<u8>) -> Result<&mut Sfx, Error> {
+        let Audio::Sfx(handle) = self.get_sfx(bank.unwrap_or(0), n)? else {
+            return Err(Error::Unsupported(
+                format!("sfx {n} is not a Pico-8 sfx").into(),
+            ));
+        };
+        self.audio
+            .sfxs
+            .get_mut(&handle)
+            .ok_or(Error::NoAsset(format!("sfx {n}").into()))
+    }
+
+    /// sfx_new([bank])
+    ///
+    /// Add a silent sfx of 32 notes to `bank` and return its number.
+    pub fn sfx_new(&mut self, bank: Option<u8>) -> Result<u8, Error> {
+        let bank = bank.unwrap_or(0) as usize;
+        let handle = self.audio.sfxs.add(Sfx::new(vec![Pico8Note(0); SFX_NOTES]));
+        let sfx = &mut self
+            .pico8_asset_mut()?
+            .audio_banks
+            .get_mut(bank)
+            .ok_or(Error::NoAsset(format!("bank {bank}").into()))?
+            .sfx;
+        let n = u8::try_from(sfx.len())
+            .map_err(|_| Error::InvalidArgument(format!("bank {bank} is full").into()))?;
+        sfx.push(Audio::Sfx(handle));
+        Ok(n)
+    }
+
+    /// sfx_note(n, index, [note,] [bank])
+    ///
+    /// Return note `index` of sfx `n` and replace it if given. Make notes with
+    /// [Pico8Note::from_pico8]. Sfx playing now are not changed.
+    pub fn sfx_note(
+        &mut self,
+        n: u8,
+        index: usize,
+        note: Option<Pico8Note>,
+        bank: Option<u8>,
+    ) -> Result<Pico8Note, Error> {
+        if index >= SFX_NOTES {
+            return Err(Error::NoSuch(format!("note {index}").into()));
+        }
+        let sfx = self.sfx_asset_mut(n, bank)?;
+        let last = sfx.notes.get(index).copied().unwrap_or(Pico8Note(0));
+        if let Some(note) = note {
+            if sfx.notes.len() <= index {
+                sfx.notes.resize(index + 1, Pico8Note(0));
+            }
+            sfx.notes[index] = note;
+        }
+        Ok(last)
+    }
+
+    /// sfx_speed(n, [speed,] [bank])
+    ///
+    /// Return the speed of sfx `n`, the ticks each note lasts, and set it if
+    /// given.
+    pub fn sfx_speed(&mut self, n: u8, speed: Option<u8>, bank: Option<u8>) -> Result<u8, Error> {
+        let sfx = self.sfx_asset_mut(n, bank)?;
+        let last = sfx.speed;
+        if let Some(speed) = speed {
+            sfx.speed = speed.max(1);
+        }
+        Ok(last)
+    }
+
+    /// sfx_loop(n, [start, end,] [bank])
+    ///
+    /// Return the loop start and end of sfx `n` and set them if given. Like
+    /// Pico-8, a start and end of 0 means it does not loop.
+    pub fn sfx_loop(
+        &mut self,
+        n: u8,
+        points: Option<(u8, u8)>,
+        bank: Option<u8>,
+    ) -> Result<(u8, u8), Error> {
+        let sfx = self.sfx_asset_mut(n, bank)?;
+        let last = match &sfx.loop_maybe {
+            Some(Loop::Unstoppable { start, end } | Loop::Stoppable { start, end, .. }) => {
+                (start.unwrap_or(0), end.unwrap_or(0))
             }
+            None => (0, 0),
+        };
+        if let Some((start, end)) = points {
+            sfx.loop_maybe = (start != 0 || end != 0).then_some(Loop::Unstoppable {
+                start: Some(start),
+                end: Some(end),
+            });
         }
+        Ok(last)
+    }
+
+    /// channel_fx(chan, fx) sets the effects applied to sfx played on channel
+    /// `chan` from now on. An empty `fx` removes them.
+    pub fn channel_fx(&mut self, chan: u8, fx: Vec<ChannelFx>) -> Result<(), Error> {
+        let id = *self
+            .audio
+            .channels
+            .get(chan as usize)
+            .ok_or(Error::NoChannel(chan))?;
+        self.commands.entity(id).insert(ChannelEffects(fx));
         Ok(())
     }
 
-    // music( n, [facems,] [channelmask,] )
+    /// music( n, [facems,] [channelmask,] )
+    ///
+    /// A `channel_mask` reserves those channels from `sfx()` until the music
+    /// stops.
     pub fn music(
         &mut self,
         n: impl Into<SfxCommand>,
@@ -104,6 +228,8 @@ impl super::Pico8<'_, '_> {
                 self.commands
                     .queue(AudioCommand::Stop(SfxDest::All, Some(PlaybackMode::Loop)));
                 // }
+                self.commands.insert_resource(ReservedChannels(0));
+                self.state.music = None;
             }
             SfxCommand::Play(n) => {
                 let sfx = self
@@ -111,11 +237,14 @@ impl super::Pico8<'_, '_> {
                     .audio_banks
                     .get(bank as usize)
                     .ok_or(Error::NoSuch(format!("audio bank {bank}").into()))?
-                    .get(n as usize)
+                    .music(n as usize)
                     .ok_or(Error::NoAsset(format!("music {n}").into()))?
                     .clone();
+                self.state.music = Some(n as usize);
 
                 if let Some(mask) = channel_mask {
+                    // Keep sfx() off the music's channels.
+                    self.commands.insert_resource(ReservedChannels(mask));
                     self.commands.queue(AudioCommand::Play(
                         sfx,
                         SfxDest::ChannelMask(mask),
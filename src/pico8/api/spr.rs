@@ -70,6 +70,9 @@ impl super::Pico8<'_, '_> {
         let x = screen_pos.x;
         let y = screen_pos.y;
         let flip = flip.unwrap_or_default();
+        self.charge(cost::pixels(
+            screen_size.unwrap_or(sprite_rect.size()).element_product(),
+        ));
         let sheet_index = sheet_index.unwrap_or(0);
         let sheet = self
             .pico8_asset()?
@@ -85,7 +88,10 @@ impl super::Pico8<'_, '_> {
                     let palette = &self.palette(None)?.clone();
                     self.gfx_handles.get_or_create(
                         palette,
-                        &self.state.pal_map,
+                        &self
+                            .state
+                            .pal_map
+                            .with_sheet_transparency(sheet.transparent.as_deref()),
                         None,
                         &handle,
                         &self.gfxs,
@@ -100,8 +106,8 @@ impl super::Pico8<'_, '_> {
             flip_y: flip.y,
             ..default()
         };
-        let clearable = Clearable::default();
-        Ok(self
+        let clearable = self.clearable();
+        let id = self
             .commands
             .spawn((
                 Name::new("spr"),
@@ -109,7 +115,8 @@ impl super::Pico8<'_, '_> {
                 Transform::from_xyz(x, negate_y(y), clearable.suggest_z()),
                 clearable,
             ))
-            .id())
+            .id();
+        Ok(self.tag_drawn(id))
     }
 
     pub(crate) fn pico8_asset(&self) -> Result<&Pico8Asset, Error> {
@@ -124,7 +131,7 @@ impl super::Pico8<'_, '_> {
             .ok_or(Error::NoSuch("Pico8Asset".into()))
     }
 
-    fn sprite_sheet(&self, sheet_index: Option<usize>) -> Result<&SpriteSheet, Error> {
+    pub(crate) fn sprite_sheet(&self, sheet_index: Option<usize>) -> Result<&SpriteSheet, Error> {
         let index = sheet_index.unwrap_or(0);
         self.pico8_asset()?
             .sprite_sheets
@@ -153,7 +160,31 @@ impl super::Pico8<'_, '_> {
         let x = pos.x;
         let y = pos.y;
         let flip = flip.unwrap_or_default();
-        let (sprites, index): (&SpriteSheet, usize) = match spr.into() {
+        let (mut sprite, pixel_size) = self.make_sprite(spr.into(), size, flip)?;
+        self.charge(cost::pixels(pixel_size.element_product() * 4.0));
+        let clearable = self.clearable();
+        let mut transform = Transform::from_xyz(x, negate_y(y), clearable.suggest_z());
+        if let Some(turns) = turns {
+            transform.translation.x += pixel_size.x;
+            transform.translation.y += negate_y(pixel_size.y);
+            sprite.anchor = Anchor::Center;
+            transform.rotation = Quat::from_rotation_z(turns * 2.0 * PI);
+        }
+        let id = self
+            .commands
+            .spawn((Name::new("spr"), sprite, transform, clearable))
+            .id();
+        Ok(self.tag_drawn(id))
+    }
+
+    /// Make the sprite for `spr` and return it with half its size in pixels.
+    pub(crate) fn make_sprite(
+        &mut self,
+        spr: Spr,
+        size: Option<Vec2>,
+        flip: BVec2,
+    ) -> Result<(Sprite, Vec2), Error> {
+        let (sprites, index): (&SpriteSheet, usize) = match spr {
             Spr::Cur { sprite } => (self.sprite_sheet(None)?, sprite),
             Spr::From { sheet, sprite } => (self.sprite_sheet(Some(sheet))?, sprite),
             Spr::Set { sheet: _ } => {
@@ -172,13 +203,17 @@ impl super::Pico8<'_, '_> {
         });
         let pixel_size = sprites.sprite_size.as_vec2() * size.unwrap_or(Vec2::ONE) / 2.0;
 
+        let transparent = sprites.transparent.clone();
         let image = match sprites.handle.clone() {
             SprHandle::Image(handle) => handle,
             SprHandle::Gfx(handle) => {
                 let palette = &self.palette(None)?.clone();
                 self.gfx_handles.get_or_create(
                     palette,
-                    &self.state.pal_map,
+                    &self
+                        .state
+                        .pal_map
+                        .with_sheet_transparency(transparent.as_deref()),
                     None,
                     &handle,
                     &self.gfxs,
@@ -186,7 +221,7 @@ impl super::Pico8<'_, '_> {
                 )?
             }
         };
-        let mut sprite = {
+        let sprite = {
             Sprite {
                 image,
                 anchor: Anchor::TopLeft,
@@ -197,18 +232,7 @@ impl super::Pico8<'_, '_> {
                 ..default()
             }
         };
-        let clearable = Clearable::default();
-        let mut transform = Transform::from_xyz(x, negate_y(y), clearable.suggest_z());
-        if let Some(turns) = turns {
-            transform.translation.x += pixel_size.x;
-            transform.translation.y += negate_y(pixel_size.y);
-            sprite.anchor = Anchor::Center;
-            transform.rotation = Quat::from_rotation_z(turns * 2.0 * PI);
-        }
-        Ok(self
-            .commands
-            .spawn((Name::new("spr"), sprite, transform, clearable))
-            .id())
+        Ok((sprite, pixel_size))
     }
 
     pub fn sset(
@@ -245,6 +269,7 @@ impl super::Pico8<'_, '_> {
                 image.set_color_at(pos.x, pos.y, c)?;
             }
         }
+        self.gfx_changed();
         Ok(())
     }
 
@@ -270,6 +295,15 @@ impl super::Pico8<'_, '_> {
         })
     }
 
+    /// Return the animation `name` of a sprite sheet, e.g., an Aseprite tag.
+    pub fn anim(&self, name: &str, sheet_index: Option<usize>) -> Result<crate::anim::Anim, Error> {
+        self.sprite_sheet(sheet_index)?
+            .anims
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::NoSuch(format!("animation {name:?}").into()))
+    }
+
     pub fn fget(&self, index: Option<usize>, flag_index: Option<u8>) -> Result<u8, Error> {
         if index.is_none() {
             return Ok(0);
@@ -302,6 +336,10 @@ impl super::Pico8<'_, '_> {
 
     pub fn fset(&mut self, index: usize, flag_index: Option<u8>, value: u8) -> Result<(), Error> {
         let flags = &mut self.sprite_sheet_mut(None)?.flags;
+        // Sheets without flags, e.g., a PNG, get them as they are set.
+        if flags.len() <= index {
+            flags.resize(index + 1, 0);
+        }
         match flag_index {
             Some(flag_index) => {
                 if value != 0 {
@@ -1,4 +1,9 @@
 use super::*;
+use crate::{
+    config::Config,
+    pico8::{audio::AudioStats, DrawTag},
+};
+use bevy::utils::{Duration, HashMap};
 
 /// Pico8State's state.
 #[derive(Resource, Clone, Debug, Reflect)]
@@ -9,6 +14,69 @@ pub struct Pico8State {
     /// Current palette
     pub(crate) palette: usize,
     pub(crate) draw_state: DrawState,
+    pub(crate) cartdata: Option<CartData>,
+    /// Font used after the "\x0e" control code in `print()`
+    pub(crate) custom_font: BitmapFont,
+    pub(crate) cpu: CpuCycles,
+    /// Incremented whenever sprite or map data changes so cached maps are
+    /// redrawn.
+    pub(crate) revision: u64,
+    pub(crate) rng: Rng,
+    /// Time the cart has been running
+    pub(crate) clock: Duration,
+    pub(crate) camera_follow: Option<CameraFollow>,
+    pub(crate) cursor_sprite: Option<CursorSprite>,
+    pub(crate) cursor_visible: bool,
+    pub(crate) cursor_locked: bool,
+    pub(crate) mouse: MouseState,
+    #[reflect(ignore)]
+    pub(crate) buttons: Buttons,
+    /// Draw RGB colors as the nearest color of the palette.
+    pub(crate) snap_colors: bool,
+    /// Locale of `tr()`, e.g., "en"
+    pub(crate) locale: Option<String>,
+    /// Draw states saved by `push_state()`
+    #[reflect(ignore)]
+    pub(crate) draw_stack: Vec<SavedDrawState>,
+    /// Screen mode set by `vid()`
+    pub(crate) screen_mode: u8,
+    /// Music pattern started by `music()`
+    pub(crate) music: Option<usize>,
+    #[reflect(ignore)]
+    pub(crate) audio_stats: AudioStats,
+    /// Cart ticks per second or 0 for once per frame
+    pub(crate) fps: u16,
+    pub(crate) timescale: TimeScale,
+    /// Darkness and lights set by `lighting()` and `light()`
+    pub(crate) lighting: Lighting,
+    /// Drawn entities by their tag
+    #[reflect(ignore)]
+    pub(crate) tagged: HashMap<String, Vec<Entity>>,
+}
+
+/// What `push_state()` saves and `pop_state()` restores
+#[derive(Clone, Debug)]
+pub(crate) struct SavedDrawState {
+    pen: PColor,
+    camera_position: Vec2,
+    fill_pat: Option<FillPat>,
+    pal_map: PalMap,
+    palette: usize,
+    priority: i8,
+    tag: Option<String>,
+}
+
+impl Pico8State {
+    /// Return the color to draw for `color`, which is the pen's color for
+    /// [N9Color::Pen]. If colors are snapped, an RGB color becomes the nearest
+    /// color of the current palette.
+    pub(crate) fn pcolor(&self, color: impl Into<N9Color>, asset: &Pico8Asset) -> PColor {
+        let color = color.into().into_pcolor(&self.draw_state.pen);
+        match asset.palettes.get(self.palette) {
+            Some(palette) if self.snap_colors => color.snap(palette),
+            _ => color,
+        }
+    }
 }
 
 // XXX: Dump this after refactor.
@@ -23,6 +91,169 @@ impl FromWorld for Pico8State {
                 draw_state.pen = PColor::Palette(defaults.pen_color);
                 draw_state
             },
+            cartdata: None,
+            custom_font: BitmapFont::default(),
+            cpu: CpuCycles::default(),
+            revision: 0,
+            rng: world
+                .get_resource::<Determinism>()
+                .map(Determinism::rng)
+                .unwrap_or_else(Rng::from_clock),
+            clock: Duration::ZERO,
+            camera_follow: None,
+            cursor_sprite: None,
+            cursor_visible: true,
+            cursor_locked: false,
+            mouse: MouseState::default(),
+            buttons: Buttons::default(),
+            snap_colors: world
+                .get_resource::<Config>()
+                .and_then(|config| config.snap_colors)
+                .unwrap_or(false),
+            locale: world
+                .get_resource::<Config>()
+                .and_then(|config| config.locale.clone()),
+            draw_stack: Vec::new(),
+            screen_mode: 0,
+            music: None,
+            audio_stats: AudioStats::default(),
+            tagged: HashMap::default(),
+            timescale: TimeScale::default(),
+            lighting: world
+                .get_resource::<Config>()
+                .map(Lighting::from_config)
+                .unwrap_or_default(),
+            fps: world
+                .get_resource::<Config>()
+                .and_then(|config| config.frames_per_second)
+                .unwrap_or(crate::plugin::DEFAULT_FRAMES_PER_SECOND)
+                .into(),
+        }
+    }
+}
+
+impl super::Pico8<'_, '_> {
+    /// push_state()
+    ///
+    /// Save the pen, camera, fill pattern, and palette so that a helper may
+    /// change them and restore them with `pop_state()`.
+    pub fn push_state(&mut self) {
+        let draw_state = &self.state.draw_state;
+        let saved = SavedDrawState {
+            pen: draw_state.pen,
+            camera_position: draw_state.camera_position,
+            fill_pat: draw_state.fill_pat.clone(),
+            pal_map: self.state.pal_map.clone(),
+            palette: self.state.palette,
+            priority: draw_state.priority,
+            tag: draw_state.tag.clone(),
+        };
+        self.state.draw_stack.push(saved);
+    }
+
+    /// pop_state()
+    ///
+    /// Restore the draw state saved by the last `push_state()`.
+    pub fn pop_state(&mut self) -> Result<(), Error> {
+        let saved = self
+            .state
+            .draw_stack
+            .pop()
+            .ok_or(Error::NoSuch("pushed draw state".into()))?;
+        self.camera(Some(saved.camera_position));
+        let draw_state = &mut self.state.draw_state;
+        draw_state.pen = saved.pen;
+        draw_state.fill_pat = saved.fill_pat;
+        draw_state.priority = saved.priority;
+        draw_state.tag = saved.tag;
+        self.state.pal_map = saved.pal_map;
+        self.state.palette = saved.palette;
+        Ok(())
+    }
+
+    /// priority([priority])
+    ///
+    /// Return the priority of what is drawn next and set it if given.
+    /// Something of a higher priority is drawn above something of a lower
+    /// one regardless of the order of the calls, e.g., the HUD at 1, actors
+    /// at 0, and the background at -1.
+    pub fn priority(&mut self, priority: Option<i8>) -> i8 {
+        let last = self.state.draw_state.priority;
+        if let Some(priority) = priority {
+            self.state.draw_state.priority = priority;
+        }
+        last
+    }
+
+    /// setz(id, priority)
+    ///
+    /// Change the priority of something already drawn.
+    pub fn setz(&mut self, id: Entity, priority: i8) {
+        self.commands.queue(move |world: &mut World| {
+            let Some(mut clearable) = world.get_mut::<Clearable>(id) else {
+                warn!("Cannot set priority of {id}; it was not drawn.");
+                return;
+            };
+            clearable.priority = priority;
+            let z = clearable.suggest_z();
+            if let Some(mut transform) = world.get_mut::<Transform>(id) {
+                transform.translation.z = z;
+            }
+        });
+    }
+
+    /// tag([tag])
+    ///
+    /// Tag what is drawn next with `tag` or stop tagging with None. Return the
+    /// last tag.
+    pub fn tag(&mut self, tag: Option<&str>) -> Option<String> {
+        std::mem::replace(&mut self.state.draw_state.tag, tag.map(str::to_owned))
+    }
+
+    /// tagged(tag)
+    ///
+    /// Return what was drawn with `tag` and has not been cleared, e.g., to
+    /// move, recolor, or despawn it instead of drawing it again.
+    pub fn tagged(&self, tag: &str) -> Vec<Entity> {
+        self.state.tagged.get(tag).cloned().unwrap_or_default()
+    }
+
+    /// Despawn what was drawn with `tag`.
+    pub fn despawn_tagged(&mut self, tag: &str) {
+        for id in self.tagged(tag) {
+            if let Some(commands) = self.commands.get_entity(id) {
+                commands.despawn_recursive();
+            }
+        }
+    }
+
+    /// Tag `id` with the current tag if there is one.
+    pub(crate) fn tag_drawn(&mut self, id: Entity) -> Entity {
+        if let Some(tag) = self.state.draw_state.tag.clone() {
+            self.commands.entity(id).insert(DrawTag(tag));
+        }
+        id
+    }
+
+    /// Return a [Clearable] with the current priority.
+    pub(crate) fn clearable(&self) -> Clearable {
+        Clearable::default().with_priority(self.state.draw_state.priority)
+    }
+
+    /// Reset the cart: clear its state, stop the audio, reload it, and run
+    /// `_init` again.
+    pub fn reset_cart(&mut self) {
+        self.commands.send_event(crate::reset::ResetCart);
+    }
+
+    /// Run an external command. Only "reset" is supported.
+    pub fn extcmd(&mut self, cmd: &str) -> Result<(), Error> {
+        match cmd {
+            "reset" => {
+                self.reset_cart();
+                Ok(())
+            }
+            _ => Err(Error::Unsupported(format!("extcmd {cmd:?}").into())),
         }
     }
 }
@@ -0,0 +1,141 @@
+/// all(t)
+///
+/// Visits the values of a list in order while the list changes. Like
+/// Pico-8, deleting the current value does not skip the next one, and values
+/// added to the end are visited.
+///
+/// ```ignore
+/// let mut all = All::default();
+/// while let Some(v) = all.next(&items) {
+///     if v.dead {
+///         Pico8::del(&mut items, &v);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct All<T> {
+    index: usize,
+    last: Option<T>,
+}
+
+impl<T> Default for All<T> {
+    fn default() -> Self {
+        All {
+            index: 0,
+            last: None,
+        }
+    }
+}
+
+impl<T: Clone + PartialEq> All<T> {
+    /// Return the next value of `items`.
+    pub fn next(&mut self, items: &[T]) -> Option<T> {
+        // If the last value is still in place, move past it. Otherwise it was
+        // deleted and the next value has shifted into its index.
+        if self.last.is_some() && items.get(self.index) == self.last.as_ref() {
+            self.index += 1;
+        }
+        self.last = items.get(self.index).cloned();
+        self.last.clone()
+    }
+}
+
+impl super::Pico8<'_, '_> {
+    /// add(t, v, [i])
+    ///
+    /// Insert `value` at `index` or at the end and return it. An index past
+    /// the end adds to the end.
+    pub fn add<T>(items: &mut Vec<T>, value: T, index: Option<usize>) -> &mut T {
+        let index = index.map_or(items.len(), |i| i.min(items.len()));
+        items.insert(index, value);
+        &mut items[index]
+    }
+
+    /// del(t, v)
+    ///
+    /// Remove the first `value`, shifting later values down, and return it.
+    pub fn del<T: PartialEq>(items: &mut Vec<T>, value: &T) -> Option<T> {
+        let index = items.iter().position(|x| x == value)?;
+        Some(items.remove(index))
+    }
+
+    /// deli(t, [i])
+    ///
+    /// Remove the value at `index` or the last one and return it. An index
+    /// past the end removes nothing.
+    pub fn deli<T>(items: &mut Vec<T>, index: Option<usize>) -> Option<T> {
+        match index {
+            Some(i) if i < items.len() => Some(items.remove(i)),
+            Some(_) => None,
+            None => items.pop(),
+        }
+    }
+
+    /// count(t, [v])
+    ///
+    /// Return the length of `items` or how many times `value` occurs in it.
+    pub fn count<T: PartialEq>(items: &[T], value: Option<&T>) -> usize {
+        match value {
+            Some(value) => items.iter().filter(|x| *x == value).count(),
+            None => items.len(),
+        }
+    }
+
+    /// foreach(t, f)
+    ///
+    /// Call `f` with each value of `items` as [All] visits them. `f` may
+    /// add and delete values.
+    pub fn foreach<T: Clone + PartialEq>(items: &mut Vec<T>, mut f: impl FnMut(T, &mut Vec<T>)) {
+        let mut all = All::default();
+        while let Some(value) = all.next(items) {
+            f(value, items);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    type P = crate::pico8::Pico8<'static, 'static>;
+
+    #[test]
+    fn table_helpers() {
+        let mut items = vec![1, 2, 3];
+        assert_eq!(*P::add(&mut items, 0, Some(0)), 0);
+        assert_eq!(*P::add(&mut items, 9, Some(99)), 9);
+        assert_eq!(items, [0, 1, 2, 3, 9]);
+        assert_eq!(P::del(&mut items, &2), Some(2));
+        assert_eq!(P::del(&mut items, &2), None);
+        assert_eq!(P::deli(&mut items, Some(7)), None);
+        assert_eq!(P::deli(&mut items, None), Some(9));
+        assert_eq!(P::deli(&mut items, Some(0)), Some(0));
+        assert_eq!(items, [1, 3]);
+        assert_eq!(P::count(&[1, 2, 1], Some(&1)), 2);
+        assert_eq!(P::count(&[1, 2, 1], None), 3);
+    }
+
+    #[test]
+    fn foreach_while_deleting() {
+        // Deleting the current value does not skip the next one.
+        let mut items = vec![1, 2, 3, 4];
+        let mut seen = vec![];
+        P::foreach(&mut items, |v, items| {
+            seen.push(v);
+            if v % 2 == 1 {
+                P::del(items, &v);
+            }
+        });
+        assert_eq!(seen, [1, 2, 3, 4]);
+        assert_eq!(items, [2, 4]);
+
+        // Values added to the end are visited.
+        let mut items = vec![1];
+        let mut seen = vec![];
+        P::foreach(&mut items, |v, items| {
+            seen.push(v);
+            if v < 3 {
+                P::add(items, v + 1, None);
+            }
+        });
+        assert_eq!(seen, [1, 2, 3]);
+    }
+}
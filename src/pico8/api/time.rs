@@ -0,0 +1,204 @@
+use super::*;
+use bevy::{
+    time::{TimeSystem, TimeUpdateStrategy},
+    utils::Duration,
+    winit::WinitSettings,
+};
+
+use crate::{config::Config, error::RunState, pico8::audio::SfxChannels};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<Determinism>()
+        .register_type::<TimeScale>()
+        .init_resource::<Determinism>()
+        .add_systems(OnEnter(RunState::Init), start_clock)
+        .add_systems(
+            FixedUpdate,
+            tick_clock.run_if(in_state(RunState::Run).and(deterministic)),
+        )
+        .add_systems(
+            Update,
+            wall_clock.run_if(in_state(RunState::Run).and(not(deterministic))),
+        )
+        .add_systems(
+            First,
+            follow_frame_rate
+                .after(TimeSystem)
+                .run_if(|state: Res<Pico8State>| state.fps == 0),
+        )
+        .add_systems(Last, (apply_time_scale, scale_audio));
+}
+
+/// How fast the cart's time passes, set by `timescale()`
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct TimeScale {
+    /// 1 is normal speed, 0.5 is half speed, and 0 stops time.
+    pub factor: f32,
+    /// Whether the audio plays at the same speed, which changes its pitch
+    pub audio: bool,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        TimeScale {
+            factor: 1.0,
+            audio: false,
+        }
+    }
+}
+
+/// Audio is played no slower than this so a stopped clock does not stop
+/// the audio stream.
+const MIN_AUDIO_SPEED: f32 = 0.01;
+
+/// Deterministic mode
+///
+/// When enabled, every update runs exactly one cart tick regardless of the
+/// wall clock, `time()` counts ticks, and `rnd()` is seeded the same way each
+/// time the cart starts. Given the same input, a cart then plays out the same
+/// on any machine.
+#[derive(Debug, Clone, Copy, Default, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Determinism {
+    pub enabled: bool,
+    /// Seed of `rnd()` when the cart starts
+    ///
+    /// If none, a deterministic cart starts as though `srand(0)` were called
+    /// and other carts are seeded from the clock.
+    pub seed: Option<f32>,
+}
+
+impl Determinism {
+    pub fn from_config(config: &Config) -> Self {
+        Determinism {
+            enabled: config.deterministic.unwrap_or(false),
+            seed: config.seed,
+        }
+    }
+
+    /// Return the generator a cart starts with.
+    pub fn rng(&self) -> Rng {
+        match self.seed {
+            Some(seed) => Rng::new(seed),
+            None if self.enabled => Rng::default(),
+            None => Rng::from_clock(),
+        }
+    }
+}
+
+fn deterministic(determinism: Res<Determinism>) -> bool {
+    determinism.enabled
+}
+
+fn start_clock(determinism: Res<Determinism>, mut state: ResMut<Pico8State>) {
+    state.clock = Duration::ZERO;
+    state.rng = determinism.rng();
+}
+
+/// Advance the clock one tick. In `FixedUpdate`, [Time] is [Time<Fixed>].
+fn tick_clock(time: Res<Time>, mut state: ResMut<Pico8State>) {
+    state.clock += time.delta();
+}
+
+fn wall_clock(time: Res<Time<Virtual>>, mut state: ResMut<Pico8State>) {
+    state.clock += time.delta();
+}
+
+/// Tick the cart `fps` times a second or once per frame if zero.
+pub(crate) fn set_tick_rate(world: &mut World, fps: u16) {
+    if fps == 0 {
+        // Draw frames as fast as possible.
+        world.insert_resource(WinitSettings::game());
+        return;
+    }
+    let timestep = Duration::from_secs_f64(1.0 / fps as f64);
+    world.resource_mut::<Time<Fixed>>().set_timestep(timestep);
+    if world
+        .get_resource::<Determinism>()
+        .is_some_and(|determinism| determinism.enabled)
+    {
+        world.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+    }
+    world.insert_resource(crate::plugin::winit_settings());
+}
+
+/// Scale virtual time, which the cart ticks, `time()`, [Timers][crate::timer::Timers],
+/// and [Anim][crate::anim::Anim]s follow.
+fn apply_time_scale(state: Res<Pico8State>, mut time: ResMut<Time<Virtual>>) {
+    let factor = state.timescale.factor;
+    if time.relative_speed() != factor {
+        time.set_relative_speed(factor);
+    }
+}
+
+fn scale_audio(state: Res<Pico8State>, channels: Res<SfxChannels>, sinks: Query<&AudioSink>) {
+    let speed = if state.timescale.audio {
+        state.timescale.factor.max(MIN_AUDIO_SPEED)
+    } else {
+        1.0
+    };
+    for id in channels.iter() {
+        if let Ok(sink) = sinks.get(*id) {
+            if sink.speed() != speed {
+                sink.set_speed(speed);
+            }
+        }
+    }
+}
+
+/// Make the cart tick as long as the last frame so it ticks once per frame.
+fn follow_frame_rate(time: Res<Time<Virtual>>, mut fixed: ResMut<Time<Fixed>>) {
+    let delta = time.delta();
+    if !delta.is_zero() {
+        fixed.set_timestep(delta);
+    }
+}
+
+impl super::Pico8<'_, '_> {
+    /// time() or t()
+    ///
+    /// Return the seconds the cart has been running, not counting while it
+    /// was paused.
+    pub fn time(&self) -> f32 {
+        self.state.clock.as_secs_f32()
+    }
+
+    /// fps([n])
+    ///
+    /// Return the cart ticks per second and set it if given, e.g., 30 as for
+    /// `_update()` or 60 as for `_update60()`. With 0 the cart ticks once per
+    /// frame and frames are drawn as fast as they can be, e.g., for
+    /// benchmarking. A low rate slows the cart down for debugging.
+    pub fn fps(&mut self, n: Option<u16>) -> u16 {
+        let last = self.state.fps;
+        if let Some(n) = n {
+            self.state.fps = n;
+            self.commands
+                .queue(move |world: &mut World| set_tick_rate(world, n));
+        }
+        last
+    }
+
+    /// timescale([factor], [audio])
+    ///
+    /// Return how fast time passes and set it if given, e.g., 0.25 for slow
+    /// motion or 0 to stop. The cart ticks, `time()`, timers, and animations
+    /// all follow it. If `audio` is true, sfx and music play at the same
+    /// speed, which lowers or raises their pitch. A factor that is not finite
+    /// is an error.
+    pub fn timescale(&mut self, factor: Option<f32>, audio: Option<bool>) -> Result<f32, Error> {
+        let last = self.state.timescale.factor;
+        if let Some(factor) = factor {
+            if !factor.is_finite() {
+                return Err(Error::InvalidArgument(
+                    format!("timescale {factor} is not finite").into(),
+                ));
+            }
+            self.state.timescale.factor = factor.max(0.0);
+        }
+        if let Some(audio) = audio {
+            self.state.timescale.audio = audio;
+        }
+        Ok(last)
+    }
+}
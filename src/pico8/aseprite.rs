@@ -0,0 +1,555 @@
+//! Aseprite files
+//!
+//! Parse `.aseprite` and `.ase` files into a sprite sheet with one column per
+//! frame and an [Anim] per tag. Only visible layers with the normal blend mode
+//! are composited.
+//!
+//! See the [file format](https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md).
+use bevy::{
+    image::ImageSampler,
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    utils::HashMap,
+};
+
+use crate::{
+    anim::{Anim, AnimMode},
+    pico8::Gfx,
+};
+
+const HEADER_MAGIC: u16 = 0xa5e0;
+const FRAME_MAGIC: u16 = 0xf1fa;
+const HEADER_SIZE: usize = 128;
+const CHUNK_HEADER_SIZE: usize = 6;
+
+const LAYER_CHUNK: u16 = 0x2004;
+const CEL_CHUNK: u16 = 0x2005;
+const TAGS_CHUNK: u16 = 0x2018;
+const PALETTE_CHUNK: u16 = 0x2019;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AsepriteError {
+    #[error("not an aseprite file")]
+    Magic,
+    #[error("unexpected end of file")]
+    Eof,
+    #[error("unsupported color depth {0}")]
+    ColorDepth(u16),
+    #[error("could not decompress cel: {0:?}")]
+    Decompress(fdeflate::DecompressionError),
+    #[error("not an indexed file")]
+    NotIndexed,
+    #[error("color index {0} does not fit in an indexed sprite sheet")]
+    ColorIndex(u8),
+}
+
+/// Color depth of an Aseprite file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    Rgba,
+    Grayscale,
+    Indexed { transparent: u8 },
+}
+
+impl ColorMode {
+    fn bytes_per_pixel(&self) -> usize {
+        match self {
+            ColorMode::Rgba => 4,
+            ColorMode::Grayscale => 2,
+            ColorMode::Indexed { .. } => 1,
+        }
+    }
+}
+
+/// A tag of frames
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    pub mode: AnimMode,
+    pub reverse: bool,
+}
+
+/// The frames of an Aseprite file composited from its layers
+#[derive(Debug, Clone)]
+pub struct Aseprite {
+    pub size: UVec2,
+    pub color_mode: ColorMode,
+    /// Pixels of each frame. These are color indices if the file is indexed
+    /// and RGBA otherwise.
+    pub frames: Vec<Vec<u8>>,
+    /// Duration of each frame in milliseconds
+    pub durations: Vec<u16>,
+    pub tags: Vec<Tag>,
+    pub palette: Vec<[u8; 4]>,
+}
+
+struct Layer {
+    visible: bool,
+    opacity: u8,
+    normal: bool,
+}
+
+struct Cel {
+    layer: usize,
+    pos: IVec2,
+    opacity: u8,
+    size: UVec2,
+    pixels: Vec<u8>,
+}
+
+/// Little-endian reader
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Bytes<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], AsepriteError> {
+        if self.0.len() < n {
+            return Err(AsepriteError::Eof);
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, AsepriteError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, AsepriteError> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16, AsepriteError> {
+        Ok(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> Result<u32, AsepriteError> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn string(&mut self) -> Result<String, AsepriteError> {
+        let len = self.u16()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+}
+
+impl Aseprite {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AsepriteError> {
+        let mut header = Bytes(bytes.get(..HEADER_SIZE).ok_or(AsepriteError::Eof)?);
+        header.u32()?;
+        if header.u16()? != HEADER_MAGIC {
+            return Err(AsepriteError::Magic);
+        }
+        let frame_count = header.u16()? as usize;
+        let size = UVec2::new(header.u16()? as u32, header.u16()? as u32);
+        let depth = header.u16()?;
+        // Layer opacity is only valid with this flag.
+        let layer_opacity = header.u32()? & 1 != 0;
+        header.take(10)?;
+        let transparent = header.u8()?;
+        let color_mode = match depth {
+            32 => ColorMode::Rgba,
+            16 => ColorMode::Grayscale,
+            8 => ColorMode::Indexed { transparent },
+            x => return Err(AsepriteError::ColorDepth(x)),
+        };
+
+        let mut aseprite = Aseprite {
+            size,
+            color_mode,
+            frames: Vec::with_capacity(frame_count),
+            durations: Vec::with_capacity(frame_count),
+            tags: vec![],
+            palette: vec![],
+        };
+        let mut layers: Vec<Layer> = vec![];
+        // Visibility of the enclosing groups by child level
+        let mut groups: Vec<bool> = vec![];
+        let mut cels: Vec<Vec<Cel>> = vec![];
+        let mut rest = Bytes(&bytes[HEADER_SIZE..]);
+        for _ in 0..frame_count {
+            let frame_size = rest.u32()? as usize;
+            let mut frame = Bytes(rest.take(frame_size.saturating_sub(4))?);
+            if frame.u16()? != FRAME_MAGIC {
+                return Err(AsepriteError::Magic);
+            }
+            let old_chunks = frame.u16()? as usize;
+            aseprite.durations.push(frame.u16()?);
+            frame.take(2)?;
+            let chunks = match frame.u32()? as usize {
+                0 => old_chunks,
+                n => n,
+            };
+            let mut frame_cels = vec![];
+            for _ in 0..chunks {
+                let chunk_size = frame.u32()? as usize;
+                let chunk_type = frame.u16()?;
+                let mut chunk = Bytes(frame.take(chunk_size.saturating_sub(CHUNK_HEADER_SIZE))?);
+                match chunk_type {
+                    LAYER_CHUNK => {
+                        let flags = chunk.u16()?;
+                        let layer_type = chunk.u16()?;
+                        let level = chunk.u16()? as usize;
+                        chunk.take(4)?;
+                        let blend_mode = chunk.u16()?;
+                        let opacity = chunk.u8()?;
+                        groups.truncate(level);
+                        let visible = flags & 1 != 0 && groups.iter().all(|v| *v);
+                        if layer_type == 1 {
+                            groups.push(visible);
+                        }
+                        layers.push(Layer {
+                            visible: visible && layer_type == 0,
+                            opacity: if layer_opacity { opacity } else { 255 },
+                            normal: blend_mode == 0,
+                        });
+                    }
+                    CEL_CHUNK => {
+                        let layer = chunk.u16()? as usize;
+                        let pos = IVec2::new(chunk.i16()? as i32, chunk.i16()? as i32);
+                        let opacity = chunk.u8()?;
+                        let cel_type = chunk.u16()?;
+                        chunk.take(7)?;
+                        match cel_type {
+                            0 | 2 => {
+                                let size = UVec2::new(chunk.u16()? as u32, chunk.u16()? as u32);
+                                let pixels = if cel_type == 0 {
+                                    chunk.0.to_vec()
+                                } else {
+                                    fdeflate::decompress_to_vec(chunk.0)
+                                        .map_err(AsepriteError::Decompress)?
+                                };
+                                let len = (size.x * size.y) as usize * color_mode.bytes_per_pixel();
+                                if pixels.len() < len {
+                                    return Err(AsepriteError::Eof);
+                                }
+                                frame_cels.push(Cel {
+                                    layer,
+                                    pos,
+                                    opacity,
+                                    size,
+                                    pixels,
+                                });
+                            }
+                            1 => {
+                                // A linked cel repeats a cel of an earlier frame.
+                                let linked = chunk.u16()? as usize;
+                                if let Some(cel) = cels.get(linked).and_then(|cels: &Vec<Cel>| {
+                                    cels.iter().find(|c| c.layer == layer)
+                                }) {
+                                    frame_cels.push(Cel {
+                                        layer,
+                                        pos: cel.pos,
+                                        opacity: cel.opacity,
+                                        size: cel.size,
+                                        pixels: cel.pixels.clone(),
+                                    });
+                                }
+                            }
+                            // Tilemaps are not supported.
+                            _ => {}
+                        }
+                    }
+                    TAGS_CHUNK => {
+                        let count = chunk.u16()?;
+                        chunk.take(8)?;
+                        for _ in 0..count {
+                            let from = chunk.u16()? as usize;
+                            let to = chunk.u16()? as usize;
+                            let direction = chunk.u8()?;
+                            let repeat = chunk.u16()?;
+                            chunk.take(10)?;
+                            let name = chunk.string()?;
+                            aseprite.tags.push(Tag {
+                                name,
+                                from,
+                                to,
+                                mode: match direction {
+                                    2 | 3 => AnimMode::PingPong,
+                                    _ if repeat == 1 => AnimMode::Once,
+                                    _ => AnimMode::Loop,
+                                },
+                                reverse: direction == 1 || direction == 3,
+                            });
+                        }
+                    }
+                    PALETTE_CHUNK => {
+                        let len = chunk.u32()? as usize;
+                        let first = chunk.u32()? as usize;
+                        let last = chunk.u32()? as usize;
+                        chunk.take(8)?;
+                        aseprite
+                            .palette
+                            .resize(len.max(aseprite.palette.len()), [0; 4]);
+                        for i in first..=last {
+                            let flags = chunk.u16()?;
+                            let color = chunk.take(4)?;
+                            if flags & 1 != 0 {
+                                chunk.string()?;
+                            }
+                            if let Some(entry) = aseprite.palette.get_mut(i) {
+                                entry.copy_from_slice(color);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            aseprite
+                .frames
+                .push(aseprite.composite(&layers, &frame_cels));
+            cels.push(frame_cels);
+        }
+        Ok(aseprite)
+    }
+
+    fn composite(&self, layers: &[Layer], cels: &[Cel]) -> Vec<u8> {
+        let bpp = match self.color_mode {
+            ColorMode::Indexed { .. } => 1,
+            _ => 4,
+        };
+        let mut pixels = match self.color_mode {
+            ColorMode::Indexed { transparent } => {
+                vec![transparent; (self.size.x * self.size.y) as usize]
+            }
+            _ => vec![0; (self.size.x * self.size.y) as usize * 4],
+        };
+        let mut cels: Vec<&Cel> = cels
+            .iter()
+            .filter(|cel| {
+                layers
+                    .get(cel.layer)
+                    .is_some_and(|layer| layer.visible && layer.normal)
+            })
+            .collect();
+        cels.sort_by_key(|cel| cel.layer);
+        for cel in cels {
+            let layer_opacity = layers[cel.layer].opacity as u32;
+            let opacity = layer_opacity * cel.opacity as u32 / 255;
+            for y in 0..cel.size.y as i32 {
+                for x in 0..cel.size.x as i32 {
+                    let dest = cel.pos + IVec2::new(x, y);
+                    if dest.x < 0
+                        || dest.y < 0
+                        || dest.x >= self.size.x as i32
+                        || dest.y >= self.size.y as i32
+                    {
+                        continue;
+                    }
+                    let src = (y * cel.size.x as i32 + x) as usize;
+                    let dest = (dest.y * self.size.x as i32 + dest.x) as usize * bpp;
+                    match self.color_mode {
+                        ColorMode::Indexed { transparent } => {
+                            let index = cel.pixels[src];
+                            if index != transparent {
+                                pixels[dest] = index;
+                            }
+                        }
+                        ColorMode::Rgba => {
+                            let src = &cel.pixels[src * 4..src * 4 + 4];
+                            blend(&mut pixels[dest..dest + 4], src, opacity);
+                        }
+                        ColorMode::Grayscale => {
+                            let v = cel.pixels[src * 2];
+                            let a = cel.pixels[src * 2 + 1];
+                            blend(&mut pixels[dest..dest + 4], &[v, v, v, a], opacity);
+                        }
+                    }
+                }
+            }
+        }
+        pixels
+    }
+
+    /// Return the tags as animations of the sprite sheet.
+    pub fn anims(&self) -> HashMap<String, Anim> {
+        self.tags
+            .iter()
+            .map(|tag| {
+                let mut frames: Vec<usize> = (tag.from..=tag.to).collect();
+                if tag.reverse {
+                    frames.reverse();
+                }
+                let duration = self.durations.get(tag.from).copied().unwrap_or(100).max(1);
+                (
+                    tag.name.clone(),
+                    Anim::new(frames, 1000.0 / duration as f32).with_mode(tag.mode),
+                )
+            })
+            .collect()
+    }
+
+    /// Return the size of the sprite sheet, which has a column per frame.
+    pub fn sheet_size(&self) -> UVec2 {
+        UVec2::new(self.size.x * self.frames.len() as u32, self.size.y)
+    }
+
+    /// Lay the frames out as an RGBA image.
+    pub fn to_image(&self) -> Image {
+        let sheet_size = self.sheet_size();
+        let mut data = vec![0; (sheet_size.x * sheet_size.y) as usize * 4];
+        let width = self.size.x as usize;
+        for (i, frame) in self.frames.iter().enumerate() {
+            for y in 0..self.size.y as usize {
+                for x in 0..width {
+                    let src = y * width + x;
+                    let dest = (y * sheet_size.x as usize + i * width + x) * 4;
+                    let color = match self.color_mode {
+                        ColorMode::Indexed { transparent } if frame[src] == transparent => [0; 4],
+                        ColorMode::Indexed { .. } => self
+                            .palette
+                            .get(frame[src] as usize)
+                            .copied()
+                            .unwrap_or_default(),
+                        _ => frame[src * 4..src * 4 + 4].try_into().unwrap(),
+                    };
+                    data[dest..dest + 4].copy_from_slice(&color);
+                }
+            }
+        }
+        let mut image = Image::new(
+            Extent3d {
+                width: sheet_size.x,
+                height: sheet_size.y,
+                ..default()
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+        );
+        image.sampler = ImageSampler::nearest();
+        image
+    }
+
+    /// Lay the frames of an indexed file out as [Gfx].
+    pub fn to_gfx(&self) -> Result<Gfx, AsepriteError> {
+        if !matches!(self.color_mode, ColorMode::Indexed { .. }) {
+            return Err(AsepriteError::NotIndexed);
+        }
+        let sheet_size = self.sheet_size();
+        let mut gfx = Gfx::new(sheet_size.x as usize, sheet_size.y as usize);
+        let width = self.size.x as usize;
+        for (i, frame) in self.frames.iter().enumerate() {
+            for (src, index) in frame.iter().enumerate() {
+                if *index >= 16 {
+                    return Err(AsepriteError::ColorIndex(*index));
+                }
+                gfx.set(i * width + src % width, src / width, *index);
+            }
+        }
+        Ok(gfx)
+    }
+}
+
+/// Blend `src` over `dest` with the normal blend mode.
+fn blend(dest: &mut [u8], src: &[u8], opacity: u32) {
+    let src_a = src[3] as u32 * opacity / 255;
+    if src_a == 0 {
+        return;
+    }
+    let dest_a = dest[3] as u32;
+    let out_a = src_a + dest_a * (255 - src_a) / 255;
+    for c in 0..3 {
+        dest[c] =
+            ((src[c] as u32 * src_a + dest[c] as u32 * dest_a * (255 - src_a) / 255) / out_a) as u8;
+    }
+    dest[3] = out_a as u8;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FRAME_HEADER_SIZE: usize = 16;
+
+    /// Build a file with `frames` of one raw cel each on a single layer.
+    fn file(depth: u16, size: u16, frames: &[&[u8]]) -> Vec<u8> {
+        fn chunk(kind: u16, data: &[u8]) -> Vec<u8> {
+            let mut v = ((data.len() + CHUNK_HEADER_SIZE) as u32)
+                .to_le_bytes()
+                .to_vec();
+            v.extend(kind.to_le_bytes());
+            v.extend(data);
+            v
+        }
+        let mut layer = vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 0, 0, 0];
+        layer.extend(1u16.to_le_bytes());
+        layer.push(b'a');
+        let mut tags = 1u16.to_le_bytes().to_vec();
+        tags.extend([0; 8]);
+        tags.extend(0u16.to_le_bytes());
+        tags.extend(((frames.len() - 1) as u16).to_le_bytes());
+        tags.push(2);
+        tags.extend(0u16.to_le_bytes());
+        tags.extend([0; 10]);
+        tags.extend(4u16.to_le_bytes());
+        tags.extend(b"walk");
+
+        let mut body = vec![];
+        for (i, pixels) in frames.iter().enumerate() {
+            let mut chunks = vec![];
+            if i == 0 {
+                chunks.push(chunk(LAYER_CHUNK, &layer));
+                chunks.push(chunk(TAGS_CHUNK, &tags));
+            }
+            let mut cel = vec![0, 0, 0, 0, 0, 0, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+            cel.extend(size.to_le_bytes());
+            cel.extend(size.to_le_bytes());
+            cel.extend(*pixels);
+            chunks.push(chunk(CEL_CHUNK, &cel));
+            let data: Vec<u8> = chunks.concat();
+            body.extend(((data.len() + FRAME_HEADER_SIZE) as u32).to_le_bytes());
+            body.extend(FRAME_MAGIC.to_le_bytes());
+            body.extend((chunks.len() as u16).to_le_bytes());
+            body.extend(50u16.to_le_bytes());
+            body.extend([0; 6]);
+            body.extend(data);
+        }
+        let mut header = vec![0; HEADER_SIZE];
+        header[0..4].copy_from_slice(&((HEADER_SIZE + body.len()) as u32).to_le_bytes());
+        header[4..6].copy_from_slice(&HEADER_MAGIC.to_le_bytes());
+        header[6..8].copy_from_slice(&(frames.len() as u16).to_le_bytes());
+        header[8..10].copy_from_slice(&size.to_le_bytes());
+        header[10..12].copy_from_slice(&size.to_le_bytes());
+        header[12..14].copy_from_slice(&depth.to_le_bytes());
+        header.extend(body);
+        header
+    }
+
+    #[test]
+    fn indexed() {
+        let bytes = file(8, 2, &[&[1, 2, 3, 0], &[4, 0, 0, 5]]);
+        let aseprite = Aseprite::from_bytes(&bytes).unwrap();
+        assert_eq!(aseprite.frames.len(), 2);
+        assert_eq!(aseprite.sheet_size(), UVec2::new(4, 2));
+        let gfx = aseprite.to_gfx().unwrap();
+        assert_eq!(gfx.get(1, 0), Some(2));
+        assert_eq!(gfx.get(2, 0), Some(4));
+        assert_eq!(gfx.get(3, 1), Some(5));
+        let anims = aseprite.anims();
+        let walk = &anims["walk"];
+        assert_eq!(walk.frames, vec![0, 1]);
+        assert_eq!(walk.fps, 20.0);
+        assert_eq!(walk.mode, AnimMode::PingPong);
+    }
+
+    #[test]
+    fn rgba() {
+        let red = [255, 0, 0, 255];
+        let bytes = file(32, 1, &[&red]);
+        let aseprite = Aseprite::from_bytes(&bytes).unwrap();
+        assert_eq!(aseprite.to_image().data, red);
+        assert!(matches!(aseprite.to_gfx(), Err(AsepriteError::NotIndexed)));
+        assert!(matches!(
+            Aseprite::from_bytes(&bytes[..64]),
+            Err(AsepriteError::Eof)
+        ));
+    }
+}
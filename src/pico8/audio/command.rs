@@ -1,8 +1,8 @@
-use bevy::{audio::PlaybackMode, prelude::*};
+use bevy::{audio::PlaybackMode, prelude::*, utils::Duration};
 
 use bitvec::prelude::*;
 
-use crate::pico8::audio::{Sfx, SfxChannels};
+use crate::pico8::audio::{ChannelEffects, Sfx, SfxChannels};
 
 use std::sync::{
     atomic::{AtomicBool, Ordering},
@@ -10,16 +10,50 @@ use std::sync::{
 };
 
 pub(crate) fn plugin(app: &mut App) {
-    app.register_type::<Audio>().register_type::<AudioBank>();
+    app.register_type::<Audio>()
+        .register_type::<AudioBank>()
+        .register_type::<ReservedChannels>()
+        .init_resource::<ReservedChannels>();
 }
 
-#[derive(Clone, Debug, Deref, DerefMut, Reflect)]
-pub struct AudioBank(pub Vec<Audio>);
+#[derive(Clone, Debug, Default, Deref, DerefMut, Reflect)]
+pub struct AudioBank {
+    /// Entries played by `sfx()`
+    #[deref]
+    pub sfx: Vec<Audio>,
+    /// Music patterns of a Pico-8 cart
+    pub music: Vec<Audio>,
+}
+
+impl AudioBank {
+    pub fn new(sfx: Vec<Audio>) -> Self {
+        AudioBank { sfx, music: vec![] }
+    }
+
+    /// Return music `n`, a music pattern if the bank has them or else entry
+    /// `n`.
+    pub fn music(&self, n: usize) -> Option<&Audio> {
+        if self.music.is_empty() {
+            self.sfx.get(n)
+        } else {
+            self.music.get(n)
+        }
+    }
+}
 
 #[derive(Debug, Clone, Reflect)]
 pub enum Audio {
     Sfx(Handle<Sfx>),
     AudioSource(Handle<AudioSource>),
+    Music(MusicPattern),
+}
+
+/// A music pattern: the sfx of each channel played together
+#[derive(Debug, Clone, Default, Reflect)]
+pub struct MusicPattern {
+    /// Flags as in [crate::pico8::p8::MusicPart]
+    pub flags: u8,
+    pub channels: [Option<Handle<Sfx>>; 4],
 }
 
 pub enum SfxDest {
@@ -34,18 +68,184 @@ pub enum AudioCommand {
     Stop(SfxDest, Option<PlaybackMode>),
     Play(Audio, SfxDest, PlaybackSettings),
     Release(SfxDest),
+    /// Stop `sfx` on whichever channels are playing it.
+    StopSfx(Handle<Sfx>),
+}
+
+/// Hold audio commands until the user interacts with the app.
+///
+/// Only looping audio, i.e., music, is kept. One-shot sound effects are dropped
+/// rather than played late.
+#[derive(Resource, Default)]
+pub struct AudioUnlock {
+    pub unlocked: bool,
+    pending: Vec<AudioCommand>,
+}
+
+/// Unlock audio on the first key press, mouse click, or touch.
+pub fn unlock_audio(
+    keys: Option<Res<ButtonInput<KeyCode>>>,
+    mouse: Option<Res<ButtonInput<MouseButton>>>,
+    touches: Option<Res<Touches>>,
+    mut unlock: ResMut<AudioUnlock>,
+    mut commands: Commands,
+) {
+    if unlock.unlocked {
+        return;
+    }
+    if keys.is_some_and(|k| k.get_just_pressed().next().is_some())
+        || mouse.is_some_and(|m| m.get_just_pressed().next().is_some())
+        || touches.is_some_and(|t| t.any_just_pressed())
+    {
+        unlock.unlocked = true;
+        for command in unlock.pending.drain(..) {
+            commands.queue(command);
+        }
+    }
 }
 
 #[derive(Component)]
 struct SfxRelease(Arc<AtomicBool>);
 
+/// The sfx a channel was asked to play before any effects were applied
+#[derive(Component)]
+pub(crate) struct SfxSource(pub(crate) Handle<Sfx>);
+
+/// When a channel started playing, in elapsed app time
+#[derive(Component)]
+pub(crate) struct ChannelStarted(pub(crate) Duration);
+
+/// Channels that `sfx()` does not pick on its own, bit `i` for channel `i`
+///
+/// `music()` with a channel mask reserves those channels for itself while it
+/// plays.
+#[derive(Resource, Debug, Clone, Copy, Default, Deref, DerefMut, Reflect)]
+#[reflect(Resource)]
+pub struct ReservedChannels(pub u8);
+
+/// Return the first idle channel in `mask`, or if they are all busy, the one
+/// that started playing first.
+fn pick_channel(mask: u8, world: &World) -> Option<Entity> {
+    let mask_bits = mask.view_bits::<Lsb0>();
+    let channels: Vec<Entity> = world
+        .resource::<SfxChannels>()
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *mask_bits.get(*i).as_deref().unwrap_or(&false))
+        .map(|(_, id)| *id)
+        .collect();
+    channels
+        .iter()
+        .find(|id| {
+            world
+                .get::<AudioSink>(**id)
+                .map(|s| s.is_paused() || s.empty())
+                .unwrap_or(true)
+        })
+        .or_else(|| {
+            channels
+                .iter()
+                .min_by_key(|id| world.get::<ChannelStarted>(**id).map(|s| s.0))
+        })
+        .copied()
+}
+
+/// Stop whatever channel `id` is playing.
+fn stop(id: Entity, world: &mut World) {
+    if let Some(sink) = world.get::<AudioSink>(id) {
+        sink.stop();
+    }
+    world
+        .entity_mut(id)
+        .remove::<(AudioSink, PlaybackSettings, SfxSource, SfxRelease)>();
+}
+
+/// Play `audio` on channel `id`, stopping what it was playing.
+fn play_on(audio: Audio, id: Entity, playback_settings: PlaybackSettings, world: &mut World) {
+    stop(id, world);
+    let started = ChannelStarted(
+        world
+            .get_resource::<Time>()
+            .map(|time| time.elapsed())
+            .unwrap_or_default(),
+    );
+    match audio {
+        Audio::Sfx(sfx) => {
+            let source = SfxSource(sfx.clone());
+            let sfx = with_channel_fx(sfx, id, world);
+            let (sfx, release) = Sfx::get_stoppable_handle(sfx, world);
+            let mut channel = world.entity_mut(id);
+            if let Some(release) = release {
+                channel.insert(SfxRelease(release));
+            }
+            channel.insert((AudioPlayer(sfx), playback_settings, source, started));
+        }
+        Audio::AudioSource(source) => {
+            world
+                .entity_mut(id)
+                .insert((AudioPlayer(source), playback_settings, started));
+        }
+        Audio::Music(_) => unreachable!("music is played by channel"),
+    }
+}
+
+/// Return a copy of `sfx` with the effects of `channel` if it has any.
+fn with_channel_fx(sfx: Handle<Sfx>, channel: Entity, world: &mut World) -> Handle<Sfx> {
+    let Some(fx) = world
+        .get::<ChannelEffects>(channel)
+        .filter(|fx| !fx.is_empty())
+        .map(|fx| fx.0.clone())
+    else {
+        return sfx;
+    };
+    let mut sfxs = world.resource_mut::<Assets<Sfx>>();
+    let Some(mut sfx_fx) = sfxs.get(&sfx).cloned() else {
+        return sfx;
+    };
+    sfx_fx.fx = fx;
+    sfxs.add(sfx_fx)
+}
+
 fn mode_eq(a: PlaybackMode, b: PlaybackMode) -> bool {
     std::mem::discriminant(&a) == std::mem::discriminant(&b)
 }
 
 impl Command for AudioCommand {
     fn apply(self, world: &mut World) {
+        if let Some(mut unlock) = world.get_resource_mut::<AudioUnlock>() {
+            if !unlock.unlocked {
+                match self {
+                    AudioCommand::Play(_, _, settings)
+                        if mode_eq(settings.mode, PlaybackMode::Loop) =>
+                    {
+                        unlock.pending.push(self)
+                    }
+                    AudioCommand::Play(..) => {}
+                    AudioCommand::Stop(..)
+                    | AudioCommand::Release(_)
+                    | AudioCommand::StopSfx(_) => unlock.pending.clear(),
+                }
+                return;
+            }
+        }
         match self {
+            AudioCommand::Play(Audio::Music(pattern), dest, playback_settings) => {
+                // Play each channel's sfx on its own channel.
+                let mask = match dest {
+                    SfxDest::ChannelMask(mask) => mask,
+                    _ => 0xff,
+                };
+                for (i, sfx) in pattern.channels.into_iter().enumerate() {
+                    if let Some(sfx) = sfx.filter(|_| mask & (1 << i) != 0) {
+                        AudioCommand::Play(
+                            Audio::Sfx(sfx),
+                            SfxDest::Channel(i as u8),
+                            playback_settings,
+                        )
+                        .apply(world);
+                    }
+                }
+            }
             AudioCommand::Stop(sfx_channel, mode) => {
                 match sfx_channel {
                     SfxDest::All => {
@@ -133,117 +333,36 @@ impl Command for AudioCommand {
                 SfxDest::ChannelMask(_) => {}
             },
             AudioCommand::Play(audio, sfx_channel, playback_settings) => {
-                match sfx_channel {
-                    SfxDest::Any => {
-                        if let Some(available_channel) = world
-                            .resource::<SfxChannels>()
-                            .iter()
-                            .find(|id| {
-                                world
-                                    .get::<AudioSink>(**id)
-                                    .map(|s| s.is_paused() || s.empty())
-                                    .unwrap_or(true)
-                            })
-                            .copied()
-                        {
-                            match audio {
-                                Audio::Sfx(sfx) => {
-                                    let (sfx, release) = Sfx::get_stoppable_handle(sfx, world);
-                                    let mut commands = world.commands();
-                                    if let Some(release) = release {
-                                        commands
-                                            .entity(available_channel)
-                                            .insert(SfxRelease(release));
-                                    }
-                                    commands
-                                        .entity(available_channel)
-                                        .insert((AudioPlayer(sfx), playback_settings));
-                                }
-                                Audio::AudioSource(source) => {
-                                    let mut commands = world.commands();
-                                    commands
-                                        .entity(available_channel)
-                                        .insert((AudioPlayer(source), playback_settings));
-                                }
-                            }
-                        } else {
-                            // The channels may be busy. If we log it, it can be
-                            // noisy in the log despite it not having much of an
-                            // effect to the game, so we're not going to log it.
-
-                            warn!("Channels busy.");
-                        }
-                    }
-
-                    SfxDest::ChannelMask(mask) => {
-                        let mask_bits = mask.view_bits::<Lsb0>();
-                        if let Some(available_channel) = world
-                            .resource::<SfxChannels>()
-                            .iter()
-                            .enumerate()
-                            .find_map(|(i, id)| {
-                                (*mask_bits.get(i).as_deref().unwrap_or(&false)
-                                    && world
-                                        .get::<AudioSink>(*id)
-                                        .map(|s| s.is_paused() || s.empty())
-                                        .unwrap_or(true))
-                                .then_some(id)
-                            })
-                            .copied()
-                        {
-                            match audio {
-                                Audio::Sfx(sfx) => {
-                                    let (sfx, release) = Sfx::get_stoppable_handle(sfx, world);
-                                    let mut commands = world.commands();
-                                    if let Some(release) = release {
-                                        commands
-                                            .entity(available_channel)
-                                            .insert(SfxRelease(release));
-                                    }
-                                    commands
-                                        .entity(available_channel)
-                                        .insert((AudioPlayer(sfx), playback_settings));
-                                }
-                                Audio::AudioSource(source) => {
-                                    let mut commands = world.commands();
-                                    commands
-                                        .entity(available_channel)
-                                        .insert((AudioPlayer(source), playback_settings));
-                                }
-                            }
-                        } else {
-                            // The channels may be busy. If we log it, it can be
-                            // noisy in the log despite it not having much of an
-                            // effect to the game, so we're not going to log it.
-
-                            warn!("Channels busy for mask {mask}.");
-                        }
-                    }
-                    SfxDest::Channel(chan) => {
-                        let id = world
-                            .get_resource::<SfxChannels>()
-                            .and_then(|sfx_channels| sfx_channels.get(chan as usize))
-                            .copied();
-                        let mut commands = world.commands();
-                        if let Some(id) = id {
-                            match audio {
-                                Audio::Sfx(sfx) => {
-                                    commands
-                                        .entity(id)
-                                        .insert((AudioPlayer(sfx.clone()), playback_settings));
-                                }
-                                Audio::AudioSource(source) => {
-                                    commands
-                                        .entity(id)
-                                        .insert((AudioPlayer(source), playback_settings));
-                                }
-                            }
-                        } else {
+                let reserved = world
+                    .get_resource::<ReservedChannels>()
+                    .map(|r| r.0)
+                    .unwrap_or(0);
+                let channel = match sfx_channel {
+                    // Reserved channels are left to music.
+                    SfxDest::Any => pick_channel(!reserved, world),
+                    SfxDest::ChannelMask(mask) => pick_channel(mask, world),
+                    SfxDest::Channel(chan) => world
+                        .get_resource::<SfxChannels>()
+                        .and_then(|sfx_channels| sfx_channels.get(chan as usize))
+                        .copied()
+                        .or_else(|| {
                             warn!("Could not find audio channel {chan}");
-                        }
-                    }
+                            None
+                        }),
                     SfxDest::All => {
                         warn!("Cannot play on all channels.");
+                        None
+                    }
+                };
+                if let Some(id) = channel {
+                    play_on(audio, id, playback_settings, world);
+                }
+            }
+            AudioCommand::StopSfx(sfx) => {
+                let channels: Vec<Entity> = (*world.resource::<SfxChannels>()).clone();
+                for chan in channels {
+                    if world.get::<SfxSource>(chan).is_some_and(|s| s.0 == sfx) {
+                        stop(chan, world);
                     }
                 }
             }
@@ -263,4 +382,14 @@ mod test {
         assert!(matches!(x, _y));
         assert!(!mode_eq(x, y));
     }
+
+    #[test]
+    fn test_pick_channel() {
+        let mut world = World::new();
+        let channels: Vec<Entity> = (0..4).map(|_| world.spawn_empty().id()).collect();
+        world.insert_resource(SfxChannels(channels.clone()));
+        assert_eq!(pick_channel(!0b0001, &world), Some(channels[1]));
+        assert_eq!(pick_channel(0b1000, &world), Some(channels[3]));
+        assert_eq!(pick_channel(0, &world), None);
+    }
 }
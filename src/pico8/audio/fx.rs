@@ -0,0 +1,195 @@
+//! Channel effects
+//!
+//! Each sfx channel may have a chain of effects that are applied to the sfx
+//! played on it.
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+use super::SAMPLE_RATE;
+
+/// An effect applied to a channel
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Reflect)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ChannelFx {
+    /// Attenuate frequencies above `cutoff` Hz.
+    LowPass { cutoff: f32 },
+    /// Attenuate frequencies below `cutoff` Hz.
+    HighPass { cutoff: f32 },
+    /// Repeat the signal after `delay` seconds, each repeat scaled by
+    /// `feedback`, and mix it in scaled by `mix`.
+    Echo {
+        delay: f32,
+        feedback: f32,
+        #[serde(default = "default_mix")]
+        mix: f32,
+    },
+    /// Reduce the signal to `bits` of resolution and hold every sample for
+    /// `downsample` samples.
+    Bitcrush {
+        bits: u8,
+        #[serde(default = "default_downsample")]
+        downsample: u32,
+    },
+}
+
+fn default_mix() -> f32 {
+    0.5
+}
+
+fn default_downsample() -> u32 {
+    1
+}
+
+/// The effects of a channel
+#[derive(Debug, Clone, Default, Component, Reflect, Deref, DerefMut)]
+pub struct ChannelEffects(pub Vec<ChannelFx>);
+
+/// An effect with its state
+#[derive(Debug, Clone)]
+enum FxState {
+    LowPass {
+        alpha: f32,
+        y: f32,
+    },
+    HighPass {
+        alpha: f32,
+        y: f32,
+    },
+    Echo {
+        buffer: Vec<f32>,
+        index: usize,
+        feedback: f32,
+        mix: f32,
+    },
+    Bitcrush {
+        steps: f32,
+        downsample: u32,
+        count: u32,
+        held: f32,
+    },
+}
+
+/// One-pole filter coefficient for `cutoff` Hz
+fn alpha(cutoff: f32) -> f32 {
+    1.0 - (-TAU * cutoff.max(0.0) / SAMPLE_RATE as f32).exp()
+}
+
+impl From<ChannelFx> for FxState {
+    fn from(fx: ChannelFx) -> Self {
+        match fx {
+            ChannelFx::LowPass { cutoff } => FxState::LowPass {
+                alpha: alpha(cutoff),
+                y: 0.0,
+            },
+            ChannelFx::HighPass { cutoff } => FxState::HighPass {
+                alpha: alpha(cutoff),
+                y: 0.0,
+            },
+            ChannelFx::Echo {
+                delay,
+                feedback,
+                mix,
+            } => FxState::Echo {
+                buffer: vec![0.0; ((delay * SAMPLE_RATE as f32) as usize).max(1)],
+                index: 0,
+                feedback: feedback.clamp(0.0, 0.99),
+                mix,
+            },
+            ChannelFx::Bitcrush { bits, downsample } => FxState::Bitcrush {
+                steps: 2f32.powi(bits.clamp(1, 16) as i32 - 1),
+                downsample: downsample.max(1),
+                count: 0,
+                held: 0.0,
+            },
+        }
+    }
+}
+
+impl FxState {
+    #[inline]
+    fn process(&mut self, x: f32) -> f32 {
+        match self {
+            FxState::LowPass { alpha, y } => {
+                *y += *alpha * (x - *y);
+                *y
+            }
+            FxState::HighPass { alpha, y } => {
+                *y += *alpha * (x - *y);
+                x - *y
+            }
+            FxState::Echo {
+                buffer,
+                index,
+                feedback,
+                mix,
+            } => {
+                let delayed = buffer[*index];
+                buffer[*index] = x + *feedback * delayed;
+                *index = (*index + 1) % buffer.len();
+                x + *mix * delayed
+            }
+            FxState::Bitcrush {
+                steps,
+                downsample,
+                count,
+                held,
+            } => {
+                if *count == 0 {
+                    *held = (x * *steps).round() / *steps;
+                }
+                *count = (*count + 1) % *downsample;
+                *held
+            }
+        }
+    }
+}
+
+/// A chain of effects applied to samples in order
+#[derive(Debug, Clone, Default)]
+pub struct FxChain(Vec<FxState>);
+
+impl FxChain {
+    pub fn new(fx: &[ChannelFx]) -> Self {
+        FxChain(fx.iter().copied().map(FxState::from).collect())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[inline]
+    pub fn process(&mut self, x: f32) -> f32 {
+        self.0.iter_mut().fold(x, |x, fx| fx.process(x))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn effects() {
+        let mut crush = FxChain::new(&[ChannelFx::Bitcrush {
+            bits: 2,
+            downsample: 2,
+        }]);
+        let out: Vec<f32> = [0.3, 0.9, -0.8, 0.1].map(|x| crush.process(x)).into();
+        assert_eq!(out, [0.5, 0.5, -1.0, -1.0]);
+
+        let delay = 2.0 / SAMPLE_RATE as f32;
+        let mut echo = FxChain::new(&[ChannelFx::Echo {
+            delay,
+            feedback: 0.0,
+            mix: 0.5,
+        }]);
+        let out: Vec<f32> = [1.0, 0.0, 0.0, 0.0].map(|x| echo.process(x)).into();
+        assert_eq!(out, [1.0, 0.0, 0.5, 0.0]);
+
+        let mut low = FxChain::new(&[ChannelFx::LowPass { cutoff: 100.0 }]);
+        let mut high = FxChain::new(&[ChannelFx::HighPass { cutoff: 100.0 }]);
+        // A constant signal passes through a low-pass and not a high-pass.
+        let (l, h) = (0..10_000).fold((0.0, 0.0), |_, _| (low.process(1.0), high.process(1.0)));
+        assert!(l > 0.99 && h < 0.01);
+    }
+}
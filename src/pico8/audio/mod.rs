@@ -1,7 +1,7 @@
 //! Sfx audio
 
 use bevy::{
-    audio::{AddAudioSource, Source},
+    audio::{AddAudioSource, AudioLoader, AudioPlugin, PlaybackMode, Source},
     prelude::*,
     utils::Duration,
 };
@@ -20,10 +20,60 @@ use std::{
 
 mod command;
 pub use command::*;
-
-use crate::pico8::{to_byte, to_nybble};
+mod fx;
+pub use fx::*;
+mod stat;
+pub use stat::*;
+
+use crate::{
+    config::Config,
+    error::RunState,
+    pico8::{to_byte, to_nybble, Pico8Asset, Pico8Handle},
+};
 
 const SAMPLE_RATE: u32 = 22_050;
+/// Samples per sfx tick. A note of speed 1 lasts 183 samples, so there are
+/// about 120.5 ticks per second.
+pub const SAMPLES_PER_TICK: u32 = 183;
+
+/// Counts the samples played in whole sfx ticks
+///
+/// Notes start and end on tick boundaries of the clock, so a sfx does not
+/// drift from the ticks that music is sequenced in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TickClock {
+    samples: u64,
+}
+
+impl TickClock {
+    pub fn advance(&mut self, samples: u64) {
+        self.samples += samples;
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Whole ticks elapsed
+    pub fn ticks(&self) -> u64 {
+        self.samples / SAMPLES_PER_TICK as u64
+    }
+
+    /// Samples from now until tick `tick` begins
+    pub fn samples_until(&self, tick: u64) -> u64 {
+        (tick * SAMPLES_PER_TICK as u64).saturating_sub(self.samples)
+    }
+
+    /// How long `ticks` last
+    pub fn duration(ticks: u64) -> Duration {
+        Duration::from_secs_f64((ticks * SAMPLES_PER_TICK as u64) as f64 / SAMPLE_RATE as f64)
+    }
+
+    /// Whole ticks within `duration`
+    pub fn ticks_in(duration: Duration) -> u64 {
+        (duration.as_secs_f64() * SAMPLE_RATE as f64).round() as u64 / SAMPLES_PER_TICK as u64
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum WaveForm {
@@ -236,6 +286,10 @@ pub enum SfxError {
     InvalidEffect(u8),
     #[error("Invalid wave form: {0}")]
     InvalidWaveForm(u8),
+    #[error("Invalid pitch: {0}")]
+    InvalidPitch(u8),
+    #[error("Invalid volume: {0}")]
+    InvalidVolume(u8),
     #[error("Invalid hex: {0}")]
     InvalidHex(String),
     #[error("Missing {0}")]
@@ -318,6 +372,36 @@ impl Pico8Note {
                 | ((u8::from(effect) as u16 & 0b111) << 12),
         )
     }
+
+    /// Make a note of Pico-8's values: a pitch of 0 to 63 and a wave, volume,
+    /// and effect of 0 to 7.
+    pub fn from_pico8(pitch: u8, wave: u8, volume: u8, effect: u8) -> Result<Self, SfxError> {
+        if pitch > 63 {
+            return Err(SfxError::InvalidPitch(pitch));
+        }
+        if wave > 7 {
+            return Err(SfxError::InvalidWaveForm(wave));
+        }
+        if volume > 7 {
+            return Err(SfxError::InvalidVolume(volume));
+        }
+        Ok(Pico8Note::new(
+            pitch + PITCH_OFFSET,
+            WaveForm::try_from(wave)?,
+            volume,
+            Effect::try_from(effect)?,
+        ))
+    }
+
+    /// Return Pico-8's pitch, wave, volume, and effect of the note.
+    pub fn to_pico8(&self) -> [u8; 4] {
+        [
+            (self.0 & 0b11_1111) as u8,
+            ((self.0 >> 6) & 0b111) as u8,
+            ((self.0 >> 9) & 0b111) as u8,
+            ((self.0 >> 12) & 0b111) as u8,
+        ]
+    }
 }
 
 // impl From<u8> for Pico8Note {
@@ -435,6 +519,8 @@ pub struct Sfx {
     pub notes: Vec<Pico8Note>,
     pub speed: u8,
     pub loop_maybe: Option<Loop>,
+    /// Effects of the channel it is played on
+    pub fx: Vec<ChannelFx>,
 }
 
 #[derive(Debug, Clone, Reflect)]
@@ -456,6 +542,7 @@ impl Sfx {
             notes: notes.into_iter().collect(),
             speed: 16,
             loop_maybe: None,
+            fx: vec![],
         }
     }
 
@@ -495,6 +582,7 @@ impl Sfx {
             notes,
             speed,
             loop_maybe,
+            fx: vec![],
         }
     }
 
@@ -601,7 +689,11 @@ impl From<Sfx> for NoteIter {
 
 pub struct SfxDecoder {
     sfx_notes: NoteIter,
+    fx: FxChain,
     samples: Option<Box<dyn Iterator<Item = f32> + Sync + Send + 'static>>,
+    clock: TickClock,
+    /// Tick the current note ends on
+    note_end: u64,
 }
 
 impl Iterator for SfxDecoder {
@@ -622,7 +714,8 @@ impl Iterator for SfxDecoder {
                 let freq = 440.0 * f32::exp2((note.pitch() as i8 - 69) as f32 / 12.0);
                 // dbg!(note.pitch(), freq);
                 let hz = signal::rate(SAMPLE_RATE as f64).const_hz(freq as f64);
-                let duration = (self.sfx_notes.sfx.speed as f32 / 120.0) * SAMPLE_RATE as f32;
+                self.note_end += self.sfx_notes.sfx.speed.max(1) as u64;
+                let duration = self.clock.samples_until(self.note_end);
                 let volume: f32 = note.volume();
                 match note.wave() {
                     WaveForm::Triangle => {
@@ -696,7 +789,12 @@ impl Iterator for SfxDecoder {
                 }
             });
         }
-        result.or_else(|| self.samples.as_mut().and_then(|samples| samples.next()))
+        result
+            .or_else(|| self.samples.as_mut().and_then(|samples| samples.next()))
+            .map(|x| {
+                self.clock.advance(1);
+                self.fx.process(x)
+            })
     }
 }
 
@@ -726,7 +824,10 @@ impl Decodable for Sfx {
     fn decoder(&self) -> Self::Decoder {
         SfxDecoder {
             sfx_notes: self.clone().into(),
+            fx: FxChain::new(&self.fx),
             samples: None,
+            clock: TickClock::default(),
+            note_end: 0,
         }
     }
 }
@@ -734,22 +835,96 @@ impl Decodable for Sfx {
 pub(crate) fn plugin(app: &mut App) {
     app.register_type::<Sfx>()
         .register_type::<Loop>()
+        .register_type::<ChannelEffects>()
         .add_plugins(command::plugin)
+        .add_plugins(stat::plugin)
         .add_systems(PreStartup, add_channels)
-        .add_audio_source::<Sfx>();
+        .add_systems(OnEnter(RunState::Loaded), apply_channel_fx)
+        .add_systems(OnEnter(RunState::Pause), pause_channels)
+        .add_systems(OnExit(RunState::Pause), resume_channels);
+    if app.is_plugin_added::<AudioPlugin>() {
+        app.add_audio_source::<Sfx>();
+    } else {
+        // Without audio, still load sfx and sounds so carts run the same.
+        app.init_asset::<Sfx>()
+            .init_asset::<AudioSource>()
+            .init_asset_loader::<AudioLoader>();
+    }
+
+    // Browsers will not play audio before a user gesture.
+    #[cfg(all(feature = "web", target_arch = "wasm32"))]
+    app.init_resource::<AudioUnlock>()
+        .add_systems(PreUpdate, unlock_audio);
 }
 
 fn add_channels(mut commands: Commands) {
     let channels: Vec<Entity> = (0..4)
         .map(|i| {
             commands
-                .spawn((Name::new(format!("channel {i}")), PlaybackSettings::REMOVE))
+                .spawn((
+                    Name::new(format!("channel {i}")),
+                    PlaybackSettings::REMOVE,
+                    ChannelEffects::default(),
+                ))
                 .id()
         })
         .collect();
     commands.insert_resource(SfxChannels(channels));
 }
 
+/// Marks a channel paused by [RunState::Pause].
+#[derive(Component)]
+struct PausedChannel;
+
+/// Pause the channels where they are, except for music if the config says it
+/// should continue.
+fn pause_channels(
+    config: Option<Res<Config>>,
+    channels: Res<SfxChannels>,
+    sinks: Query<(&AudioSink, Option<&PlaybackSettings>)>,
+    mut commands: Commands,
+) {
+    let music_on_pause = config.and_then(|c| c.music_on_pause).unwrap_or(false);
+    for id in channels.iter() {
+        let Ok((sink, settings)) = sinks.get(*id) else {
+            continue;
+        };
+        let is_music = settings.is_some_and(|s| matches!(s.mode, PlaybackMode::Loop));
+        if sink.is_paused() || (is_music && music_on_pause) {
+            continue;
+        }
+        sink.pause();
+        commands.entity(*id).insert(PausedChannel);
+    }
+}
+
+/// Resume the channels that were paused.
+fn resume_channels(
+    sinks: Query<(Entity, &AudioSink), With<PausedChannel>>,
+    mut commands: Commands,
+) {
+    for (id, sink) in &sinks {
+        sink.play();
+        commands.entity(id).remove::<PausedChannel>();
+    }
+}
+
+/// Set the channel effects from the cart's config.
+fn apply_channel_fx(
+    pico8_handle: Option<Res<Pico8Handle>>,
+    pico8_assets: Res<Assets<Pico8Asset>>,
+    channels: Res<SfxChannels>,
+    mut commands: Commands,
+) {
+    let Some(pico8_asset) = pico8_handle.and_then(|handle| pico8_assets.get(&handle.handle)) else {
+        return;
+    };
+    for (i, id) in channels.iter().enumerate() {
+        let fx = pico8_asset.channel_fx.get(i).cloned().unwrap_or_default();
+        commands.entity(*id).insert(ChannelEffects(fx));
+    }
+}
+
 // fn setup(mut assets: ResMut<Assets<Sfx>>, mut commands: Commands) {
 //         // .take(duration)
 //         // .chain(hz.clone().saw().take(duration))
@@ -772,6 +947,15 @@ fn add_channels(mut commands: Commands) {
 mod test {
     use super::*;
 
+    #[test]
+    fn test_pico8_note() {
+        let note = Pico8Note::from_pico8(24, 3, 5, 2).unwrap();
+        assert_eq!(note.to_pico8(), [24, 3, 5, 2]);
+        assert_eq!(note.pitch(), 24 + PITCH_OFFSET);
+        assert!(Pico8Note::from_pico8(64, 0, 0, 0).is_err());
+        assert!(Pico8Note::from_pico8(0, 0, 8, 0).is_err());
+    }
+
     #[test]
     fn test_flat_map() {
         let a = 0..3;
@@ -791,6 +975,20 @@ mod test {
         assert_eq!((0..9).collect::<Vec<_>>(), w.collect::<Vec<_>>());
     }
 
+    #[test]
+    fn test_ticks_in() {
+        assert_eq!(TickClock::ticks_in(TickClock::duration(120)), 120);
+        assert_eq!(TickClock::ticks_in(Duration::ZERO), 0);
+    }
+
+    #[test]
+    fn test_note_samples() {
+        let note = Pico8Note::new(24, WaveForm::Triangle, 5, Effect::None);
+        let sfx = Sfx::new([note; 4]).with_speed(3);
+        let samples = sfx.decoder().count() as u64;
+        assert_eq!(samples, 4 * 3 * SAMPLES_PER_TICK as u64);
+    }
+
     #[test]
     fn check_note_conversion() {
         let a = Pico8Note::default();
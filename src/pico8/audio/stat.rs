@@ -0,0 +1,226 @@
+//! Audio positions for `stat(46)` through `stat(56)` and [NotePlayed] events
+use bevy::{audio::PlaybackMode, prelude::*, utils::Duration};
+
+use super::{Audio, ChannelStarted, Loop, Note, Sfx, SfxChannels, SfxSource, TickClock, WaveForm};
+use crate::pico8::{Pico8Asset, Pico8Handle, Pico8State};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.add_event::<NotePlayed>().add_systems(
+        PreUpdate,
+        update_audio_stats.run_if(resource_exists::<Pico8Handle>),
+    );
+}
+
+/// What the channels and music are playing
+///
+/// Positions are reckoned from when a channel started playing in ticks of
+/// [SAMPLES_PER_TICK][super::SAMPLES_PER_TICK] samples, the same ticks that
+/// notes are timed on.
+#[derive(Debug, Clone, Default)]
+pub struct AudioStats {
+    /// Index of the sfx playing on each channel
+    pub sfx: Vec<Option<usize>>,
+    /// Index of the note playing on each channel
+    pub note: Vec<Option<usize>>,
+    /// Music pattern playing
+    pub pattern: Option<usize>,
+    /// Patterns played since `music()` started
+    pub patterns: u64,
+    /// Ticks played of the current pattern
+    pub ticks: u64,
+}
+
+/// Sent when a channel starts playing a note
+///
+/// Rests, notes of zero volume, are not sent. Several may be sent in one
+/// frame for a fast sfx.
+///
+/// ```ignore
+/// fn flash(mut notes: EventReader<NotePlayed>) {
+///     for note in notes.read() {
+///         info!("channel {} played pitch {}", note.channel, note.pitch);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Event)]
+pub struct NotePlayed {
+    pub channel: u8,
+    /// Index of the sfx in the first audio bank if it is there
+    pub sfx: Option<usize>,
+    /// Index of the note in its sfx
+    pub index: usize,
+    /// Pitch in midi format
+    pub pitch: u8,
+    /// The instrument
+    pub wave: WaveForm,
+    pub volume: f32,
+}
+
+/// The most notes a channel sends [NotePlayed] for in one frame
+const MAX_NOTES_PER_FRAME: u64 = 32;
+
+/// Return the index of the note `sfx` plays `ticks` after it started or None
+/// if it is done. A `repeat`ed sfx starts over when it is done.
+///
+/// A released loop is reckoned as if it were still looping.
+fn note_at(sfx: &Sfx, ticks: u64, repeat: bool) -> Option<usize> {
+    nth_note(sfx, ticks / sfx.speed.max(1) as u64, repeat)
+}
+
+/// Return the index of the `n`th note `sfx` plays counting from zero.
+fn nth_note(sfx: &Sfx, n: u64, repeat: bool) -> Option<usize> {
+    let len = sfx.notes.len();
+    if len == 0 {
+        return None;
+    }
+    let n = n as usize;
+    let (start, end) = match sfx.loop_maybe {
+        Some(Loop::Unstoppable { start, end } | Loop::Stoppable { start, end, .. }) => {
+            (start.unwrap_or(0) as usize, end.map(|end| end as usize))
+        }
+        None => (0, None),
+    };
+    match end {
+        // Play from start through end over and over.
+        Some(end) if end < len => {
+            let period = (end + 1).saturating_sub(start).max(1);
+            Some(start + n % period)
+        }
+        _ => {
+            let period = len.saturating_sub(start).max(1);
+            if n < period {
+                Some(start + n)
+            } else if repeat {
+                Some(start + n % period)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Return how many ticks `sfx` plays for or None if it loops.
+fn sfx_ticks(sfx: &Sfx) -> Option<u64> {
+    match sfx.loop_maybe {
+        Some(Loop::Unstoppable { end: Some(_), .. } | Loop::Stoppable { end: Some(_), .. }) => None,
+        _ => Some(sfx.notes.len() as u64 * sfx.speed.max(1) as u64),
+    }
+}
+
+fn update_audio_stats(
+    time: Res<Time>,
+    channels: Res<SfxChannels>,
+    playing: Query<(
+        &SfxSource,
+        &ChannelStarted,
+        &PlaybackSettings,
+        Option<&AudioSink>,
+    )>,
+    sfxs: Res<Assets<Sfx>>,
+    pico8_handle: Res<Pico8Handle>,
+    pico8_assets: Res<Assets<Pico8Asset>>,
+    mut state: ResMut<Pico8State>,
+    mut notes_played: EventWriter<NotePlayed>,
+    // When each channel started and how many notes it had begun
+    mut last_notes: Local<Vec<(Duration, u64)>>,
+) {
+    last_notes.resize(channels.len(), (Duration::ZERO, 0));
+    let bank = pico8_assets
+        .get(&pico8_handle.handle)
+        .and_then(|pico8_asset| pico8_asset.audio_banks.first());
+    let mut stats = AudioStats {
+        pattern: state.music,
+        ..default()
+    };
+    // Music channels start together, so any one of them gives its time.
+    let mut music_ticks: Option<u64> = None;
+    let mut pattern_ticks: Option<u64> = None;
+    for (channel, id) in channels.iter().enumerate() {
+        let Some((source, started, settings, sfx)) = playing
+            .get(*id)
+            .ok()
+            .filter(|(_, _, _, sink)| sink.is_none_or(|sink| !sink.empty()))
+            .and_then(|(source, started, settings, _)| {
+                Some((source, started, settings, sfxs.get(&source.0)?))
+            })
+        else {
+            stats.sfx.push(None);
+            stats.note.push(None);
+            continue;
+        };
+        let ticks = TickClock::ticks_in(time.elapsed().saturating_sub(started.0));
+        let repeat = matches!(settings.mode, PlaybackMode::Loop);
+        let note = note_at(sfx, ticks, repeat);
+        let sfx_index = bank.and_then(|bank| {
+            bank.sfx
+                .iter()
+                .position(|audio| matches!(audio, Audio::Sfx(handle) if *handle == source.0))
+        });
+        stats.sfx.push(note.and(sfx_index));
+        stats.note.push(note);
+
+        let begun = ticks / sfx.speed.max(1) as u64 + 1;
+        let (last_started, last_begun) = last_notes[channel];
+        let first = if last_started == started.0 {
+            last_begun
+        } else {
+            0
+        };
+        for n in first.max(begun.saturating_sub(MAX_NOTES_PER_FRAME))..begun {
+            let Some(index) = nth_note(sfx, n, repeat) else {
+                break;
+            };
+            let note = sfx.notes[index];
+            if note.volume() > 0.0 {
+                notes_played.send(NotePlayed {
+                    channel: channel as u8,
+                    sfx: sfx_index,
+                    index,
+                    pitch: note.pitch(),
+                    wave: note.wave(),
+                    volume: note.volume(),
+                });
+            }
+        }
+        last_notes[channel] = (started.0, begun);
+        if repeat {
+            music_ticks = music_ticks.max(Some(ticks));
+            if let Some(length) = sfx_ticks(sfx) {
+                pattern_ticks = Some(pattern_ticks.map_or(length, |l| l.min(length)));
+            }
+        }
+    }
+    if stats.pattern.is_some() {
+        let ticks = music_ticks.unwrap_or(0);
+        match pattern_ticks.filter(|length| *length > 0) {
+            Some(length) => {
+                stats.patterns = ticks / length;
+                stats.ticks = ticks % length;
+            }
+            None => stats.ticks = ticks,
+        }
+    }
+    state.audio_stats = stats;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pico8::audio::Pico8Note;
+
+    #[test]
+    fn test_note_at() {
+        let sfx = Sfx::new([Pico8Note::default(); 4]).with_speed(2);
+        assert_eq!(note_at(&sfx, 0, false), Some(0));
+        assert_eq!(note_at(&sfx, 7, false), Some(3));
+        assert_eq!(note_at(&sfx, 8, false), None);
+        assert_eq!(note_at(&sfx, 8, true), Some(0));
+        assert_eq!(sfx_ticks(&sfx), Some(8));
+
+        let sfx = sfx.with_loop(Some(1), Some(2));
+        assert_eq!(note_at(&sfx, 0, false), Some(1));
+        assert_eq!(note_at(&sfx, 2, false), Some(2));
+        assert_eq!(note_at(&sfx, 4, false), Some(1));
+        assert_eq!(sfx_ticks(&sfx), None);
+    }
+}
@@ -9,6 +9,16 @@ use std::{
 static DRAW_COUNTER: DrawCounter = DrawCounter::new(1);
 ///
 const MAX_EXPECTED_CLEARABLES: f32 = 1000.0;
+/// The z distance between priorities
+const PRIORITY_SPAN: f32 = 2.0;
+/// The z above anything drawn at any priority, where overlays like the light
+/// shade, the debug layer, and the cursor sprite go
+pub(crate) const OVERLAY_Z: f32 = 1.0 + (i8::MAX as f32 + 1.0) * PRIORITY_SPAN;
+/// The highest z a clearable is drawn at, however many are drawn
+const MAX_DRAWN_Z: f32 = OVERLAY_Z - 1.0 / MAX_EXPECTED_CLEARABLES;
+/// The most z the draw order adds within a priority, so that the
+/// [MAX_EXPECTED_CLEARABLES]th and later draws stay below the next priority
+const MAX_DRAW_ORDER_Z: f32 = PRIORITY_SPAN - 1.0 / MAX_EXPECTED_CLEARABLES;
 
 pub(crate) fn plugin(app: &mut App) {
     app.add_event::<ClearEvent>()
@@ -95,6 +105,8 @@ pub struct Clearable {
     draw_count: usize,
     pub time_to_live: u8,
     pub hash: Option<u64>,
+    /// Drawn above everything of a lower priority regardless of draw order
+    pub priority: i8,
 }
 
 fn on_insert_hook(mut world: DeferredWorld, id: Entity, _comp_id: ComponentId) {
@@ -123,12 +135,44 @@ fn on_remove_hook(mut world: DeferredWorld, id: Entity, _comp_id: ComponentId) {
     cache.remove(&hash);
 }
 
+/// A tag given to what is drawn by `tag()` to find it again with `tagged()`
+#[derive(Debug, Component, Clone, PartialEq, Eq, Reflect)]
+#[component(on_insert = on_insert_tag)]
+#[component(on_replace = on_replace_tag)]
+pub struct DrawTag(pub String);
+
+fn on_insert_tag(mut world: DeferredWorld, id: Entity, _comp_id: ComponentId) {
+    let Some(tag) = world.get::<DrawTag>(id).map(|tag| tag.0.clone()) else {
+        return;
+    };
+    let Some(mut state) = world.get_resource_mut::<Pico8State>() else {
+        return;
+    };
+    state.tagged.entry(tag).or_default().push(id);
+}
+
+fn on_replace_tag(mut world: DeferredWorld, id: Entity, _comp_id: ComponentId) {
+    let Some(tag) = world.get::<DrawTag>(id).map(|tag| tag.0.clone()) else {
+        return;
+    };
+    let Some(mut state) = world.get_resource_mut::<Pico8State>() else {
+        return;
+    };
+    if let Some(ids) = state.tagged.get_mut(&tag) {
+        ids.retain(|x| *x != id);
+        if ids.is_empty() {
+            state.tagged.remove(&tag);
+        }
+    }
+}
+
 impl Default for Clearable {
     fn default() -> Self {
         Clearable {
             draw_count: DRAW_COUNTER.increment(),
             time_to_live: 0,
             hash: None,
+            priority: 0,
         }
     }
 }
@@ -139,6 +183,7 @@ impl Clearable {
             draw_count: DRAW_COUNTER.increment(),
             time_to_live,
             hash: None,
+            priority: 0,
         }
     }
 
@@ -148,9 +193,14 @@ impl Clearable {
         self
     }
 
-    /// Suggest a z value based on the draw count.
+    pub fn with_priority(mut self, priority: i8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Suggest a z value based on the priority and draw count.
     pub fn suggest_z(&self) -> f32 {
-        1.0 + self.draw_count as f32 / MAX_EXPECTED_CLEARABLES
+        (1.0 + priority_z(self.priority) + draw_order_z(self.draw_count)).min(MAX_DRAWN_Z)
     }
 
     /// Update the draw count, changes the suggest_z() to be current.
@@ -159,6 +209,14 @@ impl Clearable {
     }
 }
 
+fn priority_z(priority: i8) -> f32 {
+    priority as f32 * PRIORITY_SPAN
+}
+
+fn draw_order_z(draw_count: usize) -> f32 {
+    (draw_count as f32 / MAX_EXPECTED_CLEARABLES).min(MAX_DRAW_ORDER_Z)
+}
+
 fn handle_overflow(mut query: Query<&mut Clearable>) {
     if DRAW_COUNTER.overflowed() {
         for mut clearable in &mut query {
@@ -197,7 +255,8 @@ fn handle_clear_event(
         });
         for (_id, mut clearable, mut transform, _) in greater_than {
             clearable.draw_count = 0;
-            transform.translation.z = i as f32 / MAX_EXPECTED_CLEARABLES;
+            transform.translation.z =
+                (priority_z(clearable.priority) + draw_order_z(i)).min(MAX_DRAWN_Z);
             i += 1;
         }
 
@@ -221,4 +280,42 @@ mod test {
         assert_eq!(COUNTER.increment(), 1);
         assert_eq!(COUNTER.get(), 2);
     }
+
+    #[test]
+    fn test_draw_tag() {
+        let mut world = World::new();
+        world.init_resource::<crate::pico8::Defaults>();
+        world.init_resource::<Pico8State>();
+        let id = world.spawn(DrawTag("enemy".into())).id();
+        assert_eq!(world.resource::<Pico8State>().tagged["enemy"], vec![id]);
+        world.despawn(id);
+        assert!(world.resource::<Pico8State>().tagged.is_empty());
+    }
+
+    #[test]
+    fn test_priority() {
+        let hud = Clearable::default().with_priority(1);
+        let actor = Clearable::default();
+        let background = Clearable::default().with_priority(-1);
+        assert!(hud.suggest_z() > actor.suggest_z());
+        assert!(background.suggest_z() < actor.suggest_z());
+    }
+
+    #[test]
+    fn test_overlay_above_priorities() {
+        let mut top = Clearable::default().with_priority(i8::MAX);
+        top.draw_count = 10 * MAX_EXPECTED_CLEARABLES as usize;
+        assert!(top.suggest_z() < OVERLAY_Z);
+    }
+
+    #[test]
+    fn test_many_draws_below_next_priority() {
+        let mut actor = Clearable::default();
+        let hud = Clearable::default().with_priority(1);
+        for draw_count in [1999, 2000, 2500, 100_000] {
+            actor.draw_count = draw_count;
+            assert!(actor.suggest_z() < hud.suggest_z());
+            assert!(priority_z(0) + draw_order_z(draw_count) < priority_z(1));
+        }
+    }
 }
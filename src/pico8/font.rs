@@ -0,0 +1,196 @@
+//! Bitmap fonts in Pico-8's custom font layout
+use bevy::prelude::*;
+
+/// Where Pico-8 keeps the custom font in memory.
+pub const CUSTOM_FONT_ADDR: usize = 0x5600;
+/// Size of the custom font in bytes.
+pub const CUSTOM_FONT_LEN: usize = 0x800;
+
+/// A bitmap font laid out like Pico-8's custom font at 0x5600.
+///
+/// | offset  | meaning                         |
+/// |---------|---------------------------------|
+/// | 0       | width of chars 0..128           |
+/// | 1       | width of chars 128..256         |
+/// | 2       | height                          |
+/// | 3       | draw x offset                   |
+/// | 4       | draw y offset                   |
+/// | 8 * c   | 8 rows of char c, bit 0 is left |
+///
+/// Glyphs are defined for chars 16 through 255; the first 128 bytes hold the
+/// header.
+#[derive(Clone, Debug, Reflect)]
+pub struct BitmapFont {
+    pub data: Vec<u8>,
+}
+
+impl Default for BitmapFont {
+    fn default() -> Self {
+        BitmapFont {
+            data: vec![0; CUSTOM_FONT_LEN],
+        }
+    }
+}
+
+impl BitmapFont {
+    pub fn new(width: u8, width_hi: u8, height: u8) -> Self {
+        let mut font = BitmapFont::default();
+        font.data[0] = width;
+        font.data[1] = width_hi;
+        font.data[2] = height;
+        font
+    }
+
+    pub fn char_width(&self, c: u8) -> u32 {
+        if c < 128 {
+            self.data[0] as u32
+        } else {
+            self.data[1] as u32
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        self.data[2] as u32
+    }
+
+    pub fn offset(&self) -> IVec2 {
+        IVec2::new(self.data[3] as i8 as i32, self.data[4] as i8 as i32)
+    }
+
+    pub fn glyph(&self, c: u8) -> &[u8] {
+        let start = c as usize * 8;
+        &self.data[start..start + 8]
+    }
+
+    pub fn set_glyph(&mut self, c: u8, rows: [u8; 8]) {
+        let start = c as usize * 8;
+        self.data[start..start + 8].copy_from_slice(&rows);
+    }
+
+    /// Rasterize a pixel font's P8SCII glyphs at `font_size`.
+    ///
+    /// Each pixel is set if its center is inside the glyph outline, so a font
+    /// drawn on a grid of `units_per_em / font_size` units converts exactly.
+    pub fn from_ttf(data: &[u8], font_size: f32) -> Option<Self> {
+        let face = ttf_parser::Face::parse(data, 0).ok()?;
+        let scale = font_size / face.units_per_em() as f32;
+        let width = |c: char| {
+            face.glyph_index(c)
+                .and_then(|id| face.glyph_hor_advance(id))
+                .map(|advance| (advance as f32 * scale).round() as u8)
+        };
+        let height = (face.height() as f32 * scale).round() as u8;
+        let mut font = BitmapFont::new(width('a')?, width('█').unwrap_or(8), height);
+        let ascender = face.ascender() as f32;
+        let mut pixmap = tiny_skia::Pixmap::new(8, 8)?;
+        let paint = tiny_skia::Paint {
+            anti_alias: false,
+            ..default()
+        };
+        for c in 16..=255u8 {
+            let Some(id) = super::p8scii::char_to_utf8(c)
+                .and_then(|s| s.chars().next())
+                .and_then(|c| face.glyph_index(c))
+            else {
+                continue;
+            };
+            let mut outline = Outline(tiny_skia::PathBuilder::new());
+            if face.outline_glyph(id, &mut outline).is_none() {
+                continue;
+            }
+            let Some(path) = outline.0.finish() else {
+                continue;
+            };
+            // Font units are y-up from the baseline; glyph rows are y-down
+            // from the ascender.
+            let transform =
+                tiny_skia::Transform::from_row(scale, 0.0, 0.0, -scale, 0.0, ascender * scale);
+            pixmap.fill(tiny_skia::Color::TRANSPARENT);
+            pixmap.fill_path(&path, &paint, tiny_skia::FillRule::Winding, transform, None);
+            let mut rows = [0u8; 8];
+            for (y, row) in rows.iter_mut().enumerate() {
+                for x in 0..8 {
+                    if pixmap.pixel(x, y as u32).is_some_and(|p| p.alpha() > 0x7f) {
+                        *row |= 1 << x;
+                    }
+                }
+            }
+            font.set_glyph(c, rows);
+        }
+        Some(font)
+    }
+
+    /// Return the size in pixels of the text.
+    pub fn text_size(&self, text: &[u8]) -> UVec2 {
+        let mut size = UVec2::ZERO;
+        for line in text.split(|c| *c == b'\n') {
+            let width: u32 = line.iter().map(|c| self.char_width(*c)).sum();
+            size.x = size.x.max(width);
+            size.y += self.height();
+        }
+        size
+    }
+
+    /// Call `put(x, y)` for every set pixel of the text.
+    pub fn rasterize(&self, text: &[u8], mut put: impl FnMut(i32, i32)) {
+        let offset = self.offset();
+        let mut cursor = IVec2::ZERO;
+        for c in text {
+            if *c == b'\n' {
+                cursor.x = 0;
+                cursor.y += self.height() as i32;
+                continue;
+            }
+            if *c >= 16 {
+                for (y, row) in self.glyph(*c).iter().enumerate() {
+                    for x in 0..8 {
+                        if row & (1 << x) != 0 {
+                            put(cursor.x + offset.x + x, cursor.y + offset.y + y as i32);
+                        }
+                    }
+                }
+            }
+            cursor.x += self.char_width(*c) as i32;
+        }
+    }
+}
+
+/// Collect a glyph's outline into a path.
+struct Outline(tiny_skia::PathBuilder);
+
+impl ttf_parser::OutlineBuilder for Outline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.0.quad_to(x1, y1, x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.0.cubic_to(x1, y1, x2, y2, x, y);
+    }
+
+    fn close(&mut self) {
+        self.0.close();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rasterize_glyph() {
+        let mut font = BitmapFont::new(4, 8, 6);
+        font.set_glyph(b'a', [0b0001, 0b0010, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(font.text_size(b"aa\na"), UVec2::new(8, 12));
+        let mut pixels = Vec::new();
+        font.rasterize(b"aa", |x, y| pixels.push((x, y)));
+        assert_eq!(pixels, vec![(0, 0), (1, 1), (4, 0), (5, 1)]);
+    }
+}
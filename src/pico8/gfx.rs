@@ -95,6 +95,30 @@ impl<const N: usize> Gfx<N, u8> {
             )))
         }
     }
+
+    /// Encode as an 8-bit indexed PNG with `palette`, which
+    /// [from_png][Self::from_png] reads back.
+    pub fn to_png(&self, palette: &[[u8; 4]]) -> Result<Vec<u8>, png::EncodingError> {
+        let mut pixels = Vec::with_capacity(self.width * self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                pixels.push(self.get(x, y).unwrap_or(0));
+            }
+        }
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(
+            palette
+                .iter()
+                .flat_map(|c| [c[0], c[1], c[2]])
+                .collect::<Vec<u8>>(),
+        );
+        encoder.set_trns(palette.iter().map(|c| c[3]).collect::<Vec<u8>>());
+        encoder.write_header()?.write_image_data(&pixels)?;
+        Ok(bytes)
+    }
 }
 
 impl<
@@ -198,6 +222,18 @@ mod test {
         assert_eq!(15, a.get(0, 0).unwrap());
     }
 
+    #[test]
+    fn png_round_trip() {
+        let mut a = Gfx::<4>::new(8, 8);
+        a.set(1, 0, 7);
+        a.set(0, 1, 15);
+        let bytes = a.to_png(&crate::pico8::PALETTE).unwrap();
+        let b = Gfx::<4>::from_png(&bytes).unwrap();
+        assert_eq!(b.get(1, 0), Some(7));
+        assert_eq!(b.get(0, 1), Some(15));
+        assert_eq!(b.get(0, 0), Some(0));
+    }
+
     #[test]
     fn create_image() {
         let mut a = Gfx::<4>::new(8, 8);
@@ -81,6 +81,13 @@ impl GfxHandles {
         Ok(handle)
     }
 
+    /// Forget every image so they are recreated from their changed `Gfx`.
+    pub fn invalidate(&mut self) {
+        for buffer in &mut self.buffers {
+            buffer.clear();
+        }
+    }
+
     pub fn tick(&mut self) {
         self.tick += 1;
         self.buffers[self.tick % 2].clear();
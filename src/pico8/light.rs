@@ -0,0 +1,390 @@
+//! Palette lighting
+//!
+//! A cart sets how dark it is outside any light with `lighting()` and lights
+//! parts of the canvas with `light()` every frame. Each level of darkness
+//! shifts a color one step down its ramp, e.g., white to light gray, like the
+//! light level tables of Pico-8 carts.
+//!
+//! In framebuffer mode, the canvas is remapped through the ramps on the CPU.
+//! Otherwise sprites and shapes are entities that are never in the canvas, so
+//! a shader shades over them by their light level instead.
+use bevy::{
+    asset::embedded_asset,
+    image::ImageSampler,
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{AsBindGroup, Extent3d, ShaderRef, TextureDimension, TextureFormat},
+    },
+    sprite::{AlphaMode2d, Material2d, Material2dPlugin},
+};
+use std::f32::consts::TAU;
+
+use crate::{
+    config::Config,
+    pico8::{shape::ShapeInstances, Pico8, Pico8Asset, Pico8Handle, Pico8State, OVERLAY_Z},
+    N9Canvas, Nano9Camera, Nano9Sprite,
+};
+
+pub(crate) fn plugin(app: &mut App) {
+    embedded_asset!(app, "light.wgsl");
+    app.register_type::<Lighting>()
+        .add_plugins(Material2dPlugin::<LightMaterial>::default())
+        .init_resource::<LightLayer>()
+        .add_systems(First, clear_lights)
+        .add_systems(
+            PostUpdate,
+            apply_lighting.run_if(resource_exists::<Pico8Handle>),
+        );
+}
+
+/// The Pico-8 palette's colors one step darker, e.g., 7 (white) becomes 6
+/// (light gray).
+pub const PICO8_LIGHT_RAMP: [usize; 16] = [0, 0, 1, 1, 2, 1, 5, 6, 2, 4, 9, 3, 1, 1, 2, 5];
+
+/// Levels of darkness the shader shades to black
+const SHADE_LEVELS: f32 = 4.0;
+
+/// Draw the shade above the cart but below the debug layer.
+const LIGHT_Z: f32 = OVERLAY_Z;
+
+/// Limits a [Light] to a cone.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct LightCone {
+    /// The direction it points in turns: 0 is right and 0.25 is up.
+    pub dir: f32,
+    /// The width of the cone in turns
+    pub width: f32,
+}
+
+/// A light drawn by `light()` for one frame
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct Light {
+    /// The center in canvas pixels
+    pub pos: Vec2,
+    pub radius: f32,
+    /// Levels of darkness removed at the center, fading to none at the radius
+    pub strength: u8,
+    pub cone: Option<LightCone>,
+}
+
+impl Light {
+    /// Return the levels of darkness the light removes at `pos`.
+    fn brightness(&self, pos: Vec2) -> u8 {
+        let d = pos - self.pos;
+        let distance = d.length();
+        if distance >= self.radius {
+            return 0;
+        }
+        if let Some(cone) = self.cone.filter(|_| distance > 0.0) {
+            // The canvas's y points down, so negate it to turn counterclockwise.
+            let turns = (-d.y).atan2(d.x) / TAU;
+            let off = (turns - cone.dir + 0.5).rem_euclid(1.0) - 0.5;
+            if off.abs() > cone.width / 2.0 {
+                return 0;
+            }
+        }
+        (self.strength as f32 * (1.0 - distance / self.radius)).ceil() as u8
+    }
+}
+
+/// The darkness and lights of the canvas
+#[derive(Debug, Clone, Reflect)]
+pub struct Lighting {
+    /// Levels of darkness outside any light. Zero turns lighting off.
+    pub ambient: u8,
+    pub lights: Vec<Light>,
+    /// Each color's next darker color
+    pub ramp: Vec<usize>,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Lighting {
+            ambient: 0,
+            lights: Vec::new(),
+            ramp: PICO8_LIGHT_RAMP.to_vec(),
+        }
+    }
+}
+
+impl Lighting {
+    pub fn from_config(config: &Config) -> Self {
+        Lighting {
+            ramp: config
+                .light_ramp
+                .clone()
+                .unwrap_or_else(|| PICO8_LIGHT_RAMP.to_vec()),
+            ..default()
+        }
+    }
+
+    /// Return the levels of darkness of every pixel of a canvas of `size`
+    /// row by row.
+    pub fn levels(&self, size: UVec2) -> Vec<u8> {
+        let mut levels = Vec::with_capacity((size.x * size.y) as usize);
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let pos = Vec2::new(x as f32, y as f32) + 0.5;
+                let brightness = self
+                    .lights
+                    .iter()
+                    .map(|light| light.brightness(pos))
+                    .max()
+                    .unwrap_or(0);
+                levels.push(self.ambient.saturating_sub(brightness));
+            }
+        }
+        levels
+    }
+
+    /// Return `color` darkened `steps` times.
+    pub fn darken(&self, mut color: usize, steps: u8) -> usize {
+        for _ in 0..steps {
+            match self.ramp.get(color) {
+                Some(&darker) if darker != color => color = darker,
+                _ => break,
+            }
+        }
+        color
+    }
+}
+
+/// Shades the canvas by the light levels in `levels`.
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct LightMaterial {
+    /// Opacity of each level of darkness
+    #[uniform(0)]
+    shade: f32,
+    #[texture(1)]
+    #[sampler(2)]
+    levels: Handle<Image>,
+}
+
+impl Material2d for LightMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "embedded://nano9/pico8/light.wgsl".into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode2d {
+        AlphaMode2d::Blend
+    }
+}
+
+/// Marks the entity shading the canvas.
+#[derive(Debug, Component)]
+struct LightOverlay;
+
+/// The images lighting draws into
+#[derive(Debug, Resource)]
+struct LightLayer {
+    /// Light levels for the [LightMaterial]
+    levels: Handle<Image>,
+    /// The lit canvas in framebuffer mode
+    lit: Handle<Image>,
+    material: Handle<LightMaterial>,
+}
+
+impl FromWorld for LightLayer {
+    fn from_world(world: &mut World) -> Self {
+        let mut images = world.resource_mut::<Assets<Image>>();
+        let levels = images.add(Image::default());
+        let lit = images.add(Image::default());
+        let material = world
+            .resource_mut::<Assets<LightMaterial>>()
+            .add(LightMaterial {
+                shade: 1.0 / SHADE_LEVELS,
+                levels: levels.clone(),
+            });
+        LightLayer {
+            levels,
+            lit,
+            material,
+        }
+    }
+}
+
+fn clear_lights(mut state: ResMut<Pico8State>) {
+    if !state.lighting.lights.is_empty() {
+        state.lighting.lights.clear();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_lighting(
+    state: Res<Pico8State>,
+    canvas: Res<N9Canvas>,
+    pico8_handle: Res<Pico8Handle>,
+    pico8_assets: Res<Assets<Pico8Asset>>,
+    layer: Res<LightLayer>,
+    shape_instances: Res<ShapeInstances>,
+    mut images: ResMut<Assets<Image>>,
+    camera: Query<Entity, With<Nano9Camera>>,
+    mut canvas_sprites: Query<&mut Sprite, With<Nano9Sprite>>,
+    mut overlays: Query<(&mut Transform, &mut Visibility), With<LightOverlay>>,
+    mut commands: Commands,
+) {
+    let lighting = &state.lighting;
+    let on = lighting.ambient > 0;
+    let lit = on && canvas.framebuffer;
+    let shaded = on && !canvas.framebuffer;
+    // Show the lit canvas in place of the canvas in framebuffer mode.
+    for mut sprite in &mut canvas_sprites {
+        let image = if lit { &layer.lit } else { &canvas.handle };
+        if sprite.image != *image {
+            sprite.image = image.clone();
+        }
+    }
+    for (mut transform, mut visibility) in &mut overlays {
+        visibility.set_if_neq(if shaded {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+        transform.scale = canvas.size.as_vec2().extend(1.0);
+    }
+    if !on {
+        return;
+    }
+    let levels = lighting.levels(canvas.size);
+    if lit {
+        let Some(palette) = pico8_assets
+            .get(&pico8_handle.handle)
+            .and_then(|asset| asset.palettes.get(state.palette))
+        else {
+            return;
+        };
+        let Some(mut image) = images.get(&canvas.handle).cloned() else {
+            return;
+        };
+        for (i, level) in levels.into_iter().enumerate().filter(|(_, l)| *l > 0) {
+            let (x, y) = (i as u32 % canvas.size.x, i as u32 / canvas.size.x);
+            let Some(index) = image
+                .get_color_at(x, y)
+                .ok()
+                .and_then(|color| palette.index_of(color.to_srgba().to_u8_array()))
+            else {
+                continue;
+            };
+            if let Some([r, g, b, a]) = palette.data.get(lighting.darken(index, level)) {
+                let _ = image.set_color_at(x, y, Color::srgba_u8(*r, *g, *b, *a));
+            }
+        }
+        images.insert(&layer.lit, image);
+    } else {
+        let mut image = Image::new(
+            Extent3d {
+                width: canvas.size.x,
+                height: canvas.size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            levels,
+            TextureFormat::R8Unorm,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        image.sampler = ImageSampler::nearest();
+        images.insert(&layer.levels, image);
+        if overlays.is_empty() {
+            if let Ok(camera) = camera.get_single() {
+                commands
+                    .spawn((
+                        Name::new("light"),
+                        Mesh2d(shape_instances.quad()),
+                        MeshMaterial2d(layer.material.clone()),
+                        Transform::from_xyz(0.0, 0.0, LIGHT_Z)
+                            .with_scale(canvas.size.as_vec2().extend(1.0)),
+                        LightOverlay,
+                    ))
+                    .set_parent(camera);
+            }
+        }
+    }
+}
+
+impl Pico8<'_, '_> {
+    /// lighting([ambient])
+    ///
+    /// Return the levels of darkness outside any light and set it if given.
+    /// Zero turns lighting off.
+    pub fn lighting(&mut self, ambient: Option<u8>) -> u8 {
+        let last = self.state.lighting.ambient;
+        if let Some(ambient) = ambient {
+            self.state.lighting.ambient = ambient;
+        }
+        last
+    }
+
+    /// light(x, y, radius, [strength], [cone])
+    ///
+    /// Light a circle for this frame. At its center it removes `strength`
+    /// levels of darkness, all of them by default, fading to none at its
+    /// radius. With a cone, only that slice of the circle is lit.
+    pub fn light(&mut self, pos: Vec2, radius: f32, strength: Option<u8>, cone: Option<LightCone>) {
+        let pos = self.state.draw_state.apply_camera_delta(pos);
+        let strength = strength.unwrap_or(self.state.lighting.ambient);
+        self.state.lighting.lights.push(Light {
+            pos,
+            radius,
+            strength,
+            cone,
+        });
+    }
+
+    /// light_ramp(color, [darker])
+    ///
+    /// Return the color one level darker than `color` and set it if given.
+    pub fn light_ramp(&mut self, color: usize, darker: Option<usize>) -> usize {
+        let ramp = &mut self.state.lighting.ramp;
+        if ramp.len() <= color {
+            ramp.extend(ramp.len()..=color);
+        }
+        let last = ramp[color];
+        if let Some(darker) = darker {
+            ramp[color] = darker;
+        }
+        last
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn light_levels() {
+        let mut lighting = Lighting {
+            ambient: 3,
+            ..default()
+        };
+        lighting.lights.push(Light {
+            pos: Vec2::new(4.0, 4.0),
+            radius: 4.0,
+            strength: 3,
+            cone: None,
+        });
+        let levels = lighting.levels(UVec2::new(8, 8));
+        assert_eq!(levels[0], 3);
+        assert_eq!(levels[3 * 8 + 3], 0);
+        assert_eq!(lighting.darken(7, 1), 6);
+        assert_eq!(lighting.darken(7, 3), 1);
+        assert_eq!(lighting.darken(7, 9), 0);
+    }
+
+    #[test]
+    fn light_cone() {
+        let light = Light {
+            pos: Vec2::ZERO,
+            radius: 10.0,
+            strength: 2,
+            cone: Some(LightCone {
+                dir: 0.0,
+                width: 0.25,
+            }),
+        };
+        assert_eq!(light.brightness(Vec2::new(5.0, 1.0)), 1);
+        assert_eq!(light.brightness(Vec2::new(1.0, 0.0)), 2);
+        assert_eq!(light.brightness(Vec2::new(-5.0, 0.0)), 0);
+        assert_eq!(light.brightness(Vec2::new(0.0, 5.0)), 0);
+    }
+}
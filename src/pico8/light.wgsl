@@ -0,0 +1,18 @@
+// Shade the canvas by its light levels.
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+// Opacity of each level of darkness
+@group(2) @binding(0) var<uniform> shade: f32;
+// Levels of darkness of each canvas pixel
+@group(2) @binding(1) var levels: texture_2d<f32>;
+@group(2) @binding(2) var levels_sampler: sampler;
+
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    let level = textureSample(levels, levels_sampler, mesh.uv).r * 255.0;
+    let alpha = clamp(round(level) * shade, 0.0, 1.0);
+    if alpha == 0.0 {
+        discard;
+    }
+    return vec4(0.0, 0.0, 0.0, alpha);
+}
@@ -1,9 +1,16 @@
 use crate::pico8::{self, Clearable, Error, Gfx, SprHandle};
-use bevy::prelude::*;
+use bevy::{
+    image::{ImageSampler, TextureFormatPixelInfo},
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    utils::HashMap,
+};
 
 #[cfg(feature = "level")]
 use crate::level;
-use bevy_ecs_tilemap::prelude::*;
 
 #[derive(Clone, Debug, Reflect)]
 pub enum Map {
@@ -17,6 +24,8 @@ pub struct P8Map {
     #[deref]
     pub entries: Vec<u8>,
     pub sheet_index: usize,
+    /// Tiles per row
+    pub columns: u32,
 }
 
 impl From<P8Map> for Map {
@@ -26,6 +35,37 @@ impl From<P8Map> for Map {
 }
 
 impl P8Map {
+    /// Return the index into `entries` of the tile at `pos`, or `None` if it
+    /// is past the last column.
+    pub fn index(&self, pos: UVec2) -> Option<usize> {
+        (pos.x < self.columns).then_some((pos.x + pos.y * self.columns) as usize)
+    }
+
+    /// Return the sprite index at `pos` if it is non-zero and matches the flag
+    /// `mask`.
+    fn tile(
+        &self,
+        pos: UVec2,
+        mask: Option<u8>,
+        sprite_sheets: &[pico8::SpriteSheet],
+    ) -> Option<u8> {
+        let index = *self.entries.get(self.index(pos)?)?;
+        if let Some(mask) = mask {
+            let flags = sprite_sheets
+                .get(self.sheet_index)?
+                .flags
+                .get(index as usize)?;
+            if flags & mask != mask {
+                return None;
+            }
+        }
+        (index != 0).then_some(index)
+    }
+
+    /// Spawn the map region as an entity whose translation is `screen_start`.
+    ///
+    /// The tiles are copied into chunk images of [MAP_CHUNK_TILES] square, so
+    /// a full screen map is a handful of sprites rather than one per tile.
     pub fn map(
         &self,
         map_pos: UVec2,
@@ -35,98 +75,148 @@ impl P8Map {
         sprite_sheets: &[pico8::SpriteSheet],
         hash: Option<u64>,
         commands: &mut Commands,
-        mut gfx_to_image: impl FnMut(&Handle<Gfx>) -> Result<Handle<Image>, Error>,
+        mut gfx_to_image: impl FnMut(&pico8::SpriteSheet, &Handle<Gfx>) -> Result<Handle<Image>, Error>,
     ) -> Result<Entity, pico8::Error> {
-        let map_size = TilemapSize::from(size);
+        let sprites = sprite_sheets
+            .get(self.sheet_index)
+            .ok_or(Error::NoSuch("sprite sheet".into()))?;
+        let image = match &sprites.handle {
+            SprHandle::Image(handle) => handle.clone(),
+            SprHandle::Gfx(ref handle) => gfx_to_image(sprites, handle)?,
+        };
         let mut clearable = Clearable::new(2);
         clearable.hash = hash;
-        let mut tile_storage = TileStorage::empty(map_size);
-        let tilemap_entity = commands.spawn(Name::new("map")).id();
-        commands.entity(tilemap_entity).with_children(|builder| {
-            for x in 0..map_size.x {
-                for y in 0..map_size.y {
-                    let texture_index = self
-                        .entries
-                        .get((map_pos.x + x + (map_pos.y + y) * pico8::MAP_COLUMNS) as usize)
-                        .and_then(|index| {
-                            if let Some(mask) = mask {
-                                sprite_sheets
-                                    .get(self.sheet_index)
-                                    .and_then(|sprite_sheet| {
-                                        (sprite_sheet.flags[*index as usize] & mask == mask)
-                                            .then_some(index)
-                                    })
-                            } else {
-                                Some(index)
-                            }
-                        })
-                        .copied()
-                        .unwrap_or(0);
-                    if texture_index != 0 {
-                        let tile_pos = TilePos {
-                            x,
-                            y: map_size.y - y - 1,
-                        };
-                        let tile_entity = builder
-                            .spawn((
-                                TileBundle {
-                                    position: tile_pos,
-                                    tilemap_id: TilemapId(tilemap_entity),
-                                    texture_index: TileTextureIndex(texture_index as u32),
-                                    ..Default::default()
-                                },
-                                // clearable.clone(),
-                            ))
-                            .id();
-                        tile_storage.set(&tile_pos, tile_entity);
+        let map_entity = commands
+            .spawn((
+                Name::new("map"),
+                Transform::from_translation(screen_start.extend(clearable.suggest_z())),
+                Visibility::default(),
+                clearable,
+            ))
+            .id();
+        let tiles: Vec<(UVec2, u8)> = (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| UVec2::new(x, y)))
+            .filter_map(|pos| {
+                self.tile(map_pos + pos, mask, sprite_sheets)
+                    .map(|index| (pos, index))
+            })
+            .collect();
+        let chunks = Chunks {
+            size,
+            tile_size: sprites.sprite_size,
+            image,
+            layout: sprites.layout.clone(),
+        };
+        commands.queue(move |world: &mut World| {
+            if let Err(e) = chunks.spawn(world, map_entity, tiles) {
+                warn!("map error {e}");
+            }
+        });
+        Ok(map_entity)
+    }
+}
+
+/// Width and height of a map chunk in tiles
+pub const MAP_CHUNK_TILES: u32 = 16;
+
+/// A map region to be drawn as chunk images
+struct Chunks {
+    /// Size of the region in tiles
+    size: UVec2,
+    tile_size: UVec2,
+    image: Handle<Image>,
+    layout: Handle<TextureAtlasLayout>,
+}
+
+impl Chunks {
+    /// Copy `tiles` into chunk images and spawn a sprite for each chunk as a
+    /// child of `parent`.
+    fn spawn(
+        self,
+        world: &mut World,
+        parent: Entity,
+        tiles: Vec<(UVec2, u8)>,
+    ) -> Result<(), Error> {
+        let rects = world
+            .resource::<Assets<TextureAtlasLayout>>()
+            .get(&self.layout)
+            .ok_or(Error::NoAsset("sprite layout".into()))?
+            .textures
+            .clone();
+        let mut images = world.resource_mut::<Assets<Image>>();
+        let source = images
+            .get(&self.image)
+            .ok_or(Error::NoAsset("sprite sheet".into()))?;
+        let format = source.texture_descriptor.format;
+        let mut chunks: HashMap<UVec2, Image> = HashMap::new();
+        for (pos, index) in tiles {
+            let Some(rect) = rects.get(index as usize) else {
+                continue;
+            };
+            let chunk = pos / MAP_CHUNK_TILES;
+            let image = chunks
+                .entry(chunk)
+                .or_insert_with(|| self.chunk_image(chunk, format));
+            let dest = (pos - chunk * MAP_CHUNK_TILES) * self.tile_size;
+            let tile_size = self.tile_size.min(rect.size());
+            for y in 0..tile_size.y {
+                for x in 0..tile_size.x {
+                    let from = UVec3::new(rect.min.x + x, rect.min.y + y, 0);
+                    let to = UVec3::new(dest.x + x, dest.y + y, 0);
+                    if let (Some(from), Some(to)) =
+                        (source.pixel_bytes(from), image.pixel_bytes_mut(to))
+                    {
+                        to.copy_from_slice(from);
                     }
                 }
             }
+        }
+        let chunks: Vec<(UVec2, Handle<Image>)> = chunks
+            .into_iter()
+            .map(|(chunk, image)| (chunk, images.add(image)))
+            .collect();
+        let chunk_size = (MAP_CHUNK_TILES * self.tile_size).as_vec2();
+        let mut parent = world
+            .get_entity_mut(parent)
+            .map_err(|_| Error::NoSuch("map entity".into()))?;
+        parent.with_children(|builder| {
+            for (chunk, image) in chunks {
+                let offset = chunk.as_vec2() * chunk_size;
+                builder.spawn((
+                    Name::new("map chunk"),
+                    Sprite {
+                        image,
+                        anchor: bevy::sprite::Anchor::TopLeft,
+                        ..default()
+                    },
+                    Transform::from_xyz(offset.x, pico8::negate_y(offset.y), 0.0),
+                ));
+            }
         });
+        Ok(())
+    }
 
-        let sprites = &sprite_sheets[self.sheet_index];
-        let tile_size: TilemapTileSize = sprites.sprite_size.as_vec2().into();
-        let grid_size = tile_size.into();
-        let map_type = TilemapType::default();
-        let mut transform =
-            get_tilemap_top_left_transform(&map_size, &grid_size, &map_type, clearable.suggest_z());
-        transform.translation += screen_start.extend(0.0);
-
-        commands.entity(tilemap_entity).insert((
-            TilemapBundle {
-                grid_size,
-                map_type,
-                size: map_size,
-                storage: tile_storage,
-                texture: TilemapTexture::Single(match &sprites.handle {
-                    SprHandle::Image(handle) => handle.clone(),
-                    SprHandle::Gfx(ref handle) => gfx_to_image(handle)?,
-                    // self.gfx_handles.get_or_create(&self.state.pal, handle, &self.gfxs, &mut self.images)
-                }),
-                tile_size,
-                // transform: Transform::from_xyz(screen_start.x, -screen_start.y, 0.0),//get_tilemap_center_transform(&map_size, &grid_size, &map_type, 0.0),
-                transform,
-                ..Default::default()
+    /// Return a transparent image for `chunk`, which is smaller at the right
+    /// and bottom edges of the region.
+    fn chunk_image(&self, chunk: UVec2, format: TextureFormat) -> Image {
+        let tiles = (self.size - chunk * MAP_CHUNK_TILES).min(UVec2::splat(MAP_CHUNK_TILES));
+        let size = tiles * self.tile_size;
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
             },
-            clearable,
-        ));
-        Ok(tilemap_entity)
+            TextureDimension::D2,
+            &vec![0; format.pixel_size()],
+            format,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        image.sampler = ImageSampler::nearest();
+        image
     }
 }
 
-/// Calculates a [`Transform`] for a tilemap that places it so that its center is at
-/// `(0.0, 0.0, 0.0)` in world space.
-pub(crate) fn get_tilemap_top_left_transform(
-    size: &TilemapSize,
-    grid_size: &TilemapGridSize,
-    map_type: &TilemapType,
-    z: f32,
-) -> Transform {
-    assert_eq!(map_type, &TilemapType::Square);
-    let y = size.y as f32 * grid_size.y;
-    Transform::from_xyz(grid_size.x / 2.0, -y + grid_size.y / 2.0, z)
-}
-
 #[cfg(feature = "level")]
 impl From<level::Tiled> for Map {
     fn from(map: level::Tiled) -> Self {
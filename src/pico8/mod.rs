@@ -13,9 +13,20 @@ pub(crate) use pal::*;
 mod gfx;
 pub use gfx::*;
 mod fillp;
+mod font;
+pub use font::*;
+pub mod aseprite;
+#[cfg(feature = "fixed")]
+mod num;
+pub mod p8;
 pub mod p8scii;
 pub(crate) use fillp::*;
+#[cfg(feature = "fixed")]
+pub use num::*;
 mod gfx_handles;
+pub mod light;
+pub use light::{Light, LightCone, Lighting};
+pub mod shape;
 pub(crate) use gfx_handles::*;
 mod defaults;
 pub(crate) mod image;
@@ -33,5 +44,7 @@ pub(crate) fn plugin(app: &mut App) {
         .add_plugins(clear::plugin)
         .add_plugins(audio::plugin)
         .add_plugins(gfx::plugin)
-        .add_plugins(gfx_handles::plugin);
+        .add_plugins(gfx_handles::plugin)
+        .add_plugins(shape::plugin)
+        .add_plugins(light::plugin);
 }
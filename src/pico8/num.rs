@@ -0,0 +1,245 @@
+//! Pico-8's 16.16 fixed-point numbers
+use fixed::types::I16F16;
+use std::{
+    fmt,
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, Shl, Shr, Sub, SubAssign},
+};
+
+/// A Pico-8 number
+///
+/// Arithmetic wraps on overflow like Pico-8 does, so `Fix::MAX + Fix::ONE`
+/// is negative. Carts that rely on overflow or exact rounding should use this
+/// instead of `f32`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fix(pub I16F16);
+
+impl Fix {
+    pub const ZERO: Fix = Fix(I16F16::ZERO);
+    pub const ONE: Fix = Fix(I16F16::ONE);
+    /// 0x7fff.ffff
+    pub const MAX: Fix = Fix(I16F16::MAX);
+    /// -0x8000.0000
+    pub const MIN: Fix = Fix(I16F16::MIN);
+
+    pub const fn from_bits(bits: i32) -> Fix {
+        Fix(I16F16::from_bits(bits))
+    }
+
+    pub const fn to_bits(self) -> i32 {
+        self.0.to_bits()
+    }
+
+    /// Convert from a float wrapping on overflow.
+    pub fn from_f32(x: f32) -> Fix {
+        Fix(I16F16::wrapping_from_num(x))
+    }
+
+    pub fn to_f32(self) -> f32 {
+        self.0.to_num()
+    }
+
+    /// flr(x)
+    pub fn flr(self) -> Fix {
+        Fix(self.0.floor())
+    }
+
+    /// ceil(x)
+    pub fn ceil(self) -> Fix {
+        Fix(self.0.wrapping_ceil())
+    }
+
+    /// lshr(x, n), a logical shift
+    pub fn lshr(self, n: i32) -> Fix {
+        if n >= 32 {
+            return Fix::ZERO;
+        }
+        Fix::from_bits(((self.to_bits() as u32) >> n.max(0)) as i32)
+    }
+
+    /// rotl(x, n)
+    pub fn rotl(self, n: i32) -> Fix {
+        Fix::from_bits(self.to_bits().rotate_left(n.rem_euclid(32) as u32))
+    }
+
+    /// rotr(x, n)
+    pub fn rotr(self, n: i32) -> Fix {
+        Fix::from_bits(self.to_bits().rotate_right(n.rem_euclid(32) as u32))
+    }
+
+    /// band(x, y)
+    pub fn band(self, y: Fix) -> Fix {
+        Fix::from_bits(self.to_bits() & y.to_bits())
+    }
+
+    /// bor(x, y)
+    pub fn bor(self, y: Fix) -> Fix {
+        Fix::from_bits(self.to_bits() | y.to_bits())
+    }
+
+    /// bxor(x, y)
+    pub fn bxor(self, y: Fix) -> Fix {
+        Fix::from_bits(self.to_bits() ^ y.to_bits())
+    }
+
+    /// bnot(x)
+    pub fn bnot(self) -> Fix {
+        Fix::from_bits(!self.to_bits())
+    }
+}
+
+impl From<f32> for Fix {
+    fn from(x: f32) -> Fix {
+        Fix::from_f32(x)
+    }
+}
+
+impl From<i16> for Fix {
+    fn from(x: i16) -> Fix {
+        Fix(I16F16::from_num(x))
+    }
+}
+
+impl From<Fix> for f32 {
+    fn from(x: Fix) -> f32 {
+        x.to_f32()
+    }
+}
+
+impl Add for Fix {
+    type Output = Fix;
+    fn add(self, y: Fix) -> Fix {
+        Fix(self.0.wrapping_add(y.0))
+    }
+}
+
+impl Sub for Fix {
+    type Output = Fix;
+    fn sub(self, y: Fix) -> Fix {
+        Fix(self.0.wrapping_sub(y.0))
+    }
+}
+
+impl Mul for Fix {
+    type Output = Fix;
+    fn mul(self, y: Fix) -> Fix {
+        Fix(self.0.wrapping_mul(y.0))
+    }
+}
+
+impl Div for Fix {
+    type Output = Fix;
+    /// Division by zero returns the largest number with the dividend's sign.
+    fn div(self, y: Fix) -> Fix {
+        if y.0 == I16F16::ZERO {
+            return if self.0 >= I16F16::ZERO {
+                Fix::MAX
+            } else {
+                Fix::from_bits(-i32::MAX)
+            };
+        }
+        let bits = ((self.to_bits() as i64) << 16) / y.to_bits() as i64;
+        Fix::from_bits(bits as i32)
+    }
+}
+
+impl Rem for Fix {
+    type Output = Fix;
+    /// The remainder is always positive. `x % 0` is zero.
+    fn rem(self, y: Fix) -> Fix {
+        let y = (y.to_bits() as i64).abs();
+        if y == 0 {
+            return Fix::ZERO;
+        }
+        Fix::from_bits((self.to_bits() as i64).rem_euclid(y) as i32)
+    }
+}
+
+impl Neg for Fix {
+    type Output = Fix;
+    fn neg(self) -> Fix {
+        Fix(self.0.wrapping_neg())
+    }
+}
+
+impl Shl<i32> for Fix {
+    type Output = Fix;
+    /// shl(x, n)
+    fn shl(self, n: i32) -> Fix {
+        if n >= 32 {
+            return Fix::ZERO;
+        }
+        Fix::from_bits(self.to_bits() << n.max(0))
+    }
+}
+
+impl Shr<i32> for Fix {
+    type Output = Fix;
+    /// shr(x, n), an arithmetic shift
+    fn shr(self, n: i32) -> Fix {
+        Fix::from_bits(self.to_bits() >> n.clamp(0, 31))
+    }
+}
+
+impl AddAssign for Fix {
+    fn add_assign(&mut self, y: Fix) {
+        *self = *self + y;
+    }
+}
+
+impl SubAssign for Fix {
+    fn sub_assign(&mut self, y: Fix) {
+        *self = *self - y;
+    }
+}
+
+impl MulAssign for Fix {
+    fn mul_assign(&mut self, y: Fix) {
+        *self = *self * y;
+    }
+}
+
+impl DivAssign for Fix {
+    fn div_assign(&mut self, y: Fix) {
+        *self = *self / y;
+    }
+}
+
+impl fmt::Display for Fix {
+    /// Format with at most four decimal places like Pico-8's `tostr()`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = format!("{:.4}", self.to_f32());
+        let s = s.trim_end_matches('0').trim_end_matches('.');
+        f.write_str(if s == "-0" { "0" } else { s })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wrapping() {
+        assert_eq!(Fix::MAX.to_bits(), 0x7fff_ffff);
+        assert_eq!(Fix::MAX + Fix::from_bits(1), Fix::MIN);
+        assert_eq!(Fix::from(16384) * Fix::from(4), Fix::ZERO);
+        assert_eq!(Fix::ONE / Fix::ZERO, Fix::MAX);
+        assert_eq!(Fix::from(-7) % Fix::from(3), Fix::from(2));
+        assert_eq!(Fix::from(7) / Fix::from(2), Fix::from_f32(3.5));
+    }
+
+    #[test]
+    fn bits() {
+        assert_eq!(Fix::ONE << 1, Fix::from(2));
+        assert_eq!(Fix::from(-2) >> 1, Fix::from(-1));
+        assert_eq!(Fix::from(-1).lshr(16), Fix::from_bits(0xffff));
+        assert_eq!(Fix::ONE.rotr(17), Fix::from_bits(i32::MIN));
+        assert_eq!(Fix::ONE << 32, Fix::ZERO);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Fix::from_f32(0.5).to_string(), "0.5");
+        assert_eq!(Fix::from(3).to_string(), "3");
+        assert_eq!(Fix::from_bits(1).to_string(), "0");
+    }
+}
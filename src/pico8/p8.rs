@@ -0,0 +1,327 @@
+//! Pico-8 `.p8` carts
+//!
+//! Read the data sections of a text cart, e.g., `__gfx__`, so artists can
+//! keep using Pico-8's editors.
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::pico8::{
+    audio::{Sfx, SfxError},
+    to_byte, to_nybble, Gfx,
+};
+
+/// Pico-8's sprite sheet is 128 by 128 pixels.
+pub const GFX_SIZE: usize = 128;
+/// Pico-8 has a flag byte for each of its 256 sprites.
+pub const GFF_COUNT: usize = 256;
+/// Pico-8's map is 128 tiles wide.
+pub const MAP_WIDTH: usize = 128;
+/// The `__map__` section holds the top 32 rows of the map. The bottom 32 rows
+/// share memory with the bottom half of the sprite sheet.
+pub const MAP_ROWS: usize = 32;
+/// Pico-8 has 64 sound effects and 64 music patterns.
+pub const SFX_COUNT: usize = 64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum P8Error {
+    #[error("cart has no {0} section")]
+    NoSection(&'static str),
+    #[error("invalid hex digit {digit:?} in {section} line {line}")]
+    InvalidHex {
+        section: &'static str,
+        line: usize,
+        digit: char,
+    },
+    #[error("invalid sfx {index}: {source}")]
+    Sfx { index: usize, source: SfxError },
+}
+
+/// A pattern of the `__music__` section: up to four sfx played together
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+pub struct MusicPart {
+    /// Bit 0 begins a loop, bit 1 ends it, and bit 2 stops the music at the
+    /// end of the pattern.
+    pub flags: u8,
+    /// The sfx played on each channel or none if the channel is off
+    pub sfx: [Option<u8>; 4],
+}
+
+impl MusicPart {
+    pub fn begins_loop(&self) -> bool {
+        self.flags & 1 != 0
+    }
+
+    pub fn ends_loop(&self) -> bool {
+        self.flags & 2 != 0
+    }
+
+    pub fn stops(&self) -> bool {
+        self.flags & 4 != 0
+    }
+}
+
+/// The sections of a `.p8` cart by name
+#[derive(Debug, Clone)]
+pub struct P8<'a> {
+    sections: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> P8<'a> {
+    pub fn parse(text: &'a str) -> Self {
+        let mut sections = HashMap::default();
+        let mut current: Option<(&str, usize)> = None;
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            let trimmed = line.trim_end();
+            if trimmed.len() > 4 && trimmed.starts_with("__") && trimmed.ends_with("__") {
+                if let Some((name, start)) = current.take() {
+                    sections.insert(name, &text[start..offset]);
+                }
+                current = Some((&trimmed[2..trimmed.len() - 2], offset + line.len()));
+            }
+            offset += line.len();
+        }
+        if let Some((name, start)) = current {
+            sections.insert(name, &text[start..]);
+        }
+        P8 { sections }
+    }
+
+    /// Return the text of section `name`, e.g., "gfx" for `__gfx__`.
+    pub fn section(&self, name: &str) -> Option<&'a str> {
+        self.sections.get(name).copied()
+    }
+
+    /// Return the sprite sheet of the `__gfx__` section.
+    ///
+    /// Each character is the color index of a pixel. Missing lines or pixels
+    /// are zero like in Pico-8.
+    pub fn gfx(&self) -> Result<Gfx, P8Error> {
+        const SECTION: &str = "gfx";
+        let text = self.section(SECTION).ok_or(P8Error::NoSection(SECTION))?;
+        let mut gfx = Gfx::new(GFX_SIZE, GFX_SIZE);
+        for (y, line) in text.lines().take(GFX_SIZE).enumerate() {
+            for (x, c) in line.trim_end().bytes().take(GFX_SIZE).enumerate() {
+                let color = to_nybble(c).ok_or(P8Error::InvalidHex {
+                    section: SECTION,
+                    line: y,
+                    digit: c as char,
+                })?;
+                gfx.set(x, y, color);
+            }
+        }
+        Ok(gfx)
+    }
+
+    /// Return the sprite flags of the `__gff__` section or none if the cart
+    /// has none.
+    pub fn flags(&self) -> Result<Vec<u8>, P8Error> {
+        const SECTION: &str = "gff";
+        let Some(text) = self.section(SECTION) else {
+            return Ok(vec![]);
+        };
+        let mut flags = Vec::with_capacity(GFF_COUNT);
+        for (i, line) in text.lines().enumerate() {
+            for pair in line.trim_end().as_bytes().chunks_exact(2) {
+                flags.push(to_byte(pair[0], pair[1]).ok_or(P8Error::InvalidHex {
+                    section: SECTION,
+                    line: i,
+                    digit: pair[0] as char,
+                })?);
+            }
+        }
+        flags.resize(GFF_COUNT, 0);
+        Ok(flags)
+    }
+
+    /// Return the sound effects of the `__sfx__` section or none if the cart
+    /// has none.
+    pub fn sfx(&self) -> Result<Vec<Sfx>, P8Error> {
+        let Some(text) = self.section("sfx") else {
+            return Ok(vec![]);
+        };
+        text.lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .take(SFX_COUNT)
+            .enumerate()
+            .map(|(index, line)| {
+                Sfx::try_from(line).map_err(|source| P8Error::Sfx { index, source })
+            })
+            .collect()
+    }
+
+    /// Return the patterns of the `__music__` section or none if the cart has
+    /// none.
+    ///
+    /// Each line is a flag byte, a space, and a byte per channel. A channel
+    /// byte with bit 6 set is off.
+    pub fn music(&self) -> Result<Vec<MusicPart>, P8Error> {
+        const SECTION: &str = "music";
+        let Some(text) = self.section(SECTION) else {
+            return Ok(vec![]);
+        };
+        let byte = |line: usize, pair: &[u8]| {
+            to_byte(pair[0], pair[1]).ok_or(P8Error::InvalidHex {
+                section: SECTION,
+                line,
+                digit: pair[0] as char,
+            })
+        };
+        let mut parts = Vec::new();
+        for (i, line) in text.lines().take(SFX_COUNT).enumerate() {
+            let bytes: Vec<u8> = line.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+            if bytes.is_empty() {
+                continue;
+            }
+            let mut pairs = bytes.chunks_exact(2);
+            let mut part = MusicPart {
+                flags: pairs
+                    .next()
+                    .map(|pair| byte(i, pair))
+                    .transpose()?
+                    .unwrap_or(0),
+                ..default()
+            };
+            for (sfx, pair) in part.sfx.iter_mut().zip(pairs) {
+                let channel = byte(i, pair)?;
+                *sfx = (channel & 0x40 == 0).then_some(channel & 0x3f);
+            }
+            parts.push(part);
+        }
+        Ok(parts)
+    }
+
+    /// Return the map of the `__map__` section followed by the rows it shares
+    /// with the bottom half of the `__gfx__` section.
+    ///
+    /// Each entry is a sprite index, row by row, [MAP_WIDTH] to a row. The
+    /// shared rows are left out if the cart has no `__gfx__` section.
+    pub fn map(&self) -> Result<Vec<u8>, P8Error> {
+        const SECTION: &str = "map";
+        let text = self.section(SECTION).ok_or(P8Error::NoSection(SECTION))?;
+        let mut entries = vec![0; MAP_WIDTH * MAP_ROWS];
+        for (y, line) in text.lines().take(MAP_ROWS).enumerate() {
+            for (x, pair) in line
+                .trim_end()
+                .as_bytes()
+                .chunks_exact(2)
+                .take(MAP_WIDTH)
+                .enumerate()
+            {
+                entries[x + y * MAP_WIDTH] =
+                    to_byte(pair[0], pair[1]).ok_or(P8Error::InvalidHex {
+                        section: SECTION,
+                        line: y,
+                        digit: pair[0] as char,
+                    })?;
+            }
+        }
+        if self.section("gfx").is_some() {
+            // Two pixels make a byte, the first pixel its low nybble, so
+            // two rows of pixels make a row of the map.
+            let gfx = self.gfx()?;
+            entries.extend((GFX_SIZE / 2..GFX_SIZE).flat_map(|y| {
+                let gfx = &gfx;
+                (0..GFX_SIZE / 2).map(move |x| {
+                    let lo = gfx.get(2 * x, y).unwrap_or(0);
+                    let hi = gfx.get(2 * x + 1, y).unwrap_or(0);
+                    lo | hi << 4
+                })
+            }));
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CART: &str = "pico-8 cartridge // http://www.pico-8.com
+version 41
+__lua__
+print(\"__gfx__\")
+__gfx__
+01230000
+00000000
+4000000f
+__gff__
+0001ff
+__map__
+00010203
+ff
+__label__
+0000
+__sfx__
+010f00001805018050000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000
+__music__
+01 00014344
+04 41424344
+";
+
+    #[test]
+    fn sections() {
+        let p8 = P8::parse(CART);
+        assert_eq!(p8.section("lua"), Some("print(\"__gfx__\")\n"));
+        assert_eq!(p8.section("label"), Some("0000\n"));
+        assert!(p8.section("sfx").is_none());
+    }
+
+    #[test]
+    fn gfx_and_flags() {
+        let p8 = P8::parse(CART);
+        let gfx = p8.gfx().unwrap();
+        assert_eq!(gfx.width, GFX_SIZE);
+        assert_eq!(gfx.get(1, 0), Some(1));
+        assert_eq!(gfx.get(3, 0), Some(3));
+        assert_eq!(gfx.get(0, 2), Some(4));
+        assert_eq!(gfx.get(7, 2), Some(15));
+        assert_eq!(gfx.get(0, 100), Some(0));
+        let flags = p8.flags().unwrap();
+        assert_eq!(flags.len(), GFF_COUNT);
+        assert_eq!(&flags[..3], &[0x00, 0x01, 0xff]);
+        assert!(P8::parse("__gfx__\n0g\n").gfx().is_err());
+    }
+
+    #[test]
+    fn sfx_and_music() {
+        let p8 = P8::parse(CART);
+        let sfx = p8.sfx().unwrap();
+        assert_eq!(sfx.len(), 1);
+        assert_eq!(sfx[0].speed, 0x0f);
+        assert_eq!(sfx[0].notes.len(), 2);
+        let music = p8.music().unwrap();
+        assert_eq!(
+            music,
+            vec![
+                MusicPart {
+                    flags: 1,
+                    sfx: [Some(0), Some(1), None, None],
+                },
+                MusicPart {
+                    flags: 4,
+                    sfx: [None; 4],
+                },
+            ]
+        );
+        assert!(music[0].begins_loop());
+        assert!(music[1].stops());
+    }
+
+    #[test]
+    fn map() {
+        let map = P8::parse(CART).map().unwrap();
+        assert_eq!(map.len(), MAP_WIDTH * MAP_ROWS * 2);
+        assert_eq!(&map[..5], &[0, 1, 2, 3, 0]);
+        assert_eq!(map[MAP_WIDTH], 0xff);
+
+        let mut cart = String::from("__map__\n01\n__gfx__\n");
+        cart.push_str(&"\n".repeat(GFX_SIZE / 2));
+        cart.push_str("12340000\n");
+        let map = P8::parse(&cart).map().unwrap();
+        let shared = MAP_WIDTH * MAP_ROWS;
+        assert_eq!(map[0], 1);
+        assert_eq!(&map[shared..shared + 3], &[0x21, 0x43, 0]);
+        assert_eq!(P8::parse("__map__\n01\n").map().unwrap().len(), shared);
+    }
+}
@@ -192,6 +192,30 @@ pub fn vec_to_utf8(p8chars: Vec<u8>) -> String {
     accum
 }
 
+/// Convert a UTF-8 string to P8SCII.
+///
+/// Characters without a P8SCII equivalent become '?'.
+pub fn utf8_to_vec(s: &str) -> Vec<u8> {
+    let mut accum = Vec::new();
+    for c in s.chars() {
+        if c.is_ascii() {
+            accum.push(c as u8);
+        } else if c == '\u{fe0f}' {
+            // Skip the emoji variation selector.
+        } else {
+            let p8char = (16..=255)
+                .find(|p8char| {
+                    char_to_utf8(*p8char)
+                        .and_then(|utf8| utf8.chars().next())
+                        .is_some_and(|utf8| utf8 == c)
+                })
+                .unwrap_or(b'?');
+            accum.push(p8char);
+        }
+    }
+    accum
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -207,4 +231,12 @@ mod test {
         let s = vec_to_utf8(v);
         assert_eq!("hit➡\u{fe0f} or ❎ to go to next", s);
     }
+
+    #[test]
+    fn test_utf8_to_vec() {
+        assert_eq!(
+            utf8_to_vec("hit➡\u{fe0f} ❎"),
+            vec![b'h', b'i', b't', 145, b' ', 151]
+        );
+    }
 }
@@ -41,6 +41,28 @@ impl Palette {
         Ok(())
     }
 
+    /// Return the index of the first color matching `color` ignoring alpha.
+    pub fn index_of(&self, color: [u8; 4]) -> Option<usize> {
+        self.data.iter().position(|c| c[0..3] == color[0..3])
+    }
+
+    /// Return the index of the color nearest `color` in OKLab space ignoring
+    /// alpha.
+    pub fn nearest(&self, color: impl Into<Oklaba>) -> Option<usize> {
+        let color = color.into();
+        let distance = |c: &[u8; 4]| {
+            let c = Oklaba::from(Srgba::rgb_u8(c[0], c[1], c[2]));
+            (c.lightness - color.lightness).powi(2)
+                + (c.a - color.a).powi(2)
+                + (c.b - color.b).powi(2)
+        };
+        self.data
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+            .map(|(i, _)| i)
+    }
+
     pub fn get_color(&self, index: usize) -> Result<Srgba, Error> {
         self.data
             .get(index)
@@ -1,6 +1,6 @@
 use crate::pico8::Error;
 use bitvec::prelude::*;
-use std::hash::Hash;
+use std::{borrow::Cow, hash::Hash};
 
 #[derive(Debug, Clone, Eq, Hash, PartialEq)]
 pub struct PalMap {
@@ -46,6 +46,29 @@ impl PalMap {
         self.transparency.set(0, true);
     }
 
+    /// Return true if only color 0 is transparent.
+    pub fn is_default_transparency(&self) -> bool {
+        self.transparency.first().is_some_and(|bit| *bit) && self.transparency.count_ones() == 1
+    }
+
+    /// Return this map with a sprite sheet's `transparent` colors unless
+    /// `palt()` changed the transparency from its default.
+    pub fn with_sheet_transparency(&self, transparent: Option<&[usize]>) -> Cow<'_, PalMap> {
+        match transparent {
+            Some(colors) if self.is_default_transparency() => {
+                let mut pal_map = self.clone();
+                pal_map.transparency.fill(false);
+                for &i in colors {
+                    if i < pal_map.transparency.len() {
+                        pal_map.transparency.set(i, true);
+                    }
+                }
+                Cow::Owned(pal_map)
+            }
+            _ => Cow::Borrowed(self),
+        }
+    }
+
     // pub fn from_image(image: &Image) -> Self {
     //     let size = image.size();
     //     let count = (size.x * size.y) as usize;
@@ -89,3 +112,21 @@ impl PalMap {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sheet_transparency() {
+        let mut pal_map = PalMap::default();
+        let sheet = pal_map.with_sheet_transparency(Some(&[0, 14]));
+        assert!(sheet.transparency[14]);
+        assert!(!sheet.is_default_transparency());
+        // palt() overrides the sheet.
+        pal_map.transparency.set(0, false);
+        let sheet = pal_map.with_sheet_transparency(Some(&[0, 14]));
+        assert!(!sheet.transparency[14]);
+        assert!(!sheet.transparency[0]);
+    }
+}
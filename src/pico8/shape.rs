@@ -0,0 +1,469 @@
+//! Shapes drawn by a shader
+//!
+//! Circles, ovals, and lines are quads that share one mesh and one material,
+//! so effect-heavy carts that draw many particles are drawn in batches. Their
+//! pixels are picked by a fragment shader instead of being rasterized into a
+//! new image on the CPU for every call.
+//!
+//! What differs between shapes, i.e., their color, size, and line end points,
+//! is instance data kept in a texture rather than in a uniform, since WebGL2
+//! has no storage buffers. Only the rows of shapes drawn since the last frame
+//! are written to it.
+//!
+//! A shape's index into that texture is its [ShapeInstance]. The shader reads
+//! it from the z axis of the global transform, which a flat quad does not
+//! use, so the entity's [Transform] is free to be scaled or interpolated.
+use bevy::{
+    asset::embedded_asset,
+    ecs::{component::ComponentId, system::SystemParam, world::DeferredWorld},
+    math::Vec3A,
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_asset::{RenderAssetUsages, RenderAssets},
+        render_resource::{
+            AsBindGroup, Extent3d, ImageCopyTexture, ImageDataLayout, Origin3d, ShaderRef,
+            TextureAspect, TextureDimension, TextureFormat,
+        },
+        renderer::RenderQueue,
+        texture::GpuImage,
+        Render, RenderApp, RenderSet,
+    },
+    sprite::{AlphaMode2d, Material2d, Material2dPlugin},
+    transform::TransformSystem,
+    utils::HashSet,
+};
+
+use crate::pico8::Error;
+
+const SHADER: &str = "embedded://nano9/pico8/shape.wgsl";
+/// Shapes per row of the instance texture
+const SHAPES_PER_ROW: usize = 64;
+/// Texels per shape: its color; its size and kind; and its line end points
+const SHAPE_TEXELS: usize = 3;
+const TEXEL_BYTES: usize = 4 * std::mem::size_of::<f32>();
+const ROW_BYTES: usize = SHAPES_PER_ROW * SHAPE_TEXELS * TEXEL_BYTES;
+/// Rows of the instance texture at most, since WebGL2 allows only 2048
+/// texels on a side
+const MAX_ROWS: usize = 2048;
+/// The most shapes that may exist at once
+pub const MAX_SHAPES: usize = SHAPES_PER_ROW * MAX_ROWS;
+
+pub(crate) fn plugin(app: &mut App) {
+    embedded_asset!(app, "shape.wgsl");
+    app.add_plugins((
+        Material2dPlugin::<ShapeMaterial>::default(),
+        ExtractResourcePlugin::<ShapeRows>::default(),
+    ))
+    .init_resource::<ShapeInstances>()
+    .init_resource::<ShapeRows>()
+    .add_systems(
+        PostUpdate,
+        (
+            upload_shape_instances,
+            write_shape_index.after(TransformSystem::TransformPropagate),
+        ),
+    );
+    let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+        return;
+    };
+    render_app.add_systems(Render, write_shape_rows.in_set(RenderSet::PrepareResources));
+}
+
+/// The kind of shape
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Shape {
+    Oval,
+    OvalFill,
+    /// A line between two pixels relative to the upper left corner
+    Line(IVec2, IVec2),
+}
+
+impl Shape {
+    fn kind(&self) -> u32 {
+        match self {
+            Shape::Oval => 0,
+            Shape::OvalFill => 1,
+            Shape::Line(..) => 2,
+        }
+    }
+}
+
+/// What the shader needs to draw one shape
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct ShapeData {
+    color: LinearRgba,
+    size: Vec2,
+    kind: u32,
+    a: Vec2,
+    b: Vec2,
+}
+
+impl ShapeData {
+    fn new(shape: Shape, size: UVec2, color: Color) -> Self {
+        let (a, b) = match shape {
+            Shape::Line(a, b) => (a.as_vec2(), b.as_vec2()),
+            _ => (Vec2::ZERO, Vec2::ZERO),
+        };
+        ShapeData {
+            color: color.into(),
+            size: size.as_vec2(),
+            kind: shape.kind(),
+            a,
+            b,
+        }
+    }
+
+    fn texels(&self) -> [f32; 4 * SHAPE_TEXELS] {
+        let c = self.color;
+        [
+            c.red,
+            c.green,
+            c.blue,
+            c.alpha,
+            self.size.x,
+            self.size.y,
+            self.kind as f32,
+            0.0,
+            self.a.x,
+            self.a.y,
+            self.b.x,
+            self.b.y,
+        ]
+    }
+}
+
+/// Material shared by every shape
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct ShapeMaterial {
+    #[texture(0, sample_type = "float", filterable = false)]
+    instances: Handle<Image>,
+}
+
+impl Material2d for ShapeMaterial {
+    fn vertex_shader() -> ShaderRef {
+        SHADER.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        SHADER.into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode2d {
+        // Blend so shapes are sorted with the sprites they are drawn among.
+        AlphaMode2d::Blend
+    }
+}
+
+/// The index of a shape's data in [ShapeInstances]
+///
+/// It is freed for another shape when the entity is despawned.
+#[derive(Debug, Component)]
+#[component(on_remove = free_shape_instance)]
+pub struct ShapeInstance(u32);
+
+fn free_shape_instance(mut world: DeferredWorld, id: Entity, _comp_id: ComponentId) {
+    let Some(index) = world.get::<ShapeInstance>(id).map(|instance| instance.0) else {
+        return;
+    };
+    if let Some(mut instances) = world.get_resource_mut::<ShapeInstances>() {
+        instances.remove(index);
+    }
+}
+
+/// A unit quad, the shape material, and the instance data of every shape
+#[derive(Debug, Resource)]
+pub struct ShapeInstances {
+    quad: Handle<Mesh>,
+    material: Handle<ShapeMaterial>,
+    texture: Handle<Image>,
+    /// Index 0 is never used, so that no quad has a zero z axis.
+    data: Vec<ShapeData>,
+    free: Vec<u32>,
+    /// Rows of the texture
+    rows: usize,
+    /// Rows whose shapes changed since the last upload
+    dirty_rows: HashSet<usize>,
+}
+
+impl FromWorld for ShapeInstances {
+    fn from_world(world: &mut World) -> Self {
+        let data = vec![ShapeData::default()];
+        let texture = world
+            .resource_mut::<Assets<Image>>()
+            .add(instance_image(&data, 1));
+        let material = world
+            .resource_mut::<Assets<ShapeMaterial>>()
+            .add(ShapeMaterial {
+                instances: texture.clone(),
+            });
+        let quad = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Rectangle::new(1.0, 1.0));
+        ShapeInstances {
+            quad,
+            material,
+            texture,
+            data,
+            free: Vec::new(),
+            rows: 1,
+            dirty_rows: HashSet::new(),
+        }
+    }
+}
+
+impl ShapeInstances {
+    /// Store `data` and return its index, or `None` if there are already
+    /// [MAX_SHAPES].
+    fn insert(&mut self, data: ShapeData) -> Option<ShapeInstance> {
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.data[index as usize] = data;
+                index as usize
+            }
+            None if self.data.len() < MAX_SHAPES => {
+                self.data.push(data);
+                self.data.len() - 1
+            }
+            None => return None,
+        };
+        self.dirty_rows.insert(index / SHAPES_PER_ROW);
+        Some(ShapeInstance(index as u32))
+    }
+
+    fn remove(&mut self, index: u32) {
+        self.free.push(index);
+    }
+
+    /// Return the unit quad every shape is drawn on.
+    pub fn quad(&self) -> Handle<Mesh> {
+        self.quad.clone()
+    }
+
+    /// Return the number of shapes.
+    pub fn len(&self) -> usize {
+        self.data.len() - 1 - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Return the bytes of the `row`th row of [SHAPES_PER_ROW] shapes.
+fn row_bytes(data: &[ShapeData], row: usize) -> Vec<u8> {
+    let mut bytes = vec![0; ROW_BYTES];
+    let shapes = data.iter().skip(row * SHAPES_PER_ROW);
+    for (shape, chunk) in shapes.zip(bytes.chunks_exact_mut(SHAPE_TEXELS * TEXEL_BYTES)) {
+        chunk.copy_from_slice(bytemuck::cast_slice(&shape.texels()));
+    }
+    bytes
+}
+
+/// Lay out `data` in `rows` rows of [SHAPES_PER_ROW] shapes.
+fn instance_image(data: &[ShapeData], rows: usize) -> Image {
+    Image::new(
+        Extent3d {
+            width: (SHAPES_PER_ROW * SHAPE_TEXELS) as u32,
+            height: rows as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        (0..rows).flat_map(|row| row_bytes(data, row)).collect(),
+        TextureFormat::Rgba32Float,
+        RenderAssetUsages::default(),
+    )
+}
+
+/// Rows of the instance texture to write in the render world
+#[derive(Debug, Clone, Default, Resource, ExtractResource)]
+struct ShapeRows {
+    texture: AssetId<Image>,
+    rows: Vec<(u32, Vec<u8>)>,
+}
+
+fn upload_shape_instances(
+    mut instances: ResMut<ShapeInstances>,
+    mut shape_rows: ResMut<ShapeRows>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<ShapeMaterial>>,
+) {
+    if instances.dirty_rows.is_empty() {
+        return;
+    }
+    let instances = &mut *instances;
+    let needed = instances.data.len().div_ceil(SHAPES_PER_ROW);
+    if needed > instances.rows {
+        // A new texture has every row, so grow it by more than one row.
+        instances.rows = needed.next_power_of_two().min(MAX_ROWS);
+        instances.dirty_rows.clear();
+        images.insert(
+            &instances.texture,
+            instance_image(&instances.data, instances.rows),
+        );
+        // The material's bind group holds the old texture until it changes too.
+        materials.get_mut(&instances.material);
+        return;
+    }
+    shape_rows.texture = instances.texture.id();
+    shape_rows.rows = instances
+        .dirty_rows
+        .drain()
+        .map(|row| (row as u32, row_bytes(&instances.data, row)))
+        .collect();
+}
+
+fn write_shape_rows(
+    mut shape_rows: ResMut<ShapeRows>,
+    images: Res<RenderAssets<GpuImage>>,
+    queue: Res<RenderQueue>,
+) {
+    if shape_rows.rows.is_empty() {
+        return;
+    }
+    let rows = std::mem::take(&mut shape_rows.rows);
+    let Some(image) = images.get(shape_rows.texture) else {
+        return;
+    };
+    for (row, bytes) in rows {
+        queue.write_texture(
+            ImageCopyTexture {
+                texture: &image.texture,
+                mip_level: 0,
+                origin: Origin3d { x: 0, y: row, z: 0 },
+                aspect: TextureAspect::All,
+            },
+            &bytes,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(ROW_BYTES as u32),
+                rows_per_image: None,
+            },
+            Extent3d {
+                width: (SHAPES_PER_ROW * SHAPE_TEXELS) as u32,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+/// Pass each shape's index to the shader in the z axis of its global
+/// transform.
+fn write_shape_index(
+    mut query: Query<
+        (&ShapeInstance, &mut GlobalTransform),
+        Or<(Changed<ShapeInstance>, Changed<GlobalTransform>)>,
+    >,
+) {
+    for (instance, mut global) in &mut query {
+        let mut affine = global.affine();
+        affine.matrix3.z_axis = Vec3A::new(0.0, 0.0, instance.0 as f32);
+        *global = affine.into();
+    }
+}
+
+/// Shape instances
+#[derive(SystemParam)]
+pub struct Shapes<'w> {
+    pub(crate) instances: ResMut<'w, ShapeInstances>,
+}
+
+impl Shapes<'_> {
+    /// Return the mesh, material, and instance to draw `shape`, or an error
+    /// if there are already [MAX_SHAPES].
+    pub fn get(
+        &mut self,
+        shape: Shape,
+        size: UVec2,
+        color: Color,
+    ) -> Result<(Mesh2d, MeshMaterial2d<ShapeMaterial>, ShapeInstance), Error> {
+        let instance = self
+            .instances
+            .insert(ShapeData::new(shape, size, color))
+            .ok_or_else(|| Error::Unsupported(format!("more than {MAX_SHAPES} shapes").into()))?;
+        Ok((
+            Mesh2d(self.instances.quad.clone()),
+            MeshMaterial2d(self.instances.material.clone()),
+            instance,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn instances() -> ShapeInstances {
+        ShapeInstances {
+            quad: Handle::default(),
+            material: Handle::default(),
+            texture: Handle::default(),
+            data: vec![ShapeData::default()],
+            free: Vec::new(),
+            rows: 1,
+            dirty_rows: HashSet::new(),
+        }
+    }
+
+    #[test]
+    fn test_reuse_instances() {
+        let mut instances = instances();
+        let size = UVec2::splat(5);
+        let a = instances
+            .insert(ShapeData::new(Shape::OvalFill, size, Color::WHITE))
+            .unwrap();
+        let b = instances
+            .insert(ShapeData::new(Shape::Oval, size, Color::WHITE))
+            .unwrap();
+        assert_eq!((a.0, b.0), (1, 2));
+        assert_eq!(instances.len(), 2);
+        instances.remove(a.0);
+        assert_eq!(instances.len(), 1);
+        let line = Shape::Line(IVec2::ZERO, IVec2::new(4, 2));
+        let c = instances
+            .insert(ShapeData::new(line, size, Color::BLACK))
+            .unwrap();
+        assert_eq!(c.0, 1);
+        assert_eq!(instances.data[1].kind, 2);
+        assert_eq!(instances.data[1].b, Vec2::new(4.0, 2.0));
+    }
+
+    #[test]
+    fn test_instance_image() {
+        let mut data = vec![ShapeData::default(); SHAPES_PER_ROW + 2];
+        data[SHAPES_PER_ROW + 1] = ShapeData::new(Shape::OvalFill, UVec2::new(3, 4), Color::WHITE);
+        let image = instance_image(&data, 2);
+        assert_eq!(image.height(), 2);
+        assert_eq!(image.width(), (SHAPES_PER_ROW * SHAPE_TEXELS) as u32);
+        // The second shape of the second row
+        let texel_bytes = 16;
+        let offset = (SHAPES_PER_ROW * SHAPE_TEXELS + SHAPE_TEXELS + 1) * texel_bytes;
+        let texel: &[f32] = bytemuck::cast_slice(&image.data[offset..offset + texel_bytes]);
+        assert_eq!(texel, [3.0, 4.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_dirty_rows() {
+        let mut instances = instances();
+        let shape = ShapeData::new(Shape::OvalFill, UVec2::ONE, Color::WHITE);
+        for _ in 0..SHAPES_PER_ROW {
+            instances.insert(shape).unwrap();
+        }
+        assert_eq!(instances.dirty_rows, HashSet::from_iter([0, 1]));
+        instances.dirty_rows.clear();
+        instances.remove(3);
+        instances.insert(shape).unwrap();
+        assert_eq!(instances.dirty_rows, HashSet::from_iter([0]));
+    }
+
+    #[test]
+    fn test_max_shapes() {
+        let mut instances = instances();
+        instances.data.resize(MAX_SHAPES, ShapeData::default());
+        let shape = ShapeData::new(Shape::Oval, UVec2::ONE, Color::WHITE);
+        assert!(instances.insert(shape).is_none());
+        instances.remove(7);
+        assert_eq!(instances.insert(shape).map(|i| i.0), Some(7));
+    }
+}
@@ -0,0 +1,102 @@
+// Draw ovals and lines on a quad one pixel at a time.
+#import bevy_sprite::mesh2d_functions::{
+    get_world_from_local,
+    mesh2d_position_local_to_world,
+    mesh2d_position_world_to_clip,
+}
+
+// Three texels per shape: its color; its width, height, and kind; and its line
+// end points.
+@group(2) @binding(0) var instances: texture_2d<f32>;
+
+const SHAPES_PER_ROW: u32 = 64u;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+};
+
+struct Shape {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) @interpolate(flat) color: vec4<f32>,
+    // Width and height in pixels
+    @location(2) @interpolate(flat) size: vec2<f32>,
+    // 0: oval, 1: filled oval, 2: line
+    @location(3) @interpolate(flat) kind: u32,
+    // Line end points in pixels relative to the upper left corner
+    @location(4) @interpolate(flat) a: vec2<f32>,
+    @location(5) @interpolate(flat) b: vec2<f32>,
+};
+
+fn texel(index: u32, i: u32) -> vec4<f32> {
+    let x = (index % SHAPES_PER_ROW) * 3u + i;
+    return textureLoad(instances, vec2(i32(x), i32(index / SHAPES_PER_ROW)), 0);
+}
+
+@vertex
+fn vertex(vertex: Vertex) -> Shape {
+    let world_from_local = get_world_from_local(vertex.instance_index);
+    // The quad is flat, so the z axis is free to carry the shape's index.
+    let index = u32(round(world_from_local[2].z));
+    let world_position = mesh2d_position_local_to_world(world_from_local, vec4(vertex.position, 1.0));
+    var out: Shape;
+    out.clip_position = mesh2d_position_world_to_clip(world_position);
+    out.uv = vertex.uv;
+    out.color = texel(index, 0u);
+    let size_kind = texel(index, 1u);
+    out.size = size_kind.xy;
+    out.kind = u32(size_kind.z);
+    let ends = texel(index, 2u);
+    out.a = ends.xy;
+    out.b = ends.zw;
+    return out;
+}
+
+fn inside_oval(size: vec2<f32>, q: vec2<f32>) -> bool {
+    let r = size * 0.5;
+    let d = (q - r) / r;
+    return dot(d, d) <= 1.0;
+}
+
+fn on_line(a: vec2<f32>, b: vec2<f32>, p: vec2<f32>) -> bool {
+    let d = b - a;
+    if abs(d.x) >= abs(d.y) {
+        if d.x == 0.0 {
+            return all(p == a);
+        }
+        let y = a.y + (p.x - a.x) * d.y / d.x;
+        return p.y == floor(y + 0.5);
+    } else {
+        let x = a.x + (p.y - a.y) * d.x / d.y;
+        return p.x == floor(x + 0.5);
+    }
+}
+
+@fragment
+fn fragment(shape: Shape) -> @location(0) vec4<f32> {
+    let p = min(floor(shape.uv * shape.size), shape.size - 1.0);
+    let q = p + 0.5;
+    let size = shape.size;
+    var drawn = false;
+    switch shape.kind {
+        case 1u: {
+            drawn = inside_oval(size, q);
+        }
+        case 0u: {
+            // An edge pixel has a neighbor outside the oval.
+            drawn = inside_oval(size, q) && !(inside_oval(size, q + vec2(1.0, 0.0))
+                                              && inside_oval(size, q - vec2(1.0, 0.0))
+                                              && inside_oval(size, q + vec2(0.0, 1.0))
+                                              && inside_oval(size, q - vec2(0.0, 1.0)));
+        }
+        default: {
+            drawn = on_line(shape.a, shape.b, p);
+        }
+    }
+    if !drawn {
+        discard;
+    }
+    return shape.color;
+}
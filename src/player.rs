@@ -0,0 +1,197 @@
+//! Player slots
+//!
+//! Gamepads are not given to players until they are claimed, so a cart can
+//! show a "Press A to join" screen. [Players] claims and releases slots and
+//! sends [PlayerJoined] and [PlayerLeft]. A disconnected gamepad gives up its
+//! slot. A claimed gamepad's d-pad, left stick, South, and East buttons are
+//! the left, right, up, down, O, and X buttons of its slot's player in
+//! `btn()`.
+//!
+//! ```ignore
+//! fn join(gamepads: Query<(Entity, &Gamepad)>, mut players: Players) {
+//!     for (id, gamepad) in &gamepads {
+//!         if gamepad.just_pressed(GamepadButton::South) {
+//!             players.claim(id);
+//!         }
+//!     }
+//! }
+//! ```
+use bevy::{
+    ecs::system::SystemParam,
+    input::{
+        gamepad::{GamepadConnection, GamepadConnectionEvent},
+        InputSystem,
+    },
+    prelude::*,
+};
+
+/// Pico-8 has eight players.
+pub const PLAYER_COUNT: usize = 8;
+
+pub(crate) fn plugin(app: &mut App) {
+    app.init_resource::<PlayerSlots>()
+        .add_event::<PlayerJoined>()
+        .add_event::<PlayerLeft>()
+        .add_systems(PreUpdate, release_disconnected.after(InputSystem));
+}
+
+/// Sent when a gamepad claims a player slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub struct PlayerJoined {
+    pub slot: usize,
+    pub gamepad: Entity,
+}
+
+/// Sent when a player slot is released or its gamepad disconnects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub struct PlayerLeft {
+    pub slot: usize,
+    pub gamepad: Entity,
+}
+
+/// The gamepad in each player slot
+#[derive(Debug, Clone, Resource)]
+pub struct PlayerSlots {
+    slots: Vec<Option<Entity>>,
+}
+
+impl Default for PlayerSlots {
+    fn default() -> Self {
+        PlayerSlots::new(PLAYER_COUNT)
+    }
+}
+
+impl PlayerSlots {
+    pub fn new(count: usize) -> Self {
+        PlayerSlots {
+            slots: vec![None; count],
+        }
+    }
+
+    /// Return the gamepad in `slot`.
+    pub fn gamepad(&self, slot: usize) -> Option<Entity> {
+        self.slots.get(slot).copied().flatten()
+    }
+
+    /// Return the slot `gamepad` has claimed.
+    pub fn slot_of(&self, gamepad: Entity) -> Option<usize> {
+        self.slots.iter().position(|x| *x == Some(gamepad))
+    }
+
+    /// Iterate over the claimed slots and their gamepads.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, Entity)> + '_ {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, gamepad)| gamepad.map(|g| (slot, g)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Put `gamepad` in the first free slot, or `slot` if given and free.
+    /// Return none if the gamepad already has a slot or there is no room.
+    fn insert(&mut self, gamepad: Entity, slot: Option<usize>) -> Option<usize> {
+        if self.slot_of(gamepad).is_some() {
+            return None;
+        }
+        let slot = match slot {
+            Some(slot) => Some(slot).filter(|s| self.slots.get(*s) == Some(&None)),
+            None => self.slots.iter().position(Option::is_none),
+        }?;
+        self.slots[slot] = Some(gamepad);
+        Some(slot)
+    }
+
+    fn remove(&mut self, slot: usize) -> Option<Entity> {
+        self.slots.get_mut(slot).and_then(Option::take)
+    }
+}
+
+/// Claim and release player slots
+#[derive(SystemParam)]
+pub struct Players<'w> {
+    slots: ResMut<'w, PlayerSlots>,
+    joined: EventWriter<'w, PlayerJoined>,
+    left: EventWriter<'w, PlayerLeft>,
+}
+
+impl Players<'_> {
+    pub fn slots(&self) -> &PlayerSlots {
+        &self.slots
+    }
+
+    /// Give `gamepad` the first free slot and return it. If the gamepad
+    /// already has a slot, return that instead. Return none if every slot is
+    /// taken.
+    pub fn claim(&mut self, gamepad: Entity) -> Option<usize> {
+        if let Some(slot) = self.slots.slot_of(gamepad) {
+            return Some(slot);
+        }
+        let slot = self.slots.insert(gamepad, None)?;
+        self.joined.send(PlayerJoined { slot, gamepad });
+        Some(slot)
+    }
+
+    /// Give `gamepad` the free `slot`. Return false if the slot is taken or
+    /// the gamepad already has another.
+    pub fn claim_slot(&mut self, slot: usize, gamepad: Entity) -> bool {
+        if self.slots.gamepad(slot) == Some(gamepad) {
+            return true;
+        }
+        if self.slots.insert(gamepad, Some(slot)).is_none() {
+            return false;
+        }
+        self.joined.send(PlayerJoined { slot, gamepad });
+        true
+    }
+
+    /// Free `slot` and return the gamepad that held it.
+    pub fn release(&mut self, slot: usize) -> Option<Entity> {
+        let gamepad = self.slots.remove(slot)?;
+        self.left.send(PlayerLeft { slot, gamepad });
+        Some(gamepad)
+    }
+
+    /// Free every slot.
+    pub fn release_all(&mut self) {
+        for slot in 0..self.slots.len() {
+            self.release(slot);
+        }
+    }
+}
+
+fn release_disconnected(mut reader: EventReader<GamepadConnectionEvent>, mut players: Players) {
+    for event in reader.read() {
+        if matches!(event.connection, GamepadConnection::Disconnected) {
+            if let Some(slot) = players.slots.slot_of(event.gamepad) {
+                players.release(slot);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn claim_and_release() {
+        let a = Entity::from_raw(1);
+        let b = Entity::from_raw(2);
+        let mut slots = PlayerSlots::new(2);
+        assert_eq!(slots.insert(a, Some(1)), Some(1));
+        assert_eq!(slots.insert(a, None), None);
+        assert_eq!(slots.insert(b, Some(1)), None);
+        assert_eq!(slots.insert(b, None), Some(0));
+        assert_eq!(slots.insert(Entity::from_raw(3), None), None);
+        assert_eq!(slots.remove(1), Some(a));
+        assert_eq!(slots.slot_of(a), None);
+        assert_eq!(slots.iter().collect::<Vec<_>>(), vec![(0, b)]);
+    }
+}
@@ -3,22 +3,54 @@ use bevy::{
     image::ImageSampler,
     prelude::*,
     reflect::Reflect,
+    render::{camera::ClearColorConfig, view::RenderLayers},
     render::{
-        camera::{ScalingMode, Viewport},
+        camera::{RenderTarget, ScalingMode, Viewport},
         render_asset::RenderAssetUsages,
-        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
     },
+    time::TimeUpdateStrategy,
     utils::Duration,
-    window::{PresentMode, PrimaryWindow, WindowMode, WindowResized},
+    window::{PresentMode, PrimaryWindow, WindowResized},
 };
 
 use crate::{
     config::*,
     error::RunState,
     pico8::{self, FillPat, Pico8Asset, Pico8Handle},
-    PColor,
+    settings::Settings,
+    ui::{CanvasAnchor, CanvasViewport},
+    PColor, Subsystems,
 };
 
+/// The render layer of the letterbox image
+pub const LETTERBOX_LAYER: usize = 31;
+
+/// What is drawn outside the canvas
+#[derive(Resource, Debug, Clone, Default, Reflect)]
+#[reflect(Resource)]
+pub struct Letterbox {
+    /// The clear color if none
+    pub color: Option<Color>,
+    /// Stretched over the window behind the canvas
+    pub image: Option<String>,
+}
+
+impl Letterbox {
+    fn from_screen(screen: &Screen) -> Self {
+        Letterbox {
+            color: screen
+                .letterbox_color
+                .map(|[r, g, b]| Color::srgb_u8(r, g, b)),
+            image: screen.letterbox_image.clone(),
+        }
+    }
+}
+
+/// Marks the letterbox image.
+#[derive(Component)]
+pub struct LetterboxSprite;
+
 #[derive(Component)]
 pub struct Nano9Sprite;
 
@@ -29,6 +61,10 @@ pub struct DrawState {
     pub camera_position_delta: Option<Vec2>,
     pub print_cursor: Vec2,
     pub fill_pat: Option<FillPat>,
+    /// The priority of what is drawn next
+    pub priority: i8,
+    /// The tag of what is drawn next
+    pub tag: Option<String>,
 }
 
 impl DrawState {
@@ -60,6 +96,8 @@ impl DrawState {
 pub struct N9Canvas {
     pub size: UVec2,
     pub handle: Handle<Image>,
+    /// Draw text into the canvas image instead of spawning entities.
+    pub framebuffer: bool,
 }
 
 impl Default for DrawState {
@@ -71,6 +109,8 @@ impl Default for DrawState {
             print_cursor: Vec2::ZERO,
             camera_position_delta: None,
             fill_pat: None,
+            priority: 0,
+            tag: None,
         }
     }
 }
@@ -126,9 +166,172 @@ pub fn setup_canvas(mut canvas: Option<ResMut<N9Canvas>>, mut assets: ResMut<Ass
 #[derive(Component, Debug, Reflect)]
 pub struct Nano9Camera;
 
-fn spawn_camera(mut commands: Commands, canvas: Option<Res<N9Canvas>>) {
+/// Where the [Nano9Camera] renders the canvas.
+///
+/// Insert or change this resource to render the canvas into an image that the
+/// host app can use on meshes or UI nodes, e.g., an in-world arcade cabinet.
+#[derive(Resource, Debug, Clone, Default)]
+pub enum CanvasTarget {
+    /// Render into the primary window, letterboxed.
+    #[default]
+    Window,
+    /// Render into an image. It should have the `RENDER_ATTACHMENT` usage.
+    Image(Handle<Image>),
+}
+
+impl CanvasTarget {
+    /// Create an image target with one pixel per canvas pixel.
+    pub fn new_image(images: &mut Assets<Image>, canvas_size: UVec2) -> Self {
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: canvas_size.x,
+                height: canvas_size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0u8, 0u8, 0u8, 0u8],
+            TextureFormat::Bgra8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_DST
+            | TextureUsages::RENDER_ATTACHMENT;
+        image.sampler = ImageSampler::nearest();
+        CanvasTarget::Image(images.add(image))
+    }
+
+    pub fn image(&self) -> Option<&Handle<Image>> {
+        match self {
+            CanvasTarget::Window => None,
+            CanvasTarget::Image(handle) => Some(handle),
+        }
+    }
+}
+
+fn apply_canvas_target(
+    target: Res<CanvasTarget>,
+    canvas: Res<N9Canvas>,
+    anchor: Res<CanvasAnchor>,
+    primary_windows: Query<&Window, With<PrimaryWindow>>,
+    camera: Single<(&mut OrthographicProjection, &mut Camera), With<Nano9Camera>>,
+    mut canvas_viewport: ResMut<CanvasViewport>,
+    settings: Option<Res<Settings>>,
+) {
+    let (mut projection, mut camera) = camera.into_inner();
+    match &*target {
+        CanvasTarget::Window => {
+            camera.target = RenderTarget::default();
+            if let Ok(window) = primary_windows.get_single() {
+                *canvas_viewport = fit_canvas_to_window(
+                    window,
+                    canvas.size,
+                    *anchor,
+                    integer_scaling(settings),
+                    &mut projection,
+                    &mut camera,
+                );
+            }
+        }
+        CanvasTarget::Image(handle) => {
+            camera.target = RenderTarget::Image(handle.clone());
+            camera.viewport = None;
+            projection.scaling_mode = ScalingMode::WindowSize;
+            projection.scale = 1.0;
+            *canvas_viewport = CanvasViewport::default();
+        }
+    }
+}
+
+/// Resize the canvas image and recenter the camera's dolly on it when the
+/// [N9Canvas] size changes, e.g., by `vid()`.
+fn resize_canvas(
+    canvas: Res<N9Canvas>,
+    target: Res<CanvasTarget>,
+    anchor: Res<CanvasAnchor>,
+    mut images: ResMut<Assets<Image>>,
+    primary_windows: Query<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Parent, &mut OrthographicProjection, &mut Camera), With<Nano9Camera>>,
+    mut transforms: Query<&mut Transform>,
+    mut canvas_viewport: ResMut<CanvasViewport>,
+    settings: Option<Res<Settings>>,
+) {
+    let Some(image) = images.get_mut(&canvas.handle) else {
+        return;
+    };
+    if image.size() == canvas.size {
+        return;
+    }
+    info!("Resize canvas to {}", canvas.size);
+    image.resize(Extent3d {
+        width: canvas.size.x,
+        height: canvas.size.y,
+        depth_or_array_layers: 1,
+    });
+    // Resizing keeps the bytes but not the rows, so start blank.
+    image.data.fill(0);
+    let (dolly, mut projection, mut camera) = camera.into_inner();
+    if let Ok(mut transform) = transforms.get_mut(dolly.get()) {
+        transform.translation.x = canvas.size.x as f32 / 2.0;
+        transform.translation.y = -(canvas.size.y as f32) / 2.0;
+    }
+    if target.image().is_none() {
+        if let Ok(window) = primary_windows.get_single() {
+            *canvas_viewport = fit_canvas_to_window(
+                window,
+                canvas.size,
+                *anchor,
+                integer_scaling(settings),
+                &mut projection,
+                &mut camera,
+            );
+        }
+    }
+}
+
+fn spawn_camera(
+    mut commands: Commands,
+    canvas: Option<Res<N9Canvas>>,
+    target: Option<Res<CanvasTarget>>,
+    letterbox: Option<Res<Letterbox>>,
+    asset_server: Res<AssetServer>,
+) {
     let mut projection = OrthographicProjection::default_2d();
     projection.scaling_mode = ScalingMode::WindowSize;
+    let image_target = target.as_ref().and_then(|t| t.image());
+    let letterbox = letterbox.filter(|_| image_target.is_none());
+    let mut clear_color = letterbox
+        .as_ref()
+        .and_then(|letterbox| letterbox.color)
+        .map(ClearColorConfig::Custom)
+        .unwrap_or_default();
+    if let Some(image) = letterbox.as_ref().and_then(|l| l.image.as_ref()) {
+        // Draw the image with a camera behind the canvas's. It clears the
+        // window, so the canvas's camera must not.
+        commands.spawn((
+            Name::new("letterbox camera"),
+            Camera2d,
+            Camera {
+                order: -1,
+                clear_color,
+                ..default()
+            },
+            RenderLayers::layer(LETTERBOX_LAYER),
+        ));
+        commands.spawn((
+            Name::new("letterbox"),
+            Sprite::from_image(asset_server.load(image)),
+            RenderLayers::layer(LETTERBOX_LAYER),
+            LetterboxSprite,
+        ));
+        clear_color = ClearColorConfig::None;
+    }
+    let camera = Camera {
+        target: image_target
+            .map(|handle| RenderTarget::Image(handle.clone()))
+            .unwrap_or_default(),
+        clear_color,
+        ..default()
+    };
     let handle = canvas.as_ref().map(|c| c.handle.clone());
     let canvas_size: UVec2 = canvas.map(|c| c.size).unwrap_or_default();
     commands
@@ -145,6 +348,7 @@ fn spawn_camera(mut commands: Commands, canvas: Option<Res<N9Canvas>>) {
             let mut camera_commands = parent.spawn((
                 Name::new("camera"),
                 Camera2d,
+                camera,
                 Msaa::Off,
                 projection,
                 IsDefaultUiCamera,
@@ -164,100 +368,146 @@ fn spawn_camera(mut commands: Commands, canvas: Option<Res<N9Canvas>>) {
         });
 }
 
-pub fn fullscreen_key(
-    input: Res<ButtonInput<KeyCode>>,
-    mut primary_windows: Query<&mut Window, With<PrimaryWindow>>,
-) {
+pub fn fullscreen_key(input: Res<ButtonInput<KeyCode>>, mut settings: ResMut<Settings>) {
     if input.just_pressed(KeyCode::Enter)
         && input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight])
     {
-        use WindowMode::*;
-        let mut primary_window = primary_windows.single_mut();
-        primary_window.mode = match primary_window.mode {
-            Windowed => Fullscreen(MonitorSelection::Current),
-            _ => Windowed,
-        }
+        // Toggle the setting so it is remembered; applying it sets the window
+        // mode.
+        settings.fullscreen = !settings.fullscreen;
     }
 }
 
 pub fn sync_window_size(
     mut resize_event: EventReader<WindowResized>,
     canvas: Res<N9Canvas>,
-    // mut query: Query<&mut Sprite, With<Nano9Sprite>>,
+    target: Res<CanvasTarget>,
+    anchor: Res<CanvasAnchor>,
     primary_windows: Query<&Window, With<PrimaryWindow>>,
     orthographic_camera: Single<(&mut OrthographicProjection, &mut Camera), With<Nano9Camera>>,
+    mut canvas_viewport: ResMut<CanvasViewport>,
+    settings: Option<Res<Settings>>,
 ) {
     if let Some(e) = resize_event
         .read()
         .filter(|e| primary_windows.get(e.window).is_ok())
         .last()
     {
+        if target.image().is_some() {
+            // The window size does not matter when rendering to an image.
+            return;
+        }
         let primary_window = primary_windows.get(e.window).unwrap();
+        let (mut orthographic, mut camera) = orthographic_camera.into_inner();
+        *canvas_viewport = fit_canvas_to_window(
+            primary_window,
+            canvas.size,
+            *anchor,
+            integer_scaling(settings),
+            &mut orthographic,
+            &mut camera,
+        );
+    }
+}
 
-        //let window_size = primary_window.physical_size().as_vec2();
-        let window_scale = primary_window.scale_factor();
-        let window_size = Vec2::new(
-            primary_window.physical_width() as f32,
-            primary_window.physical_height() as f32,
-        ) / window_scale;
-        // let mut orthographic = orthographic.single_mut();
-
-        let canvas_size = canvas.size.as_vec2();
-        // let canvas_aspect = canvas_size.x / canvas_size.y;
-        // let window_aspect = window_size.x / window_size.y;
-
-        // `new_scale` is the number of physical pixels per logical pixels.
-        let new_scale =
-                // Canvas is longer than it is tall. Fit the width first.
-                (window_size.y / canvas_size.y).min(window_size.x / canvas_size.x);
-        // info!("window_size {window_size}");
+/// Stretch the letterbox image over the window.
+fn resize_letterbox(
+    mut resize_event: EventReader<WindowResized>,
+    primary_windows: Query<&Window, With<PrimaryWindow>>,
+    mut letterbox_sprites: Query<&mut Sprite, With<LetterboxSprite>>,
+) {
+    if let Some(primary_window) = resize_event
+        .read()
+        .filter_map(|e| primary_windows.get(e.window).ok())
+        .last()
+    {
+        for mut sprite in &mut letterbox_sprites {
+            sprite.custom_size = Some(primary_window.size());
+        }
+    }
+}
 
-        let (mut orthographic, mut camera) = orthographic_camera.into_inner();
-        // match *orthographic.into_inner() {
-        //     Projection::Orthographic(ref mut orthographic) => {
+fn integer_scaling(settings: Option<Res<Settings>>) -> bool {
+    settings.is_some_and(|settings| settings.integer_scaling)
+}
 
-        info!(
-            "oldscale {} new_scale {new_scale} window_scale {window_scale}",
-            &orthographic.scale
-        );
-        orthographic.scale = 1.0 / new_scale;
-        let viewport_size = canvas_size * new_scale * window_scale;
-        let start = (window_size * window_scale - viewport_size) / 2.0;
-        info!("viewport size {} start {}", &viewport_size, &start);
-        camera.viewport = Some(Viewport {
-            physical_position: UVec2::new(start.x as u32, start.y as u32),
-            physical_size: UVec2::new(viewport_size.x as u32, viewport_size.y as u32),
-            ..default()
-        });
+/// Scale and letterbox the camera so the canvas fits within the window. With
+/// `integer_scaling`, a canvas that fits at least once is only scaled by whole
+/// numbers.
+fn fit_canvas_to_window(
+    primary_window: &Window,
+    canvas_size: UVec2,
+    anchor: CanvasAnchor,
+    integer_scaling: bool,
+    orthographic: &mut OrthographicProjection,
+    camera: &mut Camera,
+) -> CanvasViewport {
+    let window_scale = primary_window.scale_factor();
+    let window_size = Vec2::new(
+        primary_window.physical_width() as f32,
+        primary_window.physical_height() as f32,
+    ) / window_scale;
+
+    let canvas_size = canvas_size.as_vec2();
+
+    if anchor == CanvasAnchor::Stretch {
+        orthographic.scaling_mode = ScalingMode::Fixed {
+            width: canvas_size.x,
+            height: canvas_size.y,
+        };
+        orthographic.scale = 1.0;
+        camera.viewport = None;
+        return CanvasViewport {
+            position: Vec2::ZERO,
+            scale: window_size / canvas_size,
+        };
+    }
+    orthographic.scaling_mode = ScalingMode::WindowSize;
+
+    // `new_scale` is the number of physical pixels per logical pixels.
+    let mut new_scale =
+            // Canvas is longer than it is tall. Fit the width first.
+            (window_size.y / canvas_size.y).min(window_size.x / canvas_size.x);
+    if integer_scaling && new_scale >= 1.0 {
+        new_scale = new_scale.floor();
+    }
 
-        // }
-        //     _ => { panic!("Not expecting a perspective"); }
-
-        // }
-        // settings.pixel_scale = new_scale;
-        // orthographic.scaling_mode = ScalingMode::WindowSize;
-        // }
-        // transform.scale = Vec3::splat(new_scale);
-
-        // let scale = if settings.canvas_size.x > settings.canvas_size.y
-        // {
-        //     // horizontal is longer
-        //     settings.resolution.1 as f32
-        //         / settings.canvas_size.y as f32
-        // } else {
-        //     // vertical is longer
-        //     settings.resolution.0 as f32
-        //         / settings.canvas_size.x as f32
-        // };
-
-        //     sprite.custom_size = Some(Vec2::new(
-        //         (settings.canvas_size.x as f32) * scale,
-        //         (settings.canvas_size.y as f32) * scale,
-        //     ));
+    info!(
+        "oldscale {} new_scale {new_scale} window_scale {window_scale}",
+        &orthographic.scale
+    );
+    orthographic.scale = 1.0 / new_scale;
+    let viewport_size = canvas_size * new_scale * window_scale;
+    let start = match anchor {
+        CanvasAnchor::TopLeft => Vec2::ZERO,
+        _ => (window_size * window_scale - viewport_size) / 2.0,
+    };
+    info!("viewport size {} start {}", &viewport_size, &start);
+    camera.viewport = Some(Viewport {
+        physical_position: UVec2::new(start.x as u32, start.y as u32),
+        physical_size: UVec2::new(viewport_size.x as u32, viewport_size.y as u32),
+        ..default()
+    });
+    CanvasViewport {
+        position: start / window_scale,
+        scale: Vec2::splat(new_scale),
     }
 }
 
-const DEFAULT_FRAMES_PER_SECOND: u8 = 60;
+pub(crate) const DEFAULT_FRAMES_PER_SECOND: u8 = 60;
+
+/// Redraw at least every 16 ms whether focused or not.
+pub(crate) fn winit_settings() -> bevy::winit::WinitSettings {
+    bevy::winit::WinitSettings {
+        // focused_mode: bevy::winit::UpdateMode::Continuous,
+        focused_mode: bevy::winit::UpdateMode::reactive(Duration::from_millis(16)),
+        unfocused_mode: bevy::winit::UpdateMode::reactive_low_power(Duration::from_millis(
+            // We could run it slower here, but that feels bad actually.
+            // 16 * 4,
+            16,
+        )),
+    }
+}
 
 #[derive(Default)]
 pub struct Nano9Plugin {
@@ -276,6 +526,13 @@ impl Nano9Plugin {
             primary_window: Some(Window {
                 resolution: screen_size.as_vec2().into(),
                 title: self.config.name.as_deref().unwrap_or("Nano-9").into(),
+                // Track the size of the browser element.
+                #[cfg(feature = "web")]
+                canvas: Some("#nano9".into()),
+                #[cfg(feature = "web")]
+                fit_canvas_to_parent: true,
+                #[cfg(feature = "web")]
+                prevent_default_event_handling: false,
                 // Turn off vsync to maximize CPU/GPU usage
                 present_mode: PresentMode::AutoVsync,
                 // Let's not allow resizing.
@@ -294,14 +551,10 @@ impl Nano9Plugin {
 
 impl Plugin for Nano9Plugin {
     fn build(&self, app: &mut App) {
-        app.register_type::<DrawState>();
+        app.register_type::<DrawState>()
+            .register_type::<Letterbox>();
         // How do you enable shared context since it eats the plugin?
-        let canvas_size: UVec2 = self
-            .config
-            .screen
-            .as_ref()
-            .map(|s| s.canvas_size)
-            .unwrap_or(DEFAULT_CANVAS_SIZE);
+        let canvas_size: UVec2 = self.config.canvas_size();
 
         {
             // Make our config readable by the Bevy AssetServer.
@@ -327,46 +580,81 @@ impl Plugin for Nano9Plugin {
             }
         }
 
-        // let resolution = settings.canvas_size.as_vec2() * settings.pixel_scale;
-        app.insert_resource(bevy::winit::WinitSettings {
-            // focused_mode: bevy::winit::UpdateMode::Continuous,
-            focused_mode: bevy::winit::UpdateMode::reactive(Duration::from_millis(16)),
-            unfocused_mode: bevy::winit::UpdateMode::reactive_low_power(Duration::from_millis(
-                // We could run it slower here, but that feels bad actually.
-                // 16 * 4,
-                16,
-            )),
-        })
-        .insert_resource(
-            self.config
-                .defaults
-                .as_ref()
-                .map(pico8::Defaults::from_config)
-                .unwrap_or_default(),
-        )
-        // Insert the config as a resource.
-        // TODO: Should we constrain it, if it wasn't provided as an option?
-        .insert_resource(Time::<Fixed>::from_seconds(
+        let timestep = Duration::from_secs_f64(
             1.0 / self
                 .config
                 .frames_per_second
                 .unwrap_or(DEFAULT_FRAMES_PER_SECOND) as f64,
-        ))
-        .insert_resource(N9Canvas {
-            size: canvas_size,
-            ..default()
-        })
-        .add_plugins(crate::plugin)
-        .add_systems(PreStartup, (setup_canvas, spawn_camera).chain());
+        );
+        let determinism = pico8::Determinism::from_config(&self.config);
+        if determinism.enabled {
+            // Advance time by exactly one tick each update.
+            app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+        }
+        // let resolution = settings.canvas_size.as_vec2() * settings.pixel_scale;
+        app.insert_resource(winit_settings())
+            .insert_resource(
+                self.config
+                    .defaults
+                    .as_ref()
+                    .map(pico8::Defaults::from_config)
+                    .unwrap_or_default(),
+            )
+            // Insert the config as a resource.
+            // TODO: Should we constrain it, if it wasn't provided as an option?
+            .insert_resource(Time::<Fixed>::from_duration(timestep))
+            .insert_resource(N9Canvas {
+                size: canvas_size,
+                framebuffer: self.config.framebuffer.unwrap_or(false),
+                ..default()
+            })
+            .insert_resource(self.config.on_error.unwrap_or_default())
+            .insert_resource(
+                self.config
+                    .screen
+                    .as_ref()
+                    .and_then(|s| s.anchor)
+                    .unwrap_or_default(),
+            )
+            .insert_resource(
+                self.config
+                    .screen
+                    .as_ref()
+                    .map(Letterbox::from_screen)
+                    .unwrap_or_default(),
+            )
+            .insert_resource(determinism)
+            .insert_resource(self.config.clone())
+            .add_plugins(crate::plugin)
+            .add_systems(PreStartup, setup_canvas);
 
         // bevy_ecs_ldtk will add this plugin, so let's not add that if it's
         // present.
-        #[cfg(not(feature = "level"))]
+        #[cfg(all(feature = "tilemap", not(feature = "level")))]
         app.add_plugins(bevy_ecs_tilemap::TilemapPlugin);
 
+        app.init_resource::<CanvasTarget>();
+        let subsystems = app
+            .world()
+            .get_resource::<Subsystems>()
+            .copied()
+            .unwrap_or_default();
+        if subsystems.camera {
+            app.add_systems(PreStartup, spawn_camera.after(setup_canvas))
+                .add_systems(
+                    Update,
+                    (
+                        apply_canvas_target.run_if(resource_changed::<CanvasTarget>),
+                        resize_canvas.run_if(resource_changed::<N9Canvas>),
+                    ),
+                );
+        }
+
         if app.is_plugin_added::<WindowPlugin>() {
-            app.add_systems(Update, sync_window_size)
-                .add_systems(Update, fullscreen_key);
+            app.add_systems(Update, fullscreen_key);
+            if subsystems.camera {
+                app.add_systems(Update, (sync_window_size, resize_letterbox));
+            }
         }
     }
 }
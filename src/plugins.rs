@@ -6,14 +6,84 @@ use bevy::{
     app::{PluginGroup, PluginGroupBuilder},
     audio::AudioPlugin,
     prelude::*,
+    window::ExitCondition,
+    winit::WinitPlugin,
 };
+
+/// Subsystems that may be left out at runtime, e.g., for servers, tests, and
+/// tools
+///
+/// There is no scripting subsystem to leave out; carts are Rust systems. Nor
+/// is there a minibuffer one: the "minibuffer" feature's acts are only bound
+/// if the app adds them, so an app leaves them out by not adding them.
+#[derive(Debug, Clone, Copy, Resource, Reflect)]
+pub struct Subsystems {
+    /// Play audio. Without it sfx and music are loaded but not heard.
+    pub audio: bool,
+    /// Open a window. Without it the app runs until it exits itself.
+    pub window: bool,
+    /// Load levels with the "level" feature.
+    pub level: bool,
+    /// Spawn the [Nano9Camera][crate::Nano9Camera] and keep it fitted to the
+    /// canvas. Without a window it can still render to a
+    /// [CanvasTarget::Image][crate::CanvasTarget::Image]; turn it off too when
+    /// nothing is drawn.
+    pub camera: bool,
+}
+
+impl Default for Subsystems {
+    fn default() -> Self {
+        Subsystems {
+            audio: true,
+            window: true,
+            level: true,
+            camera: true,
+        }
+    }
+}
+
 /// Nano-9 plugins
+///
+/// ```ignore
+/// app.add_plugins(Nano9Plugins::new(config).with_audio(false));
+/// ```
 pub struct Nano9Plugins {
     pub config: Config,
+    pub subsystems: Subsystems,
+}
+
+impl Nano9Plugins {
+    pub fn new(config: Config) -> Self {
+        Nano9Plugins {
+            config,
+            subsystems: Subsystems::default(),
+        }
+    }
+
+    pub fn with_audio(mut self, audio: bool) -> Self {
+        self.subsystems.audio = audio;
+        self
+    }
+
+    pub fn with_window(mut self, window: bool) -> Self {
+        self.subsystems.window = window;
+        self
+    }
+
+    pub fn with_level(mut self, level: bool) -> Self {
+        self.subsystems.level = level;
+        self
+    }
+
+    pub fn with_camera(mut self, camera: bool) -> Self {
+        self.subsystems.camera = camera;
+        self
+    }
 }
 
 impl PluginGroup for Nano9Plugins {
     fn build(self) -> PluginGroupBuilder {
+        let subsystems = self.subsystems;
         let group = PluginGroupBuilder::start::<Self>();
         #[cfg(feature = "web-asset")]
         let group = group.add(bevy_web_asset::WebAssetPlugin);
@@ -21,19 +91,36 @@ impl PluginGroup for Nano9Plugins {
         let nano9_plugin = Nano9Plugin {
             config: self.config,
         };
-        let group = group.add_group(
-            DefaultPlugins
-                // .set(AssetPlugin {
-                //     mode: AssetMode::Processed,
-                //     ..default()
-                // })
-                .set(AudioPlugin {
-                    global_volume: GlobalVolume::new(0.4),
-                    ..default()
+        let mut default_plugins = DefaultPlugins
+            // .set(AssetPlugin {
+            //     mode: AssetMode::Processed,
+            //     ..default()
+            // })
+            .set(AudioPlugin {
+                global_volume: GlobalVolume::new(0.4),
+                ..default()
+            });
+        default_plugins = if subsystems.window {
+            default_plugins.set(nano9_plugin.window_plugin())
+        } else {
+            default_plugins
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: ExitCondition::DontExit,
+                    close_when_requested: false,
                 })
-                .set(nano9_plugin.window_plugin()),
-        );
+                .disable::<WinitPlugin>()
+        };
+        if !subsystems.audio {
+            default_plugins = default_plugins.disable::<AudioPlugin>();
+        }
+        let group = group.add_group(default_plugins);
 
-        group.add(nano9_plugin)
+        // Nano9Plugin reads the subsystems, so insert them first.
+        group
+            .add(move |app: &mut App| {
+                app.insert_resource(subsystems);
+            })
+            .add(nano9_plugin)
     }
 }
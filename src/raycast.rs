@@ -4,7 +4,7 @@ use bevy::{
     prelude::*,
 };
 
-use crate::pico8::negate_y;
+use crate::{pico8::negate_y, Nano9Camera};
 
 pub struct RaycastPlugin;
 
@@ -130,3 +130,126 @@ impl Rays<'_, '_> {
         None
     }
 }
+
+/// An entity found by [Picker::pick]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pick {
+    pub entity: Entity,
+    pub name: Option<String>,
+    /// Index into the sprite sheet, if it is drawn from one
+    pub index: Option<usize>,
+}
+
+/// Find drawn sprites by canvas position.
+#[derive(SystemParam)]
+pub struct Picker<'w, 's> {
+    sprites: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Sprite,
+            &'static GlobalTransform,
+            &'static InheritedVisibility,
+            Option<&'static Name>,
+        ),
+    >,
+    camera: Query<'w, 's, &'static Transform, With<Nano9Camera>>,
+    images: Res<'w, Assets<Image>>,
+    layouts: Res<'w, Assets<TextureAtlasLayout>>,
+}
+
+impl Picker<'_, '_> {
+    fn size(&self, sprite: &Sprite) -> Option<Vec2> {
+        if let Some(size) = sprite.custom_size {
+            return Some(size);
+        }
+        if let Some(rect) = sprite.rect {
+            return Some(rect.size());
+        }
+        if let Some(atlas) = &sprite.texture_atlas {
+            let layout = self.layouts.get(&atlas.layout)?;
+            return layout
+                .textures
+                .get(atlas.index)
+                .map(|rect| rect.size().as_vec2());
+        }
+        self.images.get(&sprite.image).map(|image| image.size_f32())
+    }
+
+    /// Return the topmost visible sprite at the canvas position `pos`.
+    pub fn pick(&self, pos: Vec2) -> Option<Pick> {
+        let camera = self
+            .camera
+            .get_single()
+            .map(|transform| transform.translation.xy())
+            .unwrap_or_default();
+        let world = Vec2::new(pos.x, negate_y(pos.y)) + camera;
+        self.sprites
+            .iter()
+            .filter(|(_, _, _, visibility, _)| visibility.get())
+            .filter(|(_, sprite, transform, _, _)| {
+                let Some(size) = self.size(sprite) else {
+                    return false;
+                };
+                let local = transform
+                    .affine()
+                    .inverse()
+                    .transform_point3(world.extend(0.0))
+                    .xy();
+                let anchor = sprite.anchor.as_vec();
+                let min = (Vec2::splat(-0.5) - anchor) * size;
+                let max = (Vec2::splat(0.5) - anchor) * size;
+                min.x <= local.x && local.x < max.x && min.y <= local.y && local.y < max.y
+            })
+            .max_by(|(_, _, a, _, _), (_, _, b, _, _)| {
+                a.translation()
+                    .z
+                    .partial_cmp(&b.translation().z)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map(|(entity, sprite, _, _, name)| Pick {
+                entity,
+                name: name.map(|name| name.to_string()),
+                index: sprite.texture_atlas.as_ref().map(|atlas| atlas.index),
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn pick_topmost() {
+        let mut world = World::new();
+        world.init_resource::<Assets<Image>>();
+        world.init_resource::<Assets<TextureAtlasLayout>>();
+        let mut spawn = |name: &str, x: f32, z: f32| {
+            world
+                .spawn((
+                    Sprite {
+                        custom_size: Some(Vec2::splat(8.0)),
+                        anchor: bevy::sprite::Anchor::TopLeft,
+                        ..default()
+                    },
+                    GlobalTransform::from_xyz(x, negate_y(0.0), z),
+                    InheritedVisibility::VISIBLE,
+                    Name::new(name.to_string()),
+                ))
+                .id()
+        };
+        spawn("back", 0.0, 1.0);
+        let front = spawn("front", 4.0, 2.0);
+        let pick = |pos: Vec2| move |picker: Picker| picker.pick(pos);
+        let hit = world.run_system_once(pick(Vec2::new(5.0, 2.0))).unwrap();
+        assert_eq!(hit.map(|p| p.entity), Some(front));
+        let hit = world.run_system_once(pick(Vec2::new(2.0, 2.0))).unwrap();
+        assert_eq!(hit.and_then(|p| p.name).as_deref(), Some("back"));
+        assert!(world
+            .run_system_once(pick(Vec2::new(20.0, 2.0)))
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,74 @@
+//! Reset
+//!
+//! Restart the cart without restarting the app: the [Pico8State] is cleared,
+//! the audio stopped, the cart reloaded, and `_init` run again. If the
+//! [Pico8Handle] was swapped for another cart, that cart's config replaces
+//! the [Config] resource once it loads.
+//!
+//! ```ignore
+//! fn restart(mut events: EventWriter<ResetCart>) {
+//!     events.send(ResetCart);
+//! }
+//! ```
+use bevy::prelude::*;
+
+use crate::{
+    config::Config,
+    error::RunState,
+    pico8::{
+        audio::{AudioCommand, ReservedChannels, SfxDest},
+        set_tick_rate, ClearEvent, Pico8Handle, Pico8State,
+    },
+    N9Canvas,
+};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.add_event::<ResetCart>()
+        .add_systems(PreUpdate, request_reset.run_if(on_event::<ResetCart>))
+        .add_systems(OnEnter(RunState::Reset), reset_cart);
+}
+
+/// Send to reset the cart.
+#[derive(Debug, Clone, Copy, Default, Event)]
+pub struct ResetCart;
+
+fn request_reset(mut events: EventReader<ResetCart>, mut next_state: ResMut<NextState<RunState>>) {
+    events.clear();
+    next_state.set(RunState::Reset);
+}
+
+fn reset_cart(world: &mut World) {
+    info!("Reset cart");
+    let state = Pico8State::from_world(world);
+    let fps = state.fps;
+    world.insert_resource(state);
+    // Undo any `fps()` change.
+    set_tick_rate(world, fps);
+    AudioCommand::Stop(SfxDest::All, None).apply(world);
+    world.insert_resource(ReservedChannels(0));
+    world.send_event(ClearEvent::default());
+    // Undo any `vid()` screen mode.
+    if let Some(size) = world.get_resource::<Config>().map(Config::canvas_size) {
+        world.resource_mut::<N9Canvas>().size = size;
+    }
+    let Some(handle) = world
+        .get_resource::<Pico8Handle>()
+        .map(|pico8_handle| pico8_handle.handle.clone())
+    else {
+        world
+            .resource_mut::<NextState<RunState>>()
+            .set(RunState::Loaded);
+        return;
+    };
+    let asset_server = world.resource::<AssetServer>();
+    // Once loaded, `update_asset` goes to the Loaded state. A cart that is
+    // still loading, e.g., one just swapped in, needs no reload.
+    if asset_server.is_loaded_with_dependencies(&handle) {
+        match handle.path() {
+            Some(path) => asset_server.reload(path.clone()),
+            None => world
+                .resource_mut::<NextState<RunState>>()
+                .set(RunState::Loaded),
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! User settings
+//!
+//! The player's [Settings] are read from [Storage] at startup, applied, and
+//! saved again whenever they change, so an options menu built with
+//! `volume()`, `integer_scaling()`, `fullscreen()`, and `color_filter()`
+//! sticks between sessions. Rebound keys are kept here too, in place of the
+//! config's `[[keyboard]]` entries.
+use bevy::{
+    audio::Volume,
+    ecs::system::SystemParam,
+    prelude::*,
+    window::{MonitorSelection, PrimaryWindow, WindowMode},
+};
+use serde::{Deserialize, Serialize};
+use std::io;
+
+use crate::{
+    color_filter::ColorFilter, config::Keyboard, pico8::Pico8, storage::Storage, vars::VarStore,
+    CanvasTarget,
+};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<Settings>()
+        .init_resource::<Settings>()
+        .add_systems(Update, apply_settings.run_if(resource_changed::<Settings>));
+}
+
+/// The player's settings, shared by every cart
+#[derive(Debug, Clone, PartialEq, Resource, Reflect, Serialize, Deserialize)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct Settings {
+    /// Master volume from 0 to 1
+    pub volume: f32,
+    /// Scale the canvas by whole numbers only, letterboxing the rest.
+    pub integer_scaling: bool,
+    pub fullscreen: bool,
+    /// A color-blind filter over the screen, the config's if none
+    pub color_filter: Option<ColorFilter>,
+    /// Keys of each player, the config's if empty
+    #[serde(rename = "keyboard")]
+    pub keyboards: Vec<Keyboard>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            volume: 1.0,
+            integer_scaling: false,
+            fullscreen: false,
+            color_filter: None,
+            keyboards: Vec::new(),
+        }
+    }
+}
+
+impl FromWorld for Settings {
+    fn from_world(world: &mut World) -> Self {
+        world
+            .get_resource::<Storage>()
+            .map(|storage| match Settings::load(storage) {
+                Ok(settings) => settings.unwrap_or_default(),
+                Err(e) => {
+                    warn!("Could not load settings: {e}");
+                    Settings::default()
+                }
+            })
+            .unwrap_or_default()
+    }
+}
+
+const STORAGE_KEY: &str = "settings.toml";
+
+impl Settings {
+    /// Write the settings to `storage`.
+    pub fn save(&self, storage: &Storage) -> Result<(), io::Error> {
+        let contents = toml::to_string(self).map_err(io::Error::other)?;
+        storage.set(STORAGE_KEY, &contents)
+    }
+
+    /// Read the settings from `storage` or none if they were never saved.
+    pub fn load(storage: &Storage) -> Result<Option<Self>, io::Error> {
+        storage
+            .get(STORAGE_KEY)
+            .map(|contents| {
+                toml::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            })
+            .transpose()
+    }
+}
+
+/// What persists between sessions
+#[derive(SystemParam)]
+pub struct Persist<'w> {
+    pub(crate) storage: Res<'w, Storage>,
+    pub(crate) settings: ResMut<'w, Settings>,
+    pub(crate) vars: ResMut<'w, VarStore>,
+}
+
+fn apply_settings(
+    settings: Res<Settings>,
+    storage: Res<Storage>,
+    global_volume: Option<ResMut<GlobalVolume>>,
+    target: Option<ResMut<CanvasTarget>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    sinks: Query<&AudioSink>,
+    spatial_sinks: Query<&SpatialAudioSink>,
+) {
+    if let Some(mut global_volume) = global_volume {
+        let last = global_volume.volume.get();
+        let volume = settings.volume.clamp(0.0, 1.0);
+        global_volume.volume = Volume::new(volume);
+        // Sounds already playing took the global volume when they started.
+        let rescale = |sink: &dyn AudioSinkPlayback| {
+            sink.set_volume(if last > 0.0 {
+                sink.volume() * volume / last
+            } else {
+                volume
+            });
+        };
+        if last != volume {
+            for sink in &sinks {
+                rescale(sink);
+            }
+            for sink in &spatial_sinks {
+                rescale(sink);
+            }
+        }
+    }
+    if let Ok(mut window) = windows.get_single_mut() {
+        let fullscreen = !matches!(window.mode, WindowMode::Windowed);
+        if fullscreen != settings.fullscreen {
+            window.mode = if settings.fullscreen {
+                WindowMode::Fullscreen(MonitorSelection::Current)
+            } else {
+                WindowMode::Windowed
+            };
+        }
+    }
+    // Fit the canvas to the window again.
+    if let Some(mut target) = target {
+        target.set_changed();
+    }
+    if !settings.is_added() {
+        if let Err(e) = settings.save(&storage) {
+            warn!("Could not save settings: {e}");
+        }
+    }
+}
+
+impl Pico8<'_, '_> {
+    /// volume([level])
+    ///
+    /// Return the master volume from 0 to 1 and set it if given.
+    pub fn volume(&mut self, level: Option<f32>) -> f32 {
+        let last = self.persist.settings.volume;
+        if let Some(level) = level {
+            self.persist.settings.volume = level.clamp(0.0, 1.0);
+        }
+        last
+    }
+
+    /// integer_scaling([on])
+    ///
+    /// Return whether the canvas is only scaled by whole numbers and set it
+    /// if given.
+    pub fn integer_scaling(&mut self, on: Option<bool>) -> bool {
+        let last = self.persist.settings.integer_scaling;
+        if let Some(on) = on {
+            self.persist.settings.integer_scaling = on;
+        }
+        last
+    }
+
+    /// fullscreen([on])
+    ///
+    /// Return whether the window is fullscreen and set it if given.
+    pub fn fullscreen(&mut self, on: Option<bool>) -> bool {
+        let last = self.persist.settings.fullscreen;
+        if let Some(on) = on {
+            self.persist.settings.fullscreen = on;
+        }
+        last
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn save_and_load() {
+        let dir = std::env::temp_dir().join(format!("nano9-settings-{}", std::process::id()));
+        let storage = Storage::new(&dir);
+        assert_eq!(Settings::load(&storage).unwrap(), None);
+        let settings = Settings {
+            volume: 0.5,
+            integer_scaling: true,
+            keyboards: vec![Keyboard {
+                o: vec!["KeyZ".into()],
+                ..default()
+            }],
+            ..default()
+        };
+        settings.save(&storage).unwrap();
+        assert_eq!(Settings::load(&storage).unwrap(), Some(settings));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+//! Split-screen
+//!
+//! Divide the canvas into two to four views, each a region of the canvas with
+//! its own camera position, so local multiplayer carts can show each player
+//! their own part of the world.
+//!
+//! ```ignore
+//! fn init(mut split: ResMut<SplitScreen>) {
+//!     split.set_views(&[
+//!         URect::new(0, 0, 64, 128),
+//!         URect::new(64, 0, 128, 128),
+//!     ]).unwrap();
+//! }
+//!
+//! fn update(mut split: ResMut<SplitScreen>, players: Query<&Player>) {
+//!     for (i, player) in players.iter().enumerate() {
+//!         split.camera(i, Some(player.pos - Vec2::new(32.0, 64.0))).unwrap();
+//!     }
+//! }
+//! ```
+use bevy::{
+    prelude::*,
+    render::camera::{RenderTarget, Viewport},
+    window::PrimaryWindow,
+};
+
+use crate::{pico8::negate_y, pico8::Error, ui::CanvasViewport, N9Canvas, Nano9Camera};
+
+/// The most views a canvas may be split into
+pub const MAX_VIEWS: usize = 4;
+
+pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<SplitScreen>()
+        .init_resource::<SplitScreen>()
+        .add_systems(
+            PostUpdate,
+            sync_split_cameras
+                .run_if(resource_changed::<SplitScreen>.or(resource_changed::<CanvasViewport>))
+                .before(TransformSystem::TransformPropagate),
+        );
+}
+
+/// A region of the canvas and the camera position shown in it
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct SplitView {
+    /// Region of the canvas in pixels
+    pub region: URect,
+    /// Like `camera()`, the world position shown at the region's top left
+    pub camera: Vec2,
+}
+
+/// The views the canvas is split into
+///
+/// When there are no views, the canvas is drawn by the [Nano9Camera] alone.
+#[derive(Debug, Clone, Default, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct SplitScreen {
+    views: Vec<SplitView>,
+}
+
+/// Marks the camera rendering a [SplitView] by its index.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+pub struct SplitCamera(pub usize);
+
+impl SplitScreen {
+    /// Split the canvas into `regions`. An empty slice turns split-screen off.
+    pub fn set_views(&mut self, regions: &[URect]) -> Result<(), Error> {
+        if regions.len() == 1 || regions.len() > MAX_VIEWS {
+            return Err(Error::InvalidArgument(
+                format!("split-screen needs 2 to {MAX_VIEWS} views").into(),
+            ));
+        }
+        self.views = regions
+            .iter()
+            .map(|region| SplitView {
+                region: *region,
+                camera: Vec2::ZERO,
+            })
+            .collect();
+        Ok(())
+    }
+
+    pub fn views(&self) -> &[SplitView] {
+        &self.views
+    }
+
+    /// Get or set the camera position of view `index`, returning the last
+    /// position.
+    pub fn camera(&mut self, index: usize, pos: Option<Vec2>) -> Result<Vec2, Error> {
+        let view = self
+            .views
+            .get_mut(index)
+            .ok_or_else(|| Error::NoSuch(format!("split view {index}").into()))?;
+        let last = view.camera;
+        if let Some(pos) = pos {
+            view.camera = pos;
+        }
+        Ok(last)
+    }
+}
+
+fn sync_split_cameras(
+    split: Res<SplitScreen>,
+    canvas: Res<N9Canvas>,
+    canvas_viewport: Res<CanvasViewport>,
+    primary_windows: Query<&Window, With<PrimaryWindow>>,
+    main_camera: Single<(&Parent, &Camera, &OrthographicProjection), With<Nano9Camera>>,
+    mut split_cameras: Query<
+        (Entity, &SplitCamera, &mut Camera, &mut Transform),
+        Without<Nano9Camera>,
+    >,
+    mut commands: Commands,
+) {
+    let (dolly, main, projection) = main_camera.into_inner();
+    let scale_factor = primary_windows
+        .get_single()
+        .map(|window| window.scale_factor())
+        .unwrap_or(1.0);
+    // Rendering to an image has no letterboxing and one pixel per pixel.
+    let to_image = matches!(main.target, RenderTarget::Image(_));
+    let canvas_size = canvas.size.as_vec2();
+    let mut existing: Vec<Option<_>> = (0..MAX_VIEWS).map(|_| None).collect();
+    for (id, split_camera, camera, transform) in &mut split_cameras {
+        match existing.get_mut(split_camera.0) {
+            Some(slot @ None) if split_camera.0 < split.views.len() => {
+                *slot = Some((camera, transform));
+            }
+            _ => commands.entity(id).despawn_recursive(),
+        }
+    }
+    for (index, view) in split.views.iter().enumerate() {
+        let region = view.region.as_rect();
+        let viewport = if to_image {
+            Viewport {
+                physical_position: view.region.min,
+                physical_size: view.region.size(),
+                ..default()
+            }
+        } else {
+            let position = (canvas_viewport.canvas_to_window(region.min) * scale_factor).as_uvec2();
+            let size = (region.size() * canvas_viewport.scale * scale_factor).as_uvec2();
+            Viewport {
+                physical_position: position,
+                physical_size: size.max(UVec2::ONE),
+                ..default()
+            }
+        };
+        // The dolly is at the canvas's center.
+        let center = view.camera + (region.size() - canvas_size) / 2.0;
+        let translation = Vec3::new(center.x, negate_y(center.y), 0.0);
+        match existing[index].take() {
+            Some((mut camera, mut transform)) => {
+                camera.viewport = Some(viewport);
+                camera.target = main.target.clone();
+                transform.translation = translation;
+            }
+            None => {
+                commands.entity(dolly.get()).with_child((
+                    Name::new(format!("split camera {index}")),
+                    Camera2d,
+                    Camera {
+                        order: main.order + 1 + index as isize,
+                        target: main.target.clone(),
+                        viewport: Some(viewport),
+                        ..default()
+                    },
+                    Msaa::Off,
+                    projection.clone(),
+                    Transform::from_translation(translation),
+                    SplitCamera(index),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn set_views() {
+        let mut split = SplitScreen::default();
+        assert!(split.set_views(&[URect::new(0, 0, 128, 128)]).is_err());
+        split
+            .set_views(&[URect::new(0, 0, 64, 128), URect::new(64, 0, 128, 128)])
+            .unwrap();
+        assert_eq!(
+            split.camera(1, Some(Vec2::new(10.0, 0.0))).unwrap(),
+            Vec2::ZERO
+        );
+        assert_eq!(split.views()[1].camera.x, 10.0);
+        assert!(split.camera(2, None).is_err());
+        split.set_views(&[]).unwrap();
+        assert!(split.views().is_empty());
+    }
+}
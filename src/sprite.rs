@@ -0,0 +1,189 @@
+//! Retained sprites
+//!
+//! Sprites drawn with `spr()` are cleared on the next `cls()`. Retaining one
+//! keeps it around, and its properties may then be read and written through
+//! the [Sprites] system parameter rather than redrawing it every frame.
+use bevy::{ecs::system::SystemParam, prelude::*, sprite::Anchor};
+
+use crate::{
+    anim::Anim,
+    interpolate::Interpolate,
+    pico8::{negate_y, Clearable, Error},
+};
+
+/// The properties of a retained sprite
+///
+/// Positions are in canvas coordinates with y pointing down.
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct N9Sprite {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// Index into the sprite sheet, if it is drawn from one
+    pub index: Option<usize>,
+    pub color: Color,
+    pub vis: bool,
+    /// Anchor where (-0.5, 0.5) is the top left and (0.5, -0.5) is the bottom
+    /// right
+    pub anchor: Vec2,
+    pub parent: Option<Entity>,
+}
+
+/// Read and write retained sprites.
+#[derive(SystemParam)]
+pub struct Sprites<'w, 's> {
+    query: Query<
+        'w,
+        's,
+        (
+            &'static mut Transform,
+            &'static mut Sprite,
+            &'static mut Visibility,
+            Option<&'static Parent>,
+        ),
+    >,
+    anims: Query<'w, 's, &'static mut Anim>,
+    commands: Commands<'w, 's>,
+}
+
+impl Sprites<'_, '_> {
+    /// Keep the sprite `id` from being cleared.
+    pub fn retain(&mut self, id: Entity) -> Result<(), Error> {
+        if !self.query.contains(id) {
+            return Err(Error::NoSuch("sprite".into()));
+        }
+        self.commands
+            .entity(id)
+            .remove::<Clearable>()
+            .insert_if_new(Interpolate::default());
+        Ok(())
+    }
+
+    pub fn get(&self, id: Entity) -> Result<N9Sprite, Error> {
+        let (transform, sprite, visibility, parent) = self
+            .query
+            .get(id)
+            .map_err(|_| Error::NoSuch("sprite".into()))?;
+        Ok(N9Sprite {
+            x: transform.translation.x,
+            y: negate_y(transform.translation.y),
+            z: transform.translation.z,
+            flip_x: sprite.flip_x,
+            flip_y: sprite.flip_y,
+            index: sprite.texture_atlas.as_ref().map(|atlas| atlas.index),
+            color: sprite.color,
+            vis: *visibility != Visibility::Hidden,
+            anchor: sprite.anchor.as_vec(),
+            parent: parent.map(|parent| parent.get()),
+        })
+    }
+
+    /// Write every property of `props` to the sprite `id`.
+    pub fn set(&mut self, id: Entity, props: &N9Sprite) -> Result<(), Error> {
+        let (mut transform, mut sprite, mut visibility, parent) = self
+            .query
+            .get_mut(id)
+            .map_err(|_| Error::NoSuch("sprite".into()))?;
+        transform.translation = Vec3::new(props.x, negate_y(props.y), props.z);
+        sprite.flip_x = props.flip_x;
+        sprite.flip_y = props.flip_y;
+        if let (Some(atlas), Some(index)) = (sprite.texture_atlas.as_mut(), props.index) {
+            atlas.index = index;
+        }
+        sprite.color = props.color;
+        *visibility = if props.vis {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if sprite.anchor.as_vec() != props.anchor {
+            sprite.anchor = Anchor::Custom(props.anchor);
+        }
+        if parent.map(|parent| parent.get()) != props.parent {
+            match props.parent {
+                Some(parent) => {
+                    self.commands.entity(id).set_parent(parent);
+                }
+                None => {
+                    self.commands.entity(id).remove_parent();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Modify the sprite `id` with `f`.
+    ///
+    /// ```ignore
+    /// sprites.update(id, |s| s.x += 1.0)?;
+    /// ```
+    pub fn update(&mut self, id: Entity, f: impl FnOnce(&mut N9Sprite)) -> Result<(), Error> {
+        let mut props = self.get(id)?;
+        f(&mut props);
+        self.set(id, &props)
+    }
+
+    /// Attach `anim` to the sprite `id` replacing any animation it had.
+    pub fn animate(&mut self, id: Entity, anim: Anim) -> Result<(), Error> {
+        self.get(id)?;
+        self.commands.entity(id).insert(anim);
+        Ok(())
+    }
+
+    /// Play the animation of sprite `id` from where it stopped.
+    pub fn play(&mut self, id: Entity) -> Result<(), Error> {
+        self.anim_mut(id)?.playing = true;
+        Ok(())
+    }
+
+    /// Stop the animation of sprite `id` on its current frame.
+    pub fn stop(&mut self, id: Entity) -> Result<(), Error> {
+        self.anim_mut(id)?.playing = false;
+        Ok(())
+    }
+
+    fn anim_mut(&mut self, id: Entity) -> Result<Mut<'_, Anim>, Error> {
+        self.anims
+            .get_mut(id)
+            .map_err(|_| Error::NoSuch("sprite animation".into()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn update_sprite() {
+        let mut world = World::new();
+        let id = world
+            .spawn((
+                Sprite::default(),
+                Transform::from_xyz(1.0, negate_y(2.0), 3.0),
+                Visibility::default(),
+                Clearable::default(),
+            ))
+            .id();
+        world
+            .run_system_once(move |mut sprites: Sprites| {
+                sprites.retain(id).unwrap();
+                sprites
+                    .update(id, |s| {
+                        assert_eq!((s.x, s.y, s.z), (1.0, 2.0, 3.0));
+                        s.x += 1.0;
+                        s.flip_x = true;
+                        s.vis = false;
+                    })
+                    .unwrap();
+            })
+            .unwrap();
+        assert!(world.get::<Clearable>(id).is_none());
+        assert!(world.get::<Interpolate>(id).is_some());
+        assert_eq!(world.get::<Transform>(id).unwrap().translation.x, 2.0);
+        assert!(world.get::<Sprite>(id).unwrap().flip_x);
+        assert_eq!(world.get::<Visibility>(id), Some(&Visibility::Hidden));
+    }
+}
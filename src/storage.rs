@@ -0,0 +1,111 @@
+//! Persistent key-value storage
+//!
+//! On the desktop each key is a file in a directory. On the web, with the "web"
+//! feature, keys are kept in the browser's localStorage.
+use bevy::{asset::io::file::FileAssetReader, prelude::*};
+use std::{io, path::PathBuf};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.init_resource::<Storage>();
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct Storage {
+    /// Directory for desktop storage. Also used as a key prefix on the web.
+    pub dir: PathBuf,
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Storage {
+            dir: FileAssetReader::get_base_path().join("data"),
+        }
+    }
+}
+
+/// The longest name a cart may give its data
+pub const MAX_NAME_LEN: usize = 64;
+
+/// Return true if `name` may name a cart's data: 1 to 64 characters of a-z,
+/// 0-9, and underscore like Pico-8's cartdata ids. Since it is part of a key,
+/// this keeps a cart from reading or writing outside [Storage::dir].
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_')
+}
+
+impl Storage {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Storage { dir: dir.into() }
+    }
+
+    #[cfg(not(all(feature = "web", target_arch = "wasm32")))]
+    pub fn get(&self, key: &str) -> Option<String> {
+        std::fs::read_to_string(self.dir.join(key)).ok()
+    }
+
+    #[cfg(not(all(feature = "web", target_arch = "wasm32")))]
+    pub fn set(&self, key: &str, value: &str) -> Result<(), io::Error> {
+        let path = self.dir.join(key);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, value)
+    }
+
+    #[cfg(all(feature = "web", target_arch = "wasm32"))]
+    fn local_storage() -> Result<web_sys::Storage, io::Error> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| io::Error::other("no localStorage"))
+    }
+
+    #[cfg(all(feature = "web", target_arch = "wasm32"))]
+    fn web_key(&self, key: &str) -> String {
+        self.dir.join(key).to_string_lossy().into_owned()
+    }
+
+    #[cfg(all(feature = "web", target_arch = "wasm32"))]
+    pub fn get(&self, key: &str) -> Option<String> {
+        Self::local_storage()
+            .ok()?
+            .get_item(&self.web_key(key))
+            .ok()
+            .flatten()
+    }
+
+    #[cfg(all(feature = "web", target_arch = "wasm32"))]
+    pub fn set(&self, key: &str, value: &str) -> Result<(), io::Error> {
+        Self::local_storage()?
+            .set_item(&self.web_key(key), value)
+            .map_err(|e| io::Error::other(format!("{e:?}")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn set_and_get() {
+        let dir = std::env::temp_dir().join(format!("nano9-storage-{}", std::process::id()));
+        let storage = Storage::new(&dir);
+        assert!(storage.get("cartdata/x").is_none());
+        storage.set("cartdata/x", "1\n2").unwrap();
+        assert_eq!(storage.get("cartdata/x").as_deref(), Some("1\n2"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn valid_names() {
+        assert!(is_valid_name("my_game_2"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("../../x"));
+        assert!(!is_valid_name("a/b"));
+        assert!(!is_valid_name("Game"));
+        assert!(!is_valid_name(&"x".repeat(MAX_NAME_LEN + 1)));
+    }
+}
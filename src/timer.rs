@@ -0,0 +1,132 @@
+//! Timers
+//!
+//! Run a one-shot system after a delay or at an interval. Timers count down on
+//! the cart tick, i.e., `FixedUpdate`, and only while the cart is running, so
+//! they stop when it is paused or halted.
+//!
+//! ```ignore
+//! fn init(mut timers: ResMut<Timers>, mut commands: Commands) {
+//!     let blink = commands.register_system(blink);
+//!     timers.every(0.5, blink);
+//! }
+//! ```
+use bevy::{ecs::system::SystemId, prelude::*};
+
+use crate::error::RunState;
+
+pub(crate) fn plugin(app: &mut App) {
+    app.init_resource::<Timers>()
+        .add_systems(FixedUpdate, run_timers.run_if(in_state(RunState::Run)));
+}
+
+/// Identifies a timer so it may be cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub struct TimerHandle(u64);
+
+#[derive(Debug)]
+struct Scheduled {
+    handle: TimerHandle,
+    /// Seconds until it fires
+    remaining: f32,
+    /// Seconds between firings if it repeats
+    interval: Option<f32>,
+    system: SystemId,
+}
+
+/// The scheduled timers
+#[derive(Debug, Default, Resource)]
+pub struct Timers {
+    next: u64,
+    scheduled: Vec<Scheduled>,
+}
+
+impl Timers {
+    fn schedule(&mut self, seconds: f32, interval: Option<f32>, system: SystemId) -> TimerHandle {
+        let handle = TimerHandle(self.next);
+        self.next += 1;
+        self.scheduled.push(Scheduled {
+            handle,
+            remaining: seconds,
+            interval,
+            system,
+        });
+        handle
+    }
+
+    /// Run `system` once after `seconds`.
+    pub fn after(&mut self, seconds: f32, system: SystemId) -> TimerHandle {
+        self.schedule(seconds, None, system)
+    }
+
+    /// Run `system` every `interval` seconds.
+    pub fn every(&mut self, interval: f32, system: SystemId) -> TimerHandle {
+        self.schedule(interval, Some(interval), system)
+    }
+
+    /// Cancel a timer. Returns false if it had already fired or been
+    /// cancelled.
+    pub fn cancel(&mut self, handle: TimerHandle) -> bool {
+        let count = self.scheduled.len();
+        self.scheduled.retain(|s| s.handle != handle);
+        self.scheduled.len() != count
+    }
+
+    /// Cancel every timer.
+    pub fn clear(&mut self) {
+        self.scheduled.clear();
+    }
+
+    pub fn is_active(&self, handle: TimerHandle) -> bool {
+        self.scheduled.iter().any(|s| s.handle == handle)
+    }
+
+    /// Count down by `dt` seconds and return the systems to run in order.
+    fn tick(&mut self, dt: f32) -> Vec<SystemId> {
+        let mut due = vec![];
+        self.scheduled.retain_mut(|s| {
+            s.remaining -= dt;
+            while s.remaining <= 0.0 {
+                due.push(s.system);
+                match s.interval {
+                    // An interval of zero runs once per tick.
+                    Some(interval) if interval > 0.0 => s.remaining += interval,
+                    Some(_) => {
+                        s.remaining = 0.0;
+                        return true;
+                    }
+                    None => return false,
+                }
+            }
+            true
+        });
+        due
+    }
+}
+
+fn run_timers(time: Res<Time>, mut timers: ResMut<Timers>, mut commands: Commands) {
+    for system in timers.tick(time.delta_secs()) {
+        commands.run_system(system);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn after_and_every() {
+        let mut world = World::new();
+        let a = world.register_system(|| {});
+        let b = world.register_system(|| {});
+        let mut timers = Timers::default();
+        let once = timers.after(1.0, a);
+        let repeat = timers.every(0.5, b);
+        assert_eq!(timers.tick(0.5), [b]);
+        assert_eq!(timers.tick(0.5), [a, b]);
+        assert!(!timers.is_active(once));
+        assert_eq!(timers.tick(1.0), [b, b]);
+        assert!(timers.cancel(repeat));
+        assert!(!timers.cancel(repeat));
+        assert!(timers.tick(1.0).is_empty());
+    }
+}
@@ -0,0 +1,15 @@
+//! In-app tools
+//!
+//! With the "tools" feature, the [SpriteEditor] window is available in
+//! windowed apps. Press F2 to show or hide it.
+use bevy::prelude::*;
+
+mod sprite_editor;
+pub use sprite_editor::*;
+
+pub(crate) fn plugin(app: &mut App) {
+    if !app.is_plugin_added::<bevy_egui::EguiPlugin>() {
+        app.add_plugins(bevy_egui::EguiPlugin);
+    }
+    app.add_plugins(sprite_editor::plugin);
+}
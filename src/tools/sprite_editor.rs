@@ -0,0 +1,324 @@
+//! Sprite editor
+//!
+//! Draw into the loaded sprite sheets, pick palette colors, set sprite flags,
+//! and preview animations. Edits change the sheets in memory, so the running
+//! cart sees them immediately. "Save" writes an indexed sheet back to its PNG.
+use bevy::{asset::io::file::FileAssetReader, prelude::*};
+use bevy_egui::{egui, EguiContexts};
+use std::{fs, io, path::PathBuf};
+
+use crate::{
+    config::Config,
+    pico8::{self, Pico8, Pico8Handle, SprHandle},
+    PColor,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<SpriteEditor>().add_systems(
+        Update,
+        (
+            toggle_sprite_editor,
+            sprite_editor.run_if(
+                resource_exists::<Pico8Handle>.and(|editor: Res<SpriteEditor>| editor.open),
+            ),
+        )
+            .chain(),
+    );
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SaveError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("could not encode png: {0}")]
+    Png(#[from] png::EncodingError),
+    #[error("{0}")]
+    Pico8(#[from] pico8::Error),
+    #[error("cannot save sheet: {0}")]
+    Unsupported(String),
+}
+
+/// The sprite editor's state
+#[derive(Debug, Clone, Resource)]
+pub struct SpriteEditor {
+    pub open: bool,
+    pub sheet: usize,
+    pub sprite: usize,
+    /// The palette index to draw with
+    pub color: usize,
+    /// Window pixels per sprite pixel
+    pub zoom: f32,
+    /// The first and last sprites of the previewed animation
+    pub anim: (usize, usize),
+    pub anim_fps: f32,
+}
+
+impl Default for SpriteEditor {
+    fn default() -> Self {
+        SpriteEditor {
+            open: false,
+            sheet: 0,
+            sprite: 0,
+            color: 7,
+            zoom: 16.0,
+            anim: (0, 0),
+            anim_fps: 8.0,
+        }
+    }
+}
+
+/// The layout of a sprite sheet
+#[derive(Debug, Clone, Copy)]
+struct Sheet {
+    index: usize,
+    sprite_size: UVec2,
+    columns: u32,
+    count: usize,
+}
+
+impl Sheet {
+    fn new(pico8: &Pico8, index: usize) -> Result<Self, pico8::Error> {
+        let sheet = pico8.sprite_sheet(Some(index))?;
+        let size = match &sheet.handle {
+            SprHandle::Gfx(handle) => pico8
+                .gfxs
+                .get(handle)
+                .map(|gfx| UVec2::new(gfx.width as u32, gfx.height as u32)),
+            SprHandle::Image(handle) => pico8.images.get(handle).map(|image| image.size()),
+        }
+        .ok_or(pico8::Error::NoAsset("sprite sheet".into()))?;
+        let sprite_size = sheet.sprite_size.max(UVec2::ONE);
+        let grid = (size / sprite_size).max(UVec2::ONE);
+        Ok(Sheet {
+            index,
+            sprite_size,
+            columns: grid.x,
+            count: (grid.x * grid.y) as usize,
+        })
+    }
+
+    /// Return the sheet pixel of the upper left corner of `sprite`.
+    fn origin(&self, sprite: usize) -> UVec2 {
+        let sprite = sprite as u32;
+        UVec2::new(sprite % self.columns, sprite / self.columns) * self.sprite_size
+    }
+}
+
+fn color32(c: [u8; 4]) -> egui::Color32 {
+    egui::Color32::from_rgba_unmultiplied(c[0], c[1], c[2], c[3])
+}
+
+fn toggle_sprite_editor(input: Res<ButtonInput<KeyCode>>, mut editor: ResMut<SpriteEditor>) {
+    if input.just_pressed(KeyCode::F2) {
+        editor.open = !editor.open;
+    }
+}
+
+fn sprite_editor(
+    mut contexts: EguiContexts,
+    mut editor: ResMut<SpriteEditor>,
+    mut pico8: Pico8,
+    config: Option<Res<Config>>,
+    time: Res<Time<Real>>,
+) {
+    let mut open = true;
+    egui::Window::new("Sprite Editor")
+        .open(&mut open)
+        .show(contexts.ctx_mut(), |ui| {
+            if let Err(e) = editor_ui(
+                ui,
+                &mut editor,
+                &mut pico8,
+                config.as_deref(),
+                time.elapsed_secs(),
+            ) {
+                ui.label(e.to_string());
+            }
+        });
+    editor.open = open;
+}
+
+fn editor_ui(
+    ui: &mut egui::Ui,
+    editor: &mut SpriteEditor,
+    pico8: &mut Pico8,
+    config: Option<&Config>,
+    t: f32,
+) -> Result<(), pico8::Error> {
+    let sheet_count = pico8.pico8_asset()?.sprite_sheets.len();
+    if sheet_count == 0 {
+        ui.label("No sprite sheets");
+        return Ok(());
+    }
+    editor.sheet = editor.sheet.min(sheet_count - 1);
+    ui.horizontal(|ui| {
+        ui.label("sheet");
+        ui.add(egui::DragValue::new(&mut editor.sheet).range(0..=sheet_count - 1));
+    });
+    let sheet = Sheet::new(pico8, editor.sheet)?;
+    let last = sheet.count - 1;
+    editor.sprite = editor.sprite.min(last);
+    ui.horizontal(|ui| {
+        ui.label("sprite");
+        ui.add(egui::DragValue::new(&mut editor.sprite).range(0..=last));
+        ui.label("zoom");
+        ui.add(egui::DragValue::new(&mut editor.zoom).range(1.0..=64.0));
+    });
+    let palette = pico8.palette(None)?.data.clone();
+
+    // Draw with the left button and pick a color with the right.
+    let size = sheet.sprite_size.as_vec2() * editor.zoom;
+    let (response, painter) =
+        ui.allocate_painter(egui::vec2(size.x, size.y), egui::Sense::click_and_drag());
+    paint_sprite(
+        &painter,
+        response.rect.min,
+        editor.zoom,
+        pico8,
+        &sheet,
+        editor.sprite,
+        &palette,
+    )?;
+    if let Some(pos) = response.interact_pointer_pos() {
+        let pixel = (pos - response.rect.min) / editor.zoom;
+        if pixel.x >= 0.0 && pixel.y >= 0.0 {
+            let pixel = UVec2::new(pixel.x as u32, pixel.y as u32);
+            if pixel.cmplt(sheet.sprite_size).all() {
+                let pos = sheet.origin(editor.sprite) + pixel;
+                if response.secondary_clicked()
+                    || response.dragged_by(egui::PointerButton::Secondary)
+                {
+                    if let Some(PColor::Palette(n)) = pico8.sget(pos, Some(sheet.index))? {
+                        editor.color = n;
+                    }
+                } else if response.clicked() || response.dragged_by(egui::PointerButton::Primary) {
+                    pico8.sset(
+                        pos,
+                        Some(PColor::Palette(editor.color).into()),
+                        Some(sheet.index),
+                    )?;
+                }
+            }
+        }
+    }
+
+    ui.horizontal_wrapped(|ui| {
+        for (i, c) in palette.iter().enumerate() {
+            let (rect, response) =
+                ui.allocate_exact_size(egui::Vec2::splat(16.0), egui::Sense::click());
+            ui.painter().rect_filled(rect, 0.0, color32(*c));
+            if i == editor.color {
+                ui.painter()
+                    .rect_stroke(rect, 0.0, egui::Stroke::new(2.0, egui::Color32::WHITE));
+            }
+            if response.on_hover_text(i.to_string()).clicked() {
+                editor.color = i;
+            }
+        }
+    });
+
+    let flags = &mut pico8.pico8_asset_mut()?.sprite_sheets[sheet.index].flags;
+    ui.horizontal(|ui| {
+        ui.label("flags");
+        for bit in 0..8 {
+            let mask = 1 << bit;
+            let mut on = flags.get(editor.sprite).is_some_and(|f| f & mask != 0);
+            if ui.checkbox(&mut on, "").changed() {
+                if flags.len() <= editor.sprite {
+                    flags.resize(editor.sprite + 1, 0);
+                }
+                if on {
+                    flags[editor.sprite] |= mask;
+                } else {
+                    flags[editor.sprite] &= !mask;
+                }
+            }
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("anim");
+        ui.add(egui::DragValue::new(&mut editor.anim.0).range(0..=last));
+        ui.add(egui::DragValue::new(&mut editor.anim.1).range(0..=last));
+        ui.label("fps");
+        ui.add(egui::DragValue::new(&mut editor.anim_fps).range(0.0..=60.0));
+    });
+    let first = editor.anim.0.min(editor.anim.1);
+    let frames = editor.anim.0.max(editor.anim.1) - first + 1;
+    let frame = first + (t * editor.anim_fps) as usize % frames;
+    let preview_zoom = (editor.zoom / 2.0).max(1.0);
+    let size = sheet.sprite_size.as_vec2() * preview_zoom;
+    let (response, painter) = ui.allocate_painter(egui::vec2(size.x, size.y), egui::Sense::hover());
+    paint_sprite(
+        &painter,
+        response.rect.min,
+        preview_zoom,
+        pico8,
+        &sheet,
+        frame,
+        &palette,
+    )?;
+    ui.ctx().request_repaint();
+
+    if ui.button("Save").clicked() {
+        match save_sheet(pico8, config, sheet.index) {
+            Ok(path) => info!("Saved sprite sheet to {path:?}."),
+            Err(e) => warn!("Could not save sprite sheet: {e}"),
+        }
+    }
+    Ok(())
+}
+
+/// Paint `sprite` with its upper left corner at `min`.
+fn paint_sprite(
+    painter: &egui::Painter,
+    min: egui::Pos2,
+    zoom: f32,
+    pico8: &mut Pico8,
+    sheet: &Sheet,
+    sprite: usize,
+    palette: &[[u8; 4]],
+) -> Result<(), pico8::Error> {
+    let origin = sheet.origin(sprite);
+    for y in 0..sheet.sprite_size.y {
+        for x in 0..sheet.sprite_size.x {
+            let color = match pico8.sget(origin + UVec2::new(x, y), Some(sheet.index))? {
+                Some(PColor::Palette(n)) => palette.get(n).copied().unwrap_or_default(),
+                Some(PColor::Color(c)) => c.to_srgba().to_u8_array(),
+                None => [0; 4],
+            };
+            let rect = egui::Rect::from_min_size(
+                min + egui::vec2(x as f32, y as f32) * zoom,
+                egui::Vec2::splat(zoom),
+            );
+            painter.rect_filled(rect, 0.0, color32(color));
+        }
+    }
+    Ok(())
+}
+
+/// Write sheet `index` back to its PNG in the assets directory.
+pub fn save_sheet(
+    pico8: &Pico8,
+    config: Option<&Config>,
+    index: usize,
+) -> Result<PathBuf, SaveError> {
+    let path = config
+        .and_then(|config| config.sprite_sheets.get(index))
+        .map(|sheet| sheet.path.clone())
+        .filter(|path| path.ends_with(".png"))
+        .ok_or_else(|| SaveError::Unsupported(format!("sheet {index} is not a png")))?;
+    let SprHandle::Gfx(handle) = &pico8.sprite_sheet(Some(index))?.handle else {
+        return Err(SaveError::Unsupported(format!(
+            "sheet {index} is not indexed"
+        )));
+    };
+    let gfx = pico8
+        .gfxs
+        .get(handle)
+        .ok_or(pico8::Error::NoAsset("Gfx".into()))?;
+    let bytes = gfx.to_png(&pico8.palette(None)?.data)?;
+    let dest = FileAssetReader::get_base_path().join("assets").join(path);
+    fs::write(&dest, bytes)?;
+    Ok(dest)
+}
@@ -0,0 +1,159 @@
+//! Position bevy_ui nodes relative to canvas pixels
+use bevy::{prelude::*, ui::UiSystem};
+use serde::{Deserialize, Serialize};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<CanvasViewport>()
+        .register_type::<CanvasAnchor>()
+        .register_type::<CanvasNode>()
+        .init_resource::<CanvasViewport>()
+        .add_systems(PostUpdate, place_canvas_nodes.before(UiSystem::Layout));
+}
+
+/// Where the canvas is in the window.
+///
+/// This is updated whenever the canvas is fit to the window, so embedders need
+/// not re-derive the letterboxing and scale.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct CanvasViewport {
+    /// The top left of the canvas in logical window pixels.
+    pub position: Vec2,
+    /// The number of logical window pixels per canvas pixel. Only a
+    /// [CanvasAnchor::Stretch] canvas may scale differently in x and y.
+    pub scale: Vec2,
+}
+
+impl Default for CanvasViewport {
+    fn default() -> Self {
+        CanvasViewport {
+            position: Vec2::ZERO,
+            scale: Vec2::ONE,
+        }
+    }
+}
+
+/// Where the canvas is placed in the window.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[reflect(Resource)]
+#[serde(rename_all = "kebab-case")]
+pub enum CanvasAnchor {
+    /// Scale the canvas to fit and letterbox it in the window's center.
+    #[default]
+    Center,
+    /// Scale the canvas to fit and place it in the window's top left.
+    TopLeft,
+    /// Scale the canvas to fill the window, ignoring its aspect ratio.
+    Stretch,
+}
+
+impl CanvasViewport {
+    /// Convert a canvas position to a logical window position.
+    pub fn canvas_to_window(&self, pos: Vec2) -> Vec2 {
+        self.position + pos * self.scale
+    }
+
+    /// Convert a logical window position, e.g., the cursor, to a canvas
+    /// position.
+    pub fn window_to_canvas(&self, pos: Vec2) -> Vec2 {
+        (pos - self.position) / self.scale
+    }
+
+    /// Convert a logical window position to the canvas pixel it is over.
+    ///
+    /// The pixel is clamped to a canvas of `canvas_size`; the flag is false if
+    /// the position was outside the canvas, e.g., on the letterbox.
+    pub fn window_to_pixel(&self, pos: Vec2, canvas_size: UVec2) -> (UVec2, bool) {
+        let pos = self.window_to_canvas(pos).floor();
+        let max = canvas_size.saturating_sub(UVec2::ONE).as_vec2();
+        let inside = pos.cmpge(Vec2::ZERO).all() && pos.cmple(max).all();
+        (pos.clamp(Vec2::ZERO, max).as_uvec2(), inside)
+    }
+
+    /// Convert a canvas position to a position within the canvas's viewport.
+    ///
+    /// UI nodes rendered by the [crate::Nano9Camera], which is the default UI
+    /// camera, are laid out within its viewport.
+    pub fn canvas_to_viewport(&self, pos: Vec2) -> Vec2 {
+        pos * self.scale
+    }
+}
+
+/// Place a UI node at a canvas position, optionally with a canvas size.
+///
+/// The node's `left`, `top`, `width`, and `height` are managed while this
+/// component is present.
+#[derive(Component, Debug, Clone, Copy, Default, Reflect)]
+#[require(Node)]
+pub struct CanvasNode {
+    pub pos: Vec2,
+    pub size: Option<Vec2>,
+}
+
+fn place_canvas_nodes(
+    viewport: Res<CanvasViewport>,
+    mut query: Query<(Ref<CanvasNode>, &mut Node)>,
+) {
+    for (canvas_node, mut node) in &mut query {
+        if !viewport.is_changed() && !canvas_node.is_changed() {
+            continue;
+        }
+        let pos = viewport.canvas_to_viewport(canvas_node.pos);
+        node.position_type = PositionType::Absolute;
+        node.left = Val::Px(pos.x);
+        node.top = Val::Px(pos.y);
+        if let Some(size) = canvas_node.size {
+            let size = viewport.canvas_to_viewport(size);
+            node.width = Val::Px(size.x);
+            node.height = Val::Px(size.y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let viewport = CanvasViewport {
+            position: Vec2::new(64.0, 0.0),
+            scale: Vec2::splat(4.0),
+        };
+        assert_eq!(viewport.canvas_to_window(Vec2::ZERO), Vec2::new(64.0, 0.0));
+        assert_eq!(
+            viewport.canvas_to_window(Vec2::new(127.0, 127.0)),
+            Vec2::new(572.0, 508.0)
+        );
+        assert_eq!(
+            viewport.window_to_canvas(Vec2::new(572.0, 508.0)),
+            Vec2::new(127.0, 127.0)
+        );
+    }
+
+    #[test]
+    fn letterboxed_pixel() {
+        // A 128x128 canvas scaled by 2.5 in a 400x320 window
+        let viewport = CanvasViewport {
+            position: Vec2::new(40.0, 0.0),
+            scale: Vec2::splat(2.5),
+        };
+        let size = UVec2::splat(128);
+        assert_eq!(
+            viewport.window_to_pixel(Vec2::new(44.9, 2.4), size),
+            (UVec2::new(1, 0), true)
+        );
+        assert_eq!(
+            viewport.window_to_pixel(Vec2::new(359.9, 319.9), size),
+            (UVec2::new(127, 127), true)
+        );
+        assert_eq!(
+            viewport.window_to_pixel(Vec2::new(10.0, 100.0), size),
+            (UVec2::new(0, 40), false)
+        );
+        assert_eq!(
+            viewport.window_to_pixel(Vec2::new(360.0, 100.0), size),
+            (UVec2::new(127, 40), false)
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Cross-cart variables
+//!
+//! Unlike a cart's own state, the [VarStore] is not cleared when the cart is
+//! reset or another cart is loaded, so carts of a multi-cart game can pass
+//! progress to one another. A namespace may be saved to and loaded from
+//! [Storage] to keep it between sessions if its name, like a cartdata id, is
+//! 1-64 characters of a-z, 0-9, and underscore.
+//!
+//! ```ignore
+//! pico8.set_var("quest", "keys", Some(3.0.into()));
+//! pico8.save_vars("quest")?;
+//! ```
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+use std::io;
+
+use crate::{
+    pico8::{Error, Pico8},
+    storage::{self, Storage},
+};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<Var>().init_resource::<VarStore>();
+}
+
+/// A value kept in the [VarStore]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Reflect)]
+#[serde(untagged)]
+pub enum Var {
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+impl From<bool> for Var {
+    fn from(x: bool) -> Self {
+        Var::Bool(x)
+    }
+}
+
+impl From<f64> for Var {
+    fn from(x: f64) -> Self {
+        Var::Number(x)
+    }
+}
+
+impl From<f32> for Var {
+    fn from(x: f32) -> Self {
+        Var::Number(x as f64)
+    }
+}
+
+impl From<String> for Var {
+    fn from(x: String) -> Self {
+        Var::String(x)
+    }
+}
+
+impl From<&str> for Var {
+    fn from(x: &str) -> Self {
+        Var::String(x.to_owned())
+    }
+}
+
+/// Namespaced variables shared between carts
+#[derive(Debug, Clone, Default, Resource)]
+pub struct VarStore {
+    namespaces: HashMap<String, HashMap<String, Var>>,
+}
+
+impl VarStore {
+    pub fn get(&self, namespace: &str, key: &str) -> Option<&Var> {
+        self.namespaces.get(namespace)?.get(key)
+    }
+
+    /// Set `key` in `namespace` and return its previous value.
+    pub fn set(&mut self, namespace: &str, key: impl Into<String>, value: Var) -> Option<Var> {
+        self.namespaces
+            .entry(namespace.to_owned())
+            .or_default()
+            .insert(key.into(), value)
+    }
+
+    pub fn remove(&mut self, namespace: &str, key: &str) -> Option<Var> {
+        self.namespaces.get_mut(namespace)?.remove(key)
+    }
+
+    /// Remove all the variables of `namespace`.
+    pub fn clear(&mut self, namespace: &str) {
+        self.namespaces.remove(namespace);
+    }
+
+    /// Return the storage key of `namespace`, which must be a valid name
+    /// like a cartdata id.
+    fn storage_key(namespace: &str) -> Result<String, io::Error> {
+        if !storage::is_valid_name(namespace) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("namespace {namespace:?}; use 1-64 of a-z, 0-9, and _"),
+            ));
+        }
+        Ok(format!("vars/{namespace}.toml"))
+    }
+
+    /// Write `namespace` to `storage`.
+    pub fn save(&self, namespace: &str, storage: &Storage) -> Result<(), io::Error> {
+        let vars = self.namespaces.get(namespace).cloned().unwrap_or_default();
+        let contents = toml::to_string(&vars).map_err(io::Error::other)?;
+        storage.set(&Self::storage_key(namespace)?, &contents)
+    }
+
+    /// Read `namespace` from `storage`, replacing its variables. Returns false
+    /// if nothing was saved.
+    pub fn load(&mut self, namespace: &str, storage: &Storage) -> Result<bool, io::Error> {
+        let Some(contents) = storage.get(&Self::storage_key(namespace)?) else {
+            return Ok(false);
+        };
+        let vars: HashMap<String, Var> =
+            toml::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.namespaces.insert(namespace.to_owned(), vars);
+        Ok(true)
+    }
+}
+
+impl Pico8<'_, '_> {
+    /// var(namespace, key)
+    ///
+    /// Return the shared variable `key` of `namespace`.
+    pub fn var(&self, namespace: &str, key: &str) -> Option<Var> {
+        self.persist.vars.get(namespace, key).cloned()
+    }
+
+    /// set_var(namespace, key, [value])
+    ///
+    /// Set the shared variable `key` of `namespace` or remove it if no value
+    /// is given. Return its previous value.
+    pub fn set_var(&mut self, namespace: &str, key: &str, value: Option<Var>) -> Option<Var> {
+        match value {
+            Some(value) => self.persist.vars.set(namespace, key, value),
+            None => self.persist.vars.remove(namespace, key),
+        }
+    }
+
+    /// save_vars(namespace)
+    pub fn save_vars(&self, namespace: &str) -> Result<(), Error> {
+        self.persist
+            .vars
+            .save(namespace, &self.persist.storage)
+            .map_err(|e| Error::Unsupported(format!("storage {e}").into()))
+    }
+
+    /// load_vars(namespace)
+    ///
+    /// Replace the variables of `namespace` with those saved. Returns false if
+    /// none were saved.
+    pub fn load_vars(&mut self, namespace: &str) -> Result<bool, Error> {
+        let storage = &self.persist.storage;
+        self.persist
+            .vars
+            .load(namespace, storage)
+            .map_err(|e| Error::Unsupported(format!("storage {e}").into()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn set_and_remove() {
+        let mut vars = VarStore::default();
+        assert_eq!(vars.set("quest", "keys", 3.0.into()), None);
+        assert_eq!(
+            vars.set("quest", "keys", 4.0.into()),
+            Some(Var::Number(3.0))
+        );
+        assert_eq!(vars.get("quest", "keys"), Some(&Var::Number(4.0)));
+        assert_eq!(vars.get("other", "keys"), None);
+        assert_eq!(vars.remove("quest", "keys"), Some(Var::Number(4.0)));
+        vars.set("quest", "done", true.into());
+        vars.clear("quest");
+        assert_eq!(vars.get("quest", "done"), None);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = std::env::temp_dir().join(format!("nano9-vars-{}", std::process::id()));
+        let storage = Storage::new(&dir);
+        let mut vars = VarStore::default();
+        assert!(!vars.load("quest", &storage).unwrap());
+        vars.set("quest", "keys", 3.0.into());
+        vars.set("quest", "name", "sword".into());
+        vars.set("quest", "done", false.into());
+        vars.save("quest", &storage).unwrap();
+
+        let mut loaded = VarStore::default();
+        assert!(loaded.load("quest", &storage).unwrap());
+        assert_eq!(loaded.get("quest", "keys"), Some(&Var::Number(3.0)));
+        assert_eq!(loaded.get("quest", "name"), Some(&Var::from("sword")));
+        assert_eq!(loaded.get("quest", "done"), Some(&Var::Bool(false)));
+        assert!(vars.save("../quest", &storage).is_err());
+        assert!(loaded.load("../../quest", &storage).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,115 @@
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use nano9::{headless::Headless, prelude::*, PColor};
+
+fn init(mut pico8: Pico8) {
+    pico8.cls(Some(PColor::Palette(1))).unwrap();
+    pico8.pset(UVec2::new(2, 3), Some(8)).unwrap();
+}
+
+const GOLDEN_FRAME: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/frame.png");
+
+#[test]
+fn golden_frame() {
+    let mut headless = Headless::new(Config::pico8());
+    headless.app.add_systems(OnEnter(RunState::Init), init);
+    headless.run_until_running(1000).unwrap();
+    headless.tick(2);
+    let frame = headless.frame().unwrap();
+    assert_eq!(
+        frame.get_color_at(2, 3).unwrap().to_srgba().to_u8_array(),
+        [0xff, 0x00, 0x4d, 0xff]
+    );
+    assert!(headless.matches_golden(GOLDEN_FRAME).unwrap());
+    if std::env::var_os("NANO9_BLESS").is_none() {
+        // A changed pixel no longer matches.
+        headless
+            .app
+            .world_mut()
+            .run_system_once(|mut pico8: Pico8| pico8.pset(UVec2::new(2, 3), Some(1)).unwrap())
+            .unwrap();
+        assert!(!headless.matches_golden(GOLDEN_FRAME).unwrap());
+    }
+}
+
+#[test]
+fn missing_golden() {
+    let mut headless = Headless::new(Config::pico8());
+    headless.run_until_running(1000).unwrap();
+    let path = std::env::temp_dir().join("nano9-missing-golden.png");
+    let _ = std::fs::remove_file(&path);
+    if std::env::var_os("NANO9_BLESS").is_none() {
+        assert!(headless.matches_golden(&path).is_err());
+        assert!(!path.exists());
+    }
+}
+
+fn self_test(mut pico8: Pico8) -> Result<(), nano9::pico8::Error> {
+    pico8.cls(Some(PColor::Palette(1)))?;
+    pico8.assert_pix(UVec2::ZERO, 1)?;
+    let hash = pico8.screen_hash()?;
+    pico8.assert_screen(hash)?;
+    pico8.assert_pix(UVec2::ZERO, 2)
+}
+
+#[test]
+fn cart_self_test() {
+    let mut headless = Headless::new(Config::pico8());
+    let errors = headless.run_test(self_test).unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].callback.as_deref(), Some("_test"));
+    assert!(errors[0]
+        .message
+        .starts_with("assertion failed: pixel (0, 0)"));
+}
+
+#[test]
+fn deterministic_time() {
+    let mut headless = Headless::new(Config::pico8());
+    headless.run_until_running(1000).unwrap();
+    let start = headless
+        .app
+        .world_mut()
+        .run_system_once(|pico8: Pico8| pico8.time())
+        .unwrap();
+    headless.tick(30);
+    let end = headless
+        .app
+        .world_mut()
+        .run_system_once(|pico8: Pico8| pico8.time())
+        .unwrap();
+    assert!((end - start - 1.0).abs() < 1e-4);
+}
+
+#[test]
+fn swap_carts() {
+    let mut headless = Headless::new(Config::pico8());
+    headless.run_until_running(1000).unwrap();
+    let mut small = Config::pico8();
+    small.screen = Some(nano9::config::Screen {
+        canvas_size: UVec2::new(64, 32),
+        ..default()
+    });
+    headless
+        .app
+        .world()
+        .resource::<nano9::config::MemoryDir>()
+        .insert_asset_text(
+            std::path::Path::new("small.toml"),
+            &toml::to_string(&small).unwrap(),
+        );
+    for (path, size) in [
+        ("n9mem://small.toml", UVec2::new(64, 32)),
+        ("n9mem://Nano9.toml", UVec2::splat(128)),
+    ] {
+        let world = headless.app.world_mut();
+        let handle: Handle<nano9::pico8::Pico8Asset> = world.resource::<AssetServer>().load(path);
+        world.insert_resource(nano9::pico8::Pico8Handle::from(handle));
+        world.send_event(nano9::reset::ResetCart);
+        // Leave the running state before waiting for the swapped cart.
+        headless.tick(1);
+        headless.run_until_running(1000).unwrap();
+        let world = headless.app.world();
+        assert_eq!(world.resource::<Config>().canvas_size(), size);
+        assert_eq!(world.resource::<nano9::N9Canvas>().size, size);
+    }
+}